@@ -0,0 +1,472 @@
+//! A record of the data written to a worksheet.
+//!
+//! `rust_xlsxwriter` doesn't provide a way to read back the data written to a
+//! worksheet so the wrapper can keep its own copy of each written cell, which
+//! is used by {@link Worksheet#getCell} and the other read methods, along with
+//! the merged ranges and the column and row sizes used by
+//! {@link Worksheet#toHtml}. The formats are shared between the cells that use
+//! them to limit the memory used by the copy.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use js_sys::Date;
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::{prelude::*, JsCast};
+
+use super::{
+    excel_data::{self, ExcelData, JsExcelData},
+    format::Format,
+    format_properties::FormatProperties,
+    formula::Formula,
+    rich_string::RichString,
+    url::{Url, UrlProperties},
+    workbook_settings::{WriteSettings, EPOCH_1904_OFFSET},
+};
+
+/// The Excel 1900 epoch serial date of the Unix epoch, 1970-01-01.
+const UNIX_EPOCH_SERIAL: f64 = 25569.0;
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// The value of a written cell.
+#[derive(Clone)]
+pub(crate) enum CellValue {
+    Blank,
+    String(String),
+    Number(f64),
+    Bool(bool),
+    /// An Excel serial date in the epoch of the workbook.
+    DateTime(f64),
+    /// A formula and its cached result.
+    Formula(xlsx::Formula, String),
+    Url(UrlProperties),
+    RichString(Vec<(FormatProperties, String)>),
+    /// An image embedded in the cell.
+    Image,
+}
+
+impl CellValue {
+    /// Get the value that `data` leaves in a cell, or `None` if writing it
+    /// doesn't change the cell.
+    pub(crate) fn from_data(data: &ExcelData, formatted: bool) -> Option<CellValue> {
+        let value = match data {
+            ExcelData::Skip => return None,
+            // Unformatted empty strings are ignored by `rust_xlsxwriter`,
+            // while unformatted blank cells clear the cell, see `insert()`.
+            ExcelData::None if !formatted => return None,
+            ExcelData::String(s) if s.is_empty() && !formatted => return None,
+            ExcelData::None | ExcelData::Blank => CellValue::Blank,
+            ExcelData::NumError => CellValue::Formula(excel_data::num_error(), "#NUM!".to_string()),
+            ExcelData::String(s) => CellValue::String(s.clone()),
+            ExcelData::Number(n) => CellValue::Number(*n),
+            ExcelData::Bool(b) | ExcelData::Checkbox(b) => CellValue::Bool(*b),
+            ExcelData::DateTime(dt) => CellValue::DateTime(dt.0),
+            ExcelData::Formula(f) => CellValue::formula(f),
+            ExcelData::Url(url) => CellValue::Url(url.lock().clone()),
+            ExcelData::RichString(rich_string) => CellValue::RichString(rich_string.lock().clone()),
+        };
+        Some(value)
+    }
+
+    /// Get the value of a formula cell.
+    pub(crate) fn formula(formula: &Formula) -> CellValue {
+        CellValue::Formula(formula.lock().clone(), formula.result.lock().unwrap().clone())
+    }
+
+    /// Get the value of a string cell, ignoring unformatted empty strings
+    /// like `rust_xlsxwriter`.
+    pub(crate) fn string(string: &str, formatted: bool) -> Option<CellValue> {
+        if string.is_empty() && !formatted {
+            None
+        } else {
+            Some(CellValue::String(string.to_string()))
+        }
+    }
+
+    /// Get the values that a row or column of data leaves in its cells.
+    pub(crate) fn from_slice(data: &[ExcelData], formatted: bool) -> Vec<Option<CellValue>> {
+        data.iter().map(|data| CellValue::from_data(data, formatted)).collect()
+    }
+
+    fn to_js(&self, settings: &WriteSettings) -> JsValue {
+        match self {
+            CellValue::Blank | CellValue::Image => JsValue::NULL,
+            CellValue::String(s) => JsValue::from_str(s),
+            CellValue::Number(n) => JsValue::from_f64(*n),
+            CellValue::Bool(b) => JsValue::from_bool(*b),
+            CellValue::DateTime(serial) => date_of_serial(*serial, settings.date_1904).into(),
+            CellValue::Formula(f, result) => Formula {
+                inner: Arc::new(Mutex::new(f.clone())),
+                result: Arc::new(Mutex::new(result.clone())),
+            }
+            .into(),
+            CellValue::Url(url) => Url {
+                inner: Arc::new(Mutex::new(url.clone())),
+            }
+            .into(),
+            CellValue::RichString(rich_string) => RichString::from_parts(rich_string.clone()).into(),
+        }
+    }
+}
+
+/// Convert an Excel serial date to a JS `Date` whose UTC time is the Excel
+/// date and time.
+fn date_of_serial(serial: f64, date_1904: bool) -> Date {
+    // Times without a date are the same in both epochs.
+    let mut serial = if date_1904 && !(0.0..1.0).contains(&serial) {
+        serial + EPOCH_1904_OFFSET
+    } else {
+        serial
+    };
+    // Excel treats 1900 as a leap year so serial dates before 1900-03-01 are
+    // off by one day.
+    if serial < 61.0 {
+        serial += 1.0;
+    }
+    Date::new(&JsValue::from_f64(
+        ((serial - UNIX_EPOCH_SERIAL) * MS_PER_DAY).round(),
+    ))
+}
+
+#[derive(Clone)]
+struct StoredCell {
+    value: CellValue,
+    /// The index of the cell format in `CellStore::formats`.
+    format: Option<usize>,
+}
+
+/// The default column width in pixels.
+pub(crate) const DEFAULT_COLUMN_WIDTH: u32 = 64;
+
+/// The default row height in pixels.
+pub(crate) const DEFAULT_ROW_HEIGHT: u32 = 20;
+
+/// Convert a column width in character units to pixels, like Excel.
+pub(crate) fn column_width_pixels(width: f64) -> u32 {
+    if width <= 0.0 {
+        0
+    } else if width < 1.0 {
+        (width * 12.0).round() as u32
+    } else {
+        (width * 7.0).round() as u32 + 5
+    }
+}
+
+/// The cells written to a worksheet, in row major order.
+#[derive(Clone, Default)]
+pub(crate) struct CellStore {
+    cells: BTreeMap<(xlsx::RowNum, xlsx::ColNum), StoredCell>,
+    /// The distinct cell formats, which are shared by the cells that use them.
+    formats: Vec<FormatProperties>,
+    format_ids: HashMap<FormatProperties, usize>,
+    merged_ranges: Vec<CellRange>,
+    /// Column widths in pixels.
+    column_widths: BTreeMap<xlsx::ColNum, u32>,
+    /// Row heights in pixels.
+    row_heights: BTreeMap<xlsx::RowNum, u32>,
+}
+
+// `rust_xlsxwriter::Formula` doesn't implement `Debug`.
+impl fmt::Debug for CellStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CellStore")
+            .field("len", &self.cells.len())
+            .finish()
+    }
+}
+
+impl CellStore {
+    /// Get the index of a format in `formats`, adding it if it's new.
+    fn format_id(&mut self, format: &FormatProperties) -> usize {
+        if let Some(&id) = self.format_ids.get(format) {
+            return id;
+        }
+        let id = self.formats.len();
+        self.formats.push(format.clone());
+        self.format_ids.insert(format.clone(), id);
+        id
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        value: Option<CellValue>,
+        format: Option<&FormatProperties>,
+    ) {
+        match (value, format) {
+            (None, _) => {}
+            // A blank cell without a format is an empty cell.
+            (Some(CellValue::Blank), None) => self.remove(row, col),
+            (Some(value), format) => {
+                let format = format.map(|format| self.format_id(format));
+                self.cells.insert((row, col), StoredCell { value, format });
+            }
+        }
+    }
+
+    pub(crate) fn insert_column(
+        &mut self,
+        first_row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        values: Vec<Option<CellValue>>,
+        format: Option<&FormatProperties>,
+    ) {
+        for (row, value) in (first_row..).zip(values) {
+            self.insert(row, col, value, format);
+        }
+    }
+
+    pub(crate) fn insert_row(
+        &mut self,
+        row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        values: Vec<Option<CellValue>>,
+        format: Option<&FormatProperties>,
+    ) {
+        for (col, value) in (first_col..).zip(values) {
+            self.insert(row, col, value, format);
+        }
+    }
+
+    /// Set the format of a cell, adding a blank cell if it doesn't exist.
+    pub(crate) fn set_format(&mut self, row: xlsx::RowNum, col: xlsx::ColNum, format: &FormatProperties) {
+        let format = Some(self.format_id(format));
+        self.cells
+            .entry((row, col))
+            .and_modify(|cell| cell.format = format)
+            .or_insert(StoredCell {
+                value: CellValue::Blank,
+                format,
+            });
+    }
+
+    pub(crate) fn set_range_format(
+        &mut self,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+        format: &FormatProperties,
+    ) {
+        for row in first_row..=last_row {
+            for col in first_col..=last_col {
+                self.set_format(row, col, format);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, row: xlsx::RowNum, col: xlsx::ColNum) {
+        self.cells.remove(&(row, col));
+    }
+
+    pub(crate) fn clear_format(&mut self, row: xlsx::RowNum, col: xlsx::ColNum) {
+        if let Some(cell) = self.cells.get_mut(&(row, col)) {
+            cell.format = None;
+        }
+    }
+
+    pub(crate) fn get(&self, row: xlsx::RowNum, col: xlsx::ColNum, settings: &WriteSettings) -> Option<Cell> {
+        self.cells
+            .get(&(row, col))
+            .map(|cell| Cell::new(row, col, self.cell_format(cell), cell, settings))
+    }
+
+    pub(crate) fn cells(&self, settings: &WriteSettings) -> Vec<Cell> {
+        self.cells
+            .iter()
+            .map(|(&(row, col), cell)| Cell::new(row, col, self.cell_format(cell), cell, settings))
+            .collect()
+    }
+
+    fn cell_format(&self, cell: &StoredCell) -> Option<&FormatProperties> {
+        cell.format.map(|id| &self.formats[id])
+    }
+
+    /// Get the value and format of a cell.
+    pub(crate) fn value(&self, row: xlsx::RowNum, col: xlsx::ColNum) -> Option<(&CellValue, Option<&FormatProperties>)> {
+        self.cells
+            .get(&(row, col))
+            .map(|cell| (&cell.value, self.cell_format(cell)))
+    }
+
+    pub(crate) fn add_merged_range(&mut self, range: CellRange) {
+        self.merged_ranges.push(range);
+    }
+
+    pub(crate) fn merged_ranges(&self) -> &[CellRange] {
+        &self.merged_ranges
+    }
+
+    pub(crate) fn set_column_width(&mut self, col: xlsx::ColNum, pixels: u32) {
+        self.column_widths.insert(col, pixels);
+    }
+
+    pub(crate) fn column_width(&self, col: xlsx::ColNum) -> u32 {
+        self.column_widths.get(&col).copied().unwrap_or(DEFAULT_COLUMN_WIDTH)
+    }
+
+    pub(crate) fn set_row_height(&mut self, row: xlsx::RowNum, pixels: u32) {
+        self.row_heights.insert(row, pixels);
+    }
+
+    pub(crate) fn row_height(&self, row: xlsx::RowNum) -> u32 {
+        self.row_heights.get(&row).copied().unwrap_or(DEFAULT_ROW_HEIGHT)
+    }
+
+    pub(crate) fn used_range(&self) -> Option<CellRange> {
+        let (&(first_row, _), _) = self.cells.first_key_value()?;
+        let (&(last_row, _), _) = self.cells.last_key_value()?;
+        let first_col = self.cells.keys().map(|&(_, col)| col).min()?;
+        let last_col = self.cells.keys().map(|&(_, col)| col).max()?;
+        Some(CellRange::new(first_row, first_col, last_row, last_col))
+    }
+}
+
+/// The `Cell` struct represents a cell that has been written to a worksheet.
+///
+/// It is returned by {@link Worksheet#getCell} and {@link Worksheet#cells}.
+#[wasm_bindgen]
+pub struct Cell {
+    row: xlsx::RowNum,
+    col: xlsx::ColNum,
+    value: JsValue,
+    format: Option<FormatProperties>,
+}
+
+impl Cell {
+    fn new(
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        format: Option<&FormatProperties>,
+        cell: &StoredCell,
+        settings: &WriteSettings,
+    ) -> Cell {
+        Cell {
+            row,
+            col,
+            value: cell.value.to_js(settings),
+            format: format.cloned(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Cell {
+    /// The zero indexed row number.
+    #[wasm_bindgen(getter)]
+    pub fn row(&self) -> xlsx::RowNum {
+        self.row
+    }
+
+    /// The zero indexed column number.
+    #[wasm_bindgen(getter)]
+    pub fn col(&self) -> xlsx::ColNum {
+        self.col
+    }
+
+    /// The cell value.
+    ///
+    /// Values are returned as the {@link ExcelData} type that would write
+    /// them:
+    ///
+    /// - Strings, numbers and booleans, including checkboxes, are returned as
+    ///   is.
+    /// - Dates are returned as a `Date` whose UTC date and time is the date
+    ///   and time shown in Excel.
+    /// - Formulas, URLs and rich strings are returned as copies of the
+    ///   {@link Formula}, {@link Url} and {@link RichString} objects.
+    /// - Blank cells, which only hold a format, and cells with an embedded
+    ///   image are returned as `null`.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> JsExcelData {
+        self.value.clone().unchecked_into()
+    }
+
+    /// A copy of the cell format, or `undefined` if the cell isn't formatted.
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> Option<Format> {
+        self.format.clone().map(|format| Format {
+            inner: Arc::new(Mutex::new(format)),
+        })
+    }
+}
+
+/// The `CellRange` struct represents a range of cells.
+///
+/// It is returned by {@link Worksheet#usedRange}.
+#[derive(Clone, Copy, Debug)]
+#[wasm_bindgen]
+pub struct CellRange {
+    first_row: xlsx::RowNum,
+    first_col: xlsx::ColNum,
+    last_row: xlsx::RowNum,
+    last_col: xlsx::ColNum,
+}
+
+impl CellRange {
+    pub(crate) fn new(
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+    ) -> CellRange {
+        CellRange {
+            first_row,
+            first_col,
+            last_row,
+            last_col,
+        }
+    }
+
+    /// Check if the range has any cells in common with another range.
+    pub(crate) fn overlaps(&self, other: &CellRange) -> bool {
+        self.first_row <= other.last_row
+            && other.first_row <= self.last_row
+            && self.first_col <= other.last_col
+            && other.first_col <= self.last_col
+    }
+
+    /// Describe the range like `rust_xlsxwriter` does in its errors.
+    pub(crate) fn to_error_string(self) -> String {
+        format!(
+            "({}, {}, {}, {}) / {}",
+            self.first_row,
+            self.first_col,
+            self.last_row,
+            self.last_col,
+            xlsx::utility::cell_range(self.first_row, self.first_col, self.last_row, self.last_col)
+        )
+    }
+}
+
+#[wasm_bindgen]
+impl CellRange {
+    /// The zero indexed first row of the range.
+    #[wasm_bindgen(getter, js_name = "firstRow")]
+    pub fn first_row(&self) -> xlsx::RowNum {
+        self.first_row
+    }
+
+    /// The zero indexed first column of the range.
+    #[wasm_bindgen(getter, js_name = "firstCol")]
+    pub fn first_col(&self) -> xlsx::ColNum {
+        self.first_col
+    }
+
+    /// The zero indexed last row of the range.
+    #[wasm_bindgen(getter, js_name = "lastRow")]
+    pub fn last_row(&self) -> xlsx::RowNum {
+        self.last_row
+    }
+
+    /// The zero indexed last column of the range.
+    #[wasm_bindgen(getter, js_name = "lastCol")]
+    pub fn last_col(&self) -> xlsx::ColNum {
+        self.last_col
+    }
+}
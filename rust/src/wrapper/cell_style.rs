@@ -0,0 +1,487 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    format_properties::FormatProperties,
+    package::{self, Package},
+    workbook_settings::WorkbookSettings,
+    WasmResult,
+};
+
+/// The second dark color of the theme, used for heading text.
+const THEME_TEXT: xlsx::Color = xlsx::Color::Theme(3, 0);
+
+/// The first accent color of the theme.
+const THEME_ACCENT: xlsx::Color = xlsx::Color::Theme(4, 0);
+
+/// The `CellStyle` enum defines the built-in Excel cell styles.
+///
+/// A {@link Format} can be based on a built-in style with
+/// {@link Format.fromCellStyle}, which also copies the properties of the
+/// style. Cells with the format are shown with that style in the Excel "Cell
+/// Styles" gallery, even if the format also changes other properties.
+///
+/// The appearance of a built-in style can be changed by adding a style with
+/// the same name with {@link Workbook#addCellStyle}.
+///
+/// TODO: example omitted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum CellStyle {
+    /// The "Good" style, dark green text on a light green fill.
+    Good,
+    /// The "Bad" style, dark red text on a light red fill.
+    Bad,
+    /// The "Neutral" style, dark yellow text on a light yellow fill.
+    Neutral,
+    /// The "Heading 1" style, large bold text with a thick bottom border.
+    Heading1,
+    /// The "Heading 2" style, bold text with a thick light bottom border.
+    Heading2,
+    /// The "Heading 3" style, bold text with a medium light bottom border.
+    Heading3,
+    /// The "Heading 4" style, bold text.
+    Heading4,
+    /// The "Title" style, large bold text in the heading font.
+    Title,
+    /// The "Total" style, bold text with a single top border and a double
+    /// bottom border.
+    Total,
+    /// The "Note" style, a light yellow fill with a gray border.
+    Note,
+    /// The "Currency" style, an accounting number format with a currency
+    /// symbol.
+    Currency,
+    /// The "Percent" style, the `0%` number format.
+    Percent,
+}
+
+impl CellStyle {
+    const ALL: [CellStyle; 12] = [
+        CellStyle::Good,
+        CellStyle::Bad,
+        CellStyle::Neutral,
+        CellStyle::Heading1,
+        CellStyle::Heading2,
+        CellStyle::Heading3,
+        CellStyle::Heading4,
+        CellStyle::Title,
+        CellStyle::Total,
+        CellStyle::Note,
+        CellStyle::Currency,
+        CellStyle::Percent,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CellStyle::Good => "Good",
+            CellStyle::Bad => "Bad",
+            CellStyle::Neutral => "Neutral",
+            CellStyle::Heading1 => "Heading 1",
+            CellStyle::Heading2 => "Heading 2",
+            CellStyle::Heading3 => "Heading 3",
+            CellStyle::Heading4 => "Heading 4",
+            CellStyle::Title => "Title",
+            CellStyle::Total => "Total",
+            CellStyle::Note => "Note",
+            CellStyle::Currency => "Currency",
+            CellStyle::Percent => "Percent",
+        }
+    }
+
+    /// The id that Excel uses for the built-in style.
+    fn builtin_id(self) -> u32 {
+        match self {
+            CellStyle::Good => 26,
+            CellStyle::Bad => 27,
+            CellStyle::Neutral => 28,
+            CellStyle::Heading1 => 16,
+            CellStyle::Heading2 => 17,
+            CellStyle::Heading3 => 18,
+            CellStyle::Heading4 => 19,
+            CellStyle::Title => 15,
+            CellStyle::Total => 25,
+            CellStyle::Note => 10,
+            CellStyle::Currency => 4,
+            CellStyle::Percent => 5,
+        }
+    }
+
+    /// The format of the built-in style, with the theme colors and fonts
+    /// that Excel uses for it.
+    pub(crate) fn format(self) -> FormatProperties {
+        let format = FormatProperties::default();
+        match self {
+            CellStyle::Good => format
+                .set_font_color(xlsx::Color::RGB(0x006100))
+                .set_background_color(xlsx::Color::RGB(0xC6EFCE)),
+            CellStyle::Bad => format
+                .set_font_color(xlsx::Color::RGB(0x9C0006))
+                .set_background_color(xlsx::Color::RGB(0xFFC7CE)),
+            CellStyle::Neutral => format
+                .set_font_color(xlsx::Color::RGB(0x9C6500))
+                .set_background_color(xlsx::Color::RGB(0xFFEB9C)),
+            CellStyle::Heading1 => format
+                .set_bold()
+                .set_font_size(15)
+                .set_font_color(THEME_TEXT)
+                .set_border_bottom(xlsx::FormatBorder::Thick)
+                .set_border_bottom_color(THEME_ACCENT),
+            CellStyle::Heading2 => format
+                .set_bold()
+                .set_font_size(13)
+                .set_font_color(THEME_TEXT)
+                .set_border_bottom(xlsx::FormatBorder::Thick)
+                // Excel uses the accent color with a 50% tint, which isn't
+                // one of the theme color shades.
+                .set_border_bottom_color(xlsx::Color::RGB(0xA7BFDE)),
+            CellStyle::Heading3 => format
+                .set_bold()
+                .set_font_color(THEME_TEXT)
+                .set_border_bottom(xlsx::FormatBorder::Medium)
+                .set_border_bottom_color(xlsx::Color::Theme(4, 3)),
+            CellStyle::Heading4 => format.set_bold().set_font_color(THEME_TEXT),
+            CellStyle::Title => format
+                .set_bold()
+                .set_font_name("Cambria")
+                .set_font_scheme(xlsx::FontScheme::Headings)
+                .set_font_size(18)
+                .set_font_color(THEME_TEXT),
+            CellStyle::Total => format
+                .set_bold()
+                .set_border_top(xlsx::FormatBorder::Thin)
+                .set_border_top_color(THEME_ACCENT)
+                .set_border_bottom(xlsx::FormatBorder::Double)
+                .set_border_bottom_color(THEME_ACCENT),
+            CellStyle::Note => format
+                .set_background_color(xlsx::Color::RGB(0xFFFFCC))
+                .set_border(xlsx::FormatBorder::Thin)
+                .set_border_color(xlsx::Color::RGB(0xB2B2B2)),
+            CellStyle::Currency => format.set_num_format_index(44),
+            CellStyle::Percent => format.set_num_format_index(9),
+        }
+    }
+}
+
+/// A named cell style added to a workbook with {@link Workbook#addCellStyle}.
+#[derive(Clone, Debug)]
+pub(crate) struct NamedCellStyle {
+    pub(crate) name: String,
+    /// The built-in style that the style replaces.
+    builtin: Option<CellStyle>,
+    format: FormatProperties,
+}
+
+impl NamedCellStyle {
+    pub(crate) fn new(name: &str, format: &FormatProperties) -> WasmResult<NamedCellStyle> {
+        if name.is_empty() || name.chars().count() > 255 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Cell style name '{name}' must be 1 to 255 characters long"
+            ))));
+        }
+        if ["Normal", "Hyperlink"].iter().any(|reserved| reserved.eq_ignore_ascii_case(name)) {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Cell style name '{name}' is reserved"
+            ))));
+        }
+        Ok(NamedCellStyle {
+            name: name.to_string(),
+            builtin: CellStyle::ALL
+                .into_iter()
+                .find(|style| style.name().eq_ignore_ascii_case(name)),
+            format: format.clone(),
+        })
+    }
+
+    /// The `cellStyle` element of the style.
+    fn xml(&self, xf_id: usize, registered: bool) -> String {
+        let builtin = match self.builtin {
+            Some(style) if registered => format!(" builtinId=\"{}\" customBuiltin=\"1\"", style.builtin_id()),
+            Some(style) => format!(" builtinId=\"{}\"", style.builtin_id()),
+            None => String::new(),
+        };
+        format!(
+            "<cellStyle name=\"{}\" xfId=\"{xf_id}\"{builtin}/>",
+            package::escape_xml(&self.name)
+        )
+    }
+}
+
+/// The styles that cell formats can be based on: the styles added to the
+/// workbook, followed by the built-in styles that they don't replace.
+fn workbook_styles(settings: &WorkbookSettings) -> Vec<NamedCellStyle> {
+    let mut styles = settings.cell_styles.clone();
+    for style in CellStyle::ALL {
+        if !styles.iter().any(|added| added.builtin == Some(style)) {
+            styles.push(NamedCellStyle {
+                name: style.name().to_string(),
+                builtin: Some(style),
+                format: style.format(),
+            });
+        }
+    }
+    styles
+}
+
+/// Get the formats based on a cell style that were written to the
+/// worksheets, with the index of their style, if the workbook has the style.
+fn styled_formats(settings: &WorkbookSettings, styles: &[NamedCellStyle]) -> Vec<(FormatProperties, Option<usize>)> {
+    let mut seen = HashSet::new();
+    let mut styled = vec![];
+    for id in &settings.sheet_ids {
+        let Some(sheet) = settings.sheets.get(id) else {
+            continue;
+        };
+        for format in &sheet.styled_formats {
+            let Some(name) = &format.cell_style else {
+                continue;
+            };
+            if !seen.insert(format) {
+                continue;
+            }
+            let style = styles.iter().position(|style| style.name.eq_ignore_ascii_case(name));
+            styled.push((format.clone(), style));
+        }
+    }
+    styled
+}
+
+/// Check if the saved package has to be patched for cell styles.
+pub(crate) fn needs_package_update(settings: &WorkbookSettings) -> bool {
+    !settings.cell_styles.is_empty() || settings.sheets.values().any(|sheet| !sheet.styled_formats.is_empty())
+}
+
+/// Get the font name that marks the cell format of a format based on a style.
+///
+/// `rust_xlsxwriter` writes a single cell format for formats with the same
+/// properties, so a format based on a style is written with a font name that
+/// is unique to it. The font is replaced by the real font of the format when
+/// the package is patched, and the cell format is based on the style.
+pub(crate) fn marker_font_name(format: &FormatProperties) -> String {
+    let mut hasher = DefaultHasher::new();
+    format.hash(&mut hasher);
+    format!("\u{E000}{:016x}", hasher.finish())
+}
+
+/// Replace the marker fonts of the formats based on a style by their real
+/// fonts, and get the indexes of the cell formats of the formats with the
+/// index of their style.
+fn unmark_styled_formats(xml: &mut String, styled: &[(FormatProperties, Option<usize>)]) -> Vec<(usize, usize)> {
+    let default_font = FormatProperties::default().font_xml();
+    let mut xf_styles = vec![];
+    for (format, style) in styled {
+        let name = format!("<name val=\"{}\"/>", package::escape_xml(&marker_font_name(format)));
+        let fonts = package::child_elements(xml, "fonts", "font");
+        let Some(font_index) = fonts.iter().position(|font| font.contains(&name)) else {
+            continue;
+        };
+        let marker = fonts[font_index].to_string();
+        let font = format.font_xml();
+        *xml = xml.replacen(&marker, &font, 1);
+
+        let font_id = font_index.to_string();
+        let xf_indexes: Vec<usize> = package::child_elements(xml, "cellXfs", "xf")
+            .iter()
+            .enumerate()
+            .filter(|(_, xf)| package::xml_attribute(xf, "xf", "fontId") == Some(font_id.as_str()))
+            .map(|(index, _)| index)
+            .collect();
+        for xf_index in xf_indexes {
+            // The marker font isn't the default font even if the format's
+            // font is.
+            if font == default_font {
+                set_cell_xf_attribute(xml, xf_index, "fontId", Some("0"));
+                set_cell_xf_attribute(xml, xf_index, "applyFont", None);
+            }
+            if let Some(style) = style {
+                xf_styles.push((xf_index, *style));
+            }
+        }
+    }
+    xf_styles
+}
+
+/// Set an attribute of the cell format at an index of the `cellXfs`
+/// element.
+fn set_cell_xf_attribute(xml: &mut String, xf_index: usize, name: &str, value: Option<&str>) {
+    let Some(mut offset) = xml.find("<cellXfs") else {
+        return;
+    };
+    for _ in 0..=xf_index {
+        match xml[offset + 1..].find("<xf ") {
+            Some(pos) => offset += pos + 1,
+            None => return,
+        }
+    }
+    let mut tail = xml.split_off(offset);
+    package::set_xml_attribute(&mut tail, "xf", name, value);
+    xml.push_str(&tail);
+}
+
+/// Add the cell styles to a package saved by `rust_xlsxwriter`, and base the
+/// cell formats on them.
+pub(crate) fn update_package(settings: &WorkbookSettings, package: &mut Package) -> WasmResult<()> {
+    let styles = workbook_styles(settings);
+    let styled = styled_formats(settings, &styles);
+    let mut xf_styles = vec![];
+    if !styled.is_empty() {
+        package.edit_xml("xl/styles.xml", |xml| {
+            xf_styles = unmark_styled_formats(xml, &styled);
+            Ok(())
+        })?;
+    }
+
+    // The added styles are always written, the built-in styles only if a
+    // format is based on them.
+    let added = settings.cell_styles.len();
+    let mut written: Vec<usize> = (0..added).collect();
+    written.extend(styled.iter().filter_map(|&(_, style)| style).filter(|&style| style >= added));
+    written.sort_unstable();
+    written.dedup();
+    if written.is_empty() {
+        return Ok(());
+    }
+
+    package.edit_xml("xl/styles.xml", |xml| {
+        let count = |xml: &str, tag: &str| -> usize {
+            package::xml_attribute(xml, tag, "count")
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(0)
+        };
+        let mut font_count = count(xml, "fonts");
+        let mut fill_count = count(xml, "fills");
+        let mut border_count = count(xml, "borders");
+        let mut num_format_count = count(xml, "numFmts");
+        let mut style_xf_count = count(xml, "cellStyleXfs");
+        let cell_style_count = count(xml, "cellStyles") + written.len();
+        // User defined number formats start from 164.
+        let mut next_num_format_id = xml
+            .split("numFmtId=\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next()?.parse::<usize>().ok())
+            .fold(163, usize::max)
+            + 1;
+
+        let (mut fonts, mut fills, mut borders, mut num_formats) =
+            (String::new(), String::new(), String::new(), String::new());
+        let (mut style_xfs, mut cell_styles) = (String::new(), String::new());
+        let mut style_xf_ids = HashMap::new();
+        let default_font = FormatProperties::default().font_xml();
+        for &index in &written {
+            let style = &styles[index].format;
+            let mut attributes = String::new();
+            let font = style.font_xml();
+            let font_id = if font == default_font {
+                attributes.push_str(" applyFont=\"0\"");
+                0
+            } else {
+                fonts.push_str(&font);
+                font_count += 1;
+                font_count - 1
+            };
+
+            // Number formats that cell formats use are reused.
+            let code = package::escape_xml(&style.num_format);
+            let existing_num_format_id = || {
+                xml.split("<numFmt ")
+                    .skip(1)
+                    .filter(|element| element.contains(&format!(" formatCode=\"{code}\"")))
+                    .find_map(|element| element.strip_prefix("numFmtId=\"")?.split('"').next()?.parse().ok())
+            };
+            let num_format_id = if style.num_format_index > 0 {
+                usize::from(style.num_format_index)
+            } else if style.num_format.is_empty() {
+                attributes.push_str(" applyNumberFormat=\"0\"");
+                0
+            } else if let Some(id) = existing_num_format_id() {
+                id
+            } else {
+                num_formats.push_str(&format!(
+                    "<numFmt numFmtId=\"{next_num_format_id}\" formatCode=\"{}\"/>",
+                    code
+                ));
+                num_format_count += 1;
+                next_num_format_id += 1;
+                next_num_format_id - 1
+            };
+            let fill_id = match style.fill_xml() {
+                Some(fill) => {
+                    fills.push_str(&fill);
+                    fill_count += 1;
+                    fill_count - 1
+                }
+                None => {
+                    attributes.push_str(" applyFill=\"0\"");
+                    0
+                }
+            };
+            let border_id = match style.border_xml() {
+                Some(border) => {
+                    borders.push_str(&border);
+                    border_count += 1;
+                    border_count - 1
+                }
+                None => {
+                    attributes.push_str(" applyBorder=\"0\"");
+                    0
+                }
+            };
+
+            let mut children = String::new();
+            match style.alignment_xml() {
+                Some(alignment) => children.push_str(&alignment),
+                None => attributes.push_str(" applyAlignment=\"0\""),
+            }
+            if style.locked && !style.hidden {
+                attributes.push_str(" applyProtection=\"0\"");
+            } else {
+                let locked = if style.locked { "" } else { " locked=\"0\"" };
+                let hidden = if style.hidden { " hidden=\"1\"" } else { "" };
+                children.push_str(&format!("<protection{locked}{hidden}/>"));
+            }
+
+            let xf = format!("<xf numFmtId=\"{num_format_id}\" fontId=\"{font_id}\" fillId=\"{fill_id}\" borderId=\"{border_id}\"{attributes}");
+            if children.is_empty() {
+                style_xfs.push_str(&format!("{xf}/>"));
+            } else {
+                style_xfs.push_str(&format!("{xf}>{children}</xf>"));
+            }
+            cell_styles.push_str(&styles[index].xml(style_xf_count, index < added));
+            style_xf_ids.insert(index, style_xf_count);
+            style_xf_count += 1;
+        }
+
+        if !num_formats.is_empty() && !package::append_to_element(xml, "numFmts", &num_formats) {
+            // The number formats are the first element of the styles.
+            package::insert_after_start_tag(xml, "styleSheet", &format!("<numFmts count=\"0\">{num_formats}</numFmts>"));
+        }
+        for (tag, fragment, count) in [
+            ("numFmts", &num_formats, num_format_count),
+            ("fonts", &fonts, font_count),
+            ("fills", &fills, fill_count),
+            ("borders", &borders, border_count),
+            ("cellStyleXfs", &style_xfs, style_xf_count),
+            ("cellStyles", &cell_styles, cell_style_count),
+        ] {
+            if fragment.is_empty() {
+                continue;
+            }
+            if tag != "numFmts" && !package::append_to_element(xml, tag, fragment) {
+                return Err(XlsxError::Internal(format!("Missing {tag} element in xl/styles.xml")));
+            }
+            package::set_xml_attribute(xml, tag, "count", Some(&count.to_string()));
+        }
+
+        for (xf_index, style) in xf_styles {
+            set_cell_xf_attribute(xml, xf_index, "xfId", Some(&style_xf_ids[&style].to_string()));
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,308 @@
+//! A minimal writer for OLE compound files ([MS-CFB]), the container format
+//! used by encrypted Office documents.
+//!
+//! Only what is needed to write a new file is supported: version 3 files with
+//! 512 byte sectors, storages and streams. Timestamps and class ids are left
+//! empty.
+//!
+//! [MS-CFB]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cfb
+
+const SECTOR_SIZE: usize = 512;
+const MINI_SECTOR_SIZE: usize = 64;
+const MINI_STREAM_CUTOFF: usize = 4096;
+const DIR_ENTRY_SIZE: usize = 128;
+/// The number of FAT sector locations stored in the header.
+const HEADER_DIFAT_ENTRIES: usize = 109;
+
+const ENDOFCHAIN: u32 = 0xFFFF_FFFE;
+const FREESECT: u32 = 0xFFFF_FFFF;
+const FATSECT: u32 = 0xFFFF_FFFD;
+const DIFSECT: u32 = 0xFFFF_FFFC;
+const NOSTREAM: u32 = 0xFFFF_FFFF;
+
+const SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// A storage or stream in a compound file.
+pub(crate) enum Entry {
+    Storage { name: String, children: Vec<Entry> },
+    Stream { name: String, data: Vec<u8> },
+}
+
+impl Entry {
+    fn name(&self) -> &str {
+        match self {
+            Entry::Storage { name, .. } | Entry::Stream { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ObjectType {
+    Storage = 1,
+    Stream = 2,
+    Root = 5,
+}
+
+struct DirEntry {
+    name: Vec<u16>,
+    object_type: ObjectType,
+    black: bool,
+    left: u32,
+    right: u32,
+    child: u32,
+    start: u32,
+    size: u64,
+}
+
+/// Where the data of a stream is stored.
+enum Location {
+    Empty,
+    MiniSectors(u32),
+    Sectors,
+}
+
+/// Write a compound file with the given entries in its root storage.
+pub(crate) fn write(entries: &[Entry]) -> Vec<u8> {
+    let mut dir = vec![DirEntry {
+        name: "Root Entry".encode_utf16().collect(),
+        object_type: ObjectType::Root,
+        black: true,
+        left: NOSTREAM,
+        right: NOSTREAM,
+        child: NOSTREAM,
+        start: ENDOFCHAIN,
+        size: 0,
+    }];
+    let mut streams = vec![];
+    dir[0].child = add_children(&mut dir, &mut streams, entries);
+
+    // Small streams are stored in 64 byte mini sectors inside the mini
+    // stream, which is itself stored in normal sectors.
+    let mut mini_stream = vec![];
+    let mut mini_fat = vec![];
+    let mut locations = vec![];
+    for &(_, data) in &streams {
+        if data.is_empty() {
+            locations.push(Location::Empty);
+        } else if data.len() < MINI_STREAM_CUTOFF {
+            let start = (mini_stream.len() / MINI_SECTOR_SIZE) as u32;
+            let count = data.len().div_ceil(MINI_SECTOR_SIZE) as u32;
+            chain(&mut mini_fat, start, count);
+            mini_stream.extend_from_slice(data);
+            mini_stream.resize(mini_stream.len().next_multiple_of(MINI_SECTOR_SIZE), 0);
+            locations.push(Location::MiniSectors(start));
+        } else {
+            locations.push(Location::Sectors);
+        }
+    }
+
+    // Lay out the sectors: the large streams, the mini stream, the mini FAT,
+    // the directory and then the FAT and DIFAT.
+    let mut fat = vec![];
+    let mut sectors: Vec<&[u8]> = vec![];
+    for (location, &(id, data)) in locations.iter().zip(&streams) {
+        dir[id].size = data.len() as u64;
+        dir[id].start = match location {
+            Location::Empty => ENDOFCHAIN,
+            Location::MiniSectors(start) => *start,
+            Location::Sectors => add_region(&mut fat, &mut sectors, data),
+        };
+    }
+
+    dir[0].start = add_region(&mut fat, &mut sectors, &mini_stream);
+    dir[0].size = mini_stream.len() as u64;
+
+    let mini_fat_bytes = u32_bytes(&padded(mini_fat, SECTOR_SIZE / 4, FREESECT));
+    let mini_fat_start = add_region(&mut fat, &mut sectors, &mini_fat_bytes);
+    let mini_fat_count = mini_fat_bytes.len() / SECTOR_SIZE;
+
+    let dir_bytes = dir_bytes(&dir);
+    let dir_start = add_region(&mut fat, &mut sectors, &dir_bytes);
+
+    // The FAT has to include the sectors used by the FAT and DIFAT.
+    let data_count = fat.len();
+    let (mut fat_count, mut difat_count) = (0, 0);
+    loop {
+        let new_fat_count = (data_count + fat_count + difat_count).div_ceil(SECTOR_SIZE / 4);
+        let new_difat_count = new_fat_count
+            .saturating_sub(HEADER_DIFAT_ENTRIES)
+            .div_ceil(SECTOR_SIZE / 4 - 1);
+        if (new_fat_count, new_difat_count) == (fat_count, difat_count) {
+            break;
+        }
+        (fat_count, difat_count) = (new_fat_count, new_difat_count);
+    }
+    let fat_sectors: Vec<u32> = (data_count..data_count + fat_count).map(|n| n as u32).collect();
+    let difat_start = data_count + fat_count;
+    fat.extend(std::iter::repeat_n(FATSECT, fat_count));
+    fat.extend(std::iter::repeat_n(DIFSECT, difat_count));
+    let fat_bytes = u32_bytes(&padded(fat, SECTOR_SIZE / 4, FREESECT));
+
+    let mut difat = vec![];
+    for (i, locations) in fat_sectors
+        .get(HEADER_DIFAT_ENTRIES..)
+        .unwrap_or_default()
+        .chunks(SECTOR_SIZE / 4 - 1)
+        .enumerate()
+    {
+        let mut sector = padded(locations.to_vec(), SECTOR_SIZE / 4 - 1, FREESECT);
+        sector.push(if i + 1 < difat_count {
+            (difat_start + i + 1) as u32
+        } else {
+            ENDOFCHAIN
+        });
+        difat.extend(sector);
+    }
+
+    let mut header = Vec::with_capacity(SECTOR_SIZE);
+    header.extend_from_slice(&SIGNATURE);
+    header.extend_from_slice(&[0; 16]); // Class id.
+    header.extend_from_slice(&0x003Eu16.to_le_bytes()); // Minor version.
+    header.extend_from_slice(&0x0003u16.to_le_bytes()); // Major version.
+    header.extend_from_slice(&0xFFFEu16.to_le_bytes()); // Byte order.
+    header.extend_from_slice(&9u16.to_le_bytes()); // Sector shift.
+    header.extend_from_slice(&6u16.to_le_bytes()); // Mini sector shift.
+    header.extend_from_slice(&[0; 6]);
+    header.extend_from_slice(&0u32.to_le_bytes()); // Directory sectors, unused in version 3.
+    header.extend_from_slice(&(fat_count as u32).to_le_bytes());
+    header.extend_from_slice(&dir_start.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes()); // Transaction signature.
+    header.extend_from_slice(&(MINI_STREAM_CUTOFF as u32).to_le_bytes());
+    header.extend_from_slice(&mini_fat_start.to_le_bytes());
+    header.extend_from_slice(&(mini_fat_count as u32).to_le_bytes());
+    let first_difat = if difat_count > 0 { difat_start as u32 } else { ENDOFCHAIN };
+    header.extend_from_slice(&first_difat.to_le_bytes());
+    header.extend_from_slice(&(difat_count as u32).to_le_bytes());
+    let header_difat: Vec<u32> = fat_sectors.iter().copied().take(HEADER_DIFAT_ENTRIES).collect();
+    header.extend(u32_bytes(&padded(header_difat, HEADER_DIFAT_ENTRIES, FREESECT)));
+
+    let mut buf = Vec::with_capacity((1 + data_count + fat_count + difat_count) * SECTOR_SIZE);
+    buf.extend(header);
+    for sector in sectors {
+        buf.extend_from_slice(sector);
+        buf.resize(buf.len().next_multiple_of(SECTOR_SIZE), 0);
+    }
+    buf.extend(fat_bytes);
+    buf.extend(u32_bytes(&difat));
+    buf
+}
+
+/// Add the children of a storage to the directory and return the id of the
+/// root of their red-black tree.
+fn add_children<'a>(
+    dir: &mut Vec<DirEntry>,
+    streams: &mut Vec<(usize, &'a [u8])>,
+    entries: &'a [Entry],
+) -> u32 {
+    let mut ids = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let id = dir.len();
+        dir.push(DirEntry {
+            name: entry.name().encode_utf16().collect(),
+            object_type: ObjectType::Storage,
+            black: true,
+            left: NOSTREAM,
+            right: NOSTREAM,
+            child: NOSTREAM,
+            start: 0,
+            size: 0,
+        });
+        match entry {
+            Entry::Storage { children, .. } => {
+                dir[id].child = add_children(dir, streams, children);
+            }
+            Entry::Stream { data, .. } => {
+                dir[id].object_type = ObjectType::Stream;
+                streams.push((id, data));
+            }
+        }
+        ids.push(id);
+    }
+
+    // Siblings are ordered by name length and then by upper case name.
+    ids.sort_by_cached_key(|&id| {
+        let name = &dir[id].name;
+        let upper: Vec<u16> = String::from_utf16_lossy(name)
+            .to_uppercase()
+            .encode_utf16()
+            .collect();
+        (name.len(), upper)
+    });
+    let max_depth = ids.len().checked_ilog2().unwrap_or(0);
+    let perfect = (ids.len() + 1).is_power_of_two();
+    build_tree(dir, &ids, 0, max_depth, perfect)
+}
+
+/// Build a balanced tree from sorted siblings. The leaves of a tree that
+/// isn't perfect are colored red to keep the black height the same on all
+/// paths.
+fn build_tree(dir: &mut [DirEntry], ids: &[usize], depth: u32, max_depth: u32, perfect: bool) -> u32 {
+    if ids.is_empty() {
+        return NOSTREAM;
+    }
+    let mid = ids.len() / 2;
+    let id = ids[mid];
+    dir[id].left = build_tree(dir, &ids[..mid], depth + 1, max_depth, perfect);
+    dir[id].right = build_tree(dir, &ids[mid + 1..], depth + 1, max_depth, perfect);
+    dir[id].black = perfect || depth < max_depth;
+    id as u32
+}
+
+fn dir_bytes(dir: &[DirEntry]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(dir.len().next_multiple_of(4) * DIR_ENTRY_SIZE);
+    for entry in dir {
+        let mut name = [0u8; 64];
+        for (bytes, unit) in name.chunks_mut(2).zip(&entry.name) {
+            bytes.copy_from_slice(&unit.to_le_bytes());
+        }
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&(((entry.name.len() + 1) * 2) as u16).to_le_bytes());
+        buf.push(entry.object_type as u8);
+        buf.push(u8::from(entry.black));
+        buf.extend_from_slice(&entry.left.to_le_bytes());
+        buf.extend_from_slice(&entry.right.to_le_bytes());
+        buf.extend_from_slice(&entry.child.to_le_bytes());
+        buf.extend_from_slice(&[0; 16 + 4 + 8 + 8]); // Class id, state and timestamps.
+        let start = if entry.object_type == ObjectType::Storage { 0 } else { entry.start };
+        buf.extend_from_slice(&start.to_le_bytes());
+        buf.extend_from_slice(&entry.size.to_le_bytes());
+    }
+
+    // Unused entries.
+    while buf.len() % SECTOR_SIZE != 0 {
+        buf.extend_from_slice(&[0; 66]);
+        buf.extend_from_slice(&[0, 0]);
+        buf.extend_from_slice(&NOSTREAM.to_le_bytes());
+        buf.extend_from_slice(&NOSTREAM.to_le_bytes());
+        buf.extend_from_slice(&NOSTREAM.to_le_bytes());
+        buf.extend_from_slice(&[0; 16 + 4 + 8 + 8 + 4 + 8]);
+    }
+    buf
+}
+
+/// Add the sectors of a stream, or other region, and return its first
+/// sector.
+fn add_region<'a>(fat: &mut Vec<u32>, sectors: &mut Vec<&'a [u8]>, data: &'a [u8]) -> u32 {
+    if data.is_empty() {
+        return ENDOFCHAIN;
+    }
+    let start = fat.len() as u32;
+    let count = data.len().div_ceil(SECTOR_SIZE) as u32;
+    chain(fat, start, count);
+    sectors.extend(data.chunks(SECTOR_SIZE));
+    start
+}
+
+/// Add a chain of `count` consecutive sectors starting at `start` to a FAT.
+fn chain(fat: &mut Vec<u32>, start: u32, count: u32) {
+    fat.extend((start + 1..start + count).chain([ENDOFCHAIN]));
+}
+
+fn padded(mut values: Vec<u32>, multiple: usize, fill: u32) -> Vec<u32> {
+    values.resize(values.len().next_multiple_of(multiple), fill);
+    values
+}
+
+fn u32_bytes(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
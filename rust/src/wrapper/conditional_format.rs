@@ -31,7 +31,7 @@ impl ConditionalFormatBlank {
 
     #[wasm_bindgen(js_name = "setFormat", skip_jsdoc)]
     pub fn set_format(&mut self, format: &Format) {
-        self.inner = self.inner.clone().set_format(&*format.lock());
+        self.inner = self.inner.clone().set_format(format.to_format());
     }
 }
 
@@ -46,7 +46,7 @@ impl ConditionalFormatFormula {
 
     #[wasm_bindgen(js_name = "setFormat", skip_jsdoc)]
     pub fn set_format(&mut self, format: &Format) {
-        self.inner = self.inner.clone().set_format(&*format.lock());
+        self.inner = self.inner.clone().set_format(format.to_format());
     }
 
     #[wasm_bindgen(js_name = "setRule", skip_jsdoc)]
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use js_sys::{Array, Date, Object, Reflect};
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::WasmResult;
+
+#[wasm_bindgen]
+extern "C" {
+    /// An `Intl.DateTimeFormat` used to get the wall clock time of a date in
+    /// an IANA time zone.
+    #[wasm_bindgen(js_namespace = Intl, js_name = DateTimeFormat)]
+    #[derive(Clone, Debug)]
+    type ZoneFormat;
+
+    #[wasm_bindgen(catch, constructor, js_namespace = Intl, js_class = "DateTimeFormat")]
+    fn new(locales: &str, options: &Object) -> Result<ZoneFormat, JsValue>;
+
+    #[wasm_bindgen(method, js_name = formatToParts)]
+    fn format_to_parts(this: &ZoneFormat, date: &Date) -> Array;
+}
+
+#[derive(Clone, Debug)]
+enum DateMode {
+    Utc,
+    Local,
+    FixedOffset(i32),
+    TimeZone(ZoneFormat),
+}
+
+/// The `DateConversion` struct defines how JS `Date` values are converted to
+/// Excel dates.
+///
+/// A JS `Date` is a point in time while an Excel date is a wall clock date
+/// and time without a time zone. By default dates are converted using their
+/// UTC time, so a date created at local midnight in Tokyo is written as
+/// 15:00 on the previous day. Use {@link DateConversion.local} or
+/// {@link DateConversion.timeZone} to write the time as it is seen by the
+/// user instead.
+///
+/// The conversion is set with {@link Workbook#setDateConversion}.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct DateConversion {
+    mode: DateMode,
+    date_only: bool,
+}
+
+impl Default for DateConversion {
+    fn default() -> Self {
+        DateConversion {
+            mode: DateMode::Utc,
+            date_only: false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DateConversion {
+    /// Convert dates using their UTC time. This is the default.
+    ///
+    /// @returns {DateConversion} - The DateConversion object.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn utc() -> DateConversion {
+        DateConversion::default()
+    }
+
+    /// Convert dates using the local time zone of the host running the code,
+    /// such as the browser.
+    ///
+    /// @returns {DateConversion} - The DateConversion object.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn local() -> DateConversion {
+        DateConversion {
+            mode: DateMode::Local,
+            ..DateConversion::default()
+        }
+    }
+
+    /// Convert dates using a fixed offset from UTC.
+    ///
+    /// @param {number} minutes - The offset from UTC in minutes, for example
+    ///   `540` for UTC+09:00 or `-300` for UTC-05:00.
+    /// @returns {DateConversion} - The DateConversion object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The offset is more than 24 hours.
+    #[wasm_bindgen(js_name = "fixedOffset", skip_jsdoc)]
+    pub fn fixed_offset(minutes: i32) -> WasmResult<DateConversion> {
+        if minutes.abs() >= 24 * 60 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "UTC offset must be less than 24 hours: {minutes} minutes"
+            ))));
+        }
+        Ok(DateConversion {
+            mode: DateMode::FixedOffset(minutes),
+            ..DateConversion::default()
+        })
+    }
+
+    /// Convert dates using an IANA time zone such as `Asia/Tokyo` or
+    /// `America/New_York`.
+    ///
+    /// Daylight saving time is taken into account. The time zone data comes
+    /// from the `Intl` support of the JS host.
+    ///
+    /// @param {string} name - The IANA time zone name.
+    /// @returns {DateConversion} - The DateConversion object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The time zone isn't known by the
+    ///   host.
+    #[wasm_bindgen(js_name = "timeZone", skip_jsdoc)]
+    pub fn time_zone(name: &str) -> WasmResult<DateConversion> {
+        let options = Object::new();
+        let set = |key: &str, value: &str| {
+            Reflect::set(&options, &key.into(), &value.into()).unwrap();
+        };
+        set("timeZone", name);
+        set("hourCycle", "h23");
+        for key in ["year", "month", "day", "hour", "minute", "second"] {
+            set(key, "numeric");
+        }
+
+        let format = ZoneFormat::new("en-US", &options).map_err(|_| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Unknown time zone: {name}"
+            )))
+        })?;
+        Ok(DateConversion {
+            mode: DateMode::TimeZone(format),
+            ..DateConversion::default()
+        })
+    }
+
+    /// Drop the time part of converted dates.
+    ///
+    /// This is useful for values from date pickers, which are usually midnight
+    /// in the user's time zone, when combined with a time zone conversion.
+    ///
+    /// @param {boolean} enable - Turn the property on/off. It is off by default.
+    /// @returns {DateConversion} - The DateConversion object.
+    #[wasm_bindgen(js_name = "setDateOnly", skip_jsdoc)]
+    pub fn set_date_only(&self, enable: bool) -> DateConversion {
+        DateConversion {
+            date_only: enable,
+            ..self.clone()
+        }
+    }
+}
+
+impl DateConversion {
+    /// Convert a JS `Date` to a wall clock date and time.
+    pub(crate) fn to_naive(&self, date: &Date) -> Option<NaiveDateTime> {
+        let timestamp_ms = date.get_time();
+        if !timestamp_ms.is_finite() {
+            return None;
+        }
+        let timestamp_ms = timestamp_ms as i64;
+        let utc = DateTime::<Utc>::from_timestamp(
+            timestamp_ms.div_euclid(1000),
+            (timestamp_ms.rem_euclid(1000) * 1_000_000) as u32,
+        )?
+        .naive_utc();
+
+        let datetime = match &self.mode {
+            DateMode::Utc => utc,
+            // `getTimezoneOffset()` is UTC minus local time.
+            DateMode::Local => utc - Duration::minutes(date.get_timezone_offset() as i64),
+            DateMode::FixedOffset(minutes) => utc + Duration::minutes(*minutes as i64),
+            DateMode::TimeZone(format) => zone_datetime(format, date, utc)?,
+        };
+
+        if self.date_only {
+            Some(datetime.date().and_time(NaiveTime::MIN))
+        } else {
+            Some(datetime)
+        }
+    }
+}
+
+/// Get the wall clock time of a date in the time zone of `format`.
+fn zone_datetime(format: &ZoneFormat, date: &Date, utc: NaiveDateTime) -> Option<NaiveDateTime> {
+    let mut fields = [0u32; 6];
+    for part in format.format_to_parts(date).iter() {
+        let part_type = Reflect::get(&part, &"type".into()).ok()?.as_string()?;
+        let index = match part_type.as_str() {
+            "year" => 0,
+            "month" => 1,
+            "day" => 2,
+            "hour" => 3,
+            "minute" => 4,
+            "second" => 5,
+            _ => continue,
+        };
+        let value = Reflect::get(&part, &"value".into()).ok()?.as_string()?;
+        fields[index] = value.parse().ok()?;
+    }
+
+    let [year, month, day, hour, minute, second] = fields;
+    NaiveDate::from_ymd_opt(year as i32, month, day)?
+        .and_hms_opt(hour, minute, second)
+        .map(|datetime| datetime + Duration::milliseconds(utc.and_utc().timestamp_subsec_millis() as i64))
+}
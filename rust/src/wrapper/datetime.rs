@@ -17,6 +17,17 @@ impl xlsx::IntoExcelDateTime for ExcelDateTime {
     }
 }
 
+/// An Excel serial date/time that has already been adjusted to the date epoch
+/// of the workbook it is written to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ExcelSerialDateTime(pub(crate) f64);
+
+impl xlsx::IntoExcelDateTime for ExcelSerialDateTime {
+    fn to_excel_serial_date(&self) -> f64 {
+        self.0
+    }
+}
+
 #[wasm_bindgen]
 impl ExcelDateTime {
     // Create a `ExcelDateTime` instance from a string reference.
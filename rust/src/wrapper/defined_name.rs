@@ -0,0 +1,220 @@
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{package, workbook_settings::WorkbookSettings, worksheet::SheetId, WasmResult};
+
+/// The `DefinedName` struct describes a name created with
+/// {@link Workbook#defineName} or {@link Worksheet#defineName}.
+///
+/// It is returned by {@link Workbook#definedNames}.
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct DefinedName {
+    pub(crate) name: String,
+    pub(crate) sheet_name: Option<String>,
+    pub(crate) formula: String,
+}
+
+#[wasm_bindgen]
+impl DefinedName {
+    /// The name, without any sheet prefix.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The worksheet the name is local to, or `undefined` for a global
+    /// (workbook scope) name.
+    #[wasm_bindgen(getter, js_name = "sheetName")]
+    pub fn sheet_name(&self) -> Option<String> {
+        self.sheet_name.clone()
+    }
+
+    /// The formula, value or range that the name refers to.
+    #[wasm_bindgen(getter)]
+    pub fn formula(&self) -> String {
+        self.formula.clone()
+    }
+}
+
+/// A name defined in the workbook.
+///
+/// Local names refer to their worksheet by id rather than by name, so that
+/// they stay local to it when it is renamed or moved. `rust_xlsxwriter` binds
+/// local names to a sheet name when they are defined, so only global names
+/// are passed to it and local names are added to the package when the
+/// workbook is saved.
+#[derive(Clone, Debug)]
+pub(crate) struct NameDefinition {
+    pub(crate) name: String,
+    pub(crate) sheet_id: Option<SheetId>,
+    pub(crate) formula: String,
+}
+
+/// Split a `Sheet1!Name` or `'Sheet 1'!Name` style name into its sheet and
+/// local name parts.
+fn split_local_name(name: &str) -> (Option<String>, &str) {
+    match name.rsplit_once('!') {
+        Some((sheet_name, local_name)) => {
+            let sheet_name = sheet_name
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .map(|s| s.replace("''", "'"))
+                .unwrap_or_else(|| sheet_name.to_string());
+            (Some(sheet_name), local_name)
+        }
+        None => (None, name),
+    }
+}
+
+/// Check that a name isn't already defined in its scope. Excel allows the
+/// same name in different scopes but not in the same one.
+fn check_unique(settings: &WorkbookSettings, name: &str, sheet_id: Option<SheetId>) -> WasmResult<()> {
+    let is_duplicate = settings
+        .defined_names
+        .iter()
+        .any(|defined_name| defined_name.sheet_id == sheet_id && defined_name.name.eq_ignore_ascii_case(name));
+    if is_duplicate {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+            "Defined name '{name}' is already in use"
+        ))));
+    }
+    Ok(())
+}
+
+/// Define a name in the workbook and record it so that it can be listed.
+///
+/// Names with a `Sheet!` prefix are local to that worksheet, which must be in
+/// the workbook.
+pub(crate) fn define_name(
+    workbook: &mut xlsx::Workbook,
+    settings: &mut WorkbookSettings,
+    name: &str,
+    formula: &str,
+) -> WasmResult<()> {
+    let (sheet_name, local_name) = split_local_name(name);
+    let Some(sheet_name) = sheet_name else {
+        check_unique(settings, name, None)?;
+        workbook.define_name(name, formula)?;
+        settings.defined_names.push(NameDefinition {
+            name: name.to_string(),
+            sheet_id: None,
+            formula: formula.to_string(),
+        });
+        return Ok(());
+    };
+
+    let index = workbook
+        .worksheets()
+        .iter()
+        .position(|sheet| sheet.name() == sheet_name)
+        .ok_or_else(|| XlsxError::Xlsx(xlsx::XlsxError::UnknownWorksheetNameOrIndex(sheet_name.clone())))?;
+    define_local_name(settings, settings.sheet_ids[index], local_name, formula)
+}
+
+/// Define a name that is local to a worksheet.
+pub(crate) fn define_local_name(
+    settings: &mut WorkbookSettings,
+    sheet_id: SheetId,
+    name: &str,
+    formula: &str,
+) -> WasmResult<()> {
+    if name.starts_with('!') {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+            "Name contains a character that isn't allowed by Excel: '!'".to_string(),
+        )));
+    }
+    xlsx::utility::check_name(name)?;
+    check_unique(settings, name, Some(sheet_id))?;
+    settings.defined_names.push(NameDefinition {
+        name: name.to_string(),
+        sheet_id: Some(sheet_id),
+        formula: formula.to_string(),
+    });
+    Ok(())
+}
+
+/// Get the defined names with the current names of the worksheets that the
+/// local names belong to.
+pub(crate) fn defined_names(workbook: &mut xlsx::Workbook, settings: &WorkbookSettings) -> Vec<DefinedName> {
+    settings
+        .defined_names
+        .iter()
+        .map(|definition| DefinedName {
+            name: definition.name.clone(),
+            sheet_name: definition.sheet_id.and_then(|id| {
+                let index = settings.sheet_index(id).ok()?;
+                Some(workbook.worksheet_from_index(index).ok()?.name())
+            }),
+            formula: definition.formula.clone(),
+        })
+        .collect()
+}
+
+/// Add the local defined names to the `definedNames` element of the
+/// workbook, with the current index of their worksheet.
+pub(crate) fn update_workbook_xml(settings: &WorkbookSettings, xml: &mut String) {
+    let mut names = String::new();
+    for definition in &settings.defined_names {
+        let Some(index) = definition.sheet_id.and_then(|id| settings.sheet_index(id).ok()) else {
+            continue;
+        };
+        let formula = definition.formula.strip_prefix('=').unwrap_or(&definition.formula);
+        names.push_str(&format!(
+            r#"<definedName name="{}" localSheetId="{index}">{}</definedName>"#,
+            package::escape_xml(&definition.name),
+            package::escape_xml(formula)
+        ));
+    }
+    if names.is_empty() || package::append_to_element(xml, "definedNames", &names) {
+        return;
+    }
+    let names = format!("<definedNames>{names}</definedNames>");
+    if !package::insert_after_element(xml, "sheets", &names) {
+        package::insert_before_end_tag(xml, "workbook", &names);
+    }
+}
+
+/// Build an absolute range reference, such as `='Sheet 1'!$A$1:$B$5`, for a
+/// defined name.
+pub(crate) fn range_formula(
+    sheet_name: &str,
+    first_row: xlsx::RowNum,
+    first_col: xlsx::ColNum,
+    last_row: xlsx::RowNum,
+    last_col: xlsx::ColNum,
+) -> WasmResult<String> {
+    if first_row > last_row || first_col > last_col {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnOrderError));
+    }
+    let range = if first_row == last_row && first_col == last_col {
+        format!(
+            "{}!{}",
+            xlsx::utility::quote_sheet_name(sheet_name),
+            xlsx::utility::row_col_to_cell_absolute(first_row, first_col)
+        )
+    } else {
+        xlsx::utility::worksheet_range_absolute(sheet_name, first_row, first_col, last_row, last_col)
+    };
+    Ok(format!("={range}"))
+}
+
+/// Check if a formula refers to a worksheet with a `Sheet1!` or `'Sheet 1'!`
+/// style reference. Like Excel, sheet names are compared case insensitively.
+pub(crate) fn refers_to_sheet(formula: &str, sheet_name: &str) -> bool {
+    let formula = formula.to_lowercase();
+    let sheet_name = sheet_name.to_lowercase();
+    let quoted = format!("'{}'!", sheet_name.replace('\'', "''"));
+    let unquoted = format!("{sheet_name}!");
+
+    formula.contains(&quoted)
+        || formula.match_indices(&unquoted).any(|(index, _)| {
+            // Skip references to sheets whose name ends with the name.
+            !formula[..index]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '\''))
+        })
+}
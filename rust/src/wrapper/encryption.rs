@@ -0,0 +1,321 @@
+//! Password encryption of xlsx files with ECMA-376 agile encryption
+//! ([MS-OFFCRYPTO]), using AES-256 and SHA-512 like Excel.
+//!
+//! The encrypted package is stored in an OLE compound file along with the
+//! encryption parameters and the data space streams that tell Excel how the
+//! package was transformed.
+//!
+//! [MS-OFFCRYPTO]: https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-offcrypto
+
+use aes::Aes256;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use cbc::cipher::{block_padding::NoPadding, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use rust_xlsxwriter as xlsx;
+use sha2::{Digest, Sha512};
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    compound_file::{self, Entry},
+    WasmResult,
+};
+
+const SALT_SIZE: usize = 16;
+const BLOCK_SIZE: usize = 16;
+const KEY_SIZE: usize = 32;
+const HASH_SIZE: usize = 64;
+const SEGMENT_SIZE: usize = 4096;
+const DEFAULT_SPIN_COUNT: u32 = 100_000;
+/// The largest spin count allowed by MS-OFFCRYPTO.
+const MAX_SPIN_COUNT: u32 = 10_000_000;
+
+// Block keys used to derive the keys and IVs of the encrypted values.
+const VERIFIER_HASH_INPUT_BLOCK_KEY: [u8; 8] = [0xFE, 0xA7, 0xD2, 0x76, 0x3B, 0x4B, 0x9E, 0x79];
+const VERIFIER_HASH_VALUE_BLOCK_KEY: [u8; 8] = [0xD7, 0xAA, 0x0F, 0x6D, 0x30, 0x61, 0x34, 0x4E];
+const KEY_VALUE_BLOCK_KEY: [u8; 8] = [0x14, 0x6E, 0x0B, 0xE7, 0xAB, 0xAC, 0xD0, 0xD6];
+const HMAC_KEY_BLOCK_KEY: [u8; 8] = [0x5F, 0xB2, 0xAD, 0x01, 0x0C, 0xB9, 0xE1, 0xF6];
+const HMAC_VALUE_BLOCK_KEY: [u8; 8] = [0xA0, 0x67, 0x7F, 0x02, 0xB2, 0x2C, 0x84, 0x33];
+
+const PASSWORD_KEY_ENCRYPTOR: &str = "http://schemas.microsoft.com/office/2006/keyEncryptor/password";
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace = crypto, js_name = getRandomValues)]
+    fn get_random_values(buf: &mut [u8]) -> Result<JsValue, JsValue>;
+}
+
+/// The `EncryptionOptions` struct defines how a workbook is encrypted by
+/// {@link Workbook#saveToBufferEncrypted}.
+///
+/// TODO: example omitted
+#[derive(Clone, Copy, Debug)]
+#[wasm_bindgen]
+pub struct EncryptionOptions {
+    spin_count: u32,
+}
+
+impl Default for EncryptionOptions {
+    fn default() -> Self {
+        EncryptionOptions {
+            spin_count: DEFAULT_SPIN_COUNT,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl EncryptionOptions {
+    /// Create a new `EncryptionOptions` with the same settings as Excel.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EncryptionOptions {
+        EncryptionOptions::default()
+    }
+
+    /// Set the number of times the password is hashed to create the
+    /// encryption key.
+    ///
+    /// More iterations make the password slower to guess but also make saving,
+    /// and opening, the file slower.
+    ///
+    /// @param {number} spin_count - The number of iterations. The default, and
+    ///   the value used by Excel, is 100000.
+    /// @returns {EncryptionOptions} - The EncryptionOptions object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The spin count is larger than
+    ///   10,000,000, the limit that Excel accepts.
+    #[wasm_bindgen(js_name = "setSpinCount", skip_jsdoc)]
+    pub fn set_spin_count(&self, spin_count: u32) -> WasmResult<EncryptionOptions> {
+        if spin_count > MAX_SPIN_COUNT {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Spin count must be at most {MAX_SPIN_COUNT}"
+            ))));
+        }
+        Ok(EncryptionOptions { spin_count })
+    }
+}
+
+/// Encrypt an xlsx package with a password and return the compound file that
+/// Excel opens with a password prompt.
+pub(crate) fn encrypt(package: &[u8], password: &str, options: &EncryptionOptions) -> WasmResult<Vec<u8>> {
+    // Excel limits passwords to 255 characters.
+    if password.is_empty() || password.encode_utf16().count() > 255 {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+            "Password must be between 1 and 255 characters".to_string(),
+        )));
+    }
+
+    let key_data_salt: [u8; SALT_SIZE] = random_bytes()?;
+    let password_salt: [u8; SALT_SIZE] = random_bytes()?;
+    let secret_key: [u8; KEY_SIZE] = random_bytes()?;
+    let verifier: [u8; SALT_SIZE] = random_bytes()?;
+    let hmac_key: [u8; HASH_SIZE] = random_bytes()?;
+
+    let encrypted_package = encrypt_package(package, &secret_key, &key_data_salt);
+
+    let mut hmac = Hmac::<Sha512>::new_from_slice(&hmac_key).expect("HMAC accepts keys of any length");
+    hmac.update(&encrypted_package);
+    let hmac_value = hmac.finalize().into_bytes();
+    let encrypted_hmac_key = aes_cbc(
+        &secret_key,
+        &block_iv(&key_data_salt, &HMAC_KEY_BLOCK_KEY),
+        &hmac_key,
+    );
+    let encrypted_hmac_value = aes_cbc(
+        &secret_key,
+        &block_iv(&key_data_salt, &HMAC_VALUE_BLOCK_KEY),
+        &hmac_value,
+    );
+
+    let password_hash = password_hash(password, &password_salt, options.spin_count);
+    let encrypt_with_password = |block_key: &[u8], data: &[u8]| {
+        aes_cbc(&password_key(&password_hash, block_key), &password_salt, data)
+    };
+    let encrypted_verifier_hash_input = encrypt_with_password(&VERIFIER_HASH_INPUT_BLOCK_KEY, &verifier);
+    let encrypted_verifier_hash_value =
+        encrypt_with_password(&VERIFIER_HASH_VALUE_BLOCK_KEY, &Sha512::digest(verifier));
+    let encrypted_key_value = encrypt_with_password(&KEY_VALUE_BLOCK_KEY, &secret_key);
+
+    let cipher_params = format!(
+        r#"saltSize="{SALT_SIZE}" blockSize="{BLOCK_SIZE}" keyBits="{}" hashSize="{HASH_SIZE}" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512""#,
+        KEY_SIZE * 8
+    );
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n\
+         <encryption xmlns=\"http://schemas.microsoft.com/office/2006/encryption\" \
+         xmlns:p=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\">\
+         <keyData {cipher_params} saltValue=\"{}\"/>\
+         <dataIntegrity encryptedHmacKey=\"{}\" encryptedHmacValue=\"{}\"/>\
+         <keyEncryptors><keyEncryptor uri=\"{PASSWORD_KEY_ENCRYPTOR}\">\
+         <p:encryptedKey spinCount=\"{}\" {cipher_params} saltValue=\"{}\" \
+         encryptedVerifierHashInput=\"{}\" encryptedVerifierHashValue=\"{}\" \
+         encryptedKeyValue=\"{}\"/>\
+         </keyEncryptor></keyEncryptors></encryption>",
+        BASE64.encode(key_data_salt),
+        BASE64.encode(encrypted_hmac_key),
+        BASE64.encode(encrypted_hmac_value),
+        options.spin_count,
+        BASE64.encode(password_salt),
+        BASE64.encode(encrypted_verifier_hash_input),
+        BASE64.encode(encrypted_verifier_hash_value),
+        BASE64.encode(encrypted_key_value),
+    );
+
+    // Agile encryption is version 4.4 with the reserved flag 0x40.
+    let mut encryption_info = vec![];
+    encryption_info.extend_from_slice(&4u16.to_le_bytes());
+    encryption_info.extend_from_slice(&4u16.to_le_bytes());
+    encryption_info.extend_from_slice(&0x40u32.to_le_bytes());
+    encryption_info.extend_from_slice(xml.as_bytes());
+
+    Ok(compound_file::write(&[
+        data_spaces(),
+        stream("EncryptionInfo", encryption_info),
+        stream("EncryptedPackage", encrypted_package),
+    ]))
+}
+
+/// Encrypt the package in 4096 byte segments, each with its own IV, after the
+/// 8 byte package size.
+fn encrypt_package(package: &[u8], secret_key: &[u8], key_data_salt: &[u8]) -> Vec<u8> {
+    let mut encrypted = Vec::with_capacity(8 + package.len().next_multiple_of(BLOCK_SIZE));
+    encrypted.extend_from_slice(&(package.len() as u64).to_le_bytes());
+    for (i, segment) in package.chunks(SEGMENT_SIZE).enumerate() {
+        let iv = block_iv(key_data_salt, &(i as u32).to_le_bytes());
+        encrypted.extend(aes_cbc(secret_key, &iv, segment));
+    }
+    encrypted
+}
+
+/// Hash the password with the salt, `spin_count` times.
+fn password_hash(password: &str, salt: &[u8], spin_count: u32) -> Vec<u8> {
+    let password: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut hash = Sha512::new().chain_update(salt).chain_update(password).finalize();
+    for i in 0..spin_count {
+        hash = Sha512::new()
+            .chain_update(i.to_le_bytes())
+            .chain_update(hash)
+            .finalize();
+    }
+    hash.to_vec()
+}
+
+/// Derive a key from the password hash for one of the encrypted values.
+fn password_key(password_hash: &[u8], block_key: &[u8]) -> Vec<u8> {
+    let hash = Sha512::new()
+        .chain_update(password_hash)
+        .chain_update(block_key)
+        .finalize();
+    hash[..KEY_SIZE].to_vec()
+}
+
+fn block_iv(salt: &[u8], block_key: &[u8]) -> Vec<u8> {
+    let hash = Sha512::new().chain_update(salt).chain_update(block_key).finalize();
+    hash[..BLOCK_SIZE].to_vec()
+}
+
+/// Encrypt data with AES-256 CBC, padding it with zeros to the block size.
+fn aes_cbc(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut buf = data.to_vec();
+    buf.resize(data.len().next_multiple_of(BLOCK_SIZE), 0);
+    let len = buf.len();
+    cbc::Encryptor::<Aes256>::new_from_slices(key, iv)
+        .expect("the key is 32 bytes and the IV is 16 bytes")
+        .encrypt_padded_mut::<NoPadding>(&mut buf, len)
+        .expect("the data is padded to a multiple of the block size");
+    buf
+}
+
+fn random_bytes<const N: usize>() -> WasmResult<[u8; N]> {
+    let mut buf = [0; N];
+    get_random_values(&mut buf).map_err(|e| XlsxError::Internal(format!("{e:?}")))?;
+    Ok(buf)
+}
+
+fn stream(name: &str, data: Vec<u8>) -> Entry {
+    Entry::Stream {
+        name: name.to_string(),
+        data,
+    }
+}
+
+fn storage(name: &str, children: Vec<Entry>) -> Entry {
+    Entry::Storage {
+        name: name.to_string(),
+        children,
+    }
+}
+
+/// The `\x06DataSpaces` storage that describes the encryption transform
+/// applied to the `EncryptedPackage` stream.
+fn data_spaces() -> Entry {
+    let mut version = vec![];
+    push_unicode(&mut version, "Microsoft.Container.DataSpaces");
+    push_versions(&mut version, 3);
+
+    let mut map_entry = vec![];
+    map_entry.extend_from_slice(&1u32.to_le_bytes()); // Reference component count.
+    map_entry.extend_from_slice(&0u32.to_le_bytes()); // Stream reference.
+    push_unicode(&mut map_entry, "EncryptedPackage");
+    push_unicode(&mut map_entry, "StrongEncryptionDataSpace");
+    let mut data_space_map = vec![];
+    data_space_map.extend_from_slice(&8u32.to_le_bytes()); // Header length.
+    data_space_map.extend_from_slice(&1u32.to_le_bytes()); // Entry count.
+    data_space_map.extend_from_slice(&(map_entry.len() as u32 + 4).to_le_bytes());
+    data_space_map.extend(map_entry);
+
+    let mut definition = vec![];
+    definition.extend_from_slice(&8u32.to_le_bytes()); // Header length.
+    definition.extend_from_slice(&1u32.to_le_bytes()); // Transform count.
+    push_unicode(&mut definition, "StrongEncryptionTransform");
+
+    let mut transform_id = vec![];
+    push_unicode(&mut transform_id, "{FF9A3F03-56EF-4613-BDD5-5A41C1D07246}");
+    let mut primary = vec![];
+    primary.extend_from_slice(&(transform_id.len() as u32 + 8).to_le_bytes());
+    primary.extend_from_slice(&1u32.to_le_bytes()); // Transform type.
+    primary.extend(transform_id);
+    push_unicode(&mut primary, "Microsoft.Container.EncryptionTransform");
+    push_versions(&mut primary, 3);
+    primary.extend_from_slice(&0u32.to_le_bytes()); // Encryption name.
+    primary.extend_from_slice(&0u32.to_le_bytes()); // Encryption block size.
+    primary.extend_from_slice(&0u32.to_le_bytes()); // Cipher mode.
+    primary.extend_from_slice(&4u32.to_le_bytes()); // Reserved.
+
+    storage(
+        "\u{6}DataSpaces",
+        vec![
+            stream("Version", version),
+            stream("DataSpaceMap", data_space_map),
+            storage(
+                "DataSpaceInfo",
+                vec![stream("StrongEncryptionDataSpace", definition)],
+            ),
+            storage(
+                "TransformInfo",
+                vec![storage(
+                    "StrongEncryptionTransform",
+                    vec![stream("\u{6}Primary", primary)],
+                )],
+            ),
+        ],
+    )
+}
+
+/// Append a length prefixed UTF-16 string, padded to a multiple of 4 bytes.
+fn push_unicode(buf: &mut Vec<u8>, s: &str) {
+    let bytes: Vec<u8> = s.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&bytes);
+    buf.resize(buf.len().next_multiple_of(4), 0);
+}
+
+/// Append `count` 1.0 versions (major and minor u16 pairs).
+fn push_versions(buf: &mut Vec<u8>, count: usize) {
+    for _ in 0..count {
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+    }
+}
@@ -2,11 +2,20 @@
 
 use js_sys::wasm_bindgen;
 use rust_xlsxwriter as xlsx;
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 
 use crate::error::XlsxError;
 
-use super::{datetime::ExcelDateTime, formula::Formula, rich_string::RichString, url::Url, utils};
+use super::{
+    datetime::ExcelSerialDateTime,
+    formula::Formula,
+    rich_string::RichString,
+    url::Url,
+    utils,
+    workbook_settings::WriteSettings,
+    write_policy::{BigIntPolicy, NonFinitePolicy, NullPolicy, WritePolicy},
+    WasmResult,
+};
 
 // We only export the ExcelData type since ExcelDataArray and ExcelDataMatrix are used for
 #[wasm_bindgen(typescript_custom_section)]
@@ -15,7 +24,7 @@ const EXCEL_DATA: &'static str = r#"
  *  Data type that can be written to Excel's cells.
  *  You can write data to cells via {@link Worksheet#write}.
  */
-export type ExcelData = undefined | string | number | boolean | Date | Formula | Url | RichString;
+export type ExcelData = undefined | null | string | number | bigint | boolean | Date | Formula | Url | RichString;
 
 type ExcelDataArray = ExcelData[];
 
@@ -34,39 +43,49 @@ extern "C" {
     pub type JsExcelDataMatrix;
 }
 
-impl TryInto<ExcelData> for &JsExcelData {
-    type Error = XlsxError;
-
-    fn try_into(self) -> Result<ExcelData, Self::Error> {
-        let jsvalue = JsValue::from(self);
-        jsvalue.try_into()
+impl ExcelData {
+    /// Convert a JS value to `ExcelData` using the conversion settings of the
+    /// workbook it is written to.
+    pub(crate) fn from_js(data: &JsExcelData, settings: &WriteSettings) -> WasmResult<ExcelData> {
+        ExcelData::from_jsvalue(JsValue::from(data), settings)
     }
-}
-
-impl TryInto<Vec<ExcelData>> for &JsExcelDataArray {
-    type Error = XlsxError;
 
-    fn try_into(self) -> Result<Vec<ExcelData>, Self::Error> {
-        if !self.is_array() {
-            let js_type = self.js_typeof().as_string().unwrap();
+    /// Convert a JS array to a vector of `ExcelData`.
+    pub(crate) fn vec_from_js(
+        data: &JsExcelDataArray,
+        settings: &WriteSettings,
+    ) -> WasmResult<Vec<ExcelData>> {
+        if !data.is_array() {
+            let js_type = data.js_typeof().as_string().unwrap();
             return Err(XlsxError::Type(format!(
                 "Expected an array but found {}",
                 js_type
             )));
         }
+        jsvalue_to_vec(JsValue::from(data), settings)
+    }
+
+    /// Convert a JS array of arrays to a matrix of `ExcelData`.
+    pub(crate) fn matrix_from_js(
+        data: &JsExcelDataMatrix,
+        settings: &WriteSettings,
+    ) -> WasmResult<Vec<Vec<ExcelData>>> {
+        if !data.is_array() {
+            return Err(XlsxError::Type("Expected an array".to_string()));
+        }
 
-        let array = js_sys::Array::from(self);
+        let array = js_sys::Array::from(data);
         let mut vec = Vec::with_capacity(array.length() as usize);
         for i in 0..array.length() {
-            let jsvalue = array.get(i);
-            let excel_data: ExcelData = jsvalue.try_into()?;
-            vec.push(excel_data);
+            let js_array = array.get(i);
+            let excel_data_array: Vec<ExcelData> = jsvalue_to_vec(js_array, settings)?;
+            vec.push(excel_data_array);
         }
         Ok(vec)
     }
 }
 
-fn jsvalue_to_vec(jsvalue: JsValue) -> Result<Vec<ExcelData>, XlsxError> {
+fn jsvalue_to_vec(jsvalue: JsValue, settings: &WriteSettings) -> WasmResult<Vec<ExcelData>> {
     if !jsvalue.is_array() {
         return Err(XlsxError::Type("Expected an array".to_string()));
     }
@@ -75,75 +94,122 @@ fn jsvalue_to_vec(jsvalue: JsValue) -> Result<Vec<ExcelData>, XlsxError> {
     let mut vec = Vec::with_capacity(js_array.length() as usize);
     for i in 0..js_array.length() {
         let jsvalue = js_array.get(i);
-        let excel_data: ExcelData = jsvalue.try_into()?;
+        let excel_data = ExcelData::from_jsvalue(jsvalue, settings)?;
         vec.push(excel_data);
     }
     Ok(vec)
 }
 
-impl TryInto<Vec<Vec<ExcelData>>> for &JsExcelDataMatrix {
-    type Error = XlsxError;
-
-    fn try_into(self) -> Result<Vec<Vec<ExcelData>>, Self::Error> {
-        if !self.is_array() {
-            return Err(XlsxError::Type("Expected an array".to_string()));
-        }
-
-        let array = js_sys::Array::from(self);
-        let mut vec = Vec::with_capacity(array.length() as usize);
-        for i in 0..array.length() {
-            let js_array = array.get(i);
-            let excel_data_array: Vec<ExcelData> = jsvalue_to_vec(js_array)?;
-            vec.push(excel_data_array);
-        }
-        Ok(vec)
-    }
-}
-
 pub enum ExcelData {
     None,
+    Blank,
+    Skip,
+    NumError,
     String(String),
     Number(f64),
     Bool(bool),
-    DateTime(chrono::NaiveDateTime),
-    ExcelDateTime(ExcelDateTime),
+    Checkbox(bool),
+    DateTime(ExcelSerialDateTime),
     Formula(Formula),
     Url(Url),
     RichString(RichString),
 }
 
-impl TryInto<ExcelData> for JsValue {
-    type Error = XlsxError;
+impl ExcelData {
+    fn null(policy: &WritePolicy) -> ExcelData {
+        match policy.null {
+            NullPolicy::EmptyString => ExcelData::None,
+            NullPolicy::Blank => ExcelData::Blank,
+            NullPolicy::Skip => ExcelData::Skip,
+        }
+    }
+
+    fn number(number: f64, policy: &WritePolicy) -> WasmResult<ExcelData> {
+        if number.is_finite() {
+            return Ok(ExcelData::Number(number));
+        }
+        match policy.non_finite {
+            NonFinitePolicy::Number => Ok(ExcelData::Number(number)),
+            NonFinitePolicy::NumError => Ok(ExcelData::NumError),
+            NonFinitePolicy::Blank => Ok(ExcelData::Blank),
+            NonFinitePolicy::Error => {
+                let number = if number.is_nan() {
+                    "NaN"
+                } else if number > 0.0 {
+                    "Infinity"
+                } else {
+                    "-Infinity"
+                };
+                Err(XlsxError::Type(format!("Cannot write {number} to a cell")))
+            }
+        }
+    }
+
+    fn bigint(bigint: js_sys::BigInt, policy: &WritePolicy) -> WasmResult<ExcelData> {
+        // Integers up to `Number.MAX_SAFE_INTEGER` are exact in a f64.
+        const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
 
-    fn try_into(self) -> Result<ExcelData, Self::Error> {
-        let js_type = self.js_typeof().as_string().unwrap();
+        let digits = String::from(
+            bigint
+                .to_string(10)
+                .map_err(|e| XlsxError::Internal(format!("{e:?}")))?,
+        );
+        match policy.bigint {
+            BigIntPolicy::NumberIfSafe => match digits.parse::<i128>() {
+                Ok(n) if n.abs() <= MAX_SAFE_INTEGER => Ok(ExcelData::Number(n as f64)),
+                _ => Ok(ExcelData::String(digits)),
+            },
+            BigIntPolicy::String => Ok(ExcelData::String(digits)),
+            BigIntPolicy::Error => Err(XlsxError::Type(format!(
+                "Cannot write bigint {digits} to a cell"
+            ))),
+        }
+    }
+
+    /// Render a boolean as a checkbox instead of as `TRUE`/`FALSE`.
+    pub(crate) fn into_checkbox(self) -> ExcelData {
+        match self {
+            ExcelData::Bool(b) => ExcelData::Checkbox(b),
+            data => data,
+        }
+    }
+
+    /// Render the booleans of a matrix as checkboxes.
+    pub(crate) fn matrix_into_checkbox(matrix: Vec<Vec<ExcelData>>) -> Vec<Vec<ExcelData>> {
+        matrix
+            .into_iter()
+            .map(|values| values.into_iter().map(ExcelData::into_checkbox).collect())
+            .collect()
+    }
+
+    fn from_jsvalue(value: JsValue, settings: &WriteSettings) -> WasmResult<ExcelData> {
+        let js_type = value.js_typeof().as_string().unwrap();
+        let policy = &settings.write_policy;
 
         // https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Operators/typeof#description
         match js_type.as_str() {
-            "undefined" => Ok(ExcelData::None),
-            "string" => Ok(ExcelData::String(self.as_string().unwrap())),
-            "number" => Ok(ExcelData::Number(self.as_f64().unwrap())),
-            "bigint" => {
-                todo!("BigInt is not supported yet")
-            }
-            "boolean" => Ok(ExcelData::Bool(self.as_bool().unwrap())),
+            "undefined" => Ok(ExcelData::null(policy)),
+            "string" => Ok(ExcelData::String(value.as_string().unwrap())),
+            "number" => ExcelData::number(value.as_f64().unwrap(), policy),
+            "bigint" => ExcelData::bigint(value.unchecked_into(), policy),
+            "boolean" => Ok(ExcelData::Bool(value.as_bool().unwrap())),
             "object" => {
-                // FIXME: what should we do when writing `null`?
-                if self.is_null() {
-                    Ok(ExcelData::None)
-                } else if utils::jsval_is_datetime(&self) {
-                    let dt = utils::datetime_of_jsval(self).unwrap();
-                    Ok(ExcelData::DateTime(dt))
-                } else if let Some(excel_dt) = utils::excel_datetime_of_jsval(&self) {
-                    Ok(ExcelData::ExcelDateTime(excel_dt.clone()))
-                } else if let Some(formula) = utils::formula_of_jsval(&self) {
+                if value.is_null() {
+                    Ok(ExcelData::null(policy))
+                } else if utils::jsval_is_datetime(&value) {
+                    let dt = utils::datetime_of_jsval(value, &settings.date_conversion)
+                        .ok_or(XlsxError::InvalidDate)?;
+                    Ok(ExcelData::DateTime(settings.date_serial(&dt)?))
+                } else if let Some(excel_dt) = utils::excel_datetime_of_jsval(&value) {
+                    Ok(ExcelData::DateTime(settings.date_serial(&excel_dt)?))
+                } else if let Some(formula) = utils::formula_of_jsval(&value) {
                     Ok(ExcelData::Formula(formula))
-                } else if let Some(url) = utils::url_of_jsval(&self) {
+                } else if let Some(url) = utils::url_of_jsval(&value) {
                     Ok(ExcelData::Url(url))
-                } else if let Some(rich_string) = utils::rich_string_of_jsval(&self) {
+                } else if let Some(rich_string) = utils::rich_string_of_jsval(&value) {
                     Ok(ExcelData::RichString(rich_string))
                 } else {
-                    let ctor = js_sys::Object::get_prototype_of(&self).constructor().name();
+                    let ctor = js_sys::Object::get_prototype_of(&value).constructor().name();
                     Err(XlsxError::Type(format!(
                         "Cannot write {} (instance of {}) to a cell",
                         js_type, ctor
@@ -167,22 +233,25 @@ impl xlsx::IntoExcelData for ExcelData {
     ) -> Result<&mut rust_xlsxwriter::Worksheet, rust_xlsxwriter::XlsxError> {
         match self {
             ExcelData::None => worksheet.write_string(row, col, ""),
+            // Blank cells without a format aren't written by Excel.
+            ExcelData::Blank => {
+                worksheet.clear_cell(row, col);
+                Ok(worksheet)
+            }
+            ExcelData::Skip => Ok(worksheet),
+            ExcelData::NumError => worksheet.write_formula(row, col, num_error()),
             ExcelData::String(s) => worksheet.write_string(row, col, &s),
             ExcelData::Number(n) => worksheet.write_number(row, col, n),
             ExcelData::Bool(b) => worksheet.write_boolean(row, col, b),
+            ExcelData::Checkbox(b) => worksheet.insert_checkbox(row, col, b),
             ExcelData::DateTime(dt) => worksheet.write_datetime(row, col, dt),
-            ExcelData::ExcelDateTime(excel_dt) => {
-                let dt = excel_dt.inner.lock().unwrap().clone();
-                worksheet.write_datetime(row, col, dt)
-            }
             ExcelData::RichString(rich_string) => {
-                let rich_string = rich_string.lock();
-                let rich_string: Vec<_> =
-                    rich_string.iter().map(|(f, s)| (f, s.as_str())).collect();
-                worksheet.write_rich_string(row, col, &rich_string)
+                let parts = rich_string.formats();
+                let parts: Vec<_> = parts.iter().map(|(f, s)| (f, s.as_str())).collect();
+                worksheet.write_rich_string(row, col, &parts)
             }
             ExcelData::Formula(f) => worksheet.write_formula(row, col, &*f.lock()),
-            ExcelData::Url(url) => worksheet.write_url(row, col, &*url.lock()),
+            ExcelData::Url(url) => worksheet.write_url(row, col, url.to_url()),
         }
     }
 
@@ -194,25 +263,29 @@ impl xlsx::IntoExcelData for ExcelData {
         format: &rust_xlsxwriter::Format,
     ) -> Result<&'a mut rust_xlsxwriter::Worksheet, rust_xlsxwriter::XlsxError> {
         match self {
-            ExcelData::None => worksheet.write_blank(row, col, format),
+            ExcelData::None | ExcelData::Blank => worksheet.write_blank(row, col, format),
+            ExcelData::Skip => Ok(worksheet),
+            ExcelData::NumError => worksheet.write_formula_with_format(row, col, num_error(), format),
             ExcelData::String(s) => worksheet.write_string_with_format(row, col, &s, format),
             ExcelData::Number(n) => worksheet.write_number_with_format(row, col, n, format),
             ExcelData::Bool(b) => worksheet.write_boolean_with_format(row, col, b, format),
+            ExcelData::Checkbox(b) => worksheet.insert_checkbox_with_format(row, col, b, format),
             ExcelData::DateTime(dt) => worksheet.write_datetime_with_format(row, col, dt, format),
-            ExcelData::ExcelDateTime(excel_dt) => {
-                let dt = excel_dt.inner.lock().unwrap().clone();
-                worksheet.write_datetime_with_format(row, col, dt, format)
-            }
             ExcelData::Formula(f) => {
                 worksheet.write_formula_with_format(row, col, &*f.lock(), format)
             }
             ExcelData::RichString(rich_string) => {
-                let rich_string = rich_string.lock();
-                let rich_string: Vec<_> =
-                    rich_string.iter().map(|(f, s)| (f, s.as_str())).collect();
-                worksheet.write_rich_string_with_format(row, col, &rich_string, format)
+                let parts = rich_string.formats();
+                let parts: Vec<_> = parts.iter().map(|(f, s)| (f, s.as_str())).collect();
+                worksheet.write_rich_string_with_format(row, col, &parts, format)
             }
-            ExcelData::Url(url) => worksheet.write_url_with_format(row, col, &*url.lock(), format),
+            ExcelData::Url(url) => worksheet.write_url_with_format(row, col, url.to_url(), format),
         }
     }
 }
+
+/// A formula that evaluates to the Excel `#NUM!` error, which is what Excel
+/// uses for numbers that it can't represent.
+pub(crate) fn num_error() -> xlsx::Formula {
+    xlsx::Formula::new("=#NUM!").set_result("#NUM!")
+}
@@ -1,9 +1,16 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use rust_xlsxwriter as xlsx;
 use wasm_bindgen::prelude::*;
 
-use super::color::Color;
+use super::{
+    cell_style::CellStyle,
+    color::Color,
+    format_object::{self, JsFormatObject},
+    format_properties::FormatProperties,
+    WasmResult,
+};
 
 /// The `Format` struct is used to define cell formatting for data in a
 /// worksheet.
@@ -173,7 +180,7 @@ use super::color::Color;
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct Format {
-    pub(crate) inner: Arc<Mutex<xlsx::Format>>,
+    pub(crate) inner: Arc<Mutex<FormatProperties>>,
 }
 
 macro_rules! impl_method {
@@ -196,14 +203,36 @@ impl Format {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Format {
         Format {
-            inner: Arc::new(Mutex::new(xlsx::Format::new())),
+            inner: Arc::new(Mutex::new(FormatProperties::default())),
         }
     }
 
-    pub(crate) fn lock(&self) -> MutexGuard<xlsx::Format> {
+    pub(crate) fn lock(&self) -> MutexGuard<FormatProperties> {
         self.inner.lock().unwrap()
     }
 
+    /// Build the `rust_xlsxwriter` format with the current properties.
+    pub(crate) fn to_format(&self) -> xlsx::Format {
+        self.lock().to_format()
+    }
+
+    /// Create a new Format object based on a built-in cell style.
+    ///
+    /// The format has the properties of the style, and can be changed like
+    /// any other format. Cells with the format are shown with the style in
+    /// the Excel "Cell Styles" gallery, see {@link Format#setCellStyle}.
+    ///
+    /// @param {CellStyle} style - A {@link CellStyle} enum value.
+    /// @return {Format} - The Format instance.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "fromCellStyle", skip_jsdoc)]
+    pub fn from_cell_style(style: CellStyle) -> Format {
+        Format {
+            inner: Arc::new(Mutex::new(style.format().set_cell_style(style.name()))),
+        }
+    }
+
     /// Clone a Format object.
     #[wasm_bindgen(js_name = "clone")]
     pub fn deep_clone(&self) -> Format {
@@ -213,6 +242,102 @@ impl Format {
         }
     }
 
+    /// Create a new Format object from a plain object of format properties.
+    ///
+    /// Each property of the object has the name of the Format setter it
+    /// corresponds to, for example `{ bold: true, fontSize: 12, numFormat:
+    /// "0.00" }` is the same as `new
+    /// Format().setBold().setFontSize(12).setNumFormat("0.00")`. The
+    /// properties are applied in the order of the object.
+    ///
+    /// Colors can be given as a {@link Color} or in a JSON compatible form,
+    /// see {@link FormatColor}, so the object can be stored as JSON.
+    ///
+    /// @param {FormatObject} object - The format properties.
+    /// @return {Format} - The Format instance.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The object has an unknown
+    ///   property, an invalid color or an out of range value.
+    /// - [`XlsxError::Type`] - A property has the wrong type.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "fromObject", skip_jsdoc)]
+    pub fn from_object(object: &JsFormatObject) -> WasmResult<Format> {
+        let format = format_object::format_of_object(object)?;
+        Ok(Format {
+            inner: Arc::new(Mutex::new(format)),
+        })
+    }
+
+    /// Get the properties of the Format as a plain object.
+    ///
+    /// Only the properties that differ from a new Format are included, and
+    /// colors are converted to a JSON compatible form, so the object can be
+    /// stored as JSON or sent to a web worker and turned back into an equal
+    /// Format with {@link Format.fromObject}.
+    ///
+    /// @return {FormatObject} - The format properties.
+    #[wasm_bindgen(js_name = "toObject", skip_jsdoc)]
+    pub fn to_object(&self) -> JsFormatObject {
+        format_object::object_of_format(&self.lock()).unchecked_into()
+    }
+
+    /// Check if two Format objects have the same properties.
+    ///
+    /// Formats with the same properties, including their cell style, are
+    /// written to the file as a single cell format, regardless of the order
+    /// their properties were set in.
+    ///
+    /// @param {Format} other - The Format to compare with.
+    /// @return {boolean} - `true` if the formats are equal.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn equals(&self, other: &Format) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner) || *self.lock() == *other.lock()
+    }
+
+    /// Get a hash of the Format properties.
+    ///
+    /// Equal formats, see {@link Format#equals}, have the same hash, so the
+    /// hash can be used as a key to deduplicate formats. The hash is the same
+    /// in every instance of the same version of the library, for example in
+    /// web workers.
+    ///
+    /// @return {string} - The hash as a hexadecimal string.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn hash(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.lock().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Merge the properties of another Format into the Format.
+    ///
+    /// The properties that `other` sets, the properties that differ from a
+    /// new Format, replace the properties of the Format. This allows formats
+    /// to be built in layers, for example a highlight on top of a base
+    /// style. The style and color of each border side are merged separately.
+    ///
+    /// Like the setters, `merge()` changes the Format in place, so use
+    /// {@link Format#clone} first to keep the original format.
+    ///
+    /// @param {Format} other - The Format whose properties are merged.
+    /// @param {FormatMergeMode} [mode] - How the properties are merged, see
+    ///   {@link FormatMergeMode}. The default is `Overlay`.
+    /// @return {Format} - The Format instance.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn merge(&self, other: &Format, mode: Option<FormatMergeMode>) -> Format {
+        let other = other.lock().clone();
+        let mut inner = self.lock();
+        *inner = mode.unwrap_or_default().merge(&inner, &other);
+        Format {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
     /// Set the Format alignment properties.
     ///
     /// This method is used to set the horizontal and vertical data alignment
@@ -679,6 +804,53 @@ impl Format {
     pub fn set_quote_prefix(&self) -> Format {
         impl_method!(self.set_quote_prefix());
     }
+
+    /// Base the Format on a cell style.
+    ///
+    /// Cells with the format are shown with the style in the Excel "Cell
+    /// Styles" gallery. The style is either a built-in style, such as `"Good"`
+    /// or `"Heading 1"`, or a style added with {@link Workbook#addCellStyle}.
+    /// The name is matched case-insensitively when the workbook is saved, and
+    /// a name that isn't a style of the workbook is ignored.
+    ///
+    /// The cell style doesn't change the other properties of the format. Use
+    /// {@link Format.fromCellStyle} to also copy the properties of a built-in
+    /// style. Formats that only differ in their cell style are written as
+    /// separate Excel formats, each based on its own style.
+    ///
+    /// @param {string} name - The name of the cell style.
+    /// @return {Format} - The Format instance.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "setCellStyle", skip_jsdoc)]
+    pub fn set_cell_style(&self, name: &str) -> Format {
+        impl_method!(self.set_cell_style(name));
+    }
+}
+
+/// The `FormatMergeMode` enum defines how the properties of a {@link Format}
+/// are merged into another format.
+///
+/// It is used with {@link Format#merge} and {@link Worksheet#applyFormatToRange}.
+#[derive(Debug, Clone, Copy, Default)]
+#[wasm_bindgen]
+pub enum FormatMergeMode {
+    /// The properties of the merged format replace the existing properties.
+    #[default]
+    Overlay,
+    /// The merged format only sets the properties that the existing format
+    /// doesn't set.
+    Underlay,
+}
+
+impl FormatMergeMode {
+    /// Merge the properties of `other` into `properties`.
+    pub(crate) fn merge(self, properties: &FormatProperties, other: &FormatProperties) -> FormatProperties {
+        match self {
+            FormatMergeMode::Overlay => properties.overlay(other),
+            FormatMergeMode::Underlay => other.overlay(properties),
+        }
+    }
 }
 
 /// The `FormatAlign` enum defines the vertical and horizontal alignment properties
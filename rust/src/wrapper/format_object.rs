@@ -0,0 +1,517 @@
+//! Conversion of a `Format` to and from a plain JS object.
+//!
+//! A `FormatObject` lists the properties of a format by the name of their
+//! `Format` setter, so that formats can be stored as JSON or sent between
+//! workers and rebuilt with a single call into wasm.
+
+use js_sys::{Array, Object, Reflect};
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    color::Color,
+    format::{FontScheme, FormatAlign, FormatBorder, FormatDiagonalBorder, FormatPattern, FormatScript, FormatUnderline},
+    format_properties::{BorderSide, FormatProperties},
+    utils, WasmResult,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const FORMAT_OBJECT: &'static str = r##"
+/**
+ * A color of a {@link FormatObject}: a {@link Color}, an RGB number such as
+ * `0xFF0000`, an Html style string such as `"#FF0000"`, a named color such as
+ * `"red"` or `"automatic"`, or a theme color such as `{ theme: 4, shade: 0 }`.
+ */
+export type FormatColor = Color | number | string | { theme: number; shade: number };
+
+/**
+ * The properties of a {@link Format} as a plain object. Each property has the
+ * name of the `Format` setter that it corresponds to.
+ *
+ * See {@link Format.fromObject} and {@link Format#toObject}.
+ */
+export interface FormatObject {
+  numFormat?: string;
+  numFormatIndex?: number;
+  hyperlink?: boolean;
+  fontName?: string;
+  fontSize?: number;
+  fontColor?: FormatColor;
+  fontFamily?: number;
+  fontCharset?: number;
+  fontScheme?: FontScheme;
+  bold?: boolean;
+  italic?: boolean;
+  underline?: FormatUnderline;
+  fontStrikethrough?: boolean;
+  fontScript?: FormatScript;
+  align?: FormatAlign;
+  verticalAlign?: FormatAlign;
+  textWrap?: boolean;
+  rotation?: number;
+  indent?: number;
+  shrink?: boolean;
+  readingDirection?: number;
+  border?: FormatBorder;
+  borderColor?: FormatColor;
+  borderTop?: FormatBorder;
+  borderTopColor?: FormatColor;
+  borderBottom?: FormatBorder;
+  borderBottomColor?: FormatColor;
+  borderLeft?: FormatBorder;
+  borderLeftColor?: FormatColor;
+  borderRight?: FormatBorder;
+  borderRightColor?: FormatColor;
+  borderDiagonal?: FormatBorder;
+  borderDiagonalColor?: FormatColor;
+  borderDiagonalType?: FormatDiagonalBorder;
+  pattern?: FormatPattern;
+  foregroundColor?: FormatColor;
+  backgroundColor?: FormatColor;
+  locked?: boolean;
+  hidden?: boolean;
+  checkbox?: boolean;
+  quotePrefix?: boolean;
+  cellStyle?: string;
+}
+"##;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "FormatObject")]
+    pub type JsFormatObject;
+}
+
+const ALIGNS: &[FormatAlign] = &[
+    FormatAlign::General,
+    FormatAlign::Left,
+    FormatAlign::Center,
+    FormatAlign::Right,
+    FormatAlign::Fill,
+    FormatAlign::Justify,
+    FormatAlign::CenterAcross,
+    FormatAlign::Distributed,
+    FormatAlign::Top,
+    FormatAlign::Bottom,
+    FormatAlign::VerticalCenter,
+    FormatAlign::VerticalJustify,
+    FormatAlign::VerticalDistributed,
+];
+
+const BORDERS: &[FormatBorder] = &[
+    FormatBorder::None,
+    FormatBorder::Thin,
+    FormatBorder::Medium,
+    FormatBorder::Dashed,
+    FormatBorder::Dotted,
+    FormatBorder::Thick,
+    FormatBorder::Double,
+    FormatBorder::Hair,
+    FormatBorder::MediumDashed,
+    FormatBorder::DashDot,
+    FormatBorder::MediumDashDot,
+    FormatBorder::DashDotDot,
+    FormatBorder::MediumDashDotDot,
+    FormatBorder::SlantDashDot,
+];
+
+const DIAGONAL_BORDERS: &[FormatDiagonalBorder] = &[
+    FormatDiagonalBorder::None,
+    FormatDiagonalBorder::BorderUp,
+    FormatDiagonalBorder::BorderDown,
+    FormatDiagonalBorder::BorderUpDown,
+];
+
+const PATTERNS: &[FormatPattern] = &[
+    FormatPattern::None,
+    FormatPattern::Solid,
+    FormatPattern::MediumGray,
+    FormatPattern::DarkGray,
+    FormatPattern::LightGray,
+    FormatPattern::DarkHorizontal,
+    FormatPattern::DarkVertical,
+    FormatPattern::DarkDown,
+    FormatPattern::DarkUp,
+    FormatPattern::DarkGrid,
+    FormatPattern::DarkTrellis,
+    FormatPattern::LightHorizontal,
+    FormatPattern::LightVertical,
+    FormatPattern::LightDown,
+    FormatPattern::LightUp,
+    FormatPattern::LightGrid,
+    FormatPattern::LightTrellis,
+    FormatPattern::Gray125,
+    FormatPattern::Gray0625,
+];
+
+const UNDERLINES: &[FormatUnderline] = &[
+    FormatUnderline::None,
+    FormatUnderline::Single,
+    FormatUnderline::Double,
+    FormatUnderline::SingleAccounting,
+    FormatUnderline::DoubleAccounting,
+];
+
+const SCRIPTS: &[FormatScript] = &[FormatScript::None, FormatScript::Superscript, FormatScript::Subscript];
+
+const FONT_SCHEMES: &[FontScheme] = &[FontScheme::Body, FontScheme::Headings, FontScheme::None];
+
+const NAMED_COLORS: &[(&str, xlsx::Color)] = &[
+    ("automatic", xlsx::Color::Automatic),
+    ("black", xlsx::Color::Black),
+    ("blue", xlsx::Color::Blue),
+    ("brown", xlsx::Color::Brown),
+    ("cyan", xlsx::Color::Cyan),
+    ("gray", xlsx::Color::Gray),
+    ("green", xlsx::Color::Green),
+    ("lime", xlsx::Color::Lime),
+    ("magenta", xlsx::Color::Magenta),
+    ("navy", xlsx::Color::Navy),
+    ("orange", xlsx::Color::Orange),
+    ("pink", xlsx::Color::Pink),
+    ("purple", xlsx::Color::Purple),
+    ("red", xlsx::Color::Red),
+    ("silver", xlsx::Color::Silver),
+    ("white", xlsx::Color::White),
+    ("yellow", xlsx::Color::Yellow),
+];
+
+/// Build a format from the properties of a `FormatObject`.
+///
+/// The properties are applied in the order of the object, like a chain of
+/// setters, so a later property overrides what an earlier one implies, such
+/// as the font color of `hyperlink`.
+pub(crate) fn format_of_object(object: &JsValue) -> WasmResult<FormatProperties> {
+    if !object.is_object() || Array::is_array(object) {
+        return Err(XlsxError::Type("Expected a format object".to_string()));
+    }
+
+    let mut format = FormatProperties::default();
+    for entry in Object::entries(object.unchecked_ref()).iter() {
+        let entry = Array::from(&entry);
+        let key = entry.get(0).as_string().unwrap_or_default();
+        let value = entry.get(1);
+        if value.is_undefined() {
+            continue;
+        }
+        let value = PropertyValue { key: &key, value: &value };
+
+        format = match key.as_str() {
+            "numFormat" => format.set_num_format(value.string()?),
+            "numFormatIndex" => format.set_num_format_index(value.integer()?),
+            "hyperlink" if value.flag()? => format.set_hyperlink(),
+            "fontName" => format.set_font_name(value.string()?),
+            "fontSize" => format.set_font_size(value.number()?),
+            "fontColor" => format.set_font_color(value.color()?),
+            "fontFamily" => format.set_font_family(value.integer()?),
+            "fontCharset" => format.set_font_charset(value.integer()?),
+            "fontScheme" => format.set_font_scheme(value.variant(FONT_SCHEMES)?.into()),
+            "bold" if value.flag()? => format.set_bold(),
+            "italic" if value.flag()? => format.set_italic(),
+            "underline" => format.set_underline(value.variant(UNDERLINES)?.into()),
+            "fontStrikethrough" if value.flag()? => format.set_font_strikethrough(),
+            "fontScript" => format.set_font_script(value.variant(SCRIPTS)?.into()),
+            "align" | "verticalAlign" => format.set_align(value.variant(ALIGNS)?.into()),
+            "textWrap" if value.flag()? => format.set_text_wrap(),
+            "rotation" => format.set_rotation(value.integer()?),
+            "indent" => format.set_indent(value.integer()?),
+            "shrink" if value.flag()? => format.set_shrink(),
+            "readingDirection" => format.set_reading_direction(value.integer()?),
+            "border" => format.set_border(value.variant(BORDERS)?.into()),
+            "borderColor" => format.set_border_color(value.color()?),
+            "borderTop" => format.set_border_top(value.variant(BORDERS)?.into()),
+            "borderTopColor" => format.set_border_top_color(value.color()?),
+            "borderBottom" => format.set_border_bottom(value.variant(BORDERS)?.into()),
+            "borderBottomColor" => format.set_border_bottom_color(value.color()?),
+            "borderLeft" => format.set_border_left(value.variant(BORDERS)?.into()),
+            "borderLeftColor" => format.set_border_left_color(value.color()?),
+            "borderRight" => format.set_border_right(value.variant(BORDERS)?.into()),
+            "borderRightColor" => format.set_border_right_color(value.color()?),
+            "borderDiagonal" => format.set_border_diagonal(value.variant(BORDERS)?.into()),
+            "borderDiagonalColor" => format.set_border_diagonal_color(value.color()?),
+            "borderDiagonalType" => format.set_border_diagonal_type(value.variant(DIAGONAL_BORDERS)?.into()),
+            "pattern" => format.set_pattern(value.variant(PATTERNS)?.into()),
+            "foregroundColor" => format.set_foreground_color(value.color()?),
+            "backgroundColor" => format.set_background_color(value.color()?),
+            "locked" if value.flag()? => format.set_locked(),
+            "locked" => format.set_unlocked(),
+            "hidden" if value.flag()? => format.set_hidden(),
+            "checkbox" if value.flag()? => format.set_checkbox(),
+            "quotePrefix" if value.flag()? => format.set_quote_prefix(),
+            "cellStyle" => format.set_cell_style(value.string()?),
+            // A flag that is turned off keeps the default.
+            "hyperlink" | "bold" | "italic" | "fontStrikethrough" | "textWrap" | "shrink" | "hidden" | "checkbox"
+            | "quotePrefix" => format,
+            _ => {
+                return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                    "Unknown format property '{key}'"
+                ))))
+            }
+        };
+    }
+    Ok(format)
+}
+
+/// Get the `FormatObject` of a format. Only the properties that differ from
+/// the default format are set, leaving out the font properties that
+/// `hyperlink` implies.
+pub(crate) fn object_of_format(properties: &FormatProperties) -> JsValue {
+    let default = if properties.hyperlink {
+        FormatProperties::default().set_hyperlink()
+    } else {
+        FormatProperties::default()
+    };
+    let object = Object::new();
+    let set = |key: &str, value: JsValue| {
+        Reflect::set(&object, &key.into(), &value).unwrap();
+    };
+
+    if properties.num_format != default.num_format {
+        set("numFormat", properties.num_format.as_str().into());
+    }
+    if properties.num_format_index != default.num_format_index {
+        set("numFormatIndex", properties.num_format_index.into());
+    }
+    // The hyperlink style implies font properties, so it comes before them.
+    if properties.hyperlink {
+        set("hyperlink", true.into());
+    }
+    if properties.font_name != default.font_name {
+        set("fontName", properties.font_name.as_str().into());
+    }
+    if properties.font_size != default.font_size {
+        set("fontSize", properties.font_size.into());
+    }
+    if properties.font_color != default.font_color {
+        set("fontColor", color_to_js(properties.font_color));
+    }
+    if properties.font_family != default.font_family {
+        set("fontFamily", properties.font_family.into());
+    }
+    if properties.font_charset != default.font_charset {
+        set("fontCharset", properties.font_charset.into());
+    }
+    if properties.font_scheme != default.font_scheme {
+        set("fontScheme", variant_index(FONT_SCHEMES, properties.font_scheme));
+    }
+    if properties.bold {
+        set("bold", true.into());
+    }
+    if properties.italic {
+        set("italic", true.into());
+    }
+    if properties.underline != default.underline {
+        set("underline", variant_index(UNDERLINES, properties.underline));
+    }
+    if properties.strikethrough {
+        set("fontStrikethrough", true.into());
+    }
+    if properties.script != default.script {
+        set("fontScript", variant_index(SCRIPTS, properties.script));
+    }
+
+    if properties.align != default.align {
+        set("align", variant_index(ALIGNS, properties.align));
+    }
+    if properties.vertical_align != default.vertical_align {
+        set("verticalAlign", variant_index(ALIGNS, properties.vertical_align));
+    }
+    if properties.text_wrap {
+        set("textWrap", true.into());
+    }
+    if properties.rotation != default.rotation {
+        set("rotation", properties.rotation_angle().into());
+    }
+    if properties.indent != default.indent {
+        set("indent", properties.indent.into());
+    }
+    if properties.shrink {
+        set("shrink", true.into());
+    }
+    if properties.reading_direction != default.reading_direction {
+        set("readingDirection", properties.reading_direction.into());
+    }
+
+    let sides = [
+        ("borderTop", properties.border_top),
+        ("borderBottom", properties.border_bottom),
+        ("borderLeft", properties.border_left),
+        ("borderRight", properties.border_right),
+        ("borderDiagonal", properties.border_diagonal),
+    ];
+    for (key, BorderSide { style, color }) in sides {
+        if style != xlsx::FormatBorder::None {
+            set(key, variant_index(BORDERS, style));
+        }
+        if color != xlsx::Color::Default {
+            set(&format!("{key}Color"), color_to_js(color));
+        }
+    }
+    if properties.border_diagonal_type != default.border_diagonal_type {
+        set(
+            "borderDiagonalType",
+            variant_index(DIAGONAL_BORDERS, properties.border_diagonal_type),
+        );
+    }
+
+    if properties.pattern != default.pattern {
+        set("pattern", variant_index(PATTERNS, properties.pattern));
+    }
+    if properties.foreground_color != default.foreground_color {
+        set("foregroundColor", color_to_js(properties.foreground_color));
+    }
+    if properties.background_color != default.background_color {
+        set("backgroundColor", color_to_js(properties.background_color));
+    }
+
+    if !properties.locked {
+        set("locked", false.into());
+    }
+    if properties.hidden {
+        set("hidden", true.into());
+    }
+    if properties.checkbox {
+        set("checkbox", true.into());
+    }
+    if properties.quote_prefix {
+        set("quotePrefix", true.into());
+    }
+    if let Some(cell_style) = &properties.cell_style {
+        set("cellStyle", cell_style.as_str().into());
+    }
+    object.into()
+}
+
+/// A property of a `FormatObject`, with the conversions to the types of the
+/// `Format` setters.
+struct PropertyValue<'a> {
+    key: &'a str,
+    value: &'a JsValue,
+}
+
+impl PropertyValue<'_> {
+    fn type_error(&self, expected: &str) -> XlsxError {
+        XlsxError::Type(format!(
+            "Expected {expected} for format property '{}'",
+            self.key
+        ))
+    }
+
+    fn flag(&self) -> WasmResult<bool> {
+        self.value.as_bool().ok_or_else(|| self.type_error("a boolean"))
+    }
+
+    fn string(&self) -> WasmResult<String> {
+        self.value.as_string().ok_or_else(|| self.type_error("a string"))
+    }
+
+    fn number(&self) -> WasmResult<f64> {
+        self.value
+            .as_f64()
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| self.type_error("a number"))
+    }
+
+    fn integer<T: TryFrom<i64>>(&self) -> WasmResult<T> {
+        let number = self.number()?;
+        if number.fract() != 0.0 {
+            return Err(self.type_error("an integer"));
+        }
+        T::try_from(number as i64).map_err(|_| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Format property '{}' is out of range: {number}",
+                self.key
+            )))
+        })
+    }
+
+    fn variant<T: Copy>(&self, variants: &[T]) -> WasmResult<T> {
+        let index: usize = self.integer().map_err(|_| self.type_error("an enum value"))?;
+        variants.get(index).copied().ok_or_else(|| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Format property '{}' has an unknown enum value: {index}",
+                self.key
+            )))
+        })
+    }
+
+    fn color(&self) -> WasmResult<xlsx::Color> {
+        let value = self.value;
+        if let Some(rgb) = value.as_f64() {
+            return match rgb {
+                rgb if rgb.fract() == 0.0 && (0.0..=f64::from(0xFFFFFF)).contains(&rgb) => {
+                    Ok(xlsx::Color::RGB(rgb as u32))
+                }
+                _ => Err(self.color_error()),
+            };
+        }
+        if let Some(name) = value.as_string() {
+            let hex = name.strip_prefix('#').unwrap_or(&name);
+            if hex.len() == 6 {
+                if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                    return Ok(xlsx::Color::RGB(rgb));
+                }
+            }
+            return NAMED_COLORS
+                .iter()
+                .find(|(color_name, _)| color_name.eq_ignore_ascii_case(&name))
+                .map(|(_, color)| *color)
+                .ok_or_else(|| self.color_error());
+        }
+        if let Ok(color) = utils::generic_of_jsval::<Color>(value, "Color") {
+            return Ok(color.inner);
+        }
+        let field = |name: &str| {
+            Reflect::get(value, &name.into())
+                .ok()
+                .and_then(|field| field.as_f64())
+                .filter(|number| number.fract() == 0.0 && (0.0..=255.0).contains(number))
+        };
+        // Excel has 10 theme colors with 6 shades each.
+        match (field("theme"), field("shade")) {
+            (Some(theme), Some(shade)) if theme <= 9.0 && shade <= 5.0 => {
+                Ok(xlsx::Color::Theme(theme as u8, shade as u8))
+            }
+            _ => Err(self.color_error()),
+        }
+    }
+
+    fn color_error(&self) -> XlsxError {
+        XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+            "Format property '{}' isn't a valid color",
+            self.key
+        )))
+    }
+}
+
+/// Get the JS enum value of a `rust_xlsxwriter` enum.
+fn variant_index<T, X>(variants: &[T], value: X) -> JsValue
+where
+    T: Copy,
+    X: From<T> + PartialEq,
+{
+    let index = variants
+        .iter()
+        .position(|&variant| X::from(variant) == value)
+        .unwrap_or_default();
+    JsValue::from(index as u32)
+}
+
+/// Get the `FormatColor` of a color, as a JSON compatible value.
+fn color_to_js(color: xlsx::Color) -> JsValue {
+    match color {
+        xlsx::Color::RGB(rgb) => format!("#{rgb:06X}").into(),
+        xlsx::Color::Theme(theme, shade) => {
+            let object = Object::new();
+            Reflect::set(&object, &"theme".into(), &theme.into()).unwrap();
+            Reflect::set(&object, &"shade".into(), &shade.into()).unwrap();
+            object.into()
+        }
+        color => NAMED_COLORS
+            .iter()
+            .find(|(_, named)| *named == color)
+            .map_or(JsValue::UNDEFINED, |(name, _)| JsValue::from(*name)),
+    }
+}
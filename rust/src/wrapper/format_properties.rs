@@ -0,0 +1,901 @@
+//! The properties of a format.
+//!
+//! `rust_xlsxwriter` doesn't provide getters for the properties of a `Format`
+//! so the wrapper keeps its own record of them, which is updated by the
+//! setters like the `rust_xlsxwriter` setters update a `Format`, and builds
+//! the `rust_xlsxwriter` format from it when it's needed.
+
+use std::hash::{Hash, Hasher};
+
+use rust_xlsxwriter as xlsx;
+
+use super::{
+    package,
+    theme::{ThemeColors, OFFICE_THEME_COLORS},
+};
+
+/// The style and color of one side of a cell border.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct BorderSide {
+    pub(crate) style: xlsx::FormatBorder,
+    pub(crate) color: xlsx::Color,
+}
+
+/// The properties of a format, in terms of the Excel "Format Cells" dialog.
+#[derive(Clone, Debug)]
+pub(crate) struct FormatProperties {
+    pub(crate) num_format: String,
+    pub(crate) num_format_index: u16,
+
+    pub(crate) font_name: String,
+    pub(crate) font_size: f64,
+    pub(crate) font_color: xlsx::Color,
+    pub(crate) font_family: u8,
+    pub(crate) font_charset: u8,
+    pub(crate) font_scheme: xlsx::FontScheme,
+    pub(crate) bold: bool,
+    pub(crate) italic: bool,
+    pub(crate) underline: xlsx::FormatUnderline,
+    pub(crate) strikethrough: bool,
+    pub(crate) script: xlsx::FormatScript,
+    pub(crate) hyperlink: bool,
+
+    pub(crate) align: xlsx::FormatAlign,
+    pub(crate) vertical_align: xlsx::FormatAlign,
+    pub(crate) text_wrap: bool,
+    pub(crate) rotation: i16,
+    pub(crate) indent: u8,
+    pub(crate) shrink: bool,
+    pub(crate) reading_direction: u8,
+
+    pub(crate) border_top: BorderSide,
+    pub(crate) border_bottom: BorderSide,
+    pub(crate) border_left: BorderSide,
+    pub(crate) border_right: BorderSide,
+    pub(crate) border_diagonal: BorderSide,
+    pub(crate) border_diagonal_type: xlsx::FormatDiagonalBorder,
+
+    pub(crate) pattern: xlsx::FormatPattern,
+    pub(crate) foreground_color: xlsx::Color,
+    pub(crate) background_color: xlsx::Color,
+
+    pub(crate) locked: bool,
+    pub(crate) hidden: bool,
+    pub(crate) checkbox: bool,
+    pub(crate) quote_prefix: bool,
+
+    /// The name of the cell style that the format is based on. It doesn't
+    /// change how cells look, only the style that Excel shows for them.
+    pub(crate) cell_style: Option<String>,
+}
+
+impl Default for FormatProperties {
+    /// The properties of `Format::new()`.
+    fn default() -> Self {
+        FormatProperties {
+            num_format: String::new(),
+            num_format_index: 0,
+            font_name: "Calibri".to_string(),
+            font_size: 11.0,
+            font_color: xlsx::Color::Default,
+            font_family: 2,
+            font_charset: 0,
+            font_scheme: xlsx::FontScheme::Body,
+            bold: false,
+            italic: false,
+            underline: xlsx::FormatUnderline::None,
+            strikethrough: false,
+            script: xlsx::FormatScript::None,
+            hyperlink: false,
+            align: xlsx::FormatAlign::General,
+            vertical_align: xlsx::FormatAlign::General,
+            text_wrap: false,
+            rotation: 0,
+            indent: 0,
+            shrink: false,
+            reading_direction: 0,
+            border_top: BorderSide::default(),
+            border_bottom: BorderSide::default(),
+            border_left: BorderSide::default(),
+            border_right: BorderSide::default(),
+            border_diagonal: BorderSide::default(),
+            border_diagonal_type: xlsx::FormatDiagonalBorder::None,
+            pattern: xlsx::FormatPattern::None,
+            foreground_color: xlsx::Color::Default,
+            background_color: xlsx::Color::Default,
+            locked: true,
+            hidden: false,
+            checkbox: false,
+            quote_prefix: false,
+            cell_style: None,
+        }
+    }
+}
+
+// The font size is the only property that isn't `Eq`. Like
+// `rust_xlsxwriter`, which stores it as a string, sizes are compared by their
+// representation, so `Eq` and `Hash` agree for `-0.0` and `NaN`.
+impl PartialEq for FormatProperties {
+    fn eq(&self, other: &FormatProperties) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for FormatProperties {}
+
+impl Hash for FormatProperties {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl FormatProperties {
+    /// Get the properties as a value that is compared and hashed in place of
+    /// the format, with the font size as its bits.
+    fn key(&self) -> impl Eq + Hash + '_ {
+        let FormatProperties {
+            num_format,
+            num_format_index,
+            font_name,
+            font_size,
+            font_color,
+            font_family,
+            font_charset,
+            font_scheme,
+            bold,
+            italic,
+            underline,
+            strikethrough,
+            script,
+            hyperlink,
+            align,
+            vertical_align,
+            text_wrap,
+            rotation,
+            indent,
+            shrink,
+            reading_direction,
+            border_top,
+            border_bottom,
+            border_left,
+            border_right,
+            border_diagonal,
+            border_diagonal_type,
+            pattern,
+            foreground_color,
+            background_color,
+            locked,
+            hidden,
+            checkbox,
+            quote_prefix,
+            cell_style,
+        } = self;
+        (
+            (num_format, num_format_index),
+            (font_name, font_size.to_bits(), font_color, font_family, font_charset, font_scheme),
+            (bold, italic, underline, strikethrough, script, hyperlink),
+            (align, vertical_align, text_wrap, rotation, indent, shrink, reading_direction),
+            (border_top, border_bottom, border_left, border_right, border_diagonal, border_diagonal_type),
+            (pattern, foreground_color, background_color),
+            (locked, hidden, checkbox, quote_prefix),
+            cell_style,
+        )
+    }
+}
+
+/// Define the setters of the style and color of a border side.
+macro_rules! border_side_setters {
+    ($($side:ident: $set_style:ident, $set_color:ident;)*) => {
+        $(
+            pub(crate) fn $set_style(mut self, style: xlsx::FormatBorder) -> FormatProperties {
+                self.$side.style = style;
+                self
+            }
+
+            pub(crate) fn $set_color(mut self, color: impl Into<xlsx::Color>) -> FormatProperties {
+                let color = color.into();
+                if is_valid_color(color) {
+                    self.$side.color = color;
+                }
+                self
+            }
+        )*
+    };
+}
+
+/// The setters have the names and the behavior of the `rust_xlsxwriter`
+/// `Format` setters, including the properties that they imply and the values
+/// that they ignore.
+impl FormatProperties {
+    pub(crate) fn set_num_format(mut self, num_format: impl Into<String>) -> FormatProperties {
+        self.num_format = num_format.into();
+        self
+    }
+
+    /// A built-in number format index takes precedence over the format
+    /// string when the format is written.
+    pub(crate) fn set_num_format_index(mut self, num_format_index: u8) -> FormatProperties {
+        self.num_format_index = u16::from(num_format_index);
+        self.num_format = String::new();
+        self
+    }
+
+    pub(crate) fn set_bold(mut self) -> FormatProperties {
+        self.bold = true;
+        self
+    }
+
+    pub(crate) fn set_italic(mut self) -> FormatProperties {
+        self.italic = true;
+        self
+    }
+
+    pub(crate) fn set_font_color(mut self, color: impl Into<xlsx::Color>) -> FormatProperties {
+        let color = color.into();
+        if is_valid_color(color) {
+            self.font_color = color;
+        }
+        self
+    }
+
+    /// Setting the font name implies a font that isn't a theme font.
+    pub(crate) fn set_font_name(mut self, font_name: impl Into<String>) -> FormatProperties {
+        self.font_name = font_name.into();
+        self.font_scheme = xlsx::FontScheme::None;
+        self
+    }
+
+    pub(crate) fn set_font_size(mut self, font_size: impl Into<f64>) -> FormatProperties {
+        self.font_size = font_size.into();
+        self
+    }
+
+    pub(crate) fn set_font_scheme(mut self, font_scheme: xlsx::FontScheme) -> FormatProperties {
+        self.font_scheme = font_scheme;
+        self
+    }
+
+    pub(crate) fn set_font_family(mut self, font_family: u8) -> FormatProperties {
+        self.font_family = font_family;
+        self
+    }
+
+    pub(crate) fn set_font_charset(mut self, font_charset: u8) -> FormatProperties {
+        self.font_charset = font_charset;
+        self
+    }
+
+    pub(crate) fn set_underline(mut self, underline: xlsx::FormatUnderline) -> FormatProperties {
+        self.underline = underline;
+        self
+    }
+
+    pub(crate) fn set_font_strikethrough(mut self) -> FormatProperties {
+        self.strikethrough = true;
+        self
+    }
+
+    pub(crate) fn set_font_script(mut self, script: xlsx::FormatScript) -> FormatProperties {
+        self.script = script;
+        self
+    }
+
+    /// Set the horizontal or the vertical alignment, depending on `align`.
+    /// The general alignment resets both.
+    pub(crate) fn set_align(mut self, align: xlsx::FormatAlign) -> FormatProperties {
+        match align {
+            xlsx::FormatAlign::General => {
+                self.align = align;
+                self.vertical_align = align;
+            }
+            xlsx::FormatAlign::Top
+            | xlsx::FormatAlign::Bottom
+            | xlsx::FormatAlign::VerticalCenter
+            | xlsx::FormatAlign::VerticalJustify
+            | xlsx::FormatAlign::VerticalDistributed => self.vertical_align = align,
+            _ => self.align = align,
+        }
+        self
+    }
+
+    pub(crate) fn set_text_wrap(mut self) -> FormatProperties {
+        self.text_wrap = true;
+        self
+    }
+
+    pub(crate) fn set_indent(mut self, indent: u8) -> FormatProperties {
+        self.indent = indent;
+        self
+    }
+
+    /// Angles outside of -90 to 90, other than 270 for vertical text, are
+    /// ignored.
+    pub(crate) fn set_rotation(mut self, rotation: i16) -> FormatProperties {
+        match rotation {
+            270 => self.rotation = 255,
+            -90..=-1 => self.rotation = 90 - rotation,
+            0..=90 => self.rotation = rotation,
+            _ => {}
+        }
+        self
+    }
+
+    pub(crate) fn set_reading_direction(mut self, reading_direction: u8) -> FormatProperties {
+        if reading_direction <= 2 {
+            self.reading_direction = reading_direction;
+        }
+        self
+    }
+
+    pub(crate) fn set_shrink(mut self) -> FormatProperties {
+        self.shrink = true;
+        self
+    }
+
+    pub(crate) fn set_pattern(mut self, pattern: xlsx::FormatPattern) -> FormatProperties {
+        self.pattern = pattern;
+        self
+    }
+
+    pub(crate) fn set_background_color(mut self, color: impl Into<xlsx::Color>) -> FormatProperties {
+        let color = color.into();
+        if is_valid_color(color) {
+            self.background_color = color;
+        }
+        self
+    }
+
+    pub(crate) fn set_foreground_color(mut self, color: impl Into<xlsx::Color>) -> FormatProperties {
+        let color = color.into();
+        if is_valid_color(color) {
+            self.foreground_color = color;
+        }
+        self
+    }
+
+    /// Set the style of the top, bottom, left and right borders.
+    pub(crate) fn set_border(self, style: xlsx::FormatBorder) -> FormatProperties {
+        self.set_border_top(style)
+            .set_border_bottom(style)
+            .set_border_left(style)
+            .set_border_right(style)
+    }
+
+    /// Set the color of the top, bottom, left and right borders.
+    pub(crate) fn set_border_color(self, color: impl Into<xlsx::Color>) -> FormatProperties {
+        let color = color.into();
+        self.set_border_top_color(color)
+            .set_border_bottom_color(color)
+            .set_border_left_color(color)
+            .set_border_right_color(color)
+    }
+
+    border_side_setters! {
+        border_top: set_border_top, set_border_top_color;
+        border_bottom: set_border_bottom, set_border_bottom_color;
+        border_left: set_border_left, set_border_left_color;
+        border_right: set_border_right, set_border_right_color;
+        border_diagonal: set_border_diagonal, set_border_diagonal_color;
+    }
+
+    pub(crate) fn set_border_diagonal_type(mut self, diagonal_type: xlsx::FormatDiagonalBorder) -> FormatProperties {
+        self.border_diagonal_type = diagonal_type;
+        self
+    }
+
+    /// The hyperlink style uses the theme hyperlink color and a single
+    /// underline, with a font that isn't a theme font.
+    pub(crate) fn set_hyperlink(mut self) -> FormatProperties {
+        self.hyperlink = true;
+        self.font_color = xlsx::Color::Theme(10, 0);
+        self.underline = xlsx::FormatUnderline::Single;
+        self.font_scheme = xlsx::FontScheme::None;
+        self
+    }
+
+    pub(crate) fn set_locked(mut self) -> FormatProperties {
+        self.locked = true;
+        self
+    }
+
+    pub(crate) fn set_unlocked(mut self) -> FormatProperties {
+        self.locked = false;
+        self
+    }
+
+    pub(crate) fn set_hidden(mut self) -> FormatProperties {
+        self.hidden = true;
+        self
+    }
+
+    pub(crate) fn set_checkbox(mut self) -> FormatProperties {
+        self.checkbox = true;
+        self
+    }
+
+    pub(crate) fn set_quote_prefix(mut self) -> FormatProperties {
+        self.quote_prefix = true;
+        self
+    }
+}
+
+impl FormatProperties {
+    /// Base the format on a cell style. `rust_xlsxwriter` doesn't support
+    /// cell styles, so the style is added when the workbook is saved.
+    pub(crate) fn set_cell_style(mut self, name: impl Into<String>) -> FormatProperties {
+        self.cell_style = Some(name.into());
+        self
+    }
+
+    /// Get the properties of `self` with the properties that `top` sets, the
+    /// properties that differ from the default, replacing them.
+    ///
+    /// The number format string and index are a single property, while the
+    /// style and color of each border side are separate properties.
+    pub(crate) fn overlay(&self, top: &FormatProperties) -> FormatProperties {
+        let default = FormatProperties::default();
+        let mut properties = self.clone();
+        if top.num_format != default.num_format || top.num_format_index != default.num_format_index {
+            properties.num_format.clone_from(&top.num_format);
+            properties.num_format_index = top.num_format_index;
+        }
+        macro_rules! overlay {
+            ($($field:ident $(.$side:ident)?),* $(,)?) => {
+                $(if top.$field$(.$side)? != default.$field$(.$side)? {
+                    properties.$field$(.$side)? = top.$field$(.$side)?.clone();
+                })*
+            };
+        }
+        overlay!(
+            font_name,
+            font_size,
+            font_color,
+            font_family,
+            font_charset,
+            font_scheme,
+            bold,
+            italic,
+            underline,
+            strikethrough,
+            script,
+            hyperlink,
+            align,
+            vertical_align,
+            text_wrap,
+            rotation,
+            indent,
+            shrink,
+            reading_direction,
+            border_top.style,
+            border_top.color,
+            border_bottom.style,
+            border_bottom.color,
+            border_left.style,
+            border_left.color,
+            border_right.style,
+            border_right.color,
+            border_diagonal.style,
+            border_diagonal.color,
+            border_diagonal_type,
+            pattern,
+            foreground_color,
+            background_color,
+            locked,
+            hidden,
+            checkbox,
+            quote_prefix,
+            cell_style,
+        );
+        properties
+    }
+
+    /// Get the rotation as the angle passed to `Format::set_rotation()`.
+    /// `rust_xlsxwriter` stores it like Excel, with negative angles as 91 to
+    /// 180 and vertical text as 255.
+    pub(crate) fn rotation_angle(&self) -> i16 {
+        match self.rotation {
+            255 => 270,
+            91..=180 => 90 - self.rotation,
+            rotation => rotation,
+        }
+    }
+
+    /// Build a `rust_xlsxwriter` format with the properties.
+    pub(crate) fn to_format(&self) -> xlsx::Format {
+        let default = FormatProperties::default();
+        let mut format = xlsx::Format::new();
+        // A built-in number format index takes precedence over the string.
+        if self.num_format_index != default.num_format_index {
+            format = format.set_num_format_index(u8::try_from(self.num_format_index).unwrap_or_default());
+        } else if self.num_format != default.num_format {
+            format = format.set_num_format(&self.num_format);
+        }
+
+        // The hyperlink style and the font name imply other font properties,
+        // so they are set first.
+        if self.hyperlink {
+            format = format.set_hyperlink();
+        }
+        if self.font_name != default.font_name {
+            format = format.set_font_name(&self.font_name);
+        }
+        // The hyperlink color is outside the range that `set_font_color()`
+        // accepts.
+        if !(self.hyperlink && self.font_color == xlsx::Color::Theme(10, 0)) {
+            format = format.set_font_color(self.font_color);
+        }
+        format = format
+            .set_font_size(self.font_size)
+            .set_font_family(self.font_family)
+            .set_font_charset(self.font_charset)
+            .set_font_scheme(self.font_scheme)
+            .set_underline(self.underline)
+            .set_font_script(self.script);
+        if self.bold {
+            format = format.set_bold();
+        }
+        if self.italic {
+            format = format.set_italic();
+        }
+        if self.strikethrough {
+            format = format.set_font_strikethrough();
+        }
+
+        format = format
+            .set_align(self.align)
+            .set_align(self.vertical_align)
+            .set_rotation(self.rotation_angle())
+            .set_indent(self.indent)
+            .set_reading_direction(self.reading_direction);
+        if self.text_wrap {
+            format = format.set_text_wrap();
+        }
+        if self.shrink {
+            format = format.set_shrink();
+        }
+
+        format = format
+            .set_border_top(self.border_top.style)
+            .set_border_top_color(self.border_top.color)
+            .set_border_bottom(self.border_bottom.style)
+            .set_border_bottom_color(self.border_bottom.color)
+            .set_border_left(self.border_left.style)
+            .set_border_left_color(self.border_left.color)
+            .set_border_right(self.border_right.style)
+            .set_border_right_color(self.border_right.color)
+            .set_border_diagonal(self.border_diagonal.style)
+            .set_border_diagonal_color(self.border_diagonal.color)
+            .set_border_diagonal_type(self.border_diagonal_type)
+            .set_pattern(self.pattern)
+            .set_foreground_color(self.foreground_color)
+            .set_background_color(self.background_color);
+
+        if !self.locked {
+            format = format.set_unlocked();
+        }
+        if self.hidden {
+            format = format.set_hidden();
+        }
+        if self.checkbox {
+            format = format.set_checkbox();
+        }
+        if self.quote_prefix {
+            format = format.set_quote_prefix();
+        }
+        format
+    }
+
+    /// The color that fills the cell background, if any.
+    ///
+    /// Like Excel, a solid fill uses the foreground color. `rust_xlsxwriter`
+    /// treats a background color without a pattern as a solid fill.
+    pub(crate) fn fill_color(&self) -> Option<xlsx::Color> {
+        let is_set = |color: xlsx::Color| !matches!(color, xlsx::Color::Default | xlsx::Color::Automatic);
+        match self.pattern {
+            xlsx::FormatPattern::None | xlsx::FormatPattern::Solid => [self.foreground_color, self.background_color]
+                .into_iter()
+                .find(|&color| is_set(color)),
+            // Approximate other patterns with the color of the pattern.
+            _ => [self.foreground_color, self.background_color]
+                .into_iter()
+                .find(|&color| is_set(color))
+                .or(Some(xlsx::Color::Black)),
+        }
+    }
+
+    /// Get the `dxf` element of the differential format used by table
+    /// styles. Only the font style and color, fill and borders are written,
+    /// since they are the properties that Excel supports for table styles.
+    pub(crate) fn dxf_xml(&self) -> String {
+        let mut font = self.font_style_xml();
+        font.push_str(&color_xml("color", self.font_color));
+
+        let mut xml = String::from("<dxf>");
+        if !font.is_empty() {
+            xml.push_str(&format!("<font>{font}</font>"));
+        }
+        // Differential fills use the background color for solid fills.
+        if let Some(fill) = self.fill_color() {
+            xml.push_str(&format!("<fill><patternFill>{}</patternFill></fill>", color_xml("bgColor", fill)));
+        }
+        if let Some(border) = self.border_xml() {
+            xml.push_str(&border);
+        }
+        xml.push_str("</dxf>");
+        xml
+    }
+
+    /// Get the `font` element of the format, like `rust_xlsxwriter` writes
+    /// it for cell formats.
+    pub(crate) fn font_xml(&self) -> String {
+        let mut xml = String::from("<font>");
+        xml.push_str(&self.font_style_xml());
+        match self.script {
+            xlsx::FormatScript::None => {}
+            xlsx::FormatScript::Superscript => xml.push_str("<vertAlign val=\"superscript\"/>"),
+            xlsx::FormatScript::Subscript => xml.push_str("<vertAlign val=\"subscript\"/>"),
+        }
+        xml.push_str(&format!("<sz val=\"{}\"/>", self.font_size));
+        match self.font_color {
+            xlsx::Color::Automatic => {}
+            xlsx::Color::Default => xml.push_str("<color theme=\"1\"/>"),
+            color => xml.push_str(&color_xml("color", color)),
+        }
+        xml.push_str(&format!("<name val=\"{}\"/>", package::escape_xml(&self.font_name)));
+        if self.font_family > 0 {
+            xml.push_str(&format!("<family val=\"{}\"/>", self.font_family));
+        }
+        if self.font_charset > 0 {
+            xml.push_str(&format!("<charset val=\"{}\"/>", self.font_charset));
+        }
+        match self.font_scheme {
+            xlsx::FontScheme::Body => xml.push_str("<scheme val=\"minor\"/>"),
+            xlsx::FontScheme::Headings => xml.push_str("<scheme val=\"major\"/>"),
+            xlsx::FontScheme::None => {}
+        }
+        xml.push_str("</font>");
+        xml
+    }
+
+    /// Get the `fill` element of the format, or `None` if the format doesn't
+    /// have a fill.
+    pub(crate) fn fill_xml(&self) -> Option<String> {
+        let fill = self.fill_color()?;
+        let xml = match self.pattern {
+            xlsx::FormatPattern::None | xlsx::FormatPattern::Solid => format!(
+                "<fill><patternFill patternType=\"solid\">{}<bgColor indexed=\"64\"/></patternFill></fill>",
+                color_xml("fgColor", fill)
+            ),
+            pattern => format!(
+                "<fill><patternFill patternType=\"{pattern}\">{}{}</patternFill></fill>",
+                color_xml("fgColor", self.foreground_color),
+                color_xml("bgColor", self.background_color)
+            ),
+        };
+        Some(xml)
+    }
+
+    /// Get the `border` element of the format, or `None` if the format
+    /// doesn't have borders.
+    pub(crate) fn border_xml(&self) -> Option<String> {
+        let sides = [
+            ("left", self.border_left),
+            ("right", self.border_right),
+            ("top", self.border_top),
+            ("bottom", self.border_bottom),
+        ];
+        if sides.iter().all(|(_, side)| side.style == xlsx::FormatBorder::None) {
+            return None;
+        }
+        let mut xml = String::from("<border>");
+        for (tag, side) in sides {
+            match border_style_name(side.style) {
+                Some(style) => xml.push_str(&format!(
+                    "<{tag} style=\"{style}\">{}</{tag}>",
+                    color_xml("color", side.color)
+                )),
+                None => xml.push_str(&format!("<{tag}/>")),
+            }
+        }
+        xml.push_str("</border>");
+        Some(xml)
+    }
+
+    /// Get the `alignment` element of the format, or `None` if the format
+    /// uses the default alignment.
+    pub(crate) fn alignment_xml(&self) -> Option<String> {
+        let mut attributes = String::new();
+        let horizontal = match self.align {
+            xlsx::FormatAlign::Center => Some("center"),
+            xlsx::FormatAlign::CenterAcross => Some("centerContinuous"),
+            xlsx::FormatAlign::Distributed => Some("distributed"),
+            xlsx::FormatAlign::Fill => Some("fill"),
+            xlsx::FormatAlign::Justify => Some("justify"),
+            xlsx::FormatAlign::Left => Some("left"),
+            xlsx::FormatAlign::Right => Some("right"),
+            _ => None,
+        };
+        if let Some(horizontal) = horizontal {
+            attributes.push_str(&format!(" horizontal=\"{horizontal}\""));
+        }
+        let vertical = match self.vertical_align {
+            xlsx::FormatAlign::VerticalCenter => Some("center"),
+            xlsx::FormatAlign::VerticalDistributed => Some("distributed"),
+            xlsx::FormatAlign::VerticalJustify => Some("justify"),
+            xlsx::FormatAlign::Top => Some("top"),
+            _ => None,
+        };
+        if let Some(vertical) = vertical {
+            attributes.push_str(&format!(" vertical=\"{vertical}\""));
+        }
+        if self.rotation != 0 {
+            attributes.push_str(&format!(" textRotation=\"{}\"", self.rotation));
+        }
+        if self.indent != 0 {
+            attributes.push_str(&format!(" indent=\"{}\"", self.indent));
+        }
+        if self.text_wrap {
+            attributes.push_str(" wrapText=\"1\"");
+        }
+        if self.shrink {
+            attributes.push_str(" shrinkToFit=\"1\"");
+        }
+        (!attributes.is_empty()).then(|| format!("<alignment{attributes}/>"))
+    }
+
+    /// The bold, italic, strikethrough and underline elements of a font.
+    fn font_style_xml(&self) -> String {
+        let mut xml = String::new();
+        if self.bold {
+            xml.push_str("<b/>");
+        }
+        if self.italic {
+            xml.push_str("<i/>");
+        }
+        if self.strikethrough {
+            xml.push_str("<strike/>");
+        }
+        match self.underline {
+            xlsx::FormatUnderline::None => {}
+            xlsx::FormatUnderline::Single => xml.push_str("<u/>"),
+            xlsx::FormatUnderline::Double => xml.push_str("<u val=\"double\"/>"),
+            xlsx::FormatUnderline::SingleAccounting => xml.push_str("<u val=\"singleAccounting\"/>"),
+            xlsx::FormatUnderline::DoubleAccounting => xml.push_str("<u val=\"doubleAccounting\"/>"),
+        }
+        xml
+    }
+}
+
+/// Get a color element with the theme color or the RGB value of a color, or
+/// an empty string for the default and automatic colors.
+fn color_xml(tag: &str, color: xlsx::Color) -> String {
+    if let xlsx::Color::Theme(theme, shade) = color {
+        let tint = theme_tint(theme, shade);
+        return if tint == 0.0 {
+            format!("<{tag} theme=\"{theme}\"/>")
+        } else {
+            format!("<{tag} theme=\"{theme}\" tint=\"{tint}\"/>")
+        };
+    }
+    rgb_of_color(color, &OFFICE_THEME_COLORS)
+        .map(|rgb| format!("<{tag} rgb=\"FF{rgb:06X}\"/>"))
+        .unwrap_or_default()
+}
+
+/// Check a color like `rust_xlsxwriter`, which ignores invalid colors.
+fn is_valid_color(color: xlsx::Color) -> bool {
+    match color {
+        xlsx::Color::RGB(rgb) => rgb <= 0xFFFFFF,
+        xlsx::Color::Theme(color, shade) => color <= 9 && shade <= 5,
+        _ => true,
+    }
+}
+
+/// Get the XML name of a border style.
+fn border_style_name(style: xlsx::FormatBorder) -> Option<&'static str> {
+    let name = match style {
+        xlsx::FormatBorder::None => return None,
+        xlsx::FormatBorder::Thin => "thin",
+        xlsx::FormatBorder::Medium => "medium",
+        xlsx::FormatBorder::Dashed => "dashed",
+        xlsx::FormatBorder::Dotted => "dotted",
+        xlsx::FormatBorder::Thick => "thick",
+        xlsx::FormatBorder::Double => "double",
+        xlsx::FormatBorder::Hair => "hair",
+        xlsx::FormatBorder::MediumDashed => "mediumDashed",
+        xlsx::FormatBorder::DashDot => "dashDot",
+        xlsx::FormatBorder::MediumDashDot => "mediumDashDot",
+        xlsx::FormatBorder::DashDotDot => "dashDotDot",
+        xlsx::FormatBorder::MediumDashDotDot => "mediumDashDotDot",
+        xlsx::FormatBorder::SlantDashDot => "slantDashDot",
+    };
+    Some(name)
+}
+
+/// Get the RGB value of a color, or `None` for the default and automatic
+/// colors. Theme colors are resolved with the colors of the workbook theme.
+pub(crate) fn rgb_of_color(color: xlsx::Color, theme_colors: &ThemeColors) -> Option<u32> {
+    let rgb = match color {
+        xlsx::Color::Default | xlsx::Color::Automatic => return None,
+        xlsx::Color::RGB(rgb) => rgb,
+        xlsx::Color::Theme(color, shade) => apply_tint(*theme_colors.get(usize::from(color))?, theme_tint(color, shade)),
+        xlsx::Color::Black => 0x000000,
+        xlsx::Color::Blue => 0x0000FF,
+        xlsx::Color::Brown => 0x800000,
+        xlsx::Color::Cyan => 0x00FFFF,
+        xlsx::Color::Gray => 0x808080,
+        xlsx::Color::Green => 0x008000,
+        xlsx::Color::Lime => 0x00FF00,
+        xlsx::Color::Magenta => 0xFF00FF,
+        xlsx::Color::Navy => 0x000080,
+        xlsx::Color::Orange => 0xFF6600,
+        xlsx::Color::Pink => 0xFFC0CB,
+        xlsx::Color::Purple => 0x800080,
+        xlsx::Color::Red => 0xFF0000,
+        xlsx::Color::Silver => 0xC0C0C0,
+        xlsx::Color::White => 0xFFFFFF,
+        xlsx::Color::Yellow => 0xFFFF00,
+    };
+    Some(rgb)
+}
+
+/// Get the tint of a shade of a theme color, using the same tints as
+/// `rust_xlsxwriter`.
+fn theme_tint(color: u8, shade: u8) -> f64 {
+    match (color, shade) {
+        (_, 0) => 0.0,
+        (0, shade) => [-0.05, -0.15, -0.25, -0.35, -0.5][usize::from(shade.min(5)) - 1],
+        (1, shade) => [0.5, 0.35, 0.25, 0.15, 0.05][usize::from(shade.min(5)) - 1],
+        (2, shade) => [-0.1, -0.25, -0.5, -0.75, -0.9][usize::from(shade.min(5)) - 1],
+        (_, shade) => [0.8, 0.6, 0.4, -0.25, -0.5][usize::from(shade.min(5)) - 1],
+    }
+}
+
+/// Apply an Excel tint to the luminance of a color.
+fn apply_tint(rgb: u32, tint: f64) -> u32 {
+    if tint == 0.0 {
+        return rgb;
+    }
+    let channel = |shift: u32| f64::from((rgb >> shift) & 0xFF) / 255.0;
+    let (r, g, b) = (channel(16), channel(8), channel(0));
+
+    // Convert to HSL.
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lum = (max + min) / 2.0;
+    let delta = max - min;
+    let (hue, sat) = if delta == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let sat = delta / (1.0 - (2.0 * lum - 1.0).abs());
+        let hue = if max == r {
+            ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / delta + 2.0
+        } else {
+            (r - g) / delta + 4.0
+        };
+        (hue * 60.0, sat)
+    };
+
+    let lum = if tint < 0.0 {
+        lum * (1.0 + tint)
+    } else {
+        lum * (1.0 - tint) + tint
+    };
+
+    // Convert back to RGB.
+    let chroma = (1.0 - (2.0 * lum - 1.0).abs()) * sat;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = lum - chroma / 2.0;
+    let (r, g, b) = match hue {
+        h if h < 60.0 => (chroma, x, 0.0),
+        h if h < 120.0 => (x, chroma, 0.0),
+        h if h < 180.0 => (0.0, chroma, x),
+        h if h < 240.0 => (0.0, x, chroma),
+        h if h < 300.0 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |value: f64| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u32;
+    (channel(r) << 16) | (channel(g) << 8) | channel(b)
+}
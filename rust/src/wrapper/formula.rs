@@ -7,6 +7,9 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct Formula {
     pub(crate) inner: Arc<Mutex<xlsx::Formula>>,
+    /// The cached result, which `rust_xlsxwriter` doesn't provide a getter
+    /// for. It is shown by {@link Worksheet#toHtml}.
+    pub(crate) result: Arc<Mutex<String>>,
 }
 
 macro_rules! impl_method {
@@ -17,6 +20,7 @@ macro_rules! impl_method {
         let _ = std::mem::replace(&mut *lock, inner);
         return Formula {
             inner: Arc::clone(&$self.inner),
+            result: Arc::clone(&$self.result),
         }
     };
 }
@@ -31,11 +35,13 @@ impl Formula {
     pub fn new(formula: &str) -> Formula {
         Formula {
             inner: Arc::new(Mutex::new(xlsx::Formula::new(formula))),
+            result: Arc::default(),
         }
     }
 
     #[wasm_bindgen(js_name = "setResult")]
     pub fn set_result(&self, result: &str) -> Formula {
+        *self.result.lock().unwrap() = result.to_string();
         impl_method!(self.set_result(result));
     }
 }
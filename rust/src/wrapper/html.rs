@@ -0,0 +1,465 @@
+//! Rendering of worksheet data as an HTML table.
+
+use std::collections::HashMap;
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    cell_store::{CellRange, CellStore, CellValue},
+    format_properties::{self, BorderSide, FormatProperties},
+    number_format,
+    theme::ThemeColors,
+    WasmResult,
+};
+
+/// The color of the worksheet gridlines.
+const GRIDLINE_COLOR: &str = "#D4D4D4";
+
+/// The width of the row header column in pixels.
+const ROW_HEADER_WIDTH: u32 = 40;
+
+/// The `HtmlOptions` struct defines options for rendering a worksheet as HTML
+/// with {@link Worksheet#toHtml}.
+///
+/// TODO: example omitted
+#[derive(Clone, Copy, Debug)]
+#[wasm_bindgen]
+pub struct HtmlOptions {
+    range: Option<CellRange>,
+    gridlines: bool,
+    headers: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        HtmlOptions {
+            range: None,
+            gridlines: true,
+            headers: false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl HtmlOptions {
+    /// Create a new `HtmlOptions` with the default options.
+    ///
+    /// By default the used range of the worksheet is rendered with gridlines
+    /// and without row and column headers.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> HtmlOptions {
+        HtmlOptions::default()
+    }
+
+    /// Set the range of cells to render.
+    ///
+    /// @param {number} first_row - The first row of the range. (All zero indexed.)
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    /// @returns {HtmlOptions} - The HtmlOptions object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnOrderError`] - First row or column larger
+    ///   than the last row or column.
+    #[wasm_bindgen(js_name = "setRange", skip_jsdoc)]
+    pub fn set_range(
+        &self,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+    ) -> WasmResult<HtmlOptions> {
+        if first_row > last_row || first_col > last_col {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnOrderError));
+        }
+        Ok(HtmlOptions {
+            range: Some(CellRange::new(first_row, first_col, last_row, last_col)),
+            ..*self
+        })
+    }
+
+    /// Show or hide the worksheet gridlines. The default is to show them.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {HtmlOptions} - The HtmlOptions object.
+    #[wasm_bindgen(js_name = "setGridlines", skip_jsdoc)]
+    pub fn set_gridlines(&self, enable: bool) -> HtmlOptions {
+        HtmlOptions {
+            gridlines: enable,
+            ..*self
+        }
+    }
+
+    /// Show or hide the row numbers and column letters. The default is to
+    /// hide them.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {HtmlOptions} - The HtmlOptions object.
+    #[wasm_bindgen(js_name = "setHeaders", skip_jsdoc)]
+    pub fn set_headers(&self, enable: bool) -> HtmlOptions {
+        HtmlOptions {
+            headers: enable,
+            ..*self
+        }
+    }
+}
+
+/// A cell of the rendered range that isn't hidden by a merged range.
+struct HtmlCell<'a> {
+    value: Option<&'a CellValue>,
+    properties: Option<&'a FormatProperties>,
+    row_span: u32,
+    col_span: u16,
+}
+
+/// Render the cells of a worksheet as an HTML table.
+/// Theme colors are resolved with the colors of the workbook theme.
+pub(crate) fn render(store: &CellStore, date_1904: bool, theme_colors: &ThemeColors, options: &HtmlOptions) -> String {
+    let mut html = String::new();
+    let Some(range) = options.range.or_else(|| store.used_range()) else {
+        html.push_str("<table style=\"border-collapse:collapse\"></table>");
+        return html;
+    };
+    let rows = range.first_row()..=range.last_row();
+    let cols = range.first_col()..=range.last_col();
+
+    // Find the cells covered by merged ranges, clipped to the rendered range.
+    let mut spans = HashMap::new();
+    let mut covered = HashMap::new();
+    for merged in store.merged_ranges() {
+        let first_row = merged.first_row().max(range.first_row());
+        let first_col = merged.first_col().max(range.first_col());
+        let last_row = merged.last_row().min(range.last_row());
+        let last_col = merged.last_col().min(range.last_col());
+        if first_row > last_row || first_col > last_col {
+            continue;
+        }
+        spans.insert((first_row, first_col), (last_row - first_row + 1, last_col - first_col + 1));
+        for row in first_row..=last_row {
+            for col in first_col..=last_col {
+                if (row, col) != (first_row, first_col) {
+                    covered.insert((row, col), ());
+                }
+            }
+        }
+    }
+
+    let properties_at = |row, col| store.value(row, col).and_then(|(_, format)| format);
+
+    let header_width = if options.headers { ROW_HEADER_WIDTH } else { 0 };
+    let table_width: u32 = header_width + cols.clone().map(|col| store.column_width(col)).sum::<u32>();
+    html.push_str(&format!(
+        "<table style=\"border-collapse:collapse;table-layout:fixed;width:{table_width}px;\
+         font-family:Calibri,sans-serif;font-size:11pt\"><colgroup>"
+    ));
+    if options.headers {
+        html.push_str(&format!("<col style=\"width:{ROW_HEADER_WIDTH}px\">"));
+    }
+    for col in cols.clone() {
+        html.push_str(&format!("<col style=\"width:{}px\">", store.column_width(col)));
+    }
+    html.push_str("</colgroup><tbody>");
+
+    let header_style = format!(
+        "background-color:#F0F0F0;border:1px solid {GRIDLINE_COLOR};font-weight:normal;text-align:center;color:#444444"
+    );
+    if options.headers {
+        html.push_str(&format!("<tr><th style=\"{header_style}\"></th>"));
+        for col in cols.clone() {
+            html.push_str(&format!(
+                "<th style=\"{header_style}\">{}</th>",
+                xlsx::utility::column_number_to_name(col)
+            ));
+        }
+        html.push_str("</tr>");
+    }
+
+    for row in rows {
+        html.push_str(&format!("<tr style=\"height:{}px\">", store.row_height(row)));
+        if options.headers {
+            html.push_str(&format!("<th style=\"{header_style}\">{}</th>", row + 1));
+        }
+        for col in cols.clone() {
+            if covered.contains_key(&(row, col)) {
+                continue;
+            }
+            let (row_span, col_span) = spans.get(&(row, col)).copied().unwrap_or((1, 1));
+            let cell = HtmlCell {
+                value: store.value(row, col).map(|(value, _)| value),
+                properties: properties_at(row, col),
+                row_span,
+                col_span,
+            };
+
+            // Borders are shared with the neighboring cells, like in Excel.
+            let last_row = row + row_span - 1;
+            let last_col = col + col_span - 1;
+            let neighbor = |row: Option<xlsx::RowNum>, col: Option<xlsx::ColNum>, side: fn(&FormatProperties) -> BorderSide| {
+                let (row, col) = (row?, col?);
+                properties_at(row, col).map(side)
+            };
+            let borders = [
+                ("top", neighbor(row.checked_sub(1), Some(col), |p| p.border_bottom)),
+                ("bottom", neighbor(last_row.checked_add(1), Some(col), |p| p.border_top)),
+                ("left", neighbor(Some(row), col.checked_sub(1), |p| p.border_right)),
+                ("right", neighbor(Some(row), last_col.checked_add(1), |p| p.border_left)),
+            ];
+            render_cell(&mut html, &cell, &borders, date_1904, theme_colors, options.gridlines);
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+fn render_cell(
+    html: &mut String,
+    cell: &HtmlCell,
+    neighbor_borders: &[(&str, Option<BorderSide>); 4],
+    date_1904: bool,
+    theme_colors: &ThemeColors,
+    gridlines: bool,
+) {
+    let default_properties = FormatProperties::default();
+    let properties = cell.properties.unwrap_or(&default_properties);
+    let mut style = vec![];
+
+    // Get the text and the alignment of the General format.
+    let mut link = None;
+    let mut text_color = None;
+    let (content, general_align) = match cell.value {
+        None | Some(CellValue::Blank) | Some(CellValue::Image) => (String::new(), "left"),
+        // Formulas aren't calculated so their cached result is shown, with
+        // the alignment of the type of value it holds.
+        Some(CellValue::Formula(_, result)) => match result.parse::<f64>() {
+            Ok(n) if n.is_finite() => {
+                let formatted = number_format::format_number(n, &num_format_code(properties), date_1904);
+                text_color = formatted.color;
+                (escape_html(&formatted.text), "right")
+            }
+            _ if result == "TRUE" || result == "FALSE" || result.starts_with('#') => (escape_html(result), "center"),
+            _ => {
+                let formatted = number_format::format_text(result, &num_format_code(properties));
+                text_color = formatted.color;
+                (escape_html(&formatted.text), "left")
+            }
+        },
+        Some(CellValue::String(s)) => {
+            let formatted = number_format::format_text(s, &num_format_code(properties));
+            text_color = formatted.color;
+            (escape_html(&formatted.text), "left")
+        }
+        Some(CellValue::Number(n)) | Some(CellValue::DateTime(n)) => {
+            let formatted = number_format::format_number(*n, &num_format_code(properties), date_1904);
+            text_color = formatted.color;
+            (escape_html(&formatted.text), "right")
+        }
+        Some(CellValue::Bool(b)) => {
+            let text = match (properties.checkbox, *b) {
+                (true, true) => "\u{2611}",
+                (true, false) => "\u{2610}",
+                (false, true) => "TRUE",
+                (false, false) => "FALSE",
+            };
+            (text.to_string(), "center")
+        }
+        Some(CellValue::Url(url)) => {
+            link = Some(url.link.clone());
+            (escape_html(&url.cell_text()), "left")
+        }
+        Some(CellValue::RichString(parts)) => {
+            let mut content = String::new();
+            for (format, text) in parts {
+                let mut span_style = vec![];
+                font_style(&mut span_style, format, theme_colors);
+                content.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    span_style.join(";"),
+                    escape_html(text)
+                ));
+            }
+            (content, "left")
+        }
+    };
+
+    font_style(&mut style, properties, theme_colors);
+    if let Some(color) = text_color {
+        style.push(format!("color:#{color:06X}"));
+    } else if link.is_some() && cell.properties.is_none() {
+        // Unformatted hyperlinks use the theme hyperlink color.
+        style.push(format!("color:#{:06X};text-decoration:underline", theme_colors[10]));
+    }
+
+    let fill = properties
+        .fill_color()
+        .and_then(|fill| format_properties::rgb_of_color(fill, theme_colors));
+    if let Some(fill) = fill {
+        style.push(format!("background-color:#{fill:06X}"));
+    }
+
+    let own_borders = [
+        properties.border_top,
+        properties.border_bottom,
+        properties.border_left,
+        properties.border_right,
+    ];
+    for ((side, neighbor), own) in neighbor_borders.iter().zip(own_borders) {
+        let border = if own.style != xlsx::FormatBorder::None {
+            Some(own)
+        } else {
+            neighbor.filter(|border| border.style != xlsx::FormatBorder::None)
+        };
+        match border {
+            Some(border) => style.push(format!("border-{side}:{}", border_css(border, theme_colors))),
+            // Filled cells hide the gridlines.
+            None if gridlines => match fill {
+                Some(fill) => style.push(format!("border-{side}:1px solid #{fill:06X}")),
+                None => style.push(format!("border-{side}:1px solid {GRIDLINE_COLOR}")),
+            },
+            None => {}
+        }
+    }
+
+    let align = match properties.align {
+        xlsx::FormatAlign::Left | xlsx::FormatAlign::Fill => "left",
+        xlsx::FormatAlign::Center | xlsx::FormatAlign::CenterAcross => "center",
+        xlsx::FormatAlign::Right => "right",
+        xlsx::FormatAlign::Justify | xlsx::FormatAlign::Distributed => "justify",
+        _ => general_align,
+    };
+    style.push(format!("text-align:{align}"));
+    let vertical_align = match properties.vertical_align {
+        xlsx::FormatAlign::Top => "top",
+        xlsx::FormatAlign::VerticalCenter
+        | xlsx::FormatAlign::VerticalJustify
+        | xlsx::FormatAlign::VerticalDistributed => "middle",
+        _ => "bottom",
+    };
+    style.push(format!("vertical-align:{vertical_align}"));
+    if properties.text_wrap {
+        style.push("white-space:pre-wrap;overflow-wrap:break-word".to_string());
+    } else {
+        style.push("white-space:pre;overflow:hidden".to_string());
+    }
+    let indent = u32::from(properties.indent) * 9;
+    match align {
+        "right" => style.push(format!("padding:0 {}px 0 3px", 3 + indent)),
+        _ => style.push(format!("padding:0 3px 0 {}px", 3 + indent)),
+    }
+
+    html.push_str("<td");
+    if cell.row_span > 1 {
+        html.push_str(&format!(" rowspan=\"{}\"", cell.row_span));
+    }
+    if cell.col_span > 1 {
+        html.push_str(&format!(" colspan=\"{}\"", cell.col_span));
+    }
+    html.push_str(&format!(" style=\"{}\">", style.join(";")));
+    match link.filter(|link| is_safe_link(link)) {
+        Some(link) => html.push_str(&format!(
+            "<a href=\"{}\" style=\"color:inherit;text-decoration:inherit\">{content}</a>",
+            escape_html(&link)
+        )),
+        None => html.push_str(&content),
+    }
+    html.push_str("</td>");
+}
+
+/// Get the number format code of a format.
+fn num_format_code(properties: &FormatProperties) -> String {
+    // A built-in number format index takes precedence over the format
+    // string, like in `rust_xlsxwriter`.
+    if properties.num_format_index > 0 || properties.num_format.is_empty() {
+        number_format::builtin_num_format(properties.num_format_index).to_string()
+    } else {
+        properties.num_format.clone()
+    }
+}
+
+/// Add the CSS for the font properties that differ from the default font.
+fn font_style(style: &mut Vec<String>, properties: &FormatProperties, theme_colors: &ThemeColors) {
+    let default = FormatProperties::default();
+    if properties.font_name != default.font_name {
+        style.push(format!("font-family:'{}'", escape_html(&properties.font_name.replace('\'', ""))));
+    }
+    if properties.font_size != default.font_size {
+        style.push(format!("font-size:{}pt", properties.font_size));
+    }
+    if properties.bold {
+        style.push("font-weight:bold".to_string());
+    }
+    if properties.italic {
+        style.push("font-style:italic".to_string());
+    }
+    let mut decorations = vec![];
+    if properties.underline != xlsx::FormatUnderline::None || properties.hyperlink {
+        decorations.push("underline");
+    }
+    if properties.strikethrough {
+        decorations.push("line-through");
+    }
+    if !decorations.is_empty() {
+        style.push(format!("text-decoration:{}", decorations.join(" ")));
+    }
+    if matches!(
+        properties.underline,
+        xlsx::FormatUnderline::Double | xlsx::FormatUnderline::DoubleAccounting
+    ) {
+        style.push("text-decoration-style:double".to_string());
+    }
+    match properties.script {
+        xlsx::FormatScript::Superscript => style.push("vertical-align:super;font-size:smaller".to_string()),
+        xlsx::FormatScript::Subscript => style.push("vertical-align:sub;font-size:smaller".to_string()),
+        xlsx::FormatScript::None => {}
+    }
+    if let Some(color) = format_properties::rgb_of_color(properties.font_color, theme_colors) {
+        style.push(format!("color:#{color:06X}"));
+    }
+}
+
+/// Get the CSS for an Excel border.
+fn border_css(border: BorderSide, theme_colors: &ThemeColors) -> String {
+    let (width, line) = match border.style {
+        xlsx::FormatBorder::Thin => (1, "solid"),
+        xlsx::FormatBorder::Medium => (2, "solid"),
+        xlsx::FormatBorder::Thick => (3, "solid"),
+        xlsx::FormatBorder::Double => (3, "double"),
+        xlsx::FormatBorder::Dotted | xlsx::FormatBorder::Hair => (1, "dotted"),
+        xlsx::FormatBorder::Dashed | xlsx::FormatBorder::DashDot | xlsx::FormatBorder::DashDotDot => (1, "dashed"),
+        xlsx::FormatBorder::MediumDashed
+        | xlsx::FormatBorder::MediumDashDot
+        | xlsx::FormatBorder::MediumDashDotDot
+        | xlsx::FormatBorder::SlantDashDot => (2, "dashed"),
+        xlsx::FormatBorder::None => (0, "none"),
+    };
+    let color = format_properties::rgb_of_color(border.color, theme_colors).unwrap_or(0);
+    format!("{width}px {line} #{color:06X}")
+}
+
+/// Only link to web and email URLs so that the HTML is safe to embed.
+fn is_safe_link(link: &str) -> bool {
+    let lower = link.to_ascii_lowercase();
+    ["http://", "https://", "ftp://", "ftps://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,1119 @@
+//! Parsing of HTML tables for {@link Worksheet#writeHtmlTable}.
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{format_properties::FormatProperties, WasmResult};
+
+/// The maximum number of columns a cell can span, like in browsers.
+const MAX_COL_SPAN: u32 = 1000;
+
+/// The maximum number of rows a cell can span, like in browsers.
+const MAX_ROW_SPAN: u32 = 65534;
+
+/// The maximum number of cells a cell can span. A spanning cell is written as
+/// a merged range, which `rust_xlsxwriter` fills with blank cells, so larger
+/// spans are reduced to fewer rows.
+const MAX_SPAN_CELLS: u32 = 10_000;
+
+/// The `HtmlTableOptions` struct defines options for writing an HTML table
+/// with {@link Worksheet#writeHtmlTable}.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct HtmlTableOptions {
+    detect_numbers: bool,
+    detect_dates: bool,
+    date_format: String,
+    datetime_format: String,
+}
+
+impl Default for HtmlTableOptions {
+    fn default() -> Self {
+        HtmlTableOptions {
+            detect_numbers: true,
+            detect_dates: true,
+            date_format: "yyyy-mm-dd".to_string(),
+            datetime_format: "yyyy-mm-dd hh:mm:ss".to_string(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl HtmlTableOptions {
+    /// Create a new `HtmlTableOptions` with the default options.
+    ///
+    /// By default numbers and ISO 8601 dates are detected and written as
+    /// Excel numbers and dates.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> HtmlTableOptions {
+        HtmlTableOptions::default()
+    }
+
+    /// Turn the detection of numbers on or off.
+    ///
+    /// Numbers like `1234.5`, `-1,234.50`, `(12)`, `$1,234.00` and `12.5%`
+    /// are written as numbers with a matching number format. Numbers with
+    /// leading zeros or more than 15 significant digits, like ids and phone
+    /// numbers, are written as text since Excel would change them.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {HtmlTableOptions} - The HtmlTableOptions object.
+    #[wasm_bindgen(js_name = "setDetectNumbers", skip_jsdoc)]
+    pub fn set_detect_numbers(&self, enable: bool) -> HtmlTableOptions {
+        HtmlTableOptions {
+            detect_numbers: enable,
+            ..self.clone()
+        }
+    }
+
+    /// Turn the detection of dates on or off.
+    ///
+    /// ISO 8601 dates like `2024-01-31` and date times like
+    /// `2024-01-31 12:30:45` or `2024-01-31T12:30:45Z` are written as Excel
+    /// dates.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {HtmlTableOptions} - The HtmlTableOptions object.
+    #[wasm_bindgen(js_name = "setDetectDates", skip_jsdoc)]
+    pub fn set_detect_dates(&self, enable: bool) -> HtmlTableOptions {
+        HtmlTableOptions {
+            detect_dates: enable,
+            ..self.clone()
+        }
+    }
+
+    /// Set the number format of detected dates. The default is
+    /// `yyyy-mm-dd`.
+    ///
+    /// @param {string} num_format - The number format code.
+    /// @returns {HtmlTableOptions} - The HtmlTableOptions object.
+    #[wasm_bindgen(js_name = "setDateFormat", skip_jsdoc)]
+    pub fn set_date_format(&self, num_format: &str) -> HtmlTableOptions {
+        HtmlTableOptions {
+            date_format: num_format.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Set the number format of detected date times. The default is
+    /// `yyyy-mm-dd hh:mm:ss`.
+    ///
+    /// @param {string} num_format - The number format code.
+    /// @returns {HtmlTableOptions} - The HtmlTableOptions object.
+    #[wasm_bindgen(js_name = "setDatetimeFormat", skip_jsdoc)]
+    pub fn set_datetime_format(&self, num_format: &str) -> HtmlTableOptions {
+        HtmlTableOptions {
+            datetime_format: num_format.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The value of a cell of an HTML table.
+pub(crate) enum HtmlTableValue {
+    Blank,
+    String(String),
+    Number(f64),
+    DateTime(xlsx::ExcelDateTime),
+}
+
+/// A cell of an HTML table, positioned relative to the top left cell.
+pub(crate) struct HtmlTableCell {
+    pub(crate) row: u32,
+    pub(crate) col: u32,
+    pub(crate) row_span: u32,
+    pub(crate) col_span: u32,
+    pub(crate) value: HtmlTableValue,
+    pub(crate) format: Option<FormatProperties>,
+}
+
+/// Parse the first `<table>` in an HTML fragment.
+pub(crate) fn parse(html: &str, options: &HtmlTableOptions) -> WasmResult<Vec<HtmlTableCell>> {
+    let mut builder = TableBuilder::default();
+    let mut tokens = Tokenizer { html, pos: 0 };
+    let mut found = false;
+    while let Some(token) = tokens.next_token() {
+        match token {
+            Token::Open(tag) if tag.name == "table" => {
+                if builder.depth == 0 {
+                    if found {
+                        break;
+                    }
+                    found = true;
+                    builder.table_style = CssStyle::default().with_attributes(&tag);
+                }
+                builder.depth += 1;
+            }
+            Token::Close(name) if name == "table" && builder.depth > 0 => {
+                builder.depth -= 1;
+                if builder.depth == 0 {
+                    builder.end_cell();
+                }
+            }
+            _ if builder.depth == 0 => {}
+            token => builder.add(token),
+        }
+    }
+    if !found {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+            "HTML doesn't contain a <table> element".to_string(),
+        )));
+    }
+    Ok(builder.cells.into_iter().map(|cell| cell.finish(options)).collect())
+}
+
+/// A cell that is being parsed.
+struct PendingCell {
+    row: u32,
+    col: u32,
+    row_span: u32,
+    col_span: u32,
+    text: String,
+    style: CssStyle,
+}
+
+impl PendingCell {
+    fn finish(self, options: &HtmlTableOptions) -> HtmlTableCell {
+        let text = self
+            .text
+            .split('\n')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim_matches('\n')
+            .to_string();
+        let mut style = self.style;
+        if text.contains('\n') && style.text_wrap.is_none() {
+            style.text_wrap = Some(true);
+        }
+
+        let mut num_format = None;
+        let value = if text.is_empty() {
+            HtmlTableValue::Blank
+        } else if let Some((datetime, has_time)) = options.detect_dates.then(|| detect_date(&text)).flatten() {
+            num_format = Some(if has_time {
+                options.datetime_format.clone()
+            } else {
+                options.date_format.clone()
+            });
+            HtmlTableValue::DateTime(datetime)
+        } else if let Some((number, format)) = options.detect_numbers.then(|| detect_number(&text)).flatten() {
+            num_format = format;
+            HtmlTableValue::Number(number)
+        } else {
+            HtmlTableValue::String(text)
+        };
+
+        let mut format = style.to_format();
+        if let Some(num_format) = num_format {
+            format = Some(format.unwrap_or_default().set_num_format(&num_format));
+        }
+        HtmlTableCell {
+            row: self.row,
+            col: self.col,
+            row_span: self.row_span,
+            col_span: self.col_span,
+            value,
+            format,
+        }
+    }
+}
+
+/// Build the cells of a table from the tokens inside it.
+#[derive(Default)]
+struct TableBuilder {
+    depth: u32,
+    table_style: CssStyle,
+    row_style: CssStyle,
+    row: Option<u32>,
+    next_row: u32,
+    col: u32,
+    /// The columns, from the first to the last exclusive, that are occupied
+    /// by cells of earlier rows up to a row, exclusive.
+    row_spans: Vec<(u32, u32, u32)>,
+    cell: Option<PendingCell>,
+    in_caption: bool,
+    cells: Vec<PendingCell>,
+}
+
+impl TableBuilder {
+    fn add(&mut self, token: Token) {
+        // The structure of nested tables is ignored but their text is kept.
+        let nested = self.depth > 1;
+        match token {
+            Token::Open(tag) => match tag.name.as_str() {
+                "caption" if !nested => self.in_caption = true,
+                "tr" if !nested => {
+                    self.end_cell();
+                    self.start_row(&tag);
+                }
+                "td" | "th" if !nested => {
+                    self.end_cell();
+                    self.start_cell(&tag);
+                }
+                "br" => self.push_text("\n"),
+                "p" | "div" | "li" | "tr" => self.push_line_break(),
+                "b" | "strong" => self.apply_inline(|style| style.bold = Some(true)),
+                "i" | "em" => self.apply_inline(|style| style.italic = Some(true)),
+                "u" | "ins" => self.apply_inline(|style| style.underline = Some(true)),
+                "s" | "strike" | "del" => self.apply_inline(|style| style.strikethrough = Some(true)),
+                "sup" => self.apply_inline(|style| style.script = Some(xlsx::FormatScript::Superscript)),
+                "sub" => self.apply_inline(|style| style.script = Some(xlsx::FormatScript::Subscript)),
+                "span" | "font" | "a" | "code" | "label" | "small" | "big" => {
+                    let inline = CssStyle::default().with_attributes(&tag);
+                    self.apply_inline(|style| style.overlay_font(&inline));
+                }
+                _ => {}
+            },
+            Token::Close(name) => match name.as_str() {
+                "caption" if !nested => self.in_caption = false,
+                "td" | "th" if !nested => self.end_cell(),
+                "tr" if !nested => {
+                    self.end_cell();
+                    self.row = None;
+                }
+                "thead" | "tbody" | "tfoot" if !nested => {
+                    self.end_cell();
+                    self.row = None;
+                }
+                "p" | "div" | "li" | "tr" => self.push_line_break(),
+                _ => {}
+            },
+            Token::Text(text) => {
+                if !self.in_caption {
+                    self.push_text(&collapse_whitespace(&text));
+                }
+            }
+        }
+    }
+
+    fn start_row(&mut self, tag: &Tag) {
+        let row = self.next_row;
+        self.row = Some(row);
+        self.row_spans.retain(|&(_, _, end_row)| end_row > row);
+        self.next_row += 1;
+        self.col = 0;
+        self.row_style = CssStyle::default().with_attributes(tag);
+    }
+
+    fn start_cell(&mut self, tag: &Tag) {
+        if self.row.is_none() {
+            self.start_row(&Tag::default());
+        }
+        let row = self.row.unwrap_or_default();
+        while let Some(&(_, end_col, _)) = self
+            .row_spans
+            .iter()
+            .find(|&&(first_col, end_col, _)| (first_col..end_col).contains(&self.col))
+        {
+            self.col = end_col;
+        }
+        let span = |name, max| {
+            tag.attribute(name)
+                .and_then(|span| span.trim().parse::<u32>().ok())
+                .unwrap_or(1)
+                .clamp(1, max)
+        };
+        let col_span = span("colspan", MAX_COL_SPAN);
+        let row_span = span("rowspan", MAX_ROW_SPAN).min(MAX_SPAN_CELLS / col_span);
+        if row_span > 1 {
+            self.row_spans.push((self.col, self.col + col_span, row + row_span));
+        }
+
+        let mut style = CssStyle::default();
+        style.overlay_font(&self.table_style);
+        style.align = self.table_style.align;
+        style.overlay(&self.row_style);
+        if tag.name == "th" {
+            style.bold = Some(true);
+            style.align = Some(xlsx::FormatAlign::Center);
+        }
+        style.overlay(&CssStyle::default().with_attributes(tag));
+
+        self.cell = Some(PendingCell {
+            row,
+            col: self.col,
+            row_span,
+            col_span,
+            text: String::new(),
+            style,
+        });
+        self.col += col_span;
+    }
+
+    fn end_cell(&mut self) {
+        if let Some(cell) = self.cell.take() {
+            self.cells.push(cell);
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(cell) = &mut self.cell {
+            cell.text.push_str(text);
+        }
+    }
+
+    /// Start a new line for a block element, unless the text is already at
+    /// the start of a line.
+    fn push_line_break(&mut self) {
+        if let Some(cell) = &mut self.cell {
+            if !cell.text.is_empty() && !cell.text.ends_with('\n') {
+                cell.text.push('\n');
+            }
+        }
+    }
+
+    fn apply_inline(&mut self, f: impl FnOnce(&mut CssStyle)) {
+        if let Some(cell) = &mut self.cell {
+            f(&mut cell.style);
+        }
+    }
+}
+
+/// The CSS properties of a cell that map to a `Format`.
+#[derive(Clone, Default)]
+struct CssStyle {
+    bold: Option<bool>,
+    italic: Option<bool>,
+    underline: Option<bool>,
+    strikethrough: Option<bool>,
+    script: Option<xlsx::FormatScript>,
+    font_name: Option<String>,
+    font_size: Option<f64>,
+    color: Option<u32>,
+    background: Option<u32>,
+    align: Option<xlsx::FormatAlign>,
+    vertical_align: Option<xlsx::FormatAlign>,
+    text_wrap: Option<bool>,
+    /// The top, bottom, left and right borders.
+    borders: [BorderCss; 4],
+}
+
+#[derive(Clone, Copy, Default)]
+struct BorderCss {
+    width: Option<f64>,
+    style: Option<BorderStyle>,
+    color: Option<u32>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BorderStyle {
+    None,
+    Solid,
+    Dotted,
+    Dashed,
+    Double,
+}
+
+impl CssStyle {
+    /// Add the legacy presentation attributes and the `style` attribute of
+    /// an element.
+    fn with_attributes(mut self, tag: &Tag) -> CssStyle {
+        if let Some(color) = tag.attribute("bgcolor").and_then(parse_color) {
+            self.background = Some(color);
+        }
+        if let Some(color) = tag.attribute("color").and_then(parse_color) {
+            self.color = Some(color);
+        }
+        if let Some(face) = tag.attribute("face") {
+            self.font_name = first_font_family(face);
+        }
+        if tag.name == "font" {
+            let sizes = [8.0, 10.0, 12.0, 14.0, 18.0, 24.0, 36.0];
+            if let Some(size) = tag.attribute("size").and_then(|size| size.trim().parse::<usize>().ok()) {
+                self.font_size = Some(sizes[size.clamp(1, 7) - 1]);
+            }
+        }
+        if let Some(align) = tag.attribute("align") {
+            self.set("text-align", align);
+        }
+        if let Some(align) = tag.attribute("valign") {
+            self.set("vertical-align", align);
+        }
+        if tag.attribute("nowrap").is_some() {
+            self.text_wrap = Some(false);
+        }
+        for declaration in tag.attribute("style").unwrap_or_default().split(';') {
+            if let Some((property, value)) = declaration.split_once(':') {
+                let value = value.trim().trim_end_matches("!important").trim();
+                self.set(&property.trim().to_ascii_lowercase(), value);
+            }
+        }
+        self
+    }
+
+    /// Set a CSS property. Unsupported properties and values are ignored.
+    fn set(&mut self, property: &str, value: &str) {
+        let lower = value.to_ascii_lowercase();
+        match property {
+            "font-weight" => {
+                self.bold = match lower.as_str() {
+                    "bold" | "bolder" => Some(true),
+                    "normal" | "lighter" => Some(false),
+                    weight => weight.parse::<u32>().ok().map(|weight| weight >= 600),
+                }
+            }
+            "font-style" => self.italic = Some(lower == "italic" || lower == "oblique"),
+            "text-decoration" | "text-decoration-line" => {
+                self.underline = Some(lower.contains("underline"));
+                self.strikethrough = Some(lower.contains("line-through"));
+            }
+            "font-family" => self.font_name = first_font_family(value),
+            "font-size" => {
+                // Round the size in points to the nearest half point.
+                let size = parse_length(&lower).map(|px| (px * 0.75 * 2.0).round() / 2.0);
+                self.font_size = size.or(self.font_size);
+            }
+            "color" => self.color = parse_color(&lower),
+            "background-color" => self.background = parse_color(&lower),
+            "background" => self.background = lower.split_whitespace().find_map(parse_color),
+            "text-align" => {
+                self.align = match lower.as_str() {
+                    "left" | "start" => Some(xlsx::FormatAlign::Left),
+                    "center" => Some(xlsx::FormatAlign::Center),
+                    "right" | "end" => Some(xlsx::FormatAlign::Right),
+                    "justify" => Some(xlsx::FormatAlign::Justify),
+                    _ => self.align,
+                }
+            }
+            "vertical-align" => {
+                self.vertical_align = match lower.as_str() {
+                    "top" => Some(xlsx::FormatAlign::Top),
+                    "middle" | "center" => Some(xlsx::FormatAlign::VerticalCenter),
+                    "bottom" => Some(xlsx::FormatAlign::Bottom),
+                    "super" => {
+                        self.script = Some(xlsx::FormatScript::Superscript);
+                        self.vertical_align
+                    }
+                    "sub" => {
+                        self.script = Some(xlsx::FormatScript::Subscript);
+                        self.vertical_align
+                    }
+                    _ => self.vertical_align,
+                }
+            }
+            "white-space" => self.text_wrap = Some(matches!(lower.as_str(), "normal" | "pre-wrap" | "pre-line")),
+            "border" => {
+                let border = parse_border(&lower);
+                self.borders = [border; 4];
+            }
+            "border-top" => self.borders[0] = parse_border(&lower),
+            "border-bottom" => self.borders[1] = parse_border(&lower),
+            "border-left" => self.borders[2] = parse_border(&lower),
+            "border-right" => self.borders[3] = parse_border(&lower),
+            "border-width" => {
+                for (border, value) in self.borders.iter_mut().zip(box_values(&lower)) {
+                    border.width = parse_border_width(value);
+                }
+            }
+            "border-style" => {
+                for (border, value) in self.borders.iter_mut().zip(box_values(&lower)) {
+                    border.style = parse_border_style(value);
+                }
+            }
+            "border-color" => {
+                for (border, value) in self.borders.iter_mut().zip(box_values(&lower)) {
+                    border.color = parse_color(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Override the properties that are set in `other`.
+    fn overlay(&mut self, other: &CssStyle) {
+        self.overlay_font(other);
+        self.background = other.background.or(self.background);
+        self.align = other.align.or(self.align);
+        self.vertical_align = other.vertical_align.or(self.vertical_align);
+        self.text_wrap = other.text_wrap.or(self.text_wrap);
+        for (border, other) in self.borders.iter_mut().zip(other.borders) {
+            if other.style.is_some() || other.width.is_some() || other.color.is_some() {
+                *border = other;
+            }
+        }
+    }
+
+    /// Override the font properties that are set in `other`.
+    fn overlay_font(&mut self, other: &CssStyle) {
+        self.bold = other.bold.or(self.bold);
+        self.italic = other.italic.or(self.italic);
+        self.underline = other.underline.or(self.underline);
+        self.strikethrough = other.strikethrough.or(self.strikethrough);
+        self.script = other.script.or(self.script);
+        self.font_name = other.font_name.clone().or(self.font_name.take());
+        self.font_size = other.font_size.or(self.font_size);
+        self.color = other.color.or(self.color);
+    }
+
+    /// Convert the style to a format, or `None` if it doesn't change the
+    /// default format.
+    fn to_format(&self) -> Option<FormatProperties> {
+        let mut format = FormatProperties::default();
+        if self.bold == Some(true) {
+            format = format.set_bold();
+        }
+        if self.italic == Some(true) {
+            format = format.set_italic();
+        }
+        if self.underline == Some(true) {
+            format = format.set_underline(xlsx::FormatUnderline::Single);
+        }
+        if self.strikethrough == Some(true) {
+            format = format.set_font_strikethrough();
+        }
+        if let Some(script) = self.script {
+            format = format.set_font_script(script);
+        }
+        if let Some(name) = &self.font_name {
+            format = format.set_font_name(name);
+        }
+        if let Some(size) = self.font_size {
+            format = format.set_font_size(size);
+        }
+        if let Some(color) = self.color {
+            format = format.set_font_color(xlsx::Color::RGB(color));
+        }
+        if let Some(color) = self.background {
+            format = format.set_background_color(xlsx::Color::RGB(color));
+        }
+        if let Some(align) = self.align {
+            format = format.set_align(align);
+        }
+        if let Some(align) = self.vertical_align {
+            format = format.set_align(align);
+        }
+        if self.text_wrap == Some(true) {
+            format = format.set_text_wrap();
+        }
+        let [top, bottom, left, right] = self.borders.map(|border| border.to_format_border());
+        if let Some((style, color)) = top {
+            format = format.set_border_top(style).set_border_top_color(color);
+        }
+        if let Some((style, color)) = bottom {
+            format = format.set_border_bottom(style).set_border_bottom_color(color);
+        }
+        if let Some((style, color)) = left {
+            format = format.set_border_left(style).set_border_left_color(color);
+        }
+        if let Some((style, color)) = right {
+            format = format.set_border_right(style).set_border_right_color(color);
+        }
+        (format != FormatProperties::default()).then_some(format)
+    }
+}
+
+impl BorderCss {
+    /// Map a CSS border to the closest Excel border.
+    fn to_format_border(self) -> Option<(xlsx::FormatBorder, xlsx::Color)> {
+        let style = self.style?;
+        // Most borders are 1px so that is assumed if there is no width.
+        let width = self.width.unwrap_or(1.0);
+        let border = match style {
+            BorderStyle::None => return None,
+            _ if width <= 0.0 => return None,
+            BorderStyle::Double => xlsx::FormatBorder::Double,
+            BorderStyle::Dotted => xlsx::FormatBorder::Dotted,
+            BorderStyle::Dashed if width >= 2.0 => xlsx::FormatBorder::MediumDashed,
+            BorderStyle::Dashed => xlsx::FormatBorder::Dashed,
+            BorderStyle::Solid if width < 1.5 => xlsx::FormatBorder::Thin,
+            BorderStyle::Solid if width < 2.5 => xlsx::FormatBorder::Medium,
+            BorderStyle::Solid => xlsx::FormatBorder::Thick,
+        };
+        let color = self.color.map_or(xlsx::Color::Default, xlsx::Color::RGB);
+        Some((border, color))
+    }
+}
+
+/// Parse a `border` shorthand like `1px solid #000`.
+fn parse_border(value: &str) -> BorderCss {
+    let mut border = BorderCss {
+        style: Some(BorderStyle::None),
+        ..BorderCss::default()
+    };
+    for token in css_tokens(value) {
+        if let Some(style) = parse_border_style(token) {
+            border.style = Some(style);
+        } else if let Some(width) = parse_border_width(token) {
+            border.width = Some(width);
+        } else if let Some(color) = parse_color(token) {
+            border.color = Some(color);
+        }
+    }
+    border
+}
+
+fn parse_border_style(value: &str) -> Option<BorderStyle> {
+    match value {
+        "none" | "hidden" => Some(BorderStyle::None),
+        "solid" | "groove" | "ridge" | "inset" | "outset" => Some(BorderStyle::Solid),
+        "dotted" => Some(BorderStyle::Dotted),
+        "dashed" => Some(BorderStyle::Dashed),
+        "double" => Some(BorderStyle::Double),
+        _ => None,
+    }
+}
+
+fn parse_border_width(value: &str) -> Option<f64> {
+    match value {
+        "thin" => Some(1.0),
+        "medium" => Some(2.0),
+        "thick" => Some(3.0),
+        value => parse_length(value),
+    }
+}
+
+/// Expand the 1 to 4 values of a box property like `border-width` to the
+/// top, bottom, left and right values.
+fn box_values(value: &str) -> Vec<&str> {
+    let values = css_tokens(value);
+    let [top, right, bottom, left] = match values[..] {
+        [all] => [all; 4],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left, ..] => [top, right, bottom, left],
+        [] => return vec![],
+    };
+    vec![top, bottom, left, right]
+}
+
+/// Split a CSS value on whitespace, keeping functions like `rgb(1, 2, 3)`
+/// together.
+fn css_tokens(value: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut depth = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    tokens.push(&value[start..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(start) = start {
+        tokens.push(&value[start..]);
+    }
+    tokens
+}
+
+/// Parse a CSS length to pixels.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let number = value[..split].parse::<f64>().ok()?;
+    let px = match &value[split..] {
+        "px" | "" => number,
+        "pt" => number / 0.75,
+        "em" | "rem" => number * 16.0,
+        "pc" => number * 16.0,
+        "in" => number * 96.0,
+        "cm" => number * 96.0 / 2.54,
+        "mm" => number * 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(px)
+}
+
+fn first_font_family(value: &str) -> Option<String> {
+    let family = value.split(',').next()?.trim().trim_matches(['"', '\'']).trim();
+    let generic = ["serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui", "inherit"];
+    (!family.is_empty() && !generic.contains(&family.to_ascii_lowercase().as_str())).then(|| family.to_string())
+}
+
+/// Parse a CSS color to RGB. Transparent and unknown colors are ignored.
+fn parse_color(value: &str) -> Option<u32> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u32> = hex.chars().map(|c| c.to_digit(16)).collect::<Option<_>>()?;
+        return match digits[..] {
+            [r, g, b] | [r, g, b, _] => Some((r * 17) << 16 | (g * 17) << 8 | (b * 17)),
+            [r1, r2, g1, g2, b1, b2] | [r1, r2, g1, g2, b1, b2, _, _] => {
+                Some((r1 << 4 | r2) << 16 | (g1 << 4 | g2) << 8 | (b1 << 4 | b2))
+            }
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|args| args.strip_suffix(')'))
+    {
+        let channels: Vec<&str> = args.split([',', ' ', '/']).filter(|arg| !arg.is_empty()).collect();
+        if channels.len() == 4 && channels[3].parse::<f64>().ok()? == 0.0 {
+            return None;
+        }
+        let channel = |arg: &str| -> Option<u32> {
+            let value = match arg.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok()? * 2.55,
+                None => arg.parse::<f64>().ok()?,
+            };
+            Some(value.round().clamp(0.0, 255.0) as u32)
+        };
+        return match channels[..] {
+            [r, g, b, ..] => Some(channel(r)? << 16 | channel(g)? << 8 | channel(b)?),
+            _ => None,
+        };
+    }
+    let rgb = match value.as_str() {
+        "black" => 0x000000,
+        "white" => 0xFFFFFF,
+        "red" => 0xFF0000,
+        "green" => 0x008000,
+        "lime" => 0x00FF00,
+        "blue" => 0x0000FF,
+        "yellow" => 0xFFFF00,
+        "cyan" | "aqua" => 0x00FFFF,
+        "magenta" | "fuchsia" => 0xFF00FF,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xC0C0C0,
+        "lightgray" | "lightgrey" => 0xD3D3D3,
+        "darkgray" | "darkgrey" => 0xA9A9A9,
+        "maroon" => 0x800000,
+        "navy" => 0x000080,
+        "olive" => 0x808000,
+        "purple" => 0x800080,
+        "teal" => 0x008080,
+        "orange" => 0xFFA500,
+        "brown" => 0xA52A2A,
+        "pink" => 0xFFC0CB,
+        "gold" => 0xFFD700,
+        "darkred" => 0x8B0000,
+        "darkgreen" => 0x006400,
+        "darkblue" => 0x00008B,
+        "lightblue" => 0xADD8E6,
+        "lightgreen" => 0x90EE90,
+        "lightyellow" => 0xFFFFE0,
+        _ => return None,
+    };
+    Some(rgb)
+}
+
+/// Detect a number, with the number format that displays it like the text.
+fn detect_number(text: &str) -> Option<(f64, Option<String>)> {
+    let mut text = text.trim();
+    let mut negative = false;
+    let parenthesized = text.starts_with('(') && text.ends_with(')');
+    if parenthesized {
+        negative = true;
+        text = &text[1..text.len() - 1];
+    }
+    for sign in ['-', '\u{2212}'] {
+        if let Some(rest) = text.strip_prefix(sign) {
+            negative = !negative;
+            text = rest;
+            break;
+        }
+    }
+    let mut currency = None;
+    for symbol in ['$', '\u{20AC}', '\u{00A3}', '\u{00A5}'] {
+        if let Some(rest) = text.strip_prefix(symbol) {
+            currency = Some(symbol);
+            text = rest.trim_start();
+            break;
+        }
+    }
+    let percent = text.ends_with('%');
+    let text = text.trim_end_matches('%');
+
+    let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+    if int.is_empty() || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let grouped = int.contains(',');
+    let mut groups = int.split(',');
+    let first = groups.next()?;
+    if first.is_empty()
+        || !first.chars().all(|c| c.is_ascii_digit())
+        || (grouped && first.len() > 3)
+        || !groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()))
+    {
+        return None;
+    }
+    let digits: String = int.chars().filter(|c| c.is_ascii_digit()).chain(frac.chars()).collect();
+    // Keep ids with leading zeros and long numbers that Excel would round as
+    // text.
+    let significant = digits.trim_start_matches('0').len();
+    if (first.len() > 1 && first.starts_with('0')) || significant > 15 {
+        return None;
+    }
+
+    let mut number: f64 = format!("{}.{frac}", int.replace(',', "")).trim_end_matches('.').parse().ok()?;
+    if negative {
+        number = -number;
+    }
+    let decimals = if frac.is_empty() {
+        String::new()
+    } else {
+        format!(".{}", "0".repeat(frac.len()))
+    };
+    let num_format = if percent {
+        number /= 100.0;
+        Some(format!("0{decimals}%"))
+    } else if let Some(symbol) = currency {
+        let prefix = if symbol == '$' {
+            "$".to_string()
+        } else {
+            format!("[${symbol}]")
+        };
+        let positive = format!("{prefix}#,##0{decimals}");
+        if parenthesized {
+            Some(format!("{positive};({positive})"))
+        } else {
+            Some(positive)
+        }
+    } else if grouped {
+        Some(format!("#,##0{decimals}"))
+    } else if !frac.is_empty() && frac.ends_with('0') {
+        Some(format!("0{decimals}"))
+    } else {
+        None
+    };
+    Some((number, num_format))
+}
+
+/// Detect an ISO 8601 date or date time. Returns the date and whether it has
+/// a time.
+fn detect_date(text: &str) -> Option<(xlsx::ExcelDateTime, bool)> {
+    let bytes = text.as_bytes();
+    let is_digits = |range: std::ops::Range<usize>| bytes.get(range).is_some_and(|b| b.iter().all(u8::is_ascii_digit));
+    let is_date = bytes.len() >= 10 && is_digits(0..4) && bytes[4] == b'-' && is_digits(5..7) && bytes[7] == b'-' && is_digits(8..10);
+    if !is_date {
+        return None;
+    }
+    let has_time = match &text[10..] {
+        "" => false,
+        time => {
+            let time = time.strip_suffix('Z').unwrap_or(time);
+            let mut chars = time.chars();
+            if !matches!(chars.next(), Some('T' | ' ')) {
+                return None;
+            }
+            let time = chars.as_str();
+            let valid = time.split(':').enumerate().all(|(i, part)| {
+                let whole = if i == 2 { part.split('.').next().unwrap_or_default() } else { part };
+                whole.len() == 2 && part.chars().all(|c| c.is_ascii_digit() || (i == 2 && c == '.'))
+            });
+            if !valid || !(2..=3).contains(&time.split(':').count()) {
+                return None;
+            }
+            true
+        }
+    };
+    let datetime = xlsx::ExcelDateTime::parse_from_str(text).ok()?;
+    Some((datetime, has_time))
+}
+
+/// Collapse runs of whitespace to a single space, like HTML rendering.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        // Non-breaking spaces are kept.
+        if c.is_whitespace() && c != '\u{A0}' {
+            if !space {
+                collapsed.push(' ');
+            }
+            space = true;
+        } else {
+            collapsed.push(if c == '\u{A0}' { ' ' } else { c });
+            space = false;
+        }
+    }
+    collapsed
+}
+
+#[derive(Default)]
+struct Tag {
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+enum Token {
+    Open(Tag),
+    Close(String),
+    Text(String),
+}
+
+/// A lenient HTML tokenizer. Comments, doctypes and the contents of
+/// `<script>` and `<style>` elements are skipped.
+struct Tokenizer<'a> {
+    html: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.html[self.pos..]
+    }
+
+    /// Skip past the next occurrence of `pattern`, or to the end.
+    fn skip_past(&mut self, pattern: &str) {
+        self.pos = match self.rest().find(pattern) {
+            Some(i) => self.pos + i + pattern.len(),
+            None => self.html.len(),
+        };
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return None;
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = decode_entities(&rest[..end]);
+                self.pos += end;
+                return Some(Token::Text(text));
+            }
+            if rest.starts_with("<!--") {
+                self.skip_past("-->");
+                continue;
+            }
+            if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.skip_past(">");
+                continue;
+            }
+            let closing = rest.starts_with("</");
+            let name_start = if closing { 2 } else { 1 };
+            let name_len = rest[name_start..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+                .unwrap_or(rest.len() - name_start);
+            if name_len == 0 {
+                self.pos += 1;
+                return Some(Token::Text("<".to_string()));
+            }
+            let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+            self.pos += name_start + name_len;
+            if closing {
+                self.skip_past(">");
+                return Some(Token::Close(name));
+            }
+            let attributes = self.attributes();
+            if name == "script" || name == "style" {
+                self.skip_past(&format!("</{name}"));
+                self.skip_past(">");
+                continue;
+            }
+            return Some(Token::Open(Tag { name, attributes }));
+        }
+    }
+
+    /// Parse the attributes of a tag, up to and including the closing `>`.
+    fn attributes(&mut self) -> Vec<(String, String)> {
+        let mut attributes = vec![];
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+            self.pos += rest.len() - trimmed.len();
+            let rest = self.rest();
+            if rest.is_empty() {
+                return attributes;
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                return attributes;
+            }
+            let name_len = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+                .unwrap_or(rest.len())
+                .max(1);
+            let name = rest[..name_len].to_ascii_lowercase();
+            self.pos += name_len;
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            let mut value = String::new();
+            if let Some(after_equals) = trimmed.strip_prefix('=') {
+                let after_equals = after_equals.trim_start();
+                self.pos += rest.len() - after_equals.len();
+                let quote = after_equals.chars().next().filter(|c| *c == '"' || *c == '\'');
+                let raw = match quote {
+                    Some(quote) => {
+                        let inner = &after_equals[1..];
+                        let end = inner.find(quote).unwrap_or(inner.len());
+                        self.pos += 1 + end + usize::from(end < inner.len());
+                        inner[..end].to_string()
+                    }
+                    None => {
+                        let end = after_equals
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after_equals.len());
+                        self.pos += end;
+                        after_equals[..end].to_string()
+                    }
+                };
+                value = decode_entities(&raw);
+            }
+            attributes.push((name, value));
+        }
+    }
+}
+
+/// Decode the common named and numeric character references.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..=end]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{A0}'),
+            "euro" => Some('\u{20AC}'),
+            "pound" => Some('\u{00A3}'),
+            "yen" => Some('\u{00A5}'),
+            "copy" => Some('\u{00A9}'),
+            "ndash" => Some('\u{2013}'),
+            "mdash" => Some('\u{2014}'),
+            _ => {
+                let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, c) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
@@ -1,10 +1,21 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+};
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use rust_xlsxwriter as xlsx;
 use wasm_bindgen::prelude::*;
 
+use crate::error::XlsxError;
 use crate::wrapper::WasmResult;
 use crate::wrapper::object_movement::ObjectMovement;
+use crate::wrapper::png;
+use crate::wrapper::svg::{self, SvgImage, SvgOptions};
+
+/// The largest width or height of an SVG image without a fallback image, in
+/// pixels. The SVG is rendered to a fallback image with the size of the SVG.
+const MAX_RASTER_SIZE: u32 = 4096;
 
 /// Since the xlsx::Image does not have a default value, we use the smallest PNG image data as a dummy data.
 fn new_dummy_image() -> xlsx::Image {
@@ -26,6 +37,9 @@ fn new_dummy_image() -> xlsx::Image {
 #[wasm_bindgen]
 pub struct Image {
     pub(crate) inner: Arc<Mutex<xlsx::Image>>,
+    /// The SVG version of the image, if it was created with
+    /// {@link Image.fromSvg}.
+    pub(crate) svg: Option<Arc<SvgImage>>,
 }
 
 macro_rules! impl_method {
@@ -39,6 +53,7 @@ macro_rules! impl_method {
         let _ = std::mem::replace(&mut *lock, inner);
         return Image {
             inner: Arc::clone(&$self.inner),
+            svg: $self.svg.clone(),
         }
     };
 }
@@ -65,10 +80,8 @@ impl Image {
     /// same way as other image file formats. It allows SVG to be inserted into
     /// a worksheet but converts them to, and displays them as, PNG files. It
     /// stores the original SVG image in the file so the original format can be
-    /// retrieved. This removes the file size and resolution advantage of using
-    /// SVG files. As such SVG files are not supported by `rust_xlsxwriter`
-    /// since a conversion to the PNG format would be required and that format
-    /// is already supported.
+    /// retrieved. SVG images aren't supported by this constructor, use
+    /// {@link Image.fromSvg} instead.
     ///
     /// @param {array} buffer - The image data as a u8 array or vector.
     /// @returns {Image} - The Image object.
@@ -83,6 +96,149 @@ impl Image {
         let image = xlsx::Image::new_from_buffer(&buffer)?;
         Ok(Image {
             inner: Arc::new(Mutex::new(image)),
+            svg: None,
+        })
+    }
+
+    /// Create an Image object from raw RGBA pixels.
+    ///
+    /// The pixels are encoded as a PNG image. This can be used for pixels
+    /// read from a `<canvas>`, for example with the `data` of the `ImageData`
+    /// returned by `getImageData()`, without encoding them in JS first:
+    ///
+    /// ```javascript
+    /// const { width, height, data } = context.getImageData(0, 0, w, h);
+    /// const image = Image.fromRgba(width, height, new Uint8Array(data.buffer));
+    /// ```
+    ///
+    /// @param {number} width - The width of the image in pixels.
+    /// @param {number} height - The height of the image in pixels.
+    /// @param {Uint8Array} data - The pixels in row major order, with 4 bytes,
+    ///   red, green, blue and alpha, per pixel.
+    /// @returns {Image} - The Image object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The width or height is 0, or the
+    ///   data doesn't have 4 bytes per pixel.
+    #[wasm_bindgen(js_name = "fromRgba", skip_jsdoc)]
+    pub fn from_rgba(width: u32, height: u32, data: &[u8]) -> WasmResult<Image> {
+        if width == 0 || height == 0 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Image dimensions must be non-zero: {width}x{height}"
+            ))));
+        }
+        if (data.len() as u64) != u64::from(width) * u64::from(height) * 4 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Image data of {} bytes doesn't match the RGBA pixels of a {width}x{height} image",
+                data.len()
+            ))));
+        }
+        Image::new(png::encode_rgba(width, height, data))
+    }
+
+    /// Create an Image object from a base64 data URL.
+    ///
+    /// The data URL is decoded and the image is read like in
+    /// {@link Image.constructor}, so it must be a PNG, JPG, GIF or BMP
+    /// image, such as a URL returned by `canvas.toDataURL()`.
+    ///
+    /// @param {string} url - A data URL such as `data:image/png;base64,...`.
+    /// @returns {Image} - The Image object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The string isn't a base64 data
+    ///   URL.
+    /// - [`XlsxError::UnknownImageType`] - Unknown image type.
+    #[wasm_bindgen(js_name = "fromDataUrl", skip_jsdoc)]
+    pub fn from_data_url(url: &str) -> WasmResult<Image> {
+        let invalid = || {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The string isn't a base64 data URL".to_string(),
+            ))
+        };
+        let (media_type, data) = url
+            .trim()
+            .strip_prefix("data:")
+            .and_then(|url| url.split_once(','))
+            .ok_or_else(invalid)?;
+        if !media_type
+            .rsplit(';')
+            .next()
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("base64"))
+        {
+            return Err(invalid());
+        }
+        // Data URLs in HTML attributes may be wrapped over several lines.
+        let data: String = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        let buffer = BASE64.decode(data).map_err(|_| invalid())?;
+        Image::new(buffer)
+    }
+
+    /// Create an Image object from an SVG image.
+    ///
+    /// Excel 2016 and later show the SVG image in worksheet drawings, such as
+    /// images inserted with {@link Worksheet#insertImage}. Older viewers show
+    /// a raster fallback image instead, which is also used for cell images
+    /// and header/footer images since they don't support SVG.
+    ///
+    /// The fallback image can be set with {@link SvgOptions#setFallback}, for
+    /// example a PNG image rendered from the SVG with a `<canvas>`. Without it
+    /// the SVG is rendered to a PNG image with the size of the SVG. The text
+    /// of the SVG isn't rendered since no fonts are available.
+    ///
+    /// The size of the image is read from the `width`, `height` and `viewBox`
+    /// attributes of the SVG, or set with {@link SvgOptions#setSize}.
+    ///
+    /// @param {Uint8Array} buffer - The SVG image data.
+    /// @param {SvgOptions} options - The options for the SVG image. Optional.
+    /// @returns {Image} - The Image object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The data isn't an SVG image, or the
+    ///   fallback image isn't a PNG image, or the image has no fallback image
+    ///   and no size, or a width or height larger than 4096 pixels.
+    #[wasm_bindgen(js_name = "fromSvg", skip_jsdoc)]
+    pub fn from_svg(buffer: Vec<u8>, options: Option<SvgOptions>) -> WasmResult<Image> {
+        let options = options.unwrap_or_default();
+        let size = options.size.or(svg::parse_svg(&buffer)?);
+        let fallback = match (options.fallback, size) {
+            (Some(fallback), _) => fallback,
+            (None, Some((width, height))) => {
+                if width > MAX_RASTER_SIZE || height > MAX_RASTER_SIZE {
+                    return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                        "The SVG image is larger than {MAX_RASTER_SIZE}x{MAX_RASTER_SIZE} pixels. \
+                         Set a fallback image with SvgOptions"
+                    ))));
+                }
+                svg::rasterize(&buffer, width, height)?
+            }
+            (None, None) => {
+                return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                    "The SVG image has no size. Set the size or a fallback image with SvgOptions".to_string(),
+                )))
+            }
+        };
+        // The SVG images are found by their fallback image when the workbook
+        // is saved, so the hash of the SVG makes the fallback images distinct
+        // from each other and from other raster images.
+        let mut hasher = DefaultHasher::new();
+        buffer.hash(&mut hasher);
+        let fallback = png::add_text(&fallback, "Source", &format!("{:016x}", hasher.finish())).ok_or_else(|| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The fallback image of an SVG image must be a PNG image".to_string(),
+            ))
+        })?;
+
+        let mut image = xlsx::Image::new_from_buffer(&fallback)?;
+        if let Some((width, height)) = size {
+            image = image.set_width(width).set_height(height);
+        }
+        Ok(Image {
+            inner: Arc::new(Mutex::new(image)),
+            svg: Some(Arc::new(SvgImage { svg: buffer, fallback })),
         })
     }
 
@@ -1,20 +1,44 @@
 mod chart;
+mod cell_store;
+mod cell_style;
 mod color;
+mod compound_file;
+mod date_conversion;
 mod datetime;
+mod defined_name;
 mod doc_properties;
+mod encryption;
 mod excel_data;
 mod format;
+mod format_object;
+mod format_properties;
 mod formula;
 mod header_image_position;
+mod html;
+mod html_table;
+mod ignore_error;
 mod image;
 mod note;
+mod num_format;
+mod number_format;
 mod object_movement;
+mod package;
+mod pivot_table;
+mod png;
 mod rich_string;
+mod save_options;
+mod svg;
 mod table;
+mod table_style;
+mod template;
+mod theme;
 mod url;
 mod utils;
+mod vba;
 mod workbook;
+mod workbook_settings;
 mod worksheet;
+mod write_policy;
 mod conditional_format;
 
 use crate::error::XlsxError;
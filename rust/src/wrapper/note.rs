@@ -86,7 +86,7 @@ impl Note {
     #[doc(hidden)]
     #[wasm_bindgen(js_name = "setFormat", skip_jsdoc)]
     pub fn set_format(&self, format: Format) -> Note {
-        impl_method!(self.set_format(&*format.lock()));
+        impl_method!(self.set_format(&format.to_format()));
     }
     #[wasm_bindgen(js_name = "setAltText", skip_jsdoc)]
     pub fn set_alt_text(&self, alt_text: &str) -> Note {
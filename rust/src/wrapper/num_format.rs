@@ -0,0 +1,680 @@
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::WasmResult;
+
+/// The maximum number of decimal places in an Excel number format.
+const MAX_DECIMAL_PLACES: u8 = 30;
+
+/// The `NumFormat` struct is a builder for Excel number format codes.
+///
+/// Number format codes like `#,##0.00 [$€-407];[Red]-#,##0.00 [$€-407]` are
+/// easy to get wrong, especially for currencies and dates in different
+/// locales. `NumFormat` builds them from their parts and validates them. The
+/// format code is returned by {@link NumFormat#toString} and can be used
+/// with {@link Format#setNumFormat} or {@link ChartAxis#setNumFormat}.
+///
+/// A new `NumFormat` displays numbers as integers, like `0`. The locale set
+/// with {@link NumFormat#setLocale} chooses the default currency and the date
+/// patterns. Excel always stores the format with `.` as the decimal point and
+/// `,` as the thousands separator; they are displayed with the separators of
+/// the user's system.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct NumFormat {
+    locale: &'static Locale,
+    kind: Kind,
+    decimal_places: Option<u8>,
+    thousands_separator: bool,
+    currency: Option<String>,
+    currency_placement: Option<CurrencyPlacement>,
+    negative_style: NegativeStyle,
+    prefix: String,
+    suffix: String,
+    text_section: Option<(String, String)>,
+    conditions: Vec<(String, f64, NumFormat)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Number,
+    Percent,
+    Scientific,
+    Date(DateStyle),
+    DatePattern(String),
+}
+
+/// The `CurrencyPlacement` enum defines where the currency symbol of a
+/// {@link NumFormat} is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum CurrencyPlacement {
+    /// Before the number, like `$1.00`.
+    Before,
+    /// Before the number with a space, like `R$ 1,00`.
+    BeforeWithSpace,
+    /// After the number, like `1.00€`.
+    After,
+    /// After the number with a space, like `1,00 €`.
+    AfterWithSpace,
+}
+
+/// The `NegativeStyle` enum defines how a {@link NumFormat} displays negative
+/// numbers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum NegativeStyle {
+    /// With a minus sign, like `-1.00`.
+    #[default]
+    Minus,
+    /// In red with a minus sign.
+    Red,
+    /// In parentheses, like `(1.00)`.
+    Parentheses,
+    /// In red and in parentheses.
+    RedParentheses,
+}
+
+/// The `DateStyle` enum defines the locale date and time patterns of a
+/// {@link NumFormat}.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum DateStyle {
+    /// A numeric date, like `1/31/2024` in en-US or `31.01.2024` in de-DE.
+    Short,
+    /// A date with the month name, like `Wednesday, January 31, 2024`.
+    Long,
+    /// A time in hours and minutes.
+    Time,
+    /// A time in hours, minutes and seconds.
+    TimeWithSeconds,
+    /// A short date with a time in hours and minutes.
+    DateTime,
+}
+
+/// The number and date conventions of a locale.
+#[derive(Debug, PartialEq)]
+struct Locale {
+    tag: &'static str,
+    /// The Windows locale id used in `[$-409]` tags.
+    lcid: &'static str,
+    currency: &'static str,
+    currency_placement: CurrencyPlacement,
+    currency_decimal_places: u8,
+    short_date: &'static str,
+    long_date: &'static str,
+    time: &'static str,
+    time_with_seconds: &'static str,
+}
+
+const LOCALES: &[Locale] = &[
+    Locale {
+        tag: "en-US",
+        lcid: "409",
+        currency: "$",
+        currency_placement: CurrencyPlacement::Before,
+        currency_decimal_places: 2,
+        short_date: "m/d/yyyy",
+        long_date: "dddd, mmmm d, yyyy",
+        time: "h:mm AM/PM",
+        time_with_seconds: "h:mm:ss AM/PM",
+    },
+    Locale {
+        tag: "en-GB",
+        lcid: "809",
+        currency: "£",
+        currency_placement: CurrencyPlacement::Before,
+        currency_decimal_places: 2,
+        short_date: "dd/mm/yyyy",
+        long_date: "dd mmmm yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "de-DE",
+        lcid: "407",
+        currency: "€",
+        currency_placement: CurrencyPlacement::AfterWithSpace,
+        currency_decimal_places: 2,
+        short_date: "dd.mm.yyyy",
+        long_date: "dddd, d. mmmm yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "fr-FR",
+        lcid: "40C",
+        currency: "€",
+        currency_placement: CurrencyPlacement::AfterWithSpace,
+        currency_decimal_places: 2,
+        short_date: "dd/mm/yyyy",
+        long_date: "dddd d mmmm yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "es-ES",
+        lcid: "C0A",
+        currency: "€",
+        currency_placement: CurrencyPlacement::AfterWithSpace,
+        currency_decimal_places: 2,
+        short_date: "dd/mm/yyyy",
+        long_date: "dddd, d \"de\" mmmm \"de\" yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "it-IT",
+        lcid: "410",
+        currency: "€",
+        currency_placement: CurrencyPlacement::AfterWithSpace,
+        currency_decimal_places: 2,
+        short_date: "dd/mm/yyyy",
+        long_date: "dddd d mmmm yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "pt-BR",
+        lcid: "416",
+        currency: "R$",
+        currency_placement: CurrencyPlacement::BeforeWithSpace,
+        currency_decimal_places: 2,
+        short_date: "dd/mm/yyyy",
+        long_date: "dddd, d \"de\" mmmm \"de\" yyyy",
+        time: "hh:mm",
+        time_with_seconds: "hh:mm:ss",
+    },
+    Locale {
+        tag: "ja-JP",
+        lcid: "411",
+        currency: "¥",
+        currency_placement: CurrencyPlacement::Before,
+        currency_decimal_places: 0,
+        short_date: "yyyy/m/d",
+        long_date: "yyyy\"年\"m\"月\"d\"日\"",
+        time: "h:mm",
+        time_with_seconds: "h:mm:ss",
+    },
+    Locale {
+        tag: "zh-CN",
+        lcid: "804",
+        currency: "¥",
+        currency_placement: CurrencyPlacement::Before,
+        currency_decimal_places: 2,
+        short_date: "yyyy/m/d",
+        long_date: "yyyy\"年\"m\"月\"d\"日\"",
+        time: "h:mm",
+        time_with_seconds: "h:mm:ss",
+    },
+    Locale {
+        tag: "ko-KR",
+        lcid: "412",
+        currency: "₩",
+        currency_placement: CurrencyPlacement::Before,
+        currency_decimal_places: 0,
+        short_date: "yyyy-mm-dd",
+        long_date: "yyyy\"년\" m\"월\" d\"일\" dddd",
+        time: "AM/PM h:mm",
+        time_with_seconds: "AM/PM h:mm:ss",
+    },
+];
+
+impl Default for NumFormat {
+    fn default() -> Self {
+        NumFormat {
+            locale: &LOCALES[0],
+            kind: Kind::Number,
+            decimal_places: None,
+            thousands_separator: false,
+            currency: None,
+            currency_placement: None,
+            negative_style: NegativeStyle::default(),
+            prefix: String::new(),
+            suffix: String::new(),
+            text_section: None,
+            conditions: vec![],
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl NumFormat {
+    /// Create a new `NumFormat` for integers in the en-US locale.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> NumFormat {
+        NumFormat::default()
+    }
+
+    /// Set the locale of the number format.
+    ///
+    /// The locale chooses the currency symbol, its placement and the number
+    /// of decimal places used by {@link NumFormat#setCurrency}, and the
+    /// patterns used by {@link NumFormat#setDate}.
+    ///
+    /// The supported locales are `en-US`, `en-GB`, `de-DE`, `fr-FR`, `es-ES`,
+    /// `it-IT`, `pt-BR`, `ja-JP`, `zh-CN` and `ko-KR`.
+    ///
+    /// @param {string} locale - The BCP 47 language tag of the locale.
+    /// @returns {NumFormat} - The NumFormat object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The locale isn't supported.
+    #[wasm_bindgen(js_name = "setLocale", skip_jsdoc)]
+    pub fn set_locale(&self, locale: &str) -> WasmResult<NumFormat> {
+        let tag = locale.replace('_', "-");
+        let Some(locale) = LOCALES.iter().find(|known| known.tag.eq_ignore_ascii_case(&tag)) else {
+            return Err(parameter_error(format!("Unsupported locale '{tag}'")));
+        };
+        Ok(NumFormat {
+            locale,
+            ..self.clone()
+        })
+    }
+
+    /// Set the number of decimal places.
+    ///
+    /// @param {number} places - The number of decimal places, from 0 to 30.
+    /// @returns {NumFormat} - The NumFormat object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - More than 30 decimal places.
+    #[wasm_bindgen(js_name = "setDecimalPlaces", skip_jsdoc)]
+    pub fn set_decimal_places(&self, places: u8) -> WasmResult<NumFormat> {
+        if places > MAX_DECIMAL_PLACES {
+            return Err(parameter_error(format!(
+                "Number formats can't have more than {MAX_DECIMAL_PLACES} decimal places"
+            )));
+        }
+        Ok(NumFormat {
+            decimal_places: Some(places),
+            ..self.clone()
+        })
+    }
+
+    /// Turn the thousands separator on or off.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setThousandsSeparator", skip_jsdoc)]
+    pub fn set_thousands_separator(&self, enable: bool) -> NumFormat {
+        NumFormat {
+            thousands_separator: enable,
+            ..self.clone()
+        }
+    }
+
+    /// Display the number as a currency amount.
+    ///
+    /// This also turns on the thousands separator and, unless
+    /// {@link NumFormat#setDecimalPlaces} has been called, sets the decimal
+    /// places of the locale currency.
+    ///
+    /// @param {string | undefined} symbol - The currency symbol, like `"€"`
+    ///   or `"CHF"`. The default is the symbol of the locale.
+    /// @returns {NumFormat} - The NumFormat object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The symbol is empty or contains
+    ///   `[`, `]` or `;`.
+    #[wasm_bindgen(js_name = "setCurrency", skip_jsdoc)]
+    pub fn set_currency(&self, symbol: Option<String>) -> WasmResult<NumFormat> {
+        let symbol = symbol.unwrap_or_else(|| self.locale.currency.to_string());
+        if symbol.is_empty() || symbol.contains(['[', ']', ';']) {
+            return Err(parameter_error(format!("Invalid currency symbol '{symbol}'")));
+        }
+        let decimal_places = self.decimal_places.unwrap_or(self.locale.currency_decimal_places);
+        Ok(NumFormat {
+            currency: Some(symbol),
+            thousands_separator: true,
+            decimal_places: Some(decimal_places),
+            ..self.clone()
+        })
+    }
+
+    /// Set the placement of the currency symbol. The default is the placement
+    /// of the locale.
+    ///
+    /// @param {CurrencyPlacement} placement - The currency symbol placement.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setCurrencyPlacement", skip_jsdoc)]
+    pub fn set_currency_placement(&self, placement: CurrencyPlacement) -> NumFormat {
+        NumFormat {
+            currency_placement: Some(placement),
+            ..self.clone()
+        }
+    }
+
+    /// Set how negative numbers are displayed.
+    ///
+    /// @param {NegativeStyle} style - The negative number style.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setNegativeStyle", skip_jsdoc)]
+    pub fn set_negative_style(&self, style: NegativeStyle) -> NumFormat {
+        NumFormat {
+            negative_style: style,
+            ..self.clone()
+        }
+    }
+
+    /// Display the number as a percentage, like `12.5%` for 0.125.
+    ///
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setPercent", skip_jsdoc)]
+    pub fn set_percent(&self) -> NumFormat {
+        NumFormat {
+            kind: Kind::Percent,
+            ..self.clone()
+        }
+    }
+
+    /// Display the number in scientific notation, like `1.23E+04`.
+    ///
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setScientific", skip_jsdoc)]
+    pub fn set_scientific(&self) -> NumFormat {
+        NumFormat {
+            kind: Kind::Scientific,
+            ..self.clone()
+        }
+    }
+
+    /// Display the number as a date or time using a pattern of the locale.
+    ///
+    /// Patterns of locales other than en-US are tagged with the locale so
+    /// that Excel displays month and day names in its language.
+    ///
+    /// @param {DateStyle} style - The date or time style.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setDate", skip_jsdoc)]
+    pub fn set_date(&self, style: DateStyle) -> NumFormat {
+        NumFormat {
+            kind: Kind::Date(style),
+            ..self.clone()
+        }
+    }
+
+    /// Display the number as a date or time using a custom pattern.
+    ///
+    /// The pattern can contain the Excel date and time codes `y`, `m`, `d`,
+    /// `h`, `s`, `AM/PM`, `A/P`, elapsed time like `[h]`, fractional seconds
+    /// like `ss.00`, and literal text in double quotes. Separators like `-`,
+    /// `/`, `.`, `:`, `,` and spaces can be used without quotes.
+    ///
+    /// @param {string} pattern - The date pattern, like `"yyyy-mm-dd hh:mm"`.
+    /// @returns {NumFormat} - The NumFormat object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The pattern contains characters
+    ///   that aren't date or time codes.
+    #[wasm_bindgen(js_name = "setDatePattern", skip_jsdoc)]
+    pub fn set_date_pattern(&self, pattern: &str) -> WasmResult<NumFormat> {
+        validate_date_pattern(pattern)?;
+        Ok(NumFormat {
+            kind: Kind::DatePattern(pattern.to_string()),
+            ..self.clone()
+        })
+    }
+
+    /// Set literal text displayed before the number.
+    ///
+    /// @param {string} text - The prefix text.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setPrefix", skip_jsdoc)]
+    pub fn set_prefix(&self, text: &str) -> NumFormat {
+        NumFormat {
+            prefix: text.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Set literal text displayed after the number, like a unit.
+    ///
+    /// @param {string} text - The suffix text, like `" kg"`.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setSuffix", skip_jsdoc)]
+    pub fn set_suffix(&self, text: &str) -> NumFormat {
+        NumFormat {
+            suffix: text.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Add a text section, used when the cell holds text instead of a
+    /// number.
+    ///
+    /// The text of the cell is displayed between the prefix and suffix.
+    ///
+    /// @param {string} prefix - The text before the cell text.
+    /// @param {string} suffix - The text after the cell text.
+    /// @returns {NumFormat} - The NumFormat object.
+    #[wasm_bindgen(js_name = "setTextSection", skip_jsdoc)]
+    pub fn set_text_section(&self, prefix: &str, suffix: &str) -> NumFormat {
+        NumFormat {
+            text_section: Some((prefix.to_string(), suffix.to_string())),
+            ..self.clone()
+        }
+    }
+
+    /// Add a conditional section.
+    ///
+    /// Numbers that match the condition are displayed with `format`. Excel
+    /// allows up to 2 conditions, which are checked in the order they are
+    /// added; other numbers are displayed with this number format. For
+    /// example a condition `"<", 1` can display small numbers with more
+    /// decimal places.
+    ///
+    /// Conditions replace the negative number section so the negative style
+    /// isn't used with them.
+    ///
+    /// @param {string} operator - The comparison: `<`, `<=`, `>`, `>=`, `=`
+    ///   or `<>`.
+    /// @param {number} value - The value to compare the number to.
+    /// @param {NumFormat} format - The number format of the matching numbers.
+    /// @returns {NumFormat} - The NumFormat object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The operator is invalid, the
+    ///   value isn't finite, there are already 2 conditions, or `format` has
+    ///   its own conditions or text section.
+    #[wasm_bindgen(js_name = "addCondition", skip_jsdoc)]
+    pub fn add_condition(&self, operator: &str, value: f64, format: &NumFormat) -> WasmResult<NumFormat> {
+        if !["<", "<=", ">", ">=", "=", "<>"].contains(&operator) {
+            return Err(parameter_error(format!("Invalid condition operator '{operator}'")));
+        }
+        if !value.is_finite() {
+            return Err(parameter_error("Condition values must be finite numbers".to_string()));
+        }
+        if self.conditions.len() == 2 {
+            return Err(parameter_error("Number formats can't have more than 2 conditions".to_string()));
+        }
+        if !format.conditions.is_empty() || format.text_section.is_some() {
+            return Err(parameter_error(
+                "The format of a condition can't have conditions or a text section".to_string(),
+            ));
+        }
+        let mut num_format = self.clone();
+        num_format.conditions.push((operator.to_string(), value, format.clone()));
+        Ok(num_format)
+    }
+
+    /// Get the Excel number format code.
+    ///
+    /// @returns {string} - The number format code, for use with
+    ///   {@link Format#setNumFormat}.
+    #[wasm_bindgen(js_name = "toString", skip_jsdoc)]
+    pub fn format_code(&self) -> String {
+        let positive = self.positive_section();
+        let mut sections = if !self.conditions.is_empty() {
+            let mut sections: Vec<String> = self
+                .conditions
+                .iter()
+                .map(|(operator, value, format)| format!("[{operator}{value}]{}", format.positive_section()))
+                .collect();
+            sections.push(positive);
+            sections
+        } else if matches!(self.kind, Kind::Date(_) | Kind::DatePattern(_)) {
+            vec![positive]
+        } else {
+            match self.negative_style {
+                NegativeStyle::Minus if self.text_section.is_some() => {
+                    vec![positive.clone(), format!("-{positive}"), positive]
+                }
+                NegativeStyle::Minus => vec![positive],
+                NegativeStyle::Red => vec![positive.clone(), format!("[Red]-{positive}")],
+                NegativeStyle::Parentheses => vec![format!("{positive}_)"), format!("({positive})")],
+                NegativeStyle::RedParentheses => vec![format!("{positive}_)"), format!("[Red]({positive})")],
+            }
+        };
+        if let Some((prefix, suffix)) = &self.text_section {
+            // The text section is always the fourth section. The sections
+            // before it are padded with the section for positive numbers,
+            // which for conditional formats is the section without a
+            // condition.
+            let padding = if self.conditions.is_empty() {
+                sections[0].clone()
+            } else {
+                self.positive_section()
+            };
+            while sections.len() < 3 {
+                sections.push(padding.clone());
+            }
+            sections.truncate(3);
+            sections.push(format!("{}@{}", quote(prefix), quote(suffix)));
+        }
+        sections.join(";")
+    }
+}
+
+impl NumFormat {
+    /// Get the section for positive numbers, without conditions or colors.
+    fn positive_section(&self) -> String {
+        let decimals = match self.decimal_places {
+            Some(places) if places > 0 => format!(".{}", "0".repeat(places.into())),
+            _ => String::new(),
+        };
+        let integer = if self.thousands_separator { "#,##0" } else { "0" };
+        let number = match &self.kind {
+            Kind::Number => format!("{integer}{decimals}"),
+            Kind::Percent => format!("{integer}{decimals}%"),
+            Kind::Scientific => format!("0{decimals}E+00"),
+            Kind::Date(style) => return self.locale_tag() + self.date_pattern(*style),
+            Kind::DatePattern(pattern) => return pattern.clone(),
+        };
+        let number = match &self.currency {
+            Some(symbol) => {
+                let symbol = self.currency_code(symbol);
+                match self.currency_placement.unwrap_or(self.locale.currency_placement) {
+                    CurrencyPlacement::Before => format!("{symbol}{number}"),
+                    CurrencyPlacement::BeforeWithSpace => format!("{symbol} {number}"),
+                    CurrencyPlacement::After => format!("{number}{symbol}"),
+                    CurrencyPlacement::AfterWithSpace => format!("{number} {symbol}"),
+                }
+            }
+            None => number,
+        };
+        format!("{}{number}{}", quote(&self.prefix), quote(&self.suffix))
+    }
+
+    /// Get the format code of a currency symbol. Symbols other than `$` are
+    /// tagged with the locale, like `[$€-407]`, as in the formats that Excel
+    /// writes.
+    fn currency_code(&self, symbol: &str) -> String {
+        if symbol == "$" && self.locale.lcid == "409" {
+            "$".to_string()
+        } else {
+            format!("[${symbol}-{}]", self.locale.lcid)
+        }
+    }
+
+    fn locale_tag(&self) -> String {
+        if self.locale.lcid == "409" {
+            String::new()
+        } else {
+            format!("[$-{}]", self.locale.lcid)
+        }
+    }
+
+    fn date_pattern(&self, style: DateStyle) -> &'static str {
+        match style {
+            DateStyle::Short => self.locale.short_date,
+            DateStyle::Long => self.locale.long_date,
+            DateStyle::Time => self.locale.time,
+            DateStyle::TimeWithSeconds => self.locale.time_with_seconds,
+            DateStyle::DateTime => match self.locale.tag {
+                "en-US" => "m/d/yyyy h:mm",
+                "en-GB" | "fr-FR" | "es-ES" | "it-IT" | "pt-BR" => "dd/mm/yyyy hh:mm",
+                "de-DE" => "dd.mm.yyyy hh:mm",
+                "ko-KR" => "yyyy-mm-dd h:mm",
+                _ => "yyyy/m/d h:mm",
+            },
+        }
+    }
+}
+
+/// Quote literal text for a format code.
+fn quote(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    // Quoted text can't contain a double quote so they are escaped.
+    text.split('"')
+        .map(|part| if part.is_empty() { String::new() } else { format!("\"{part}\"") })
+        .collect::<Vec<_>>()
+        .join("\\\"")
+}
+
+/// Check that a custom date pattern only contains date and time codes and
+/// literal text.
+fn validate_date_pattern(pattern: &str) -> WasmResult<()> {
+    let invalid = || parameter_error(format!("Invalid date pattern '{pattern}'"));
+    let mut has_code = false;
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '"' {
+            rest[1..].find('"').ok_or_else(invalid)? + 2
+        } else if c == '\\' {
+            1 + rest[1..].chars().next().ok_or_else(invalid)?.len_utf8()
+        } else if c == '[' {
+            let end = rest.find(']').ok_or_else(invalid)?;
+            let inner = rest[1..end].to_ascii_lowercase();
+            let first = inner.chars().next();
+            let elapsed = matches!(first, Some('h' | 'm' | 's')) && inner.chars().all(|c| Some(c) == first);
+            if !elapsed && !inner.starts_with('$') {
+                return Err(invalid());
+            }
+            has_code |= elapsed;
+            end + 1
+        } else if rest.get(..5).is_some_and(|code| code.eq_ignore_ascii_case("AM/PM")) {
+            5
+        } else if rest.get(..3).is_some_and(|code| code.eq_ignore_ascii_case("A/P")) {
+            3
+        } else if "ymdhsegb".contains(c.to_ascii_lowercase()) {
+            has_code = true;
+            1
+        } else if c == '0' || " -/.:,()".contains(c) {
+            1
+        } else {
+            return Err(invalid());
+        };
+        rest = &rest[len..];
+    }
+    if has_code {
+        Ok(())
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parameter_error(message: String) -> XlsxError {
+    XlsxError::Xlsx(xlsx::XlsxError::ParameterError(message))
+}
@@ -0,0 +1,994 @@
+//! Rendering of values with Excel number formats.
+//!
+//! This covers the parts of the Excel number format syntax that are commonly
+//! used: sections and conditions, colors, digit placeholders, thousands
+//! separators and scaling, percentages, scientific notation, fractions, dates
+//! and times, and text. Month and day names are in English.
+
+use chrono::{Datelike, NaiveDate, TimeDelta};
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const DAY_NAMES: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+/// A value rendered with a number format.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FormattedValue {
+    pub(crate) text: String,
+    /// The RGB color set by the format section, like `[Red]`.
+    pub(crate) color: Option<u32>,
+}
+
+/// Get the format code of a built-in Excel number format index.
+pub(crate) fn builtin_num_format(index: u16) -> &'static str {
+    match index {
+        1 => "0",
+        2 => "0.00",
+        3 => "#,##0",
+        4 => "#,##0.00",
+        5 => "($#,##0_);($#,##0)",
+        6 => "($#,##0_);[Red]($#,##0)",
+        7 => "($#,##0.00_);($#,##0.00)",
+        8 => "($#,##0.00_);[Red]($#,##0.00)",
+        9 => "0%",
+        10 => "0.00%",
+        11 => "0.00E+00",
+        12 => "# ?/?",
+        13 => "# ??/??",
+        14 => "m/d/yyyy",
+        15 => "d-mmm-yy",
+        16 => "d-mmm",
+        17 => "mmm-yy",
+        18 => "h:mm AM/PM",
+        19 => "h:mm:ss AM/PM",
+        20 => "h:mm",
+        21 => "h:mm:ss",
+        22 => "m/d/yyyy h:mm",
+        37 => "#,##0_);(#,##0)",
+        38 => "#,##0_);[Red](#,##0)",
+        39 => "#,##0.00_);(#,##0.00)",
+        40 => "#,##0.00_);[Red](#,##0.00)",
+        41 => r#"_(* #,##0_);_(* \(#,##0\);_(* "-"_);_(@_)"#,
+        42 => r#"_($* #,##0_);_($* \(#,##0\);_($* "-"_);_(@_)"#,
+        43 => r#"_(* #,##0.00_);_(* \(#,##0.00\);_(* "-"??_);_(@_)"#,
+        44 => r#"_($* #,##0.00_);_($* \(#,##0.00\);_($* "-"??_);_(@_)"#,
+        45 => "mm:ss",
+        46 => "[h]:mm:ss",
+        47 => "mm:ss.0",
+        48 => "##0.0E+0",
+        49 => "@",
+        _ => "General",
+    }
+}
+
+/// Render a number, or an Excel serial date, with a number format.
+pub(crate) fn format_number(value: f64, format_code: &str, date_1904: bool) -> FormattedValue {
+    let sections = parse_sections(format_code);
+    let (section, value) = select_section(&sections, value);
+    let text = match section.kind() {
+        SectionKind::General => general(value),
+        SectionKind::Date => section.format_date(value, date_1904),
+        SectionKind::Fraction => section.format_fraction(value),
+        SectionKind::Number => section.format_number(value),
+        SectionKind::Literal => section.literal_text(""),
+    };
+    FormattedValue {
+        text,
+        color: section.color,
+    }
+}
+
+/// Render a string with the text section of a number format, if it has one.
+pub(crate) fn format_text(text: &str, format_code: &str) -> FormattedValue {
+    let sections = parse_sections(format_code);
+    let section = if sections.len() == 4 {
+        sections.get(3)
+    } else {
+        sections
+            .iter()
+            .find(|section| section.tokens.contains(&Token::Text))
+    };
+    match section {
+        Some(section) => FormattedValue {
+            text: section.literal_text(text),
+            color: section.color,
+        },
+        None => FormattedValue {
+            text: text.to_string(),
+            color: None,
+        },
+    }
+}
+
+/// Render a number like the Excel "General" format in a standard width
+/// column.
+pub(crate) fn general(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return "0".to_string();
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    let abs = value.abs();
+    let exponent = abs.log10().floor() as i32;
+    let width = if value < 0.0 { 12 } else { 11 };
+
+    let text = if (-4..=-1).contains(&exponent) {
+        decimal(abs, 9)
+    } else if exponent.abs() <= 9 {
+        let text = decimal(abs, 12);
+        if text.len() + sign.len() <= width {
+            text
+        } else if exponent >= 0 {
+            decimal(abs, (9 - exponent) as usize)
+        } else {
+            scientific(abs, 5)
+        }
+    } else if exponent == 10 {
+        decimal(abs.trunc(), 0)
+    } else {
+        scientific(abs, 5)
+    };
+    format!("{sign}{text}")
+}
+
+/// Format a non-negative number with up to `decimals` decimal places and
+/// without trailing zeros.
+fn decimal(value: f64, decimals: usize) -> String {
+    let (int, frac) = round_decimal(value, decimals);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        int
+    } else {
+        format!("{int}.{frac}")
+    }
+}
+
+/// Format a non-negative number in scientific notation with up to `decimals`
+/// decimal places, like `1.23457E+11`.
+fn scientific(value: f64, decimals: usize) -> String {
+    let (mut mantissa, mut exponent) = mantissa_exponent(value, 1);
+    let (int, _) = round_decimal(mantissa, decimals);
+    if int == "10" {
+        mantissa /= 10.0;
+        exponent += 1;
+    }
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+    format!(
+        "{}E{exponent_sign}{:02}",
+        decimal(mantissa, decimals),
+        exponent.abs()
+    )
+}
+
+/// Split a number into a mantissa and exponent, with the exponent a multiple
+/// of `step`.
+fn mantissa_exponent(value: f64, step: i32) -> (f64, i32) {
+    if value == 0.0 {
+        return (0.0, 0);
+    }
+    let exponent = value.log10().floor() as i32;
+    let exponent = exponent.div_euclid(step) * step;
+    (value / 10f64.powi(exponent), exponent)
+}
+
+/// Round a non-negative number to `decimals` decimal places, half away from
+/// zero, and return the integer and decimal digits.
+///
+/// Like Excel the number is first rounded to 15 significant digits so that
+/// binary floating point artifacts don't affect the result.
+fn round_decimal(value: f64, decimals: usize) -> (String, String) {
+    if value == 0.0 || !value.is_finite() {
+        return ("0".to_string(), "0".repeat(decimals));
+    }
+    let repr = format!("{value:.14e}");
+    let (mantissa, exponent) = repr.split_once('e').unwrap_or((&repr, "0"));
+    let mut digits: Vec<u8> = mantissa.bytes().filter(u8::is_ascii_digit).map(|b| b - b'0').collect();
+    let mut point = exponent.parse::<i64>().unwrap_or(0) + 1;
+
+    let keep = point + decimals as i64;
+    if keep < 0 {
+        return ("0".to_string(), "0".repeat(decimals));
+    }
+    let keep = keep as usize;
+    if keep < digits.len() {
+        let round_up = digits[keep] >= 5;
+        digits.truncate(keep);
+        if round_up {
+            let mut i = keep;
+            loop {
+                if i == 0 {
+                    digits.insert(0, 1);
+                    point += 1;
+                    break;
+                }
+                i -= 1;
+                if digits[i] == 9 {
+                    digits[i] = 0;
+                } else {
+                    digits[i] += 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    let digit = |i: i64| {
+        if i >= 0 && (i as usize) < digits.len() {
+            char::from(b'0' + digits[i as usize])
+        } else {
+            '0'
+        }
+    };
+    let int: String = if point <= 0 {
+        "0".to_string()
+    } else {
+        (0..point).map(digit).collect()
+    };
+    let frac: String = (point..point + decimals as i64).map(digit).collect();
+    (int, frac)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DatePart {
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+    SubSecond(usize),
+    ElapsedHours(usize),
+    ElapsedMinutes(usize),
+    ElapsedSeconds(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    /// A `0`, `#` or `?` digit placeholder.
+    Digit(char),
+    Point,
+    Comma,
+    Percent,
+    /// An exponent, and whether it shows a `+` sign.
+    Exponent(bool),
+    Slash,
+    Text,
+    Date(DatePart),
+    AmPm(String, String),
+    General,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Condition {
+    Lt(f64),
+    Le(f64),
+    Gt(f64),
+    Ge(f64),
+    Eq(f64),
+    Ne(f64),
+}
+
+impl Condition {
+    fn parse(s: &str) -> Option<Condition> {
+        let (op, number) = ["<=", ">=", "<>", "<", ">", "="]
+            .iter()
+            .find_map(|op| s.strip_prefix(op).map(|number| (*op, number)))?;
+        let number = number.trim().parse().ok()?;
+        let condition = match op {
+            "<=" => Condition::Le(number),
+            ">=" => Condition::Ge(number),
+            "<>" => Condition::Ne(number),
+            "<" => Condition::Lt(number),
+            ">" => Condition::Gt(number),
+            _ => Condition::Eq(number),
+        };
+        Some(condition)
+    }
+
+    fn matches(self, value: f64) -> bool {
+        match self {
+            Condition::Lt(n) => value < n,
+            Condition::Le(n) => value <= n,
+            Condition::Gt(n) => value > n,
+            Condition::Ge(n) => value >= n,
+            Condition::Eq(n) => value == n,
+            Condition::Ne(n) => value != n,
+        }
+    }
+}
+
+enum SectionKind {
+    General,
+    Date,
+    Fraction,
+    Number,
+    Literal,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Section {
+    tokens: Vec<Token>,
+    color: Option<u32>,
+    condition: Option<Condition>,
+}
+
+/// Split a format code into sections and parse them.
+fn parse_sections(format_code: &str) -> Vec<Section> {
+    let mut sections = vec![];
+    let mut start = 0;
+    let mut chars = format_code.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                for (_, c) in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            ';' => {
+                sections.push(parse_section(&format_code[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    sections.push(parse_section(&format_code[start..]));
+    sections
+}
+
+fn parse_section(code: &str) -> Section {
+    let mut section = Section::default();
+    let chars: Vec<char> = code.chars().collect();
+    let mut i = 0;
+    let starts_with = |i: usize, s: &str| {
+        let s: Vec<char> = s.chars().collect();
+        chars.len() >= i + s.len() && chars[i..i + s.len()].iter().zip(&s).all(|(a, b)| a.eq_ignore_ascii_case(b))
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        let token = match c {
+            '"' => {
+                let end = chars[i..].iter().position(|&c| c == '"').map_or(chars.len(), |pos| i + pos);
+                let literal = chars[i..end].iter().collect();
+                i = end + 1;
+                Token::Literal(literal)
+            }
+            '\\' => {
+                let literal = chars.get(i).map(char::to_string).unwrap_or_default();
+                i += 1;
+                Token::Literal(literal)
+            }
+            // Space the width of the next character.
+            '_' => {
+                i += 1;
+                Token::Literal(" ".to_string())
+            }
+            // Repeat the next character to fill the cell, which isn't
+            // supported.
+            '*' => {
+                i += 1;
+                continue;
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|&c| c == ']').map_or(chars.len(), |pos| i + pos);
+                let content: String = chars[i..end].iter().collect();
+                i = end + 1;
+                match parse_bracket(&content, &mut section) {
+                    Some(token) => token,
+                    None => continue,
+                }
+            }
+            '0' | '#' | '?' => Token::Digit(c),
+            '.' => Token::Point,
+            ',' => Token::Comma,
+            '%' => Token::Percent,
+            '/' => Token::Slash,
+            '@' => Token::Text,
+            'E' | 'e' if matches!(chars.get(i), Some('+') | Some('-')) => {
+                let plus = chars[i] == '+';
+                i += 1;
+                Token::Exponent(plus)
+            }
+            _ if starts_with(i - 1, "General") => {
+                i += "General".len() - 1;
+                Token::General
+            }
+            _ if starts_with(i - 1, "AM/PM") => {
+                let am = chars[i - 1..i + 1].iter().collect();
+                let pm = chars[i + 2..i + 4].iter().collect();
+                i += 4;
+                Token::AmPm(am, pm)
+            }
+            _ if starts_with(i - 1, "A/P") => {
+                let am = chars[i - 1].to_string();
+                let pm = chars[i + 1].to_string();
+                i += 2;
+                Token::AmPm(am, pm)
+            }
+            'y' | 'Y' | 'm' | 'M' | 'd' | 'D' | 'h' | 'H' | 's' | 'S' => {
+                let mut count = 1;
+                while chars.get(i).is_some_and(|next| next.eq_ignore_ascii_case(&c)) {
+                    count += 1;
+                    i += 1;
+                }
+                let part = match c.to_ascii_lowercase() {
+                    'y' => DatePart::Year(count),
+                    'm' => DatePart::Month(count),
+                    'd' => DatePart::Day(count),
+                    'h' => DatePart::Hour(count),
+                    _ => DatePart::Second(count),
+                };
+                Token::Date(part)
+            }
+            c => Token::Literal(c.to_string()),
+        };
+        section.tokens.push(token);
+    }
+
+    if section.tokens.iter().any(|token| matches!(token, Token::Date(_))) {
+        section.resolve_date_tokens();
+    }
+    section
+}
+
+/// Parse the content of a `[...]` block, which can set the section color or
+/// condition or be a token.
+fn parse_bracket(content: &str, section: &mut Section) -> Option<Token> {
+    let lower = content.to_ascii_lowercase();
+    let color = match lower.as_str() {
+        "black" | "color1" => Some(0x000000),
+        "white" | "color2" => Some(0xFFFFFF),
+        "red" | "color3" => Some(0xFF0000),
+        "green" | "color4" => Some(0x00FF00),
+        "blue" | "color5" => Some(0x0000FF),
+        "yellow" | "color6" => Some(0xFFFF00),
+        "magenta" | "color7" => Some(0xFF00FF),
+        "cyan" | "color8" => Some(0x00FFFF),
+        _ => None,
+    };
+    if color.is_some() {
+        section.color = color;
+        return None;
+    }
+    if let Some(condition) = Condition::parse(content) {
+        section.condition = Some(condition);
+        return None;
+    }
+    if let Some(currency) = content.strip_prefix('$') {
+        let symbol = currency.split('-').next().unwrap_or_default();
+        return Some(Token::Literal(symbol.to_string()));
+    }
+    let count = lower.len();
+    match lower.chars().next() {
+        Some(c) if lower.chars().all(|x| x == c) => match c {
+            'h' => Some(Token::Date(DatePart::ElapsedHours(count))),
+            'm' => Some(Token::Date(DatePart::ElapsedMinutes(count))),
+            's' => Some(Token::Date(DatePart::ElapsedSeconds(count))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Choose the section of a format for a number, and the value to render
+/// with it.
+fn select_section(sections: &[Section], value: f64) -> (&Section, f64) {
+    let numeric: Vec<&Section> = sections
+        .iter()
+        .take(3)
+        .filter(|section| !section.is_text_only())
+        .collect();
+    let Some(&first) = numeric.first() else {
+        return (&sections[0], value);
+    };
+
+    if numeric.iter().any(|section| section.condition.is_some()) {
+        for section in numeric.iter().take(2) {
+            match section.condition {
+                Some(condition) if condition.matches(value) => return (section, value),
+                _ => {}
+            }
+        }
+        let fallback = numeric.get(2).or(numeric.last()).copied().unwrap_or(first);
+        return (fallback, value);
+    }
+
+    match numeric.len() {
+        1 => (first, value),
+        _ if value > 0.0 => (first, value),
+        _ if value < 0.0 => (numeric[1], value.abs()),
+        2 => (first, value),
+        _ => (numeric[2], value),
+    }
+}
+
+impl Section {
+    fn is_text_only(&self) -> bool {
+        self.tokens.contains(&Token::Text)
+            && !self
+                .tokens
+                .iter()
+                .any(|token| matches!(token, Token::Digit(_) | Token::Date(_) | Token::General))
+    }
+
+    fn kind(&self) -> SectionKind {
+        let has = |f: fn(&Token) -> bool| self.tokens.iter().any(f);
+        if has(|token| matches!(token, Token::Date(_))) {
+            SectionKind::Date
+        } else if has(|token| matches!(token, Token::General)) {
+            SectionKind::General
+        } else if has(|token| matches!(token, Token::Digit(_))) {
+            if has(|token| *token == Token::Slash) {
+                SectionKind::Fraction
+            } else {
+                SectionKind::Number
+            }
+        } else if self.tokens.is_empty() {
+            SectionKind::General
+        } else {
+            SectionKind::Literal
+        }
+    }
+
+    /// Render the literal tokens, with `text` in place of `@`.
+    fn literal_text(&self, text: &str) -> String {
+        let mut output = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(literal) => output.push_str(literal),
+                Token::Text => output.push_str(text),
+                Token::Point => output.push('.'),
+                Token::Comma => output.push(','),
+                Token::Percent => output.push('%'),
+                Token::Slash => output.push('/'),
+                _ => {}
+            }
+        }
+        output
+    }
+
+    /// Distinguish months from minutes and find sub-second placeholders.
+    fn resolve_date_tokens(&mut self) {
+        let date_positions: Vec<usize> = (0..self.tokens.len())
+            .filter(|&i| matches!(self.tokens[i], Token::Date(_)))
+            .collect();
+        for (n, &i) in date_positions.iter().enumerate() {
+            let Token::Date(DatePart::Month(count)) = self.tokens[i] else {
+                continue;
+            };
+            if count > 2 {
+                continue;
+            }
+            let previous = n.checked_sub(1).map(|p| &self.tokens[date_positions[p]]);
+            let next = date_positions.get(n + 1).map(|&p| &self.tokens[p]);
+            let after_hour = matches!(
+                previous,
+                Some(Token::Date(DatePart::Hour(_))) | Some(Token::Date(DatePart::ElapsedHours(_)))
+            );
+            let before_second = matches!(next, Some(Token::Date(DatePart::Second(_))));
+            if after_hour || before_second {
+                self.tokens[i] = Token::Date(DatePart::Minute(count));
+            }
+        }
+
+        let mut i = 0;
+        while i < self.tokens.len() {
+            if self.tokens[i] == Token::Point {
+                let count = self.tokens[i + 1..]
+                    .iter()
+                    .take_while(|token| **token == Token::Digit('0'))
+                    .count();
+                if count > 0 {
+                    self.tokens.splice(
+                        i..i + 1 + count,
+                        [Token::Literal(".".to_string()), Token::Date(DatePart::SubSecond(count))],
+                    );
+                }
+            }
+            i += 1;
+        }
+    }
+
+    fn format_date(&self, serial: f64, date_1904: bool) -> String {
+        let sub_second_digits = self
+            .tokens
+            .iter()
+            .find_map(|token| match token {
+                Token::Date(DatePart::SubSecond(count)) => Some((*count).min(3)),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let has_am_pm = self.tokens.iter().any(|token| matches!(token, Token::AmPm(..)));
+
+        // Round the time to the precision that is displayed.
+        let unit = 86_400.0 * 10f64.powi(sub_second_digits as i32);
+        let serial = (serial * unit).round() / unit;
+        let days = serial.floor();
+        let time = serial - days;
+        let total_seconds = serial * 86_400.0;
+        let day_seconds = (time * 86_400.0 * 1000.0).round() / 1000.0;
+
+        // Like Excel, dates before the epoch or after 9999-12-31 can't be
+        // displayed.
+        let Some((year, month, day, weekday)) = excel_date(days, date_1904) else {
+            return "####".to_string();
+        };
+        let hour = (day_seconds / 3600.0).floor() as u32;
+        let minute = ((day_seconds / 60.0).floor() as u32) % 60;
+        let second = (day_seconds.floor() as u32) % 60;
+        let fraction = day_seconds - day_seconds.floor();
+
+        let mut output = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Date(part) => {
+                    let text = match *part {
+                        DatePart::Year(count) if count <= 2 => format!("{:02}", year % 100),
+                        DatePart::Year(_) => format!("{year:04}"),
+                        DatePart::Month(count) => {
+                            let name = MONTH_NAMES[month as usize - 1];
+                            match count {
+                                1 => month.to_string(),
+                                2 => format!("{month:02}"),
+                                3 => name[..3].to_string(),
+                                5 => name[..1].to_string(),
+                                _ => name.to_string(),
+                            }
+                        }
+                        DatePart::Day(count) => {
+                            let name = DAY_NAMES[weekday];
+                            match count {
+                                1 => day.to_string(),
+                                2 => format!("{day:02}"),
+                                3 => name[..3].to_string(),
+                                _ => name.to_string(),
+                            }
+                        }
+                        DatePart::Hour(count) => {
+                            let hour = if has_am_pm {
+                                match hour % 12 {
+                                    0 => 12,
+                                    hour => hour,
+                                }
+                            } else {
+                                hour
+                            };
+                            pad(hour.into(), count)
+                        }
+                        DatePart::Minute(count) => pad(minute.into(), count),
+                        DatePart::Second(count) => pad(second.into(), count),
+                        DatePart::SubSecond(count) => {
+                            let digits = (fraction * 10f64.powi(count as i32)).round() as u64;
+                            format!("{digits:0count$}")
+                        }
+                        DatePart::ElapsedHours(count) => pad((total_seconds / 3600.0).floor() as i64, count),
+                        DatePart::ElapsedMinutes(count) => pad((total_seconds / 60.0).floor() as i64, count),
+                        DatePart::ElapsedSeconds(count) => pad(total_seconds.floor() as i64, count),
+                    };
+                    output.push_str(&text);
+                }
+                Token::AmPm(am, pm) => output.push_str(if hour < 12 { am } else { pm }),
+                Token::Literal(literal) => output.push_str(literal),
+                Token::Digit(c) => output.push(*c),
+                Token::Point => output.push('.'),
+                Token::Comma => output.push(','),
+                Token::Percent => output.push('%'),
+                Token::Slash => output.push('/'),
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn format_number(&self, value: f64) -> String {
+        let negative = value < 0.0;
+        let mut value = value.abs();
+
+        let point = self.tokens.iter().position(|token| *token == Token::Point);
+        let exponent = self
+            .tokens
+            .iter()
+            .position(|token| matches!(token, Token::Exponent(_)));
+        let int_end = point.or(exponent).unwrap_or(self.tokens.len());
+        let frac_end = exponent.unwrap_or(self.tokens.len());
+        let is_digit = |token: &Token| matches!(token, Token::Digit(_));
+
+        let int_positions: Vec<usize> = (0..int_end).filter(|&i| is_digit(&self.tokens[i])).collect();
+        let frac_positions: Vec<usize> = match point {
+            Some(point) => (point + 1..frac_end).filter(|&i| is_digit(&self.tokens[i])).collect(),
+            None => vec![],
+        };
+        let exp_positions: Vec<usize> = match exponent {
+            Some(exponent) => (exponent + 1..self.tokens.len()).filter(|&i| is_digit(&self.tokens[i])).collect(),
+            None => vec![],
+        };
+
+        // Commas between integer placeholders group thousands and commas
+        // after the last digit placeholder scale the number by 1000.
+        let first_int = int_positions.first().copied().unwrap_or(0);
+        let last_int = int_positions.last().copied().unwrap_or(0);
+        let last_digit = frac_positions.last().or(int_positions.last()).copied();
+        let grouping = (first_int..last_int).any(|i| self.tokens[i] == Token::Comma);
+        let scaling_commas: Vec<usize> = match last_digit {
+            Some(last_digit) => (last_digit + 1..self.tokens.len())
+                .take_while(|&i| self.tokens[i] == Token::Comma)
+                .collect(),
+            None => vec![],
+        };
+        let is_separator = |i: usize| scaling_commas.contains(&i) || (i > first_int && i < last_int);
+        let percents = self.tokens.iter().filter(|token| **token == Token::Percent).count();
+        value *= 100f64.powi(percents as i32);
+        value /= 1000f64.powi(scaling_commas.len() as i32);
+
+        let mut exponent_value = 0;
+        if exponent.is_some() {
+            let step = if self.tokens[..int_end].contains(&Token::Digit('#')) {
+                int_positions.len().max(1) as i32
+            } else {
+                1
+            };
+            let (mantissa, exp) = mantissa_exponent(value, step);
+            value = mantissa;
+            exponent_value = exp;
+            // Rounding can carry the mantissa into the next exponent.
+            let (int, _) = round_decimal(value, frac_positions.len());
+            if int.len() > step as usize {
+                value /= 10f64.powi(step);
+                exponent_value += step;
+            }
+        }
+
+        let (int, frac) = round_decimal(value, frac_positions.len());
+        let is_zero = int == "0" && frac.bytes().all(|b| b == b'0');
+        let int = if int == "0" { String::new() } else { int };
+
+        let mut pieces: Vec<String> = vec![String::new(); self.tokens.len()];
+
+        // Fill the integer placeholders from the right, putting any extra
+        // digits in the first placeholder.
+        let mut digits: Vec<char> = int.chars().collect();
+        let mut count = 0;
+        for (n, &i) in int_positions.iter().enumerate().rev() {
+            let Token::Digit(placeholder) = self.tokens[i] else {
+                continue;
+            };
+            let mut piece = vec![];
+            let take = if n == 0 { digits.len() } else { digits.len().min(1) };
+            let mut emitted: Vec<char> = digits.split_off(digits.len() - take);
+            if emitted.is_empty() {
+                match placeholder {
+                    '0' => emitted.push('0'),
+                    '?' => piece.push(' '),
+                    _ => {}
+                }
+            }
+            for digit in emitted.into_iter().rev() {
+                if grouping && count > 0 && count % 3 == 0 {
+                    piece.push(',');
+                }
+                piece.push(digit);
+                count += 1;
+            }
+            pieces[i] = piece.into_iter().rev().collect();
+        }
+
+        // Fill the decimal placeholders from the left, dropping insignificant
+        // trailing zeros for `#` and `?` placeholders.
+        let frac: Vec<char> = frac.chars().collect();
+        let significant = frac.iter().rposition(|&c| c != '0').map_or(0, |pos| pos + 1);
+        for (n, &i) in frac_positions.iter().enumerate() {
+            let Token::Digit(placeholder) = self.tokens[i] else {
+                continue;
+            };
+            let forced = frac_positions[n..]
+                .iter()
+                .any(|&i| self.tokens[i] == Token::Digit('0'));
+            pieces[i] = if n < significant || forced {
+                frac[n].to_string()
+            } else if placeholder == '?' {
+                " ".to_string()
+            } else {
+                String::new()
+            };
+        }
+
+        if let Some(exponent) = exponent {
+            let plus = self.tokens[exponent] == Token::Exponent(true);
+            let sign = if exponent_value < 0 {
+                "-"
+            } else if plus {
+                "+"
+            } else {
+                ""
+            };
+            let width = exp_positions.len();
+            pieces[exponent] = format!("E{sign}{:0width$}", exponent_value.abs());
+            for &i in &exp_positions {
+                pieces[i] = String::new();
+            }
+        }
+
+        let mut output = String::new();
+        if negative && !is_zero {
+            output.push('-');
+        }
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token {
+                Token::Digit(_) | Token::Exponent(_) => output.push_str(&pieces[i]),
+                Token::Literal(literal) => output.push_str(literal),
+                Token::Point => output.push('.'),
+                Token::Percent => output.push('%'),
+                Token::Comma if !is_separator(i) => output.push(','),
+                Token::Slash => output.push('/'),
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn format_fraction(&self, value: f64) -> String {
+        let negative = value < 0.0;
+        let value = value.abs();
+        let slash = self.tokens.iter().position(|token| *token == Token::Slash).unwrap_or(0);
+
+        // The numerator is the group of placeholders before the slash and an
+        // integer part is any earlier group.
+        let mut numerator_start = slash;
+        while numerator_start > 0 && matches!(self.tokens[numerator_start - 1], Token::Digit(_)) {
+            numerator_start -= 1;
+        }
+        let has_int = self.tokens[..numerator_start]
+            .iter()
+            .any(|token| matches!(token, Token::Digit(_)));
+        let mut denominator_end = slash + 1;
+        while matches!(self.tokens.get(denominator_end), Some(Token::Digit(_))) {
+            denominator_end += 1;
+        }
+        let denominator_width = denominator_end - slash - 1;
+        // A fixed denominator, like `# ?/8`.
+        let fixed: Option<u64> = if denominator_width == 0 {
+            let digits: String = self.tokens[slash + 1..]
+                .iter()
+                .map_while(|token| match token {
+                    Token::Literal(literal) if literal.chars().all(|c| c.is_ascii_digit()) => Some(literal.as_str()),
+                    _ => None,
+                })
+                .collect();
+            denominator_end += digits.len();
+            digits.parse().ok()
+        } else {
+            None
+        };
+
+        let (mut whole, fraction) = if has_int {
+            (value.trunc() as u64, value.fract())
+        } else {
+            (0, value)
+        };
+        let (mut numerator, denominator) = match fixed {
+            Some(denominator) if denominator > 0 => ((fraction * denominator as f64).round() as u64, denominator),
+            _ => best_fraction(fraction, 10u64.pow(denominator_width.clamp(1, 4) as u32) - 1),
+        };
+        if has_int && numerator == denominator {
+            whole += 1;
+            numerator = 0;
+        }
+
+        let mut output = String::new();
+        if negative && (whole > 0 || numerator > 0) {
+            output.push('-');
+        }
+        let width = (slash - numerator_start).max(1);
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token {
+                Token::Digit(_) if i < numerator_start => {
+                    // Render the integer part once, at its first placeholder.
+                    let first = self.tokens[..i].iter().all(|token| !matches!(token, Token::Digit(_)));
+                    if first && (whole > 0 || numerator == 0) {
+                        output.push_str(&whole.to_string());
+                    }
+                }
+                Token::Digit(_) if i == numerator_start => {
+                    if numerator == 0 && has_int {
+                        output.push_str(&" ".repeat(width));
+                    } else {
+                        output.push_str(&format!("{numerator:>width$}"));
+                    }
+                }
+                Token::Digit(_) if i < slash => {}
+                Token::Slash => {
+                    output.push(if numerator == 0 && has_int { ' ' } else { '/' });
+                    if numerator == 0 && has_int {
+                        output.push_str(&" ".repeat(denominator_width.max(1)));
+                    } else if denominator_width > 0 {
+                        output.push_str(&format!("{denominator:<denominator_width$}"));
+                    } else {
+                        output.push_str(&denominator.to_string());
+                    }
+                }
+                _ if i > slash && i < denominator_end => {}
+                // Drop the separator between the integer and fraction when
+                // there is no integer.
+                Token::Literal(_) if has_int && i < numerator_start && whole == 0 && numerator > 0 => {}
+                Token::Literal(literal) => output.push_str(literal),
+                Token::Point => output.push('.'),
+                Token::Percent => output.push('%'),
+                _ => {}
+            }
+        }
+        output
+    }
+}
+
+/// Find the closest fraction to `value`, which is less than 1 for formats
+/// with an integer part, with a denominator up to `max_denominator`.
+fn best_fraction(value: f64, max_denominator: u64) -> (u64, u64) {
+    let mut best = (value.round() as u64, 1);
+    let mut best_error = (value - value.round()).abs();
+    for denominator in 2..=max_denominator {
+        let numerator = (value * denominator as f64).round();
+        let error = (value - numerator / denominator as f64).abs();
+        if error < best_error - 1e-12 {
+            best = (numerator as u64, denominator);
+            best_error = error;
+        }
+    }
+    best
+}
+
+/// Convert an Excel serial day number to a year, month, day and day of the
+/// week, with 0 for Sunday, or `None` if the day is outside of the range
+/// that Excel can display.
+///
+/// Like Excel, the 1900 epoch starts at 1900-01-00 and includes the
+/// nonexistent 1900-02-29.
+fn excel_date(days: f64, date_1904: bool) -> Option<(i32, u32, u32, usize)> {
+    // The last day that Excel can display is 9999-12-31.
+    let last_day = if date_1904 { 2_957_003.0 } else { 2_958_465.0 };
+    if !(0.0..=last_day).contains(&days) {
+        return None;
+    }
+    let days = days as i64;
+    let (epoch, weekday) = if date_1904 {
+        ((1904, 1, 1), (days + 5) % 7)
+    } else {
+        match days {
+            0 => return Some((1900, 1, 0, 6)),
+            60 => return Some((1900, 2, 29, 3)),
+            1..=59 => ((1899, 12, 31), (days + 6) % 7),
+            _ => ((1899, 12, 30), (days + 6) % 7),
+        }
+    };
+    let date = NaiveDate::from_ymd_opt(epoch.0, epoch.1, epoch.2)?.checked_add_signed(TimeDelta::try_days(days)?)?;
+    Some((date.year(), date.month(), date.day(), weekday as usize))
+}
+
+fn pad(value: i64, width: usize) -> String {
+    format!("{value:0width$}")
+}
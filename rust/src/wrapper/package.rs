@@ -0,0 +1,335 @@
+//! Post-processing of the xlsx package generated by `rust_xlsxwriter`.
+//!
+//! Some workbook features aren't exposed by `rust_xlsxwriter`. For those we
+//! let `rust_xlsxwriter` assemble the file as usual and then patch the parts
+//! of the resulting zip package before handing the buffer back to JS.
+
+use std::io::{Cursor, Read, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+use crate::error::XlsxError;
+
+use super::WasmResult;
+
+/// A single file (part) inside the xlsx zip container.
+pub(crate) struct Part {
+    pub(crate) name: String,
+    pub(crate) data: Vec<u8>,
+}
+
+/// An in-memory, editable view of an xlsx package.
+pub(crate) struct Package {
+    parts: Vec<Part>,
+}
+
+impl Package {
+    /// Read all the parts of a zipped xlsx package.
+    pub(crate) fn from_buffer(buf: &[u8]) -> WasmResult<Package> {
+        let mut archive = ZipArchive::new(Cursor::new(buf)).map_err(zip_error)?;
+        let mut parts = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(zip_error)?;
+            let mut data = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut data)
+                .map_err(|e| XlsxError::Internal(e.to_string()))?;
+            parts.push(Part {
+                name: file.name().to_string(),
+                data,
+            });
+        }
+        Ok(Package { parts })
+    }
+
+    /// Edit an XML part in place.
+    pub(crate) fn edit_xml(
+        &mut self,
+        name: &str,
+        edit: impl FnOnce(&mut String) -> WasmResult<()>,
+    ) -> WasmResult<()> {
+        let part = self
+            .parts
+            .iter_mut()
+            .find(|part| part.name == name)
+            .ok_or_else(|| XlsxError::Internal(format!("Missing package part: {name}")))?;
+        let mut xml = String::from_utf8(std::mem::take(&mut part.data))
+            .map_err(|e| XlsxError::Internal(e.to_string()))?;
+        let result = edit(&mut xml);
+        part.data = xml.into_bytes();
+        result
+    }
+
+    /// Add a new part, or replace an existing part with the same name.
+    pub(crate) fn set_part(&mut self, name: &str, data: Vec<u8>) {
+        match self.parts.iter_mut().find(|part| part.name == name) {
+            Some(part) => part.data = data,
+            None => self.parts.push(Part {
+                name: name.to_string(),
+                data,
+            }),
+        }
+    }
+
+    /// Remove a part, if it exists.
+    pub(crate) fn remove_part(&mut self, name: &str) {
+        self.parts.retain(|part| part.name != name);
+    }
+
+    /// Get the data of a part.
+    pub(crate) fn part(&self, name: &str) -> Option<&[u8]> {
+        self.parts
+            .iter()
+            .find(|part| part.name == name)
+            .map(|part| part.data.as_slice())
+    }
+
+    /// Add a relationship to a relationships part, creating the part if it
+    /// doesn't exist. Returns the id of the new relationship.
+    pub(crate) fn add_relationship(&mut self, rels_name: &str, rel_type: &str, target: &str) -> WasmResult<String> {
+        if self.part(rels_name).is_none() {
+            let rels = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                        <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+                        </Relationships>";
+            self.set_part(rels_name, rels.as_bytes().to_vec());
+        }
+        let mut id = String::new();
+        self.edit_xml(rels_name, |xml| {
+            id = format!("rId{}", xml.matches("<Relationship ").count() + 1);
+            insert_before_end_tag(
+                xml,
+                "Relationships",
+                &format!(r#"<Relationship Id="{id}" Type="{rel_type}" Target="{target}"/>"#),
+            );
+            Ok(())
+        })?;
+        Ok(id)
+    }
+
+    /// Get the names of the parts that match a predicate, in package order.
+    pub(crate) fn part_names(&self, filter: impl Fn(&str) -> bool) -> Vec<String> {
+        self.parts
+            .iter()
+            .filter(|part| filter(&part.name))
+            .map(|part| part.name.clone())
+            .collect()
+    }
+
+    /// Zip the package parts back into an xlsx buffer, optionally with a
+    /// compression level in the range 0-9.
+    pub(crate) fn to_buffer(&self, compression_level: Option<u8>) -> WasmResult<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+        // Use the same zip options as `rust_xlsxwriter`.
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(0o600)
+            .last_modified_time(DateTime::default());
+
+        // Deflate doesn't have a level 0 so store the parts uncompressed.
+        options = match compression_level {
+            Some(0) => options.compression_method(CompressionMethod::Stored),
+            Some(level) => options.compression_level(Some(level.into())),
+            None => options,
+        };
+
+        for part in &self.parts {
+            zip.start_file(part.name.as_str(), options)
+                .map_err(zip_error)?;
+            zip.write_all(&part.data)
+                .map_err(|e| XlsxError::Internal(e.to_string()))?;
+        }
+
+        let cursor = zip.finish().map_err(zip_error)?;
+        Ok(cursor.into_inner())
+    }
+}
+
+fn zip_error(e: zip::result::ZipError) -> XlsxError {
+    XlsxError::Internal(format!("ZipError({e})"))
+}
+
+/// Find the byte range of the first start (or empty) tag for `tag`.
+fn find_start_tag(xml: &str, tag: &str) -> Option<(usize, usize)> {
+    let needle = format!("<{tag}");
+    let mut offset = 0;
+    while let Some(pos) = xml[offset..].find(&needle) {
+        let start = offset + pos;
+        let after = start + needle.len();
+        // Make sure we matched the whole tag name and not a prefix of it.
+        match xml[after..].chars().next() {
+            Some(' ') | Some('>') | Some('/') => {
+                let end = after + xml[after..].find('>')? + 1;
+                return Some((start, end));
+            }
+            _ => offset = after,
+        }
+    }
+    None
+}
+
+/// Get the value of an attribute on the first `tag` element of an XML
+/// document.
+pub(crate) fn xml_attribute<'a>(xml: &'a str, tag: &str, name: &str) -> Option<&'a str> {
+    let (start, end) = find_start_tag(xml, tag)?;
+    let element = &xml[start..end];
+    let needle = format!(" {name}=\"");
+    let value_start = element.find(&needle)? + needle.len();
+    let value_end = value_start + element[value_start..].find('"')?;
+    Some(&element[value_start..value_end])
+}
+
+/// Replace the text of the first `tag` element of an XML document. Returns
+/// false if the element wasn't found.
+pub(crate) fn set_element_text(xml: &mut String, tag: &str, text: &str) -> bool {
+    let Some((_, start)) = find_start_tag(xml, tag) else {
+        return false;
+    };
+    let Some(len) = xml[start..].find(&format!("</{tag}>")) else {
+        return false;
+    };
+    xml.replace_range(start..start + len, text);
+    true
+}
+
+/// Insert an XML fragment directly after the first `tag` start tag.
+pub(crate) fn insert_after_start_tag(xml: &mut String, tag: &str, fragment: &str) -> bool {
+    match find_start_tag(xml, tag) {
+        Some((_, end)) => {
+            xml.insert_str(end, fragment);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Insert an XML fragment directly before the `tag` end tag.
+pub(crate) fn insert_before_end_tag(xml: &mut String, tag: &str, fragment: &str) -> bool {
+    match xml.rfind(&format!("</{tag}>")) {
+        Some(pos) => {
+            xml.insert_str(pos, fragment);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Insert an XML fragment directly after the first `tag` element. Returns
+/// false if the element wasn't found.
+pub(crate) fn insert_after_element(xml: &mut String, tag: &str, fragment: &str) -> bool {
+    let Some((start, end)) = find_start_tag(xml, tag) else {
+        return false;
+    };
+    if xml[start..end].ends_with("/>") {
+        xml.insert_str(end, fragment);
+        return true;
+    }
+    let end_tag = format!("</{tag}>");
+    match xml[end..].find(&end_tag) {
+        Some(pos) => {
+            xml.insert_str(end + pos + end_tag.len(), fragment);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Append an XML fragment to the children of the first `tag` element,
+/// expanding it if it is self-closing. Returns false if the element wasn't
+/// found.
+pub(crate) fn append_to_element(xml: &mut String, tag: &str, fragment: &str) -> bool {
+    let Some((start, end)) = find_start_tag(xml, tag) else {
+        return false;
+    };
+    if xml[start..end].ends_with("/>") {
+        xml.replace_range(end - 2..end, &format!(">{fragment}</{tag}>"));
+        return true;
+    }
+    match xml[end..].find(&format!("</{tag}>")) {
+        Some(pos) => {
+            xml.insert_str(end + pos, fragment);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Set, replace or (with `None`) remove an attribute on the first `tag`
+/// element of an XML document. Returns false if the element wasn't found.
+pub(crate) fn set_xml_attribute(xml: &mut String, tag: &str, name: &str, value: Option<&str>) -> bool {
+    let Some((start, end)) = find_start_tag(xml, tag) else {
+        return false;
+    };
+
+    let element = &xml[start..end];
+    let self_closing = element.ends_with("/>");
+    let needle = format!(" {name}=\"");
+    let existing = element.find(&needle).map(|pos| {
+        let value_start = pos + needle.len();
+        let value_end = value_start + element[value_start..].find('"').unwrap_or(0) + 1;
+        (start + pos, start + value_end)
+    });
+
+    match (existing, value) {
+        (Some((attr_start, attr_end)), Some(value)) => {
+            xml.replace_range(attr_start..attr_end, &format!(" {name}=\"{value}\""));
+        }
+        (Some((attr_start, attr_end)), None) => {
+            xml.replace_range(attr_start..attr_end, "");
+        }
+        (None, Some(value)) => {
+            let insert_at = if self_closing { end - 2 } else { end - 1 };
+            xml.insert_str(insert_at, &format!(" {name}=\"{value}\""));
+        }
+        (None, None) => {}
+    }
+    true
+}
+
+/// Get the `tag` children of the first `parent` element of an XML document,
+/// as XML text. The children can't have `tag` elements of their own.
+pub(crate) fn child_elements<'a>(xml: &'a str, parent: &str, tag: &str) -> Vec<&'a str> {
+    let Some((start, end)) = find_start_tag(xml, parent) else {
+        return vec![];
+    };
+    if xml[start..end].ends_with("/>") {
+        return vec![];
+    }
+    let Some(len) = xml[end..].find(&format!("</{parent}>")) else {
+        return vec![];
+    };
+
+    let mut content = &xml[end..end + len];
+    let mut children = vec![];
+    while let Some((start, end)) = find_start_tag(content, tag) {
+        let element_end = if content[start..end].ends_with("/>") {
+            end
+        } else {
+            let end_tag = format!("</{tag}>");
+            match content[end..].find(&end_tag) {
+                Some(pos) => end + pos + end_tag.len(),
+                None => break,
+            }
+        };
+        children.push(&content[start..element_end]);
+        content = &content[element_end..];
+    }
+    children
+}
+
+/// Escape text for use in XML element text or attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Unescape the predefined XML entities in element text or attribute values.
+pub(crate) fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,1028 @@
+//! Support for pivot tables.
+//!
+//! `rust_xlsxwriter` doesn't support pivot tables so the pivot cache and
+//! pivot table parts are added to the package after it has been saved. The
+//! cache is built from the cells recorded for the source worksheet, and the
+//! pivot table is marked to be refreshed when the file is opened, so Excel
+//! calculates and lays out the values.
+
+use std::collections::{HashMap, HashSet};
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    cell_store::{CellRange, CellStore, CellValue},
+    number_format,
+    package::{self, Package},
+    table,
+    workbook_settings::WorkbookSettings,
+    worksheet::{SheetId, Worksheet},
+    WasmResult,
+};
+
+const MAIN_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const RELATIONSHIPS_NAMESPACE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PIVOT_TABLE_RELATIONSHIP: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotTable";
+const PIVOT_CACHE_RELATIONSHIP: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotCacheDefinition";
+const PIVOT_RECORDS_RELATIONSHIP: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotCacheRecords";
+const PIVOT_TABLE_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotTable+xml";
+const PIVOT_CACHE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheDefinition+xml";
+const PIVOT_RECORDS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheRecords+xml";
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// The `PivotFunction` enum defines how the values of a pivot table value
+/// field are aggregated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum PivotFunction {
+    /// The sum of the values. This is the Excel default.
+    #[default]
+    Sum,
+    /// The number of values.
+    Count,
+    /// The average of the values.
+    Average,
+    /// The largest value.
+    Max,
+    /// The smallest value.
+    Min,
+}
+
+impl PivotFunction {
+    fn caption(self) -> &'static str {
+        match self {
+            PivotFunction::Sum => "Sum",
+            PivotFunction::Count => "Count",
+            PivotFunction::Average => "Average",
+            PivotFunction::Max => "Max",
+            PivotFunction::Min => "Min",
+        }
+    }
+
+    /// The `subtotal` attribute of the data field, if it isn't the default.
+    fn subtotal(self) -> Option<&'static str> {
+        match self {
+            PivotFunction::Sum => None,
+            PivotFunction::Count => Some("count"),
+            PivotFunction::Average => Some("average"),
+            PivotFunction::Max => Some("max"),
+            PivotFunction::Min => Some("min"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PivotSource {
+    Range { sheet: SheetId, range: CellRange },
+    Table(String),
+}
+
+#[derive(Clone, Debug)]
+struct ValueField {
+    name: String,
+    function: PivotFunction,
+    caption: Option<String>,
+}
+
+/// The `PivotTable` struct represents a worksheet pivot table.
+///
+/// A pivot table summarizes the data of a worksheet range or {@link Table}.
+/// The columns of the source data are the fields of the pivot table and are
+/// referred to by their header. Fields can be used as row, column and filter
+/// fields, and as value fields that are aggregated with a
+/// {@link PivotFunction}.
+///
+/// The pivot table is added to a worksheet with
+/// {@link Worksheet#addPivotTable}. The pivot cache is built from the cells
+/// written to the source worksheet when the workbook is saved, and the pivot
+/// table is refreshed by Excel when the file is opened. Formula results
+/// aren't known when the file is saved so they are only available after the
+/// refresh.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct PivotTable {
+    name: Option<String>,
+    source: Option<PivotSource>,
+    row_fields: Vec<String>,
+    column_fields: Vec<String>,
+    filter_fields: Vec<(String, Option<String>)>,
+    value_fields: Vec<ValueField>,
+    style: String,
+    row_grand_totals: bool,
+    column_grand_totals: bool,
+}
+
+impl Default for PivotTable {
+    fn default() -> Self {
+        PivotTable {
+            name: None,
+            source: None,
+            row_fields: vec![],
+            column_fields: vec![],
+            filter_fields: vec![],
+            value_fields: vec![],
+            style: "PivotStyleLight16".to_string(),
+            row_grand_totals: true,
+            column_grand_totals: true,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl PivotTable {
+    /// Create a new `PivotTable`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PivotTable {
+        PivotTable::default()
+    }
+
+    /// Set the name of the pivot table. The default is `PivotTable1`,
+    /// `PivotTable2` and so on, skipping the names of the other pivot tables
+    /// of the worksheet.
+    ///
+    /// The names of the pivot tables of a worksheet must be unique, which is
+    /// checked by {@link Worksheet#addPivotTable}.
+    ///
+    /// @param {string} name - The pivot table name.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The name is empty or longer than
+    ///   255 characters.
+    #[wasm_bindgen(js_name = "setName", skip_jsdoc)]
+    pub fn set_name(&self, name: &str) -> WasmResult<PivotTable> {
+        if name.is_empty() || name.chars().count() > 255 {
+            return Err(parameter_error(format!(
+                "Pivot table name '{name}' must be 1 to 255 characters long"
+            )));
+        }
+        Ok(PivotTable {
+            name: Some(name.to_string()),
+            ..self.clone()
+        })
+    }
+
+    /// Set the source data of the pivot table to a worksheet range.
+    ///
+    /// The first row of the range holds the field names and the other rows
+    /// hold the data. The data is read from the cells written to the
+    /// worksheet.
+    ///
+    /// @param {Worksheet} worksheet - The worksheet with the source data. It
+    ///   must be in the same workbook as the pivot table when it is saved.
+    /// @param {number} first_row - The first row of the range. (All zero indexed.)
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnOrderError`] - First row or column larger
+    ///   than the last row or column.
+    /// - [`XlsxError::ParameterError`] - The range doesn't have a data
+    ///   row below the header row.
+    #[wasm_bindgen(js_name = "setSourceRange", skip_jsdoc)]
+    pub fn set_source_range(
+        &self,
+        worksheet: &Worksheet,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+    ) -> WasmResult<PivotTable> {
+        if first_row > last_row || first_col > last_col {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnOrderError));
+        }
+        if first_row == last_row {
+            return Err(parameter_error(
+                "The pivot table source range needs a header row and at least one data row".to_string(),
+            ));
+        }
+        Ok(PivotTable {
+            source: Some(PivotSource::Range {
+                sheet: worksheet.target().id,
+                range: CellRange::new(first_row, first_col, last_row, last_col),
+            }),
+            ..self.clone()
+        })
+    }
+
+    /// Set the source data of the pivot table to a worksheet {@link Table}.
+    ///
+    /// The table is looked up by name, case insensitively, when the workbook
+    /// is saved. Tables that aren't named with {@link Table#setName} have the
+    /// default names `Table1`, `Table2` and so on.
+    ///
+    /// @param {string} name - The name of the table.
+    /// @returns {PivotTable} - The PivotTable object.
+    #[wasm_bindgen(js_name = "setSourceTable", skip_jsdoc)]
+    pub fn set_source_table(&self, name: &str) -> PivotTable {
+        PivotTable {
+            source: Some(PivotSource::Table(name.to_string())),
+            ..self.clone()
+        }
+    }
+
+    /// Add a row field. Each distinct value of the field is shown in a row.
+    ///
+    /// @param {string} name - The name of the source column.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The field is already a row,
+    ///   column or filter field.
+    #[wasm_bindgen(js_name = "addRowField", skip_jsdoc)]
+    pub fn add_row_field(&self, name: &str) -> WasmResult<PivotTable> {
+        self.check_axis_field(name)?;
+        let mut pivot_table = self.clone();
+        pivot_table.row_fields.push(name.to_string());
+        Ok(pivot_table)
+    }
+
+    /// Add a column field. Each distinct value of the field is shown in a
+    /// column.
+    ///
+    /// @param {string} name - The name of the source column.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The field is already a row,
+    ///   column or filter field.
+    #[wasm_bindgen(js_name = "addColumnField", skip_jsdoc)]
+    pub fn add_column_field(&self, name: &str) -> WasmResult<PivotTable> {
+        self.check_axis_field(name)?;
+        let mut pivot_table = self.clone();
+        pivot_table.column_fields.push(name.to_string());
+        Ok(pivot_table)
+    }
+
+    /// Add a filter field, shown above the pivot table.
+    ///
+    /// @param {string} name - The name of the source column.
+    /// @param {string | undefined} selected - The value to filter on, as it
+    ///   is displayed. The default is to show all values.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The field is already a row,
+    ///   column or filter field.
+    #[wasm_bindgen(js_name = "addFilterField", skip_jsdoc)]
+    pub fn add_filter_field(&self, name: &str, selected: Option<String>) -> WasmResult<PivotTable> {
+        self.check_axis_field(name)?;
+        let mut pivot_table = self.clone();
+        pivot_table.filter_fields.push((name.to_string(), selected));
+        Ok(pivot_table)
+    }
+
+    /// Add a value field, aggregated with a function.
+    ///
+    /// @param {string} name - The name of the source column.
+    /// @param {PivotFunction} func - The aggregation function.
+    /// @param {string | undefined} caption - The caption of the values. The
+    ///   default is like `Sum of Sales`. The captions of the value fields
+    ///   must be unique.
+    /// @returns {PivotTable} - The PivotTable object.
+    #[wasm_bindgen(js_name = "addValueField", skip_jsdoc)]
+    pub fn add_value_field(&self, name: &str, func: PivotFunction, caption: Option<String>) -> PivotTable {
+        let mut pivot_table = self.clone();
+        pivot_table.value_fields.push(ValueField {
+            name: name.to_string(),
+            function: func,
+            caption,
+        });
+        pivot_table
+    }
+
+    /// Set the pivot table style.
+    ///
+    /// @param {string} style - An Excel pivot table style name, like
+    ///   `PivotStyleLight16` (the default), `PivotStyleMedium9` or
+    ///   `PivotStyleDark1`.
+    /// @returns {PivotTable} - The PivotTable object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The style isn't a built-in
+    ///   pivot table style.
+    #[wasm_bindgen(js_name = "setStyle", skip_jsdoc)]
+    pub fn set_style(&self, style: &str) -> WasmResult<PivotTable> {
+        let number = ["PivotStyleLight", "PivotStyleMedium", "PivotStyleDark"]
+            .iter()
+            .find_map(|prefix| style.strip_prefix(prefix))
+            .and_then(|number| number.parse::<u8>().ok());
+        if !matches!(number, Some(1..=28)) {
+            return Err(parameter_error(format!("Unknown pivot table style '{style}'")));
+        }
+        Ok(PivotTable {
+            style: style.to_string(),
+            ..self.clone()
+        })
+    }
+
+    /// Show or hide the grand totals of the rows, in a column to the right
+    /// of the pivot table. The default is to show them.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {PivotTable} - The PivotTable object.
+    #[wasm_bindgen(js_name = "setRowGrandTotals", skip_jsdoc)]
+    pub fn set_row_grand_totals(&self, enable: bool) -> PivotTable {
+        PivotTable {
+            row_grand_totals: enable,
+            ..self.clone()
+        }
+    }
+
+    /// Show or hide the grand totals of the columns, in a row below the
+    /// pivot table. The default is to show them.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {PivotTable} - The PivotTable object.
+    #[wasm_bindgen(js_name = "setColumnGrandTotals", skip_jsdoc)]
+    pub fn set_column_grand_totals(&self, enable: bool) -> PivotTable {
+        PivotTable {
+            column_grand_totals: enable,
+            ..self.clone()
+        }
+    }
+}
+
+impl PivotTable {
+    fn check_axis_field(&self, name: &str) -> WasmResult<()> {
+        let used = self
+            .row_fields
+            .iter()
+            .chain(&self.column_fields)
+            .chain(self.filter_fields.iter().map(|(field, _)| field))
+            .any(|field| field.to_lowercase() == name.to_lowercase());
+        if used {
+            return Err(parameter_error(format!(
+                "Pivot table field '{name}' is already a row, column or filter field"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that the pivot table can be added to a worksheet.
+    pub(crate) fn validate(&self) -> WasmResult<()> {
+        if self.source.is_none() {
+            return Err(parameter_error(
+                "The pivot table source must be set with setSourceRange() or setSourceTable()".to_string(),
+            ));
+        }
+        if self.value_fields.is_empty() {
+            return Err(parameter_error("The pivot table needs at least one value field".to_string()));
+        }
+        let captions: Vec<&String> = self.value_fields.iter().filter_map(|field| field.caption.as_ref()).collect();
+        check_unique_captions(&captions)
+    }
+
+    /// Get the name of the pivot table, if it has been set.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// A pivot table added to a worksheet.
+#[derive(Clone, Debug)]
+pub(crate) struct PlacedPivotTable {
+    pub(crate) row: xlsx::RowNum,
+    pub(crate) col: xlsx::ColNum,
+    pub(crate) pivot_table: PivotTable,
+}
+
+/// A hashable form of a value in the pivot cache, used to find the shared
+/// item of a value.
+#[derive(PartialEq, Eq, Hash)]
+enum CacheKey<'a> {
+    Blank,
+    String(&'a str),
+    Number(u64),
+    Bool(bool),
+}
+
+/// A value in the pivot cache.
+#[derive(Clone, Debug, PartialEq)]
+enum CacheValue {
+    Blank,
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl CacheValue {
+    fn of(value: Option<&CellValue>) -> CacheValue {
+        match value {
+            None | Some(CellValue::Blank) | Some(CellValue::Formula(..)) | Some(CellValue::Image) => CacheValue::Blank,
+            Some(CellValue::String(s)) => CacheValue::String(s.clone()),
+            Some(CellValue::Number(n)) | Some(CellValue::DateTime(n)) => CacheValue::Number(*n),
+            Some(CellValue::Bool(b)) => CacheValue::Bool(*b),
+            Some(CellValue::Url(url)) => CacheValue::String(url.cell_text()),
+            Some(CellValue::RichString(parts)) => {
+                CacheValue::String(parts.iter().map(|(_, text)| text.as_str()).collect())
+            }
+        }
+    }
+
+    fn key(&self) -> CacheKey<'_> {
+        match self {
+            CacheValue::Blank => CacheKey::Blank,
+            CacheValue::String(s) => CacheKey::String(s),
+            // Zero and negative zero are the same item.
+            CacheValue::Number(n) => CacheKey::Number(if *n == 0.0 { 0 } else { n.to_bits() }),
+            CacheValue::Bool(b) => CacheKey::Bool(*b),
+        }
+    }
+
+    /// Get the value as it is displayed by a pivot table with the General
+    /// format.
+    fn text(&self) -> String {
+        match self {
+            CacheValue::Blank => "(blank)".to_string(),
+            CacheValue::String(s) => s.clone(),
+            CacheValue::Number(n) => number_format::general(*n),
+            CacheValue::Bool(true) => "TRUE".to_string(),
+            CacheValue::Bool(false) => "FALSE".to_string(),
+        }
+    }
+
+    /// Get the XML element of the value in a shared items list or record.
+    fn xml(&self) -> String {
+        match self {
+            CacheValue::Blank => "<m/>".to_string(),
+            CacheValue::String(s) => format!("<s v=\"{}\"/>", package::escape_xml(s)),
+            CacheValue::Number(n) => format!("<n v=\"{n}\"/>"),
+            CacheValue::Bool(b) => format!("<b v=\"{}\"/>", u8::from(*b)),
+        }
+    }
+
+    /// Order values like Excel sorts pivot items: numbers, text, booleans
+    /// and then blanks.
+    fn sort_key(&self) -> (u8, f64, String) {
+        match self {
+            CacheValue::Number(n) => (0, *n, String::new()),
+            CacheValue::String(s) => (1, 0.0, s.to_lowercase()),
+            CacheValue::Bool(b) => (2, f64::from(u8::from(*b)), String::new()),
+            CacheValue::Blank => (3, 0.0, String::new()),
+        }
+    }
+}
+
+/// A column of the source data.
+struct CacheField {
+    name: String,
+    values: Vec<CacheValue>,
+    /// The distinct values, in order of appearance, for fields that are used
+    /// on an axis.
+    shared_items: Option<Vec<CacheValue>>,
+}
+
+impl CacheField {
+    /// Get the index of each value in the shared items.
+    fn item_indexes(&self, shared_items: &[CacheValue]) -> Vec<usize> {
+        let ids: HashMap<CacheKey, usize> = (0..).zip(shared_items).map(|(id, item)| (item.key(), id)).collect();
+        self.values
+            .iter()
+            .map(|value| ids.get(&value.key()).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Get the shared item indexes in the order they are displayed.
+    fn sorted_items(&self) -> Vec<usize> {
+        let items = self.shared_items.as_deref().unwrap_or_default();
+        let mut sorted: Vec<usize> = (0..items.len()).collect();
+        sorted.sort_by(|&a, &b| {
+            items[a]
+                .sort_key()
+                .partial_cmp(&items[b].sort_key())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        sorted
+    }
+
+    fn shared_items_xml(&self) -> String {
+        let has = |f: fn(&CacheValue) -> bool| self.values.iter().any(f);
+        let has_string = has(|value| matches!(value, CacheValue::String(_)));
+        let has_number = has(|value| matches!(value, CacheValue::Number(_)));
+        let has_bool = has(|value| matches!(value, CacheValue::Bool(_)));
+        let has_blank = has(|value| matches!(value, CacheValue::Blank));
+        let types = [has_string, has_number, has_bool].iter().filter(|has| **has).count();
+
+        let mut attributes = String::new();
+        if !has_string && !has_blank {
+            attributes.push_str(" containsSemiMixedTypes=\"0\"");
+        }
+        if !has_string {
+            attributes.push_str(" containsString=\"0\"");
+        }
+        if has_blank {
+            attributes.push_str(" containsBlank=\"1\"");
+        }
+        if types > 1 {
+            attributes.push_str(" containsMixedTypes=\"1\"");
+        }
+        if has_number {
+            let numbers: Vec<f64> = self
+                .values
+                .iter()
+                .filter_map(|value| match value {
+                    CacheValue::Number(n) => Some(*n),
+                    _ => None,
+                })
+                .collect();
+            attributes.push_str(" containsNumber=\"1\"");
+            if numbers.iter().all(|n| n.fract() == 0.0) {
+                attributes.push_str(" containsInteger=\"1\"");
+            }
+            let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            attributes.push_str(&format!(" minValue=\"{min}\" maxValue=\"{max}\""));
+        }
+
+        match &self.shared_items {
+            Some(items) => {
+                let items: String = items.iter().map(CacheValue::xml).collect();
+                let count = self.shared_items.as_ref().map_or(0, Vec::len);
+                format!("<sharedItems{attributes} count=\"{count}\">{items}</sharedItems>")
+            }
+            None => format!("<sharedItems{attributes}/>"),
+        }
+    }
+}
+
+/// The source data of a pivot table, read from the cells written to the
+/// source worksheet.
+struct PivotCache {
+    /// The `worksheetSource` element.
+    source: String,
+    fields: Vec<CacheField>,
+    record_count: usize,
+}
+
+impl PivotCache {
+    fn new(store: &CellStore, range: CellRange, source: String, pivot_table: &PivotTable) -> WasmResult<PivotCache> {
+        let mut fields = vec![];
+        for col in range.first_col()..=range.last_col() {
+            let header = CacheValue::of(store.value(range.first_row(), col).map(|(value, _)| value));
+            if header == CacheValue::Blank {
+                return Err(parameter_error(format!(
+                    "The pivot table source header in column {} is empty",
+                    xlsx::utility::column_number_to_name(col)
+                )));
+            }
+            let name = header.text();
+            if fields.iter().any(|field: &CacheField| field.name.to_lowercase() == name.to_lowercase()) {
+                return Err(parameter_error(format!("The pivot table source header '{name}' isn't unique")));
+            }
+            let values = (range.first_row() + 1..=range.last_row())
+                .map(|row| CacheValue::of(store.value(row, col).map(|(value, _)| value)))
+                .collect();
+            fields.push(CacheField {
+                name,
+                values,
+                shared_items: None,
+            });
+        }
+
+        let mut cache = PivotCache {
+            source,
+            fields,
+            record_count: (range.last_row() - range.first_row()) as usize,
+        };
+        let axis_fields = pivot_table
+            .row_fields
+            .iter()
+            .chain(&pivot_table.column_fields)
+            .chain(pivot_table.filter_fields.iter().map(|(name, _)| name));
+        for name in axis_fields {
+            let index = cache.field_index(name)?;
+            let field = &mut cache.fields[index];
+            let mut seen = HashSet::new();
+            let items: Vec<CacheValue> = field
+                .values
+                .iter()
+                .filter(|value| seen.insert(value.key()))
+                .cloned()
+                .collect();
+            field.shared_items = Some(items);
+        }
+        Ok(cache)
+    }
+
+    fn field_index(&self, name: &str) -> WasmResult<usize> {
+        let lower = name.to_lowercase();
+        self.fields
+            .iter()
+            .position(|field| field.name.to_lowercase() == lower)
+            .ok_or_else(|| parameter_error(format!("Pivot table field '{name}' isn't in the source data")))
+    }
+
+    fn definition_xml(&self) -> String {
+        let mut xml = format!(
+            "{XML_DECLARATION}<pivotCacheDefinition xmlns=\"{MAIN_NAMESPACE}\" xmlns:r=\"{RELATIONSHIPS_NAMESPACE}\" \
+             r:id=\"rId1\" refreshOnLoad=\"1\" createdVersion=\"6\" refreshedVersion=\"6\" \
+             minRefreshableVersion=\"3\" recordCount=\"{}\"><cacheSource type=\"worksheet\">{}</cacheSource>\
+             <cacheFields count=\"{}\">",
+            self.record_count,
+            self.source,
+            self.fields.len()
+        );
+        for field in &self.fields {
+            xml.push_str(&format!(
+                "<cacheField name=\"{}\" numFmtId=\"0\">{}</cacheField>",
+                package::escape_xml(&field.name),
+                field.shared_items_xml()
+            ));
+        }
+        xml.push_str("</cacheFields></pivotCacheDefinition>");
+        xml
+    }
+
+    fn records_xml(&self) -> String {
+        let indexes: Vec<Option<Vec<usize>>> = self
+            .fields
+            .iter()
+            .map(|field| field.shared_items.as_ref().map(|items| field.item_indexes(items)))
+            .collect();
+        let mut xml = format!(
+            "{XML_DECLARATION}<pivotCacheRecords xmlns=\"{MAIN_NAMESPACE}\" xmlns:r=\"{RELATIONSHIPS_NAMESPACE}\" \
+             count=\"{}\">",
+            self.record_count
+        );
+        for record in 0..self.record_count {
+            xml.push_str("<r>");
+            for (field, indexes) in self.fields.iter().zip(&indexes) {
+                match indexes {
+                    Some(indexes) => xml.push_str(&format!("<x v=\"{}\"/>", indexes[record])),
+                    None => xml.push_str(&field.values[record].xml()),
+                }
+            }
+            xml.push_str("</r>");
+        }
+        xml.push_str("</pivotCacheRecords>");
+        xml
+    }
+
+    /// Count the lines of a compact pivot table axis: one per distinct value
+    /// at each level of the fields.
+    fn axis_lines(&self, fields: &[usize]) -> usize {
+        (1..=fields.len())
+            .map(|depth| {
+                let mut prefixes: Vec<Vec<String>> = (0..self.record_count)
+                    .map(|record| {
+                        fields[..depth]
+                            .iter()
+                            .map(|&field| self.fields[field].values[record].text())
+                            .collect()
+                    })
+                    .collect();
+                prefixes.sort();
+                prefixes.dedup();
+                prefixes.len()
+            })
+            .sum()
+    }
+}
+
+impl PivotTable {
+    fn definition_xml(&self, name: &str, cache_id: usize, cache: &PivotCache, row: u32, col: u16) -> WasmResult<String> {
+        let row_fields = self.field_indexes(cache, &self.row_fields)?;
+        let column_fields = self.field_indexes(cache, &self.column_fields)?;
+        let filter_names: Vec<String> = self.filter_fields.iter().map(|(name, _)| name.clone()).collect();
+        let filter_fields = self.field_indexes(cache, &filter_names)?;
+        let value_fields = self
+            .value_fields
+            .iter()
+            .map(|field| cache.field_index(&field.name))
+            .collect::<WasmResult<Vec<_>>>()?;
+
+        // The filters are shown above the table, with a blank row between.
+        let first_row = if filter_fields.is_empty() {
+            row
+        } else {
+            row + filter_fields.len() as u32 + 1
+        };
+        let many_values = value_fields.len() > 1;
+        let (first_header_row, first_data_row) = match (column_fields.is_empty(), many_values) {
+            (false, _) => (1, 2),
+            (true, true) => (0, 1),
+            (true, false) => (1, 1),
+        };
+        // The grand totals of the rows are shown in a column and the grand
+        // totals of the columns in a row, when there is an axis to total.
+        let row_lines = if row_fields.is_empty() {
+            1
+        } else {
+            cache.axis_lines(&row_fields) + usize::from(self.column_grand_totals)
+        };
+        let column_lines = if column_fields.is_empty() {
+            1
+        } else {
+            cache.axis_lines(&column_fields) + usize::from(self.row_grand_totals)
+        };
+        let height = first_data_row + row_lines as u32;
+        let width = usize::from(!row_fields.is_empty()) + column_lines * value_fields.len();
+        // Excel's worksheet limits.
+        if u64::from(first_row) + u64::from(height) > 1_048_576 || usize::from(col) + width > 16_384 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError));
+        }
+        let location = format!(
+            "{}:{}",
+            xlsx::utility::row_col_to_cell(first_row, col),
+            xlsx::utility::row_col_to_cell(first_row + height - 1, col + width as u16 - 1)
+        );
+
+        let mut xml = format!(
+            "{XML_DECLARATION}<pivotTableDefinition xmlns=\"{MAIN_NAMESPACE}\" name=\"{}\" cacheId=\"{cache_id}\" \
+             applyNumberFormats=\"0\" applyBorderFormats=\"0\" applyFontFormats=\"0\" applyPatternFormats=\"0\" \
+             applyAlignmentFormats=\"0\" applyWidthHeightFormats=\"1\" dataCaption=\"Values\" updatedVersion=\"6\" \
+             minRefreshableVersion=\"3\" useAutoFormatting=\"1\" itemPrintTitles=\"1\" createdVersion=\"6\" \
+             indent=\"0\" outline=\"1\" outlineData=\"1\" multipleFieldFilters=\"0\"{}{}>\
+             <location ref=\"{location}\" firstHeaderRow=\"{first_header_row}\" firstDataRow=\"{first_data_row}\" \
+             firstDataCol=\"1\"{}/><pivotFields count=\"{}\">",
+            package::escape_xml(name),
+            if self.row_grand_totals { "" } else { " rowGrandTotals=\"0\"" },
+            if self.column_grand_totals { "" } else { " colGrandTotals=\"0\"" },
+            if filter_fields.is_empty() {
+                String::new()
+            } else {
+                format!(" rowPageCount=\"{}\" colPageCount=\"1\"", filter_fields.len())
+            },
+            cache.fields.len()
+        );
+        for (index, field) in cache.fields.iter().enumerate() {
+            let axis = if row_fields.contains(&index) {
+                " axis=\"axisRow\""
+            } else if column_fields.contains(&index) {
+                " axis=\"axisCol\""
+            } else if filter_fields.contains(&index) {
+                " axis=\"axisPage\""
+            } else {
+                ""
+            };
+            let data_field = if value_fields.contains(&index) { " dataField=\"1\"" } else { "" };
+            if axis.is_empty() {
+                xml.push_str(&format!("<pivotField{data_field} showAll=\"0\"/>"));
+            } else {
+                let sorted = field.sorted_items();
+                let items: String = sorted.iter().map(|item| format!("<item x=\"{item}\"/>")).collect();
+                xml.push_str(&format!(
+                    "<pivotField{axis}{data_field} showAll=\"0\"><items count=\"{}\">{items}<item t=\"default\"/></items></pivotField>",
+                    sorted.len() + 1
+                ));
+            }
+        }
+        xml.push_str("</pivotFields>");
+
+        if !row_fields.is_empty() {
+            xml.push_str(&fields_xml("rowFields", &row_fields, false));
+        }
+        if !column_fields.is_empty() || many_values {
+            xml.push_str(&fields_xml("colFields", &column_fields, many_values));
+        }
+        if !filter_fields.is_empty() {
+            xml.push_str(&format!("<pageFields count=\"{}\">", filter_fields.len()));
+            for ((name, selected), &index) in self.filter_fields.iter().zip(&filter_fields) {
+                let item = match selected {
+                    Some(selected) => {
+                        let field = &cache.fields[index];
+                        let items = field.shared_items.as_deref().unwrap_or_default();
+                        let position = field
+                            .sorted_items()
+                            .iter()
+                            .position(|&item| items[item].text() == *selected)
+                            .ok_or_else(|| {
+                                parameter_error(format!("Pivot table filter value '{selected}' isn't in field '{name}'"))
+                            })?;
+                        format!(" item=\"{position}\"")
+                    }
+                    None => String::new(),
+                };
+                xml.push_str(&format!("<pageField fld=\"{index}\"{item} hier=\"-1\"/>"));
+            }
+            xml.push_str("</pageFields>");
+        }
+
+        let captions: Vec<String> = self
+            .value_fields
+            .iter()
+            .zip(&value_fields)
+            .map(|(field, &index)| match &field.caption {
+                Some(caption) => caption.clone(),
+                None => format!("{} of {}", field.function.caption(), cache.fields[index].name),
+            })
+            .collect();
+        check_unique_captions(&captions)?;
+
+        xml.push_str(&format!("<dataFields count=\"{}\">", value_fields.len()));
+        for ((field, &index), caption) in self.value_fields.iter().zip(&value_fields).zip(captions) {
+            // Excel doesn't allow captions that are the same as a field name.
+            if cache.field_index(&caption).is_ok() {
+                return Err(parameter_error(format!(
+                    "Pivot table value caption '{caption}' is already a field name"
+                )));
+            }
+            let subtotal = field
+                .function
+                .subtotal()
+                .map(|subtotal| format!(" subtotal=\"{subtotal}\""))
+                .unwrap_or_default();
+            xml.push_str(&format!(
+                "<dataField name=\"{}\" fld=\"{index}\"{subtotal} baseField=\"0\" baseItem=\"0\"/>",
+                package::escape_xml(&caption)
+            ));
+        }
+        xml.push_str("</dataFields>");
+
+        xml.push_str(&format!(
+            "<pivotTableStyleInfo name=\"{}\" showRowHeaders=\"1\" showColHeaders=\"1\" showRowStripes=\"0\" \
+             showColStripes=\"0\" showLastColumn=\"1\"/></pivotTableDefinition>",
+            self.style
+        ));
+        Ok(xml)
+    }
+
+    fn field_indexes(&self, cache: &PivotCache, names: &[String]) -> WasmResult<Vec<usize>> {
+        names.iter().map(|name| cache.field_index(name)).collect()
+    }
+}
+
+/// Get a `rowFields` or `colFields` element. The `-2` field is the position
+/// of the values when there is more than one value field.
+fn fields_xml(tag: &str, fields: &[usize], values: bool) -> String {
+    let mut fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+    if values {
+        fields.push("-2".to_string());
+    }
+    let items: String = fields.iter().map(|field| format!("<field x=\"{field}\"/>")).collect();
+    format!("<{tag} count=\"{}\">{items}</{tag}>", fields.len())
+}
+
+/// Add the pivot tables of the worksheets to a package saved by
+/// `rust_xlsxwriter`.
+pub(crate) fn update_package(settings: &WorkbookSettings, package: &mut Package) -> WasmResult<()> {
+    let workbook_xml = String::from_utf8_lossy(package.part("xl/workbook.xml").unwrap_or_default()).to_string();
+    // The sheet names are already escaped for use in an attribute.
+    let sheet_names: Vec<&str> = workbook_xml
+        .split("<sheet ")
+        .skip(1)
+        .filter_map(|sheet| sheet.split("name=\"").nth(1)?.split('"').next())
+        .collect();
+    let tables = table::package_tables(package, settings.sheet_ids.len());
+    let mut caches = String::new();
+    let mut content_types = String::new();
+    let mut number = 0;
+
+    for (sheet_index, id) in settings.sheet_ids.iter().enumerate() {
+        let Some(sheet) = settings.sheets.get(id) else {
+            continue;
+        };
+        // The default names skip the names of the other pivot tables of the
+        // worksheet, which must be unique.
+        let mut names: HashSet<String> = sheet
+            .pivot_tables
+            .iter()
+            .filter_map(|placed| Some(placed.pivot_table.name()?.to_lowercase()))
+            .collect();
+        for placed in &sheet.pivot_tables {
+            number += 1;
+            let pivot_table = &placed.pivot_table;
+            let (source_index, range, source) = match &pivot_table.source {
+                Some(PivotSource::Range { sheet, range }) => {
+                    let index = settings.sheet_ids.iter().position(|id| id == sheet).ok_or_else(|| {
+                        parameter_error("The pivot table source worksheet isn't in the workbook".to_string())
+                    })?;
+                    let source = format!(
+                        "<worksheetSource ref=\"{}\" sheet=\"{}\"/>",
+                        xlsx::utility::cell_range(range.first_row(), range.first_col(), range.last_row(), range.last_col()),
+                        sheet_names.get(index).copied().unwrap_or_default()
+                    );
+                    (index, *range, source)
+                }
+                Some(PivotSource::Table(name)) => {
+                    let table = tables
+                        .iter()
+                        .find(|table| table.name.to_lowercase() == name.to_lowercase())
+                        .ok_or_else(|| parameter_error(format!("The pivot table source table '{name}' doesn't exist")))?;
+                    if !table.header_row {
+                        return Err(parameter_error(format!(
+                            "The pivot table source table '{name}' doesn't have a header row"
+                        )));
+                    }
+                    // The pivot cache doesn't include the total row.
+                    let range = table.range;
+                    let last_row = range.last_row() - u32::from(table.total_row);
+                    let range = CellRange::new(range.first_row(), range.first_col(), last_row, range.last_col());
+                    let source = format!("<worksheetSource name=\"{}\"/>", package::escape_xml(&table.name));
+                    (table.sheet_index, range, source)
+                }
+                None => continue,
+            };
+
+            // The pivot cache is built from the recorded source cells.
+            let store = settings
+                .sheets
+                .get(&settings.sheet_ids[source_index])
+                .map(|sheet| sheet.cells.clone())
+                .unwrap_or_default();
+            let cache = PivotCache::new(&store.lock().unwrap(), range, source, pivot_table)?;
+            let name = match &pivot_table.name {
+                Some(name) => name.clone(),
+                None => {
+                    let name = (number..)
+                        .map(|suffix| format!("PivotTable{suffix}"))
+                        .find(|name| !names.contains(&name.to_lowercase()))
+                        .unwrap_or_default();
+                    names.insert(name.to_lowercase());
+                    name
+                }
+            };
+            let definition = pivot_table.definition_xml(&name, number, &cache, placed.row, placed.col)?;
+
+            package.set_part(
+                &format!("xl/pivotCache/pivotCacheDefinition{number}.xml"),
+                cache.definition_xml().into_bytes(),
+            );
+            package.set_part(
+                &format!("xl/pivotCache/pivotCacheRecords{number}.xml"),
+                cache.records_xml().into_bytes(),
+            );
+            package.add_relationship(
+                &format!("xl/pivotCache/_rels/pivotCacheDefinition{number}.xml.rels"),
+                PIVOT_RECORDS_RELATIONSHIP,
+                &format!("pivotCacheRecords{number}.xml"),
+            )?;
+            package.set_part(&format!("xl/pivotTables/pivotTable{number}.xml"), definition.into_bytes());
+            package.add_relationship(
+                &format!("xl/pivotTables/_rels/pivotTable{number}.xml.rels"),
+                PIVOT_CACHE_RELATIONSHIP,
+                &format!("../pivotCache/pivotCacheDefinition{number}.xml"),
+            )?;
+            package.add_relationship(
+                &format!("xl/worksheets/_rels/sheet{}.xml.rels", sheet_index + 1),
+                PIVOT_TABLE_RELATIONSHIP,
+                &format!("../pivotTables/pivotTable{number}.xml"),
+            )?;
+            let cache_rel = package.add_relationship(
+                "xl/_rels/workbook.xml.rels",
+                PIVOT_CACHE_RELATIONSHIP,
+                &format!("pivotCache/pivotCacheDefinition{number}.xml"),
+            )?;
+
+            caches.push_str(&format!("<pivotCache cacheId=\"{number}\" r:id=\"{cache_rel}\"/>"));
+            content_types.push_str(&format!(
+                "<Override PartName=\"/xl/pivotTables/pivotTable{number}.xml\" ContentType=\"{PIVOT_TABLE_CONTENT_TYPE}\"/>\
+                 <Override PartName=\"/xl/pivotCache/pivotCacheDefinition{number}.xml\" ContentType=\"{PIVOT_CACHE_CONTENT_TYPE}\"/>\
+                 <Override PartName=\"/xl/pivotCache/pivotCacheRecords{number}.xml\" ContentType=\"{PIVOT_RECORDS_CONTENT_TYPE}\"/>"
+            ));
+        }
+    }
+
+    if number == 0 {
+        return Ok(());
+    }
+    package.edit_xml("xl/workbook.xml", |xml| {
+        // The pivot caches follow the calculation properties, or the elements
+        // before them, in the workbook element order.
+        let caches = format!("<pivotCaches>{caches}</pivotCaches>");
+        let _ = ["calcPr", "definedNames", "sheets"]
+            .iter()
+            .any(|tag| package::insert_after_element(xml, tag, &caches));
+        Ok(())
+    })?;
+    package.edit_xml("[Content_Types].xml", |xml| {
+        package::insert_before_end_tag(xml, "Types", &content_types);
+        Ok(())
+    })
+}
+
+/// Check that the captions of the value fields are unique, like Excel
+/// requires.
+fn check_unique_captions(captions: &[impl AsRef<str>]) -> WasmResult<()> {
+    let mut seen = HashSet::new();
+    match captions.iter().find(|caption| !seen.insert(caption.as_ref().to_lowercase())) {
+        Some(caption) => Err(parameter_error(format!(
+            "Pivot table value caption '{}' isn't unique",
+            caption.as_ref()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn parameter_error(message: String) -> XlsxError {
+    XlsxError::Xlsx(xlsx::XlsxError::ParameterError(message))
+}
@@ -0,0 +1,91 @@
+//! A minimal PNG encoder for raw pixel data.
+//!
+//! `rust_xlsxwriter` only accepts encoded images, so pixels from sources such
+//! as a canvas are encoded as an 8 bit PNG before they are inserted.
+
+use std::io::Write;
+
+use flate2::{write::ZlibEncoder, Compression, Crc};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// The PNG filter type of a scanline that is stored as is.
+const FILTER_NONE: u8 = 0;
+
+/// Encode RGBA pixels, in row major order, as a PNG image. Opaque images are
+/// written without the alpha channel.
+///
+/// The pixel data must have `width * height * 4` bytes.
+pub(crate) fn encode_rgba(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    let opaque = pixels.chunks_exact(4).all(|pixel| pixel[3] == 0xFF);
+    let (color_type, channels) = if opaque { (2, 3) } else { (6, 4) };
+
+    let mut scanlines = Vec::with_capacity((width as usize * channels + 1) * height as usize);
+    for row in pixels.chunks_exact(width as usize * 4) {
+        scanlines.push(FILTER_NONE);
+        if opaque {
+            for pixel in row.chunks_exact(4) {
+                scanlines.extend_from_slice(&pixel[..3]);
+            }
+        } else {
+            scanlines.extend_from_slice(row);
+        }
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing to a `Vec` can't fail.
+    encoder.write_all(&scanlines).unwrap();
+    let data = encoder.finish().unwrap();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods.
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Add a `tEXt` chunk with a keyword and text to a PNG image, before its
+/// `IEND` chunk. Returns `None` if the data isn't a PNG image that ends with
+/// an `IEND` chunk.
+pub(crate) fn add_text(png: &[u8], keyword: &str, text: &str) -> Option<Vec<u8>> {
+    if !png.starts_with(&SIGNATURE) {
+        return None;
+    }
+    let mut offset = SIGNATURE.len();
+    loop {
+        let length = u32::from_be_bytes(png.get(offset..offset + 4)?.try_into().ok()?);
+        if png.get(offset + 4..offset + 8)? == b"IEND" {
+            // The `IEND` chunk is empty.
+            if length != 0 || png.len() < offset + 12 {
+                return None;
+            }
+            break;
+        }
+        // The chunk length, type and CRC take 12 bytes.
+        offset = offset.checked_add(usize::try_from(length).ok()?.checked_add(12)?)?;
+    }
+    let (image, end) = png.split_at(offset);
+    let mut data = keyword.as_bytes().to_vec();
+    data.push(0);
+    data.extend_from_slice(text.as_bytes());
+
+    let mut png = image.to_vec();
+    write_chunk(&mut png, b"tEXt", &data);
+    png.extend_from_slice(end);
+    Some(png)
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex, MutexGuard};
 
-use super::format::Format;
+use super::{format::Format, format_properties::FormatProperties};
 use rust_xlsxwriter as xlsx;
 use wasm_bindgen::prelude::*;
 
@@ -8,15 +8,29 @@ use wasm_bindgen::prelude::*;
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct RichString {
-    inner: Arc<Mutex<Vec<(xlsx::Format, String)>>>,
+    inner: Arc<Mutex<Vec<(FormatProperties, String)>>>,
 }
 
 #[wasm_bindgen]
 impl RichString {
-    pub(crate) fn lock(&self) -> MutexGuard<Vec<(xlsx::Format, String)>> {
+    pub(crate) fn lock(&self) -> MutexGuard<Vec<(FormatProperties, String)>> {
         self.inner.lock().unwrap()
     }
 
+    /// Build the `rust_xlsxwriter` formats of the parts.
+    pub(crate) fn formats(&self) -> Vec<(xlsx::Format, String)> {
+        self.lock()
+            .iter()
+            .map(|(format, text)| (format.to_format(), text.clone()))
+            .collect()
+    }
+
+    pub(crate) fn from_parts(parts: Vec<(FormatProperties, String)>) -> RichString {
+        RichString {
+            inner: Arc::new(Mutex::new(parts)),
+        }
+    }
+
     /// Create a new RichString struct.
     ///
     /// @returns {RichString} - The rich string object.
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use js_sys::Date;
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::WasmResult;
+
+/// The `SaveOptions` struct defines options for saving a workbook.
+///
+/// By default `rust_xlsxwriter` stores the current time as the creation and
+/// modification time in the document properties, so creating the same
+/// workbook twice gives different files. Setting a fixed creation time with
+/// {@link SaveOptions#setCreationDatetime} removes that difference so that
+/// identical input produces a byte-identical xlsx file with the current
+/// version of `rust_xlsxwriter`.
+///
+/// The options are used with {@link Workbook#setSaveOptions}.
+///
+/// TODO: example omitted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub struct SaveOptions {
+    creation_datetime: Option<DateTime<Utc>>,
+    compression_level: Option<u8>,
+}
+
+#[wasm_bindgen]
+impl SaveOptions {
+    /// Create a new `SaveOptions` with the default options.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SaveOptions {
+        SaveOptions::default()
+    }
+
+    /// Set a fixed creation time for the file.
+    ///
+    /// The time is stored as both the creation and the modification time of
+    /// the document, instead of the current time. It overrides the creation
+    /// time of the {@link DocProperties} set with
+    /// {@link Workbook#setProperties}.
+    ///
+    /// @param {Date} datetime - The creation date and time. The UTC time of
+    ///   the `Date` is used.
+    /// @returns {SaveOptions} - The SaveOptions object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::InvalidDate`] - The `Date` is invalid or out of
+    ///   range.
+    #[wasm_bindgen(js_name = "setCreationDatetime", skip_jsdoc)]
+    pub fn set_creation_datetime(&self, datetime: &Date) -> WasmResult<SaveOptions> {
+        let millis = datetime.get_time();
+        if !millis.is_finite() {
+            return Err(XlsxError::InvalidDate);
+        }
+        let creation_datetime = DateTime::from_timestamp_millis(millis as i64).ok_or(XlsxError::InvalidDate)?;
+        Ok(SaveOptions {
+            creation_datetime: Some(creation_datetime),
+            ..*self
+        })
+    }
+
+    /// Set the zip compression level of the file.
+    ///
+    /// The level ranges from 0, no compression, to 9, the best and slowest
+    /// compression. The default is the `rust_xlsxwriter` level, 6.
+    ///
+    /// @param {number} level - The compression level in the range 0-9.
+    /// @returns {SaveOptions} - The SaveOptions object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The level is greater than 9.
+    #[wasm_bindgen(js_name = "setCompressionLevel", skip_jsdoc)]
+    pub fn set_compression_level(&self, level: u8) -> WasmResult<SaveOptions> {
+        if level > 9 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "Compression level must be in the range 0-9".to_string(),
+            )));
+        }
+        Ok(SaveOptions {
+            compression_level: Some(level),
+            ..*self
+        })
+    }
+}
+
+impl SaveOptions {
+    /// Check if the saved package has to be recompressed.
+    pub(crate) fn needs_package_update(&self) -> bool {
+        self.compression_level.is_some()
+    }
+
+    pub(crate) fn creation_datetime(&self) -> Option<DateTime<Utc>> {
+        self.creation_datetime
+    }
+
+    pub(crate) fn compression_level(&self) -> Option<u8> {
+        self.compression_level
+    }
+}
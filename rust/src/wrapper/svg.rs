@@ -0,0 +1,290 @@
+//! Support for SVG images.
+//!
+//! Excel 2016 and later show SVG images that are stored alongside a raster
+//! fallback image, which older viewers show instead. `rust_xlsxwriter` only
+//! writes the fallback, so the SVG is added to the image drawings after the
+//! workbook has been saved.
+
+use std::sync::Arc;
+
+use resvg::{tiny_skia, usvg};
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    package::{self, Package},
+    png, WasmResult,
+};
+
+const IMAGE_RELATIONSHIP: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+
+/// The `a:blip` extension that refers to the SVG version of an image.
+const SVG_BLIP_EXTENSION: &str = "{96DAC541-7B7A-43D3-8B79-37D633B846F1}";
+
+/// The `SvgOptions` struct defines how an SVG image is created with
+/// {@link Image.fromSvg}.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug, Default)]
+#[wasm_bindgen]
+pub struct SvgOptions {
+    pub(crate) fallback: Option<Vec<u8>>,
+    pub(crate) size: Option<(u32, u32)>,
+}
+
+#[wasm_bindgen]
+impl SvgOptions {
+    /// Create a new `SvgOptions` with the default options.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SvgOptions {
+        SvgOptions::default()
+    }
+
+    /// Set the raster image that is shown by viewers that don't support SVG
+    /// images, and used for cell and header images.
+    ///
+    /// Without a fallback image the SVG is rendered to a PNG image, without
+    /// its text since no fonts are available. A fallback with the text can
+    /// be rendered from the SVG in the browser, for example by drawing it on
+    /// a `<canvas>` and using the data URL of the canvas.
+    ///
+    /// @param {Uint8Array} buffer - The fallback PNG image data.
+    /// @returns {SvgOptions} - The SvgOptions object.
+    #[wasm_bindgen(js_name = "setFallback", skip_jsdoc)]
+    pub fn set_fallback(&self, buffer: Vec<u8>) -> SvgOptions {
+        SvgOptions {
+            fallback: Some(buffer),
+            ..self.clone()
+        }
+    }
+
+    /// Set the size of the image in pixels.
+    ///
+    /// By default the size is read from the `width`, `height` and `viewBox`
+    /// attributes of the SVG. The size is required for SVG images that don't
+    /// define it and don't have a fallback image. The SVG is rendered with
+    /// this size when it doesn't have a fallback image.
+    ///
+    /// @param {number} width - The width of the image in pixels.
+    /// @param {number} height - The height of the image in pixels.
+    /// @returns {SvgOptions} - The SvgOptions object.
+    #[wasm_bindgen(js_name = "setSize", skip_jsdoc)]
+    pub fn set_size(&self, width: u32, height: u32) -> SvgOptions {
+        SvgOptions {
+            size: Some((width, height)),
+            ..self.clone()
+        }
+    }
+}
+
+/// An SVG image and the fallback image that `rust_xlsxwriter` writes for it.
+#[derive(Debug)]
+pub(crate) struct SvgImage {
+    pub(crate) svg: Vec<u8>,
+    /// The fallback PNG image, tagged with the hash of the SVG, see
+    /// `update_package()`.
+    pub(crate) fallback: Vec<u8>,
+}
+
+/// Render an SVG image to a PNG image with a size in pixels.
+///
+/// Text isn't rendered since there are no fonts to render it with.
+pub(crate) fn rasterize(data: &[u8], width: u32, height: u32) -> WasmResult<Vec<u8>> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default()).map_err(|_| not_svg())?;
+    let Some(mut pixmap) = tiny_skia::Pixmap::new(width, height) else {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+            "Image dimensions must be non-zero: {width}x{height}"
+        ))));
+    };
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // The pixmap has premultiplied alpha.
+    let pixels: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Ok(png::encode_rgba(width, height, &pixels))
+}
+
+/// Check that data is an SVG image, and get its size in pixels if the SVG
+/// defines it.
+pub(crate) fn parse_svg(data: &[u8]) -> WasmResult<Option<(u32, u32)>> {
+    let root = std::str::from_utf8(data).ok().and_then(|svg| {
+        let start = svg.find("<svg")?;
+        let end = start + svg[start..].find('>')?;
+        Some(&svg[start..end])
+    });
+    let Some(root) = root else {
+        return Err(not_svg());
+    };
+
+    let length = |name: &str| attribute(root, name).and_then(parse_length);
+    let view_box = attribute(root, "viewBox").and_then(|view_box| {
+        let values: Vec<f64> = view_box
+            .split(|c: char| c.is_ascii_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse().ok())
+            .collect::<Option<_>>()?;
+        match values[..] {
+            [_, _, width, height] if width > 0.0 && height > 0.0 => Some((width, height)),
+            _ => None,
+        }
+    });
+    let size = match (length("width"), length("height"), view_box) {
+        (Some(width), Some(height), _) => Some((width, height)),
+        (Some(width), None, Some((box_width, box_height))) => Some((width, width * box_height / box_width)),
+        (None, Some(height), Some((box_width, box_height))) => Some((height * box_width / box_height, height)),
+        (None, None, view_box) => view_box,
+        _ => None,
+    };
+    Ok(size.map(|(width, height)| (width.round().max(1.0) as u32, height.round().max(1.0) as u32)))
+}
+
+/// Get the value of an attribute of an XML start tag, in single or double
+/// quotes.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!("{name}=");
+    tag.match_indices(&needle).find_map(|(pos, _)| {
+        if !tag[..pos].ends_with(|c: char| c.is_ascii_whitespace()) {
+            return None;
+        }
+        let value = &tag[pos + needle.len()..];
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value = &value[1..];
+        Some(&value[..value.find(quote)?])
+    })
+}
+
+/// Convert an SVG length to pixels. Relative lengths, such as percentages,
+/// aren't supported.
+fn parse_length(length: &str) -> Option<f64> {
+    let length = length.trim();
+    let split = length
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E'))
+        .unwrap_or(length.len());
+    let (number, unit) = length.split_at(split);
+    let pixels_per_unit = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        _ => return None,
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| *number > 0.0)
+        .map(|number| number * pixels_per_unit)
+}
+
+/// Add the SVG images to the drawings of a package saved by
+/// `rust_xlsxwriter`.
+///
+/// The images are found by their fallback image, which is tagged with the
+/// hash of the SVG so that other raster images don't match it.
+pub(crate) fn update_package(images: &[Arc<SvgImage>], package: &mut Package) -> WasmResult<()> {
+    let drawing_rels = package.part_names(|name| name.starts_with("xl/drawings/_rels/drawing") && name.ends_with(".xml.rels"));
+    let mut has_svg = false;
+    for media_name in package.part_names(|name| name.starts_with("xl/media/")) {
+        let Some(image) = images
+            .iter()
+            .find(|image| package.part(&media_name) == Some(image.fallback.as_slice()))
+        else {
+            continue;
+        };
+        let file_name = media_name.trim_start_matches("xl/media/");
+        let svg_file_name = match file_name.rsplit_once('.') {
+            Some((stem, _)) => format!("{stem}.svg"),
+            None => format!("{file_name}.svg"),
+        };
+
+        let mut referenced = false;
+        for rels_name in &drawing_rels {
+            let rels = String::from_utf8_lossy(package.part(rels_name).unwrap_or_default()).into_owned();
+            let ids = relationship_ids(&rels, &format!("../media/{file_name}"));
+            if ids.is_empty() {
+                continue;
+            }
+            let svg_id = package.add_relationship(rels_name, IMAGE_RELATIONSHIP, &format!("../media/{svg_file_name}"))?;
+            let drawing_name = rels_name.replace("_rels/", "").trim_end_matches(".rels").to_string();
+            package.edit_xml(&drawing_name, |xml| {
+                for id in &ids {
+                    add_svg_blip(xml, id, &svg_id);
+                }
+                Ok(())
+            })?;
+            referenced = true;
+        }
+        // Cell and header images don't support SVG and only use the
+        // fallback.
+        if referenced {
+            package.set_part(&format!("xl/media/{svg_file_name}"), image.svg.clone());
+            has_svg = true;
+        }
+    }
+
+    if has_svg {
+        package.edit_xml("[Content_Types].xml", |xml| {
+            if !xml.contains("<Default Extension=\"svg\"") {
+                package::insert_after_start_tag(xml, "Types", "<Default Extension=\"svg\" ContentType=\"image/svg+xml\"/>");
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn not_svg() -> XlsxError {
+    XlsxError::Xlsx(xlsx::XlsxError::ParameterError("The data isn't an SVG image".to_string()))
+}
+
+/// Get the ids of the relationships of a relationships part with a target.
+fn relationship_ids(rels: &str, target: &str) -> Vec<String> {
+    let target = format!(" Target=\"{target}\"");
+    rels.split("<Relationship ")
+        .skip(1)
+        .filter_map(|relationship| {
+            let relationship = &relationship[..relationship.find("/>")?];
+            if !relationship.contains(&target) {
+                return None;
+            }
+            let id = &relationship[relationship.find("Id=\"")? + 4..];
+            Some(id[..id.find('"')?].to_string())
+        })
+        .collect()
+}
+
+/// Add the SVG version to the `a:blip` elements that embed an image.
+fn add_svg_blip(xml: &mut String, id: &str, svg_id: &str) {
+    let embed = format!(" r:embed=\"{id}\"/>");
+    let extension = format!(
+        " r:embed=\"{id}\"><a:extLst><a:ext uri=\"{SVG_BLIP_EXTENSION}\">\
+         <asvg:svgBlip xmlns:asvg=\"http://schemas.microsoft.com/office/drawing/2016/SVG/main\" r:embed=\"{svg_id}\"/>\
+         </a:ext></a:extLst></a:blip>"
+    );
+    let mut offset = 0;
+    while let Some(pos) = xml[offset..].find("<a:blip ") {
+        let start = offset + pos;
+        let Some(len) = xml[start..].find('>') else {
+            break;
+        };
+        let end = start + len + 1;
+        if xml[start..end].ends_with(&embed) {
+            let embed_start = end - embed.len();
+            xml.replace_range(embed_start..end, &extension);
+            offset = embed_start + extension.len();
+        } else {
+            offset = end;
+        }
+    }
+}
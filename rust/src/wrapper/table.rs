@@ -1,9 +1,21 @@
+use std::cmp::Ordering;
+
 use rust_xlsxwriter::{self as xlsx};
 use wasm_bindgen::prelude::*;
 
+use crate::error::XlsxError;
 use crate::wrapper::format::Format;
 
-use super::formula::Formula;
+use super::{
+    cell_store::{CellRange, CellValue},
+    formula::Formula,
+    number_format,
+    package::{self, Package},
+    table_style::CustomTableStyle,
+    utils,
+    workbook_settings::WorkbookSettings,
+    WasmResult,
+};
 
 /// The `Table` struct represents a worksheet Table.
 ///
@@ -34,6 +46,17 @@ use super::formula::Formula;
 #[wasm_bindgen]
 pub struct Table {
     pub(crate) inner: xlsx::Table,
+    pub(crate) name: Option<String>,
+    pub(crate) columns: Vec<TableColumn>,
+    pub(crate) autofilter: bool,
+    /// The name of a {@link CustomTableStyle} that overrides the style.
+    pub(crate) custom_style: Option<String>,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "TableStyle | CustomTableStyle")]
+    pub type JsTableStyle;
 }
 
 #[wasm_bindgen]
@@ -42,69 +65,315 @@ impl Table {
     pub fn new() -> Self {
         Table {
             inner: xlsx::Table::new(),
+            name: None,
+            columns: vec![],
+            autofilter: true,
+            custom_style: None,
         }
     }
 
     #[wasm_bindgen(js_name = "setName")]
     pub fn set_name(&self, name: &str) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_name(name),
+            inner: table.inner.set_name(name),
+            name: Some(name.to_string()),
+            ..table
         }
     }
 
-    #[wasm_bindgen(js_name = "setStyle")]
-    pub fn set_style(&self, style: TableStyle) -> Table {
-        let style = xlsx::TableStyle::from(style);
-        Table {
-            inner: self.clone().inner.set_style(style),
+    /// Set the style of the table.
+    ///
+    /// The style is either one of the built-in Excel table styles or a
+    /// {@link CustomTableStyle}. A custom style has to be registered with
+    /// {@link Workbook#addTableStyle} before the workbook is saved.
+    ///
+    /// @param {TableStyle | CustomTableStyle} style - The table style.
+    /// @returns {Table} - The Table object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::Type`] - The style isn't a {@link TableStyle} or
+    ///   {@link CustomTableStyle}.
+    #[wasm_bindgen(js_name = "setStyle", skip_jsdoc)]
+    pub fn set_style(&self, style: &JsTableStyle) -> WasmResult<Table> {
+        let style = JsValue::from(style);
+        if let Some(index) = style.as_f64() {
+            let style = (index.fract() == 0.0 && index >= 0.0)
+                .then(|| TableStyle::ALL.get(index as usize))
+                .flatten()
+                .ok_or_else(|| XlsxError::Type(format!("Invalid table style: {index}")))?;
+            let table = self.clone();
+            return Ok(Table {
+                inner: table.inner.set_style(xlsx::TableStyle::from(*style)),
+                custom_style: None,
+                ..table
+            });
         }
+        let custom_style = utils::generic_of_jsval::<CustomTableStyle>(&style, "CustomTableStyle")
+            .map_err(|_| XlsxError::Type("The table style isn't a TableStyle or CustomTableStyle".to_string()))?;
+        let table = self.clone();
+        Ok(Table {
+            // The built-in style is replaced by the custom style when the
+            // workbook is saved.
+            inner: table.inner.set_style(xlsx::TableStyle::Medium9),
+            custom_style: Some(custom_style.name.clone()),
+            ..table
+        })
     }
 
     // FIXME: ownership?
     #[wasm_bindgen(js_name = "setColumns")]
     pub fn set_columns(&self, columns: Vec<TableColumn>) -> Table {
-        let columns: Vec<_> = columns.into_iter().map(|c| c.inner).collect();
+        let inner_columns: Vec<_> = columns.iter().map(|c| c.inner.clone()).collect();
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_columns(&columns),
+            inner: table.inner.set_columns(&inner_columns),
+            columns,
+            ..table
         }
     }
 
     #[wasm_bindgen(js_name = "setFirstColumn")]
     pub fn set_first_column(&self, enable: bool) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_first_column(enable),
+            inner: table.inner.set_first_column(enable),
+            ..table
         }
     }
 
     #[wasm_bindgen(js_name = "setHeaderRow")]
     pub fn set_header_row(&self, enable: bool) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_header_row(enable),
+            inner: table.inner.set_header_row(enable),
+            ..table
         }
     }
 
     #[wasm_bindgen(js_name = "setTotalRow")]
     pub fn set_total_row(&self, enable: bool) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_total_row(enable),
+            inner: table.inner.set_total_row(enable),
+            ..table
         }
     }
 
     #[wasm_bindgen(js_name = "setBandedColumns")]
     pub fn set_banded_columns(&self, enable: bool) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_banded_columns(enable),
+            inner: table.inner.set_banded_columns(enable),
+            ..table
         }
     }
 
     #[wasm_bindgen(js_name = "setBandedRows")]
     pub fn set_banded_rows(&self, enable: bool) -> Table {
+        let table = self.clone();
+        Table {
+            inner: table.inner.set_banded_rows(enable),
+            ..table
+        }
+    }
+
+    /// Turn on/off the last column highlighting for a table.
+    ///
+    /// The last column of a worksheet table is often used for a `SUM()` or
+    /// other formula. This method can be used to highlight the column with
+    /// the table style. The default is off.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {Table} - The Table object.
+    #[wasm_bindgen(js_name = "setLastColumn", skip_jsdoc)]
+    pub fn set_last_column(&self, enable: bool) -> Table {
+        let table = self.clone();
+        Table {
+            inner: table.inner.set_last_column(enable),
+            ..table
+        }
+    }
+
+    /// Turn on/off the autofilter for a table.
+    ///
+    /// By default Excel adds an autofilter to the header of a table. This
+    /// method can be used to turn it off. Column filters, set with
+    /// {@link TableColumn#setFilter}, need the autofilter.
+    ///
+    /// @param {boolean} enable - Turn the property on/off.
+    /// @returns {Table} - The Table object.
+    #[wasm_bindgen(js_name = "setAutofilter", skip_jsdoc)]
+    pub fn set_autofilter(&self, enable: bool) -> Table {
+        let table = self.clone();
         Table {
-            inner: self.clone().inner.set_banded_rows(enable),
+            inner: table.inner.set_autofilter(enable),
+            autofilter: enable,
+            ..table
+        }
+    }
+
+    /// Get a structured reference to a column of the table.
+    ///
+    /// The reference is like `Table1[Price]`, or `Table1[@Price]` for the
+    /// value in the same row of the table, and can be used to build formulas
+    /// such as `Table1[@Price]*Table1[@Qty]`. The table name is left out, as
+    /// in `[@Price]`, if it hasn't been set with {@link Table#setName}. Such
+    /// references can only be used in formulas inside the table.
+    ///
+    /// The column is checked against the headers set with
+    /// {@link Table#setColumns}, case insensitively. Columns without a header
+    /// have the default headers `Column1`, `Column2` and so on.
+    ///
+    /// @param {string} column - The header of the column.
+    /// @param {boolean} this_row - Refer to the value in the same row.
+    /// @returns {string} - The structured reference.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The column isn't a header of the
+    ///   table.
+    #[wasm_bindgen(js_name = "columnReference", skip_jsdoc)]
+    pub fn column_reference(&self, column: &str, this_row: bool) -> WasmResult<String> {
+        let header = self.header(column)?;
+        let reference = match (this_row, needs_brackets(&header)) {
+            (true, true) => format!("[@[{}]]", escape_column(&header)),
+            (true, false) => format!("[@{}]", escape_column(&header)),
+            (false, _) => format!("[{}]", escape_column(&header)),
+        };
+        Ok(self.qualify(reference))
+    }
+
+    /// Get a structured reference to a range of columns of the table.
+    ///
+    /// The reference is like `Table1[[Quarter 1]:[Quarter 4]]`, or
+    /// `Table1[@[Quarter 1]:[Quarter 4]]` for the values in the same row of
+    /// the table. See {@link Table#columnReference} for how the columns are
+    /// checked.
+    ///
+    /// @param {string} first_column - The header of the first column.
+    /// @param {string} last_column - The header of the last column.
+    /// @param {boolean} this_row - Refer to the values in the same row.
+    /// @returns {string} - The structured reference.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - A column isn't a header of the
+    ///   table.
+    #[wasm_bindgen(js_name = "columnRangeReference", skip_jsdoc)]
+    pub fn column_range_reference(&self, first_column: &str, last_column: &str, this_row: bool) -> WasmResult<String> {
+        let first = escape_column(&self.header(first_column)?);
+        let last = escape_column(&self.header(last_column)?);
+        let at = if this_row { "@" } else { "" };
+        Ok(self.qualify(format!("[{at}[{first}]:[{last}]]")))
+    }
+
+    /// Get a `SUBTOTAL()` formula for a column of the table.
+    ///
+    /// The formula is like `SUBTOTAL(109,Table1[Amount])`, or
+    /// `SUBTOTAL(109,[Amount])` if the table name hasn't been set, and only
+    /// includes the rows that aren't hidden by a filter. See
+    /// {@link Table#columnReference} for how the column is checked.
+    ///
+    /// @param {TableFunction} table_function - The subtotal function. Custom
+    ///   functions aren't supported.
+    /// @param {string} column - The header of the column.
+    /// @returns {Formula} - The formula.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The column isn't a header of the
+    ///   table or the function is a custom function.
+    #[wasm_bindgen(js_name = "subtotalFormula", skip_jsdoc)]
+    pub fn subtotal_formula(&self, table_function: &TableFunction, column: &str) -> WasmResult<Formula> {
+        let Some(number) = subtotal_number(&table_function.inner) else {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "Custom table functions don't have a SUBTOTAL() function number".to_string(),
+            )));
+        };
+        let reference = self.column_reference(column, false)?;
+        Ok(Formula::new(&format!("SUBTOTAL({number},{reference})")))
+    }
+}
+
+impl Table {
+    /// Get the header names of the columns, for the columns that have been
+    /// configured.
+    pub(crate) fn headers(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                column
+                    .header
+                    .clone()
+                    .unwrap_or_else(|| format!("Column{}", index + 1))
+            })
+            .collect()
+    }
+
+    fn header(&self, column: &str) -> WasmResult<String> {
+        let lower = column.to_lowercase();
+        self.headers()
+            .into_iter()
+            .find(|header| header.to_lowercase() == lower)
+            .ok_or_else(|| {
+                XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                    "Column '{column}' isn't a header of the table"
+                )))
+            })
+    }
+
+    fn qualify(&self, reference: String) -> String {
+        match &self.name {
+            Some(name) => format!("{name}{reference}"),
+            None => reference,
         }
     }
 }
 
+/// Escape the characters of a column name that have a special meaning in
+/// structured references.
+fn escape_column(name: &str) -> String {
+    name.replace('\'', "''")
+        .replace('#', "'#")
+        .replace(']', "']")
+        .replace('[', "'[")
+}
+
+/// Check if a column name has to be in its own brackets when it is combined
+/// with other parts of a structured reference.
+fn needs_brackets(name: &str) -> bool {
+    name.chars()
+        .any(|c| c.is_whitespace() || ",:.[]#'\"{}$^&*+=-></".contains(c))
+}
+
+/// Get the `SUBTOTAL()` function number of a table function.
+fn subtotal_number(function: &xlsx::TableFunction) -> Option<u8> {
+    match function {
+        xlsx::TableFunction::Average => Some(101),
+        xlsx::TableFunction::CountNumbers => Some(102),
+        xlsx::TableFunction::Count => Some(103),
+        xlsx::TableFunction::Max => Some(104),
+        xlsx::TableFunction::Min => Some(105),
+        xlsx::TableFunction::StdDev => Some(107),
+        xlsx::TableFunction::Sum => Some(109),
+        xlsx::TableFunction::Var => Some(110),
+        _ => None,
+    }
+}
+
+/// Get the formula that `rust_xlsxwriter` writes in the total row of a
+/// column.
+pub(crate) fn total_formula(function: &xlsx::TableFunction, column: &str) -> Option<xlsx::Formula> {
+    match function {
+        xlsx::TableFunction::Custom(formula) => Some(formula.clone()),
+        function => subtotal_number(function)
+            .map(|number| xlsx::Formula::new(format!("SUBTOTAL({number},[{}])", escape_column(column)))),
+    }
+}
+
 /// The `TableColumn` struct represents a table column.
 ///
 /// The `TableColumn` struct is used to set the properties for columns in a
@@ -115,12 +384,19 @@ impl Table {
 /// - The total row caption.
 /// - The total row subtotal function.
 /// - A formula for the column.
+/// - The total row format.
+/// - A filter for the column.
 ///
 /// This struct is used in conjunction with the {@link Table#setColumns} method.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 #[wasm_bindgen]
 pub struct TableColumn {
-    inner: xlsx::TableColumn,
+    pub(crate) inner: xlsx::TableColumn,
+    pub(crate) header: Option<String>,
+    pub(crate) total_label: Option<String>,
+    pub(crate) total_function: Option<xlsx::TableFunction>,
+    pub(crate) total_format: Option<xlsx::Format>,
+    pub(crate) filter: Option<TableFilter>,
 }
 
 #[wasm_bindgen]
@@ -128,9 +404,7 @@ impl TableColumn {
     /// Create a new `TableColumn` to configure a Table column.
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        TableColumn {
-            inner: xlsx::TableColumn::new(),
-        }
+        TableColumn::default()
     }
 
     /// Set the header caption for a table column.
@@ -147,8 +421,11 @@ impl TableColumn {
     /// @returns {TableColumn} - The TableColumn object.
     #[wasm_bindgen(js_name = "setHeader", skip_jsdoc)]
     pub fn set_header(&self, caption: &str) -> TableColumn {
+        let column = self.clone();
         TableColumn {
-            inner: self.clone().inner.set_header(caption),
+            inner: column.inner.set_header(caption),
+            header: Some(caption.to_string()),
+            ..column
         }
     }
 
@@ -161,8 +438,10 @@ impl TableColumn {
     /// @returns {TableColumn} - The TableColumn object.
     #[wasm_bindgen(js_name = "setHeaderFormat", skip_jsdoc)]
     pub fn set_header_format(&self, format: &Format) -> TableColumn {
+        let column = self.clone();
         TableColumn {
-            inner: self.clone().inner.set_header_format(format.lock().clone()),
+            inner: column.inner.set_header_format(format.to_format()),
+            ..column
         }
     }
 
@@ -182,8 +461,10 @@ impl TableColumn {
     /// @returns {TableColumn} - The TableColumn object.
     #[wasm_bindgen(js_name = "setFormat", skip_jsdoc)]
     pub fn set_format(&self, format: &Format) -> TableColumn {
+        let column = self.clone();
         TableColumn {
-            inner: self.clone().inner.set_format(format.lock().clone()),
+            inner: column.inner.set_format(format.to_format()),
+            ..column
         }
     }
 
@@ -203,8 +484,10 @@ impl TableColumn {
     /// @returns {TableColumn} - The TableColumn object.
     #[wasm_bindgen(js_name = "setFormula", skip_jsdoc)]
     pub fn set_formula(&self, formula: &Formula) -> TableColumn {
+        let column = self.clone();
         TableColumn {
-            inner: self.clone().inner.set_formula(&*formula.lock()),
+            inner: column.inner.set_formula(&*formula.lock()),
+            ..column
         }
     }
 
@@ -222,8 +505,11 @@ impl TableColumn {
     /// @returns {TableColumn} - The TableColumn object.
     #[wasm_bindgen(js_name = "setTotalLabel", skip_jsdoc)]
     pub fn set_total_label(&self, label: &str) -> TableColumn {
+        let column = self.clone();
         TableColumn {
-            inner: self.clone().inner.set_total_label(label),
+            inner: column.inner.set_total_label(label),
+            total_label: Some(label.to_string()),
+            ..column
         }
     }
 
@@ -258,6 +544,50 @@ impl TableColumn {
                 .clone()
                 .inner
                 .set_total_function(table_function.inner.clone()),
+            total_function: Some(table_function.inner.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Set the format for the total row of a table column.
+    ///
+    /// By default the total row cell of a column with a total function uses
+    /// the column format set with {@link TableColumn#setFormat}. This
+    /// method sets a separate format, for example a bold font or a different
+    /// number format, for the total label or function.
+    ///
+    /// A total function in a column without a header set with
+    /// {@link TableColumn#setHeader} reads the header from the header row
+    /// when it is added with {@link Worksheet#addTable}.
+    ///
+    /// @param {Format} format - The {@link Format} property for the total
+    ///   row cell.
+    /// @returns {TableColumn} - The TableColumn object.
+    #[wasm_bindgen(js_name = "setTotalFormat", skip_jsdoc)]
+    pub fn set_total_format(&self, format: &Format) -> TableColumn {
+        TableColumn {
+            total_format: Some(format.to_format()),
+            ..self.clone()
+        }
+    }
+
+    /// Set a filter for the column.
+    ///
+    /// The filter is shown in the autofilter of the table header and the
+    /// rows that don't match it are hidden. Excel doesn't filter the rows
+    /// when the file is opened, so the rows are hidden based on the data
+    /// written to the worksheet before the table is added with
+    /// {@link Worksheet#addTable} or {@link Worksheet#writeTable}.
+    ///
+    /// The table needs a header row and autofilter for column filters.
+    ///
+    /// @param {TableFilter} filter - The filter for the column.
+    /// @returns {TableColumn} - The TableColumn object.
+    #[wasm_bindgen(js_name = "setFilter", skip_jsdoc)]
+    pub fn set_filter(&self, filter: &TableFilter) -> TableColumn {
+        TableColumn {
+            filter: Some(filter.clone()),
+            ..self.clone()
         }
     }
 }
@@ -274,6 +604,7 @@ impl TableColumn {
 /// style in Excel is equivalent to {@link TableStyle#Medium9}.
 ///
 /// TODO: example omitted
+#[derive(Debug, Clone, Copy)]
 #[wasm_bindgen]
 pub enum TableStyle {
     /// No table style.
@@ -400,6 +731,73 @@ pub enum TableStyle {
     Dark11,
 }
 
+impl TableStyle {
+    /// The styles in the order of their values.
+    const ALL: [TableStyle; 61] = [
+        TableStyle::None,
+        TableStyle::Light1,
+        TableStyle::Light2,
+        TableStyle::Light3,
+        TableStyle::Light4,
+        TableStyle::Light5,
+        TableStyle::Light6,
+        TableStyle::Light7,
+        TableStyle::Light8,
+        TableStyle::Light9,
+        TableStyle::Light10,
+        TableStyle::Light11,
+        TableStyle::Light12,
+        TableStyle::Light13,
+        TableStyle::Light14,
+        TableStyle::Light15,
+        TableStyle::Light16,
+        TableStyle::Light17,
+        TableStyle::Light18,
+        TableStyle::Light19,
+        TableStyle::Light20,
+        TableStyle::Light21,
+        TableStyle::Medium1,
+        TableStyle::Medium2,
+        TableStyle::Medium3,
+        TableStyle::Medium4,
+        TableStyle::Medium5,
+        TableStyle::Medium6,
+        TableStyle::Medium7,
+        TableStyle::Medium8,
+        TableStyle::Medium9,
+        TableStyle::Medium10,
+        TableStyle::Medium11,
+        TableStyle::Medium12,
+        TableStyle::Medium13,
+        TableStyle::Medium14,
+        TableStyle::Medium15,
+        TableStyle::Medium16,
+        TableStyle::Medium17,
+        TableStyle::Medium18,
+        TableStyle::Medium19,
+        TableStyle::Medium20,
+        TableStyle::Medium21,
+        TableStyle::Medium22,
+        TableStyle::Medium23,
+        TableStyle::Medium24,
+        TableStyle::Medium25,
+        TableStyle::Medium26,
+        TableStyle::Medium27,
+        TableStyle::Medium28,
+        TableStyle::Dark1,
+        TableStyle::Dark2,
+        TableStyle::Dark3,
+        TableStyle::Dark4,
+        TableStyle::Dark5,
+        TableStyle::Dark6,
+        TableStyle::Dark7,
+        TableStyle::Dark8,
+        TableStyle::Dark9,
+        TableStyle::Dark10,
+        TableStyle::Dark11,
+    ];
+}
+
 impl From<TableStyle> for xlsx::TableStyle {
     fn from(value: TableStyle) -> Self {
         match value {
@@ -554,3 +952,342 @@ impl TableFunction {
         }
     }
 }
+
+/// The `TableFilterOperator` enum defines the comparison of a custom
+/// {@link TableFilter}.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum TableFilterOperator {
+    /// Show values equal to the criterion.
+    EqualTo,
+    /// Show values that aren't equal to the criterion.
+    NotEqualTo,
+    /// Show values greater than the criterion.
+    GreaterThan,
+    /// Show values greater than or equal to the criterion.
+    GreaterThanOrEqualTo,
+    /// Show values less than the criterion.
+    LessThan,
+    /// Show values less than or equal to the criterion.
+    LessThanOrEqualTo,
+}
+
+impl TableFilterOperator {
+    fn xml_name(self) -> Option<&'static str> {
+        match self {
+            TableFilterOperator::EqualTo => None,
+            TableFilterOperator::NotEqualTo => Some("notEqual"),
+            TableFilterOperator::GreaterThan => Some("greaterThan"),
+            TableFilterOperator::GreaterThanOrEqualTo => Some("greaterThanOrEqual"),
+            TableFilterOperator::LessThan => Some("lessThan"),
+            TableFilterOperator::LessThanOrEqualTo => Some("lessThanOrEqual"),
+        }
+    }
+
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            TableFilterOperator::EqualTo => ordering == Ordering::Equal,
+            TableFilterOperator::NotEqualTo => ordering != Ordering::Equal,
+            TableFilterOperator::GreaterThan => ordering == Ordering::Greater,
+            TableFilterOperator::GreaterThanOrEqualTo => ordering != Ordering::Less,
+            TableFilterOperator::LessThan => ordering == Ordering::Less,
+            TableFilterOperator::LessThanOrEqualTo => ordering != Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum FilterKind {
+    List(Vec<String>),
+    Custom {
+        conditions: Vec<(TableFilterOperator, String)>,
+        and: bool,
+    },
+}
+
+/// The `TableFilter` struct represents a filter for a table column.
+///
+/// A filter either shows a list of values, like the checkboxes of the Excel
+/// autofilter dropdown, or the values that match one or two custom
+/// conditions. It is set on a column with {@link TableColumn#setFilter}.
+///
+/// Values are compared as numbers if both the cell and the criterion are
+/// numbers and as case insensitive text otherwise. Numbers are compared as
+/// text in the General format.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct TableFilter {
+    kind: FilterKind,
+}
+
+#[wasm_bindgen]
+impl TableFilter {
+    /// Create a filter that shows a list of values.
+    ///
+    /// @param {string[]} values - The values to show. An empty string shows
+    ///   the blank cells.
+    /// @returns {TableFilter} - The TableFilter object.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn list(values: Vec<String>) -> TableFilter {
+        TableFilter {
+            kind: FilterKind::List(values),
+        }
+    }
+
+    /// Create a filter that shows the values that match a condition.
+    ///
+    /// @param {TableFilterOperator} operator - The comparison.
+    /// @param {string} value - The criterion.
+    /// @returns {TableFilter} - The TableFilter object.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn custom(operator: TableFilterOperator, value: &str) -> TableFilter {
+        TableFilter {
+            kind: FilterKind::Custom {
+                conditions: vec![(operator, value.to_string())],
+                and: false,
+            },
+        }
+    }
+
+    /// Add a second condition that must also match.
+    ///
+    /// @param {TableFilterOperator} operator - The comparison.
+    /// @param {string} value - The criterion.
+    /// @returns {TableFilter} - The TableFilter object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The filter isn't a custom
+    ///   filter or already has two conditions.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn and(&self, operator: TableFilterOperator, value: &str) -> WasmResult<TableFilter> {
+        self.add_condition(operator, value, true)
+    }
+
+    /// Add a second condition, either of which must match.
+    ///
+    /// @param {TableFilterOperator} operator - The comparison.
+    /// @param {string} value - The criterion.
+    /// @returns {TableFilter} - The TableFilter object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The filter isn't a custom
+    ///   filter or already has two conditions.
+    #[wasm_bindgen(skip_jsdoc)]
+    pub fn or(&self, operator: TableFilterOperator, value: &str) -> WasmResult<TableFilter> {
+        self.add_condition(operator, value, false)
+    }
+}
+
+impl TableFilter {
+    fn add_condition(&self, operator: TableFilterOperator, value: &str, and: bool) -> WasmResult<TableFilter> {
+        match &self.kind {
+            FilterKind::Custom { conditions, .. } if conditions.len() == 1 => {
+                let mut conditions = conditions.clone();
+                conditions.push((operator, value.to_string()));
+                Ok(TableFilter {
+                    kind: FilterKind::Custom { conditions, and },
+                })
+            }
+            _ => Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "Only a custom filter with one condition can have another condition".to_string(),
+            ))),
+        }
+    }
+
+    /// Check if a cell value is shown by the filter.
+    pub(crate) fn matches(&self, value: Option<&CellValue>) -> bool {
+        let number = match value {
+            Some(CellValue::Number(n)) | Some(CellValue::DateTime(n)) => Some(*n),
+            _ => None,
+        };
+        let text = match value {
+            None | Some(CellValue::Blank) | Some(CellValue::Formula(..)) | Some(CellValue::Image) => String::new(),
+            Some(CellValue::String(s)) => s.clone(),
+            Some(CellValue::Number(n)) | Some(CellValue::DateTime(n)) => number_format::general(*n),
+            Some(CellValue::Bool(b)) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            Some(CellValue::Url(url)) => url.cell_text(),
+            Some(CellValue::RichString(parts)) => parts.iter().map(|(_, text)| text.as_str()).collect(),
+        }
+        .to_lowercase();
+
+        let compare = |criterion: &str| -> Option<Ordering> {
+            match (number, criterion.trim().parse::<f64>()) {
+                (Some(number), Ok(criterion)) => number.partial_cmp(&criterion),
+                // Numeric criteria only match numbers.
+                (None, Ok(_)) => None,
+                _ => Some(text.as_str().cmp(criterion.to_lowercase().as_str())),
+            }
+        };
+        match &self.kind {
+            FilterKind::List(values) => values.iter().any(|value| compare(value) == Some(Ordering::Equal)),
+            FilterKind::Custom { conditions, and } => {
+                let mut results = conditions.iter().map(|(operator, criterion)| match compare(criterion) {
+                    Some(ordering) => operator.accepts(ordering),
+                    None => *operator == TableFilterOperator::NotEqualTo,
+                });
+                if *and {
+                    results.all(|result| result)
+                } else {
+                    results.any(|result| result)
+                }
+            }
+        }
+    }
+
+    /// Get the `filterColumn` element of the filter.
+    fn xml(&self, col_id: xlsx::ColNum) -> String {
+        let filter = match &self.kind {
+            FilterKind::List(values) => {
+                let blank = if values.iter().any(String::is_empty) { " blank=\"1\"" } else { "" };
+                let values: String = values
+                    .iter()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| format!("<filter val=\"{}\"/>", package::escape_xml(value)))
+                    .collect();
+                format!("<filters{blank}>{values}</filters>")
+            }
+            FilterKind::Custom { conditions, and } => {
+                let and = if *and { " and=\"1\"" } else { "" };
+                let conditions: String = conditions
+                    .iter()
+                    .map(|(operator, value)| {
+                        let operator = operator
+                            .xml_name()
+                            .map(|name| format!(" operator=\"{name}\""))
+                            .unwrap_or_default();
+                        format!("<customFilter{operator} val=\"{}\"/>", package::escape_xml(value))
+                    })
+                    .collect();
+                format!("<customFilters{and}>{conditions}</customFilters>")
+            }
+        };
+        format!("<filterColumn colId=\"{col_id}\">{filter}</filterColumn>")
+    }
+}
+
+/// The properties of a table added to a worksheet that `rust_xlsxwriter`
+/// doesn't support, which are added to the table part when the workbook is
+/// saved.
+#[derive(Clone, Debug)]
+pub(crate) struct PlacedTable {
+    pub(crate) range: CellRange,
+    /// The filters, by the offset of the column in the table.
+    pub(crate) filters: Vec<(xlsx::ColNum, TableFilter)>,
+    /// The name of the custom table style.
+    pub(crate) style: Option<String>,
+}
+
+/// A table in a package saved by `rust_xlsxwriter`.
+pub(crate) struct PackageTable {
+    pub(crate) part: String,
+    pub(crate) name: String,
+    pub(crate) sheet_index: usize,
+    /// The range of the table, including the header and total rows.
+    pub(crate) range: CellRange,
+    pub(crate) header_row: bool,
+    pub(crate) total_row: bool,
+}
+
+/// Find the tables in a package, and the worksheets they belong to.
+pub(crate) fn package_tables(package: &Package, sheet_count: usize) -> Vec<PackageTable> {
+    let mut tables = vec![];
+    for sheet_index in 0..sheet_count {
+        let rels_name = format!("xl/worksheets/_rels/sheet{}.xml.rels", sheet_index + 1);
+        let Some(rels) = package.part(&rels_name) else {
+            continue;
+        };
+        let rels = String::from_utf8_lossy(rels);
+        for target in rels.split("Target=\"../tables/").skip(1) {
+            let Some(file) = target.split('"').next() else {
+                continue;
+            };
+            let part = format!("xl/tables/{file}");
+            let Some(xml) = package.part(&part) else {
+                continue;
+            };
+            let xml = String::from_utf8_lossy(xml);
+            let (Some(name), Some(range)) = (
+                package::xml_attribute(&xml, "table", "displayName"),
+                package::xml_attribute(&xml, "table", "ref").and_then(parse_range),
+            ) else {
+                continue;
+            };
+            tables.push(PackageTable {
+                part: part.clone(),
+                name: name.to_string(),
+                sheet_index,
+                range,
+                header_row: package::xml_attribute(&xml, "table", "headerRowCount") != Some("0"),
+                total_row: package::xml_attribute(&xml, "table", "totalsRowCount").is_some_and(|count| count != "0"),
+            });
+        }
+    }
+    tables
+}
+
+/// Parse an `A1:B2` range.
+fn parse_range(reference: &str) -> Option<CellRange> {
+    let (first, last) = reference.split_once(':')?;
+    let cell = |cell: &str| -> Option<(xlsx::RowNum, xlsx::ColNum)> {
+        let split = cell.find(|c: char| c.is_ascii_digit())?;
+        let (letters, digits) = cell.split_at(split);
+        let col = letters.chars().try_fold(0u32, |col, c| {
+            c.is_ascii_uppercase().then(|| col * 26 + u32::from(c as u8 - b'A' + 1))
+        })?;
+        let row = digits.parse::<u32>().ok()?;
+        Some((row.checked_sub(1)?, u16::try_from(col.checked_sub(1)?).ok()?))
+    };
+    let (first_row, first_col) = cell(first)?;
+    let (last_row, last_col) = cell(last)?;
+    Some(CellRange::new(first_row, first_col, last_row, last_col))
+}
+
+/// Add the column filters and custom styles of the tables to a package
+/// saved by `rust_xlsxwriter`.
+pub(crate) fn update_package(settings: &WorkbookSettings, package: &mut Package) -> WasmResult<()> {
+    let tables = package_tables(package, settings.sheet_ids.len());
+    for (sheet_index, id) in settings.sheet_ids.iter().enumerate() {
+        let Some(sheet) = settings.sheets.get(id) else {
+            continue;
+        };
+        for placed_table in &sheet.tables {
+            // Style names are case insensitive in Excel, and the table uses
+            // the name of the style as it was added to the workbook.
+            let style = match &placed_table.style {
+                Some(style) => {
+                    let Some(custom) = settings.table_styles.iter().find(|custom| custom.name.eq_ignore_ascii_case(style))
+                    else {
+                        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                            "Table style '{style}' isn't added to the workbook"
+                        ))));
+                    };
+                    Some(&custom.name)
+                }
+                None => None,
+            };
+            let range = placed_table.range;
+            let Some(table) = tables.iter().find(|table| {
+                table.sheet_index == sheet_index
+                    && (table.range.first_row(), table.range.first_col()) == (range.first_row(), range.first_col())
+                    && (table.range.last_row(), table.range.last_col()) == (range.last_row(), range.last_col())
+            }) else {
+                continue;
+            };
+            let filters: String = placed_table.filters.iter().map(|(col, filter)| filter.xml(*col)).collect();
+            package.edit_xml(&table.part, |xml| {
+                if !filters.is_empty() {
+                    package::append_to_element(xml, "autoFilter", &filters);
+                }
+                if let Some(style) = style {
+                    package::set_xml_attribute(xml, "tableStyleInfo", "name", Some(&package::escape_xml(style)));
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    format::Format,
+    format_properties::FormatProperties,
+    package::{self, Package},
+    WasmResult,
+};
+
+/// The parts of a table that a custom table style can format, in the order
+/// that Excel writes them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TableStyleElement {
+    WholeTable,
+    HeaderRow,
+    TotalRow,
+    FirstColumn,
+    LastColumn,
+    FirstRowStripe,
+    SecondRowStripe,
+    FirstColumnStripe,
+    SecondColumnStripe,
+}
+
+impl TableStyleElement {
+    fn xml_type(self) -> &'static str {
+        match self {
+            TableStyleElement::WholeTable => "wholeTable",
+            TableStyleElement::HeaderRow => "headerRow",
+            TableStyleElement::TotalRow => "totalRow",
+            TableStyleElement::FirstColumn => "firstColumn",
+            TableStyleElement::LastColumn => "lastColumn",
+            TableStyleElement::FirstRowStripe => "firstRowStripe",
+            TableStyleElement::SecondRowStripe => "secondRowStripe",
+            TableStyleElement::FirstColumnStripe => "firstColumnStripe",
+            TableStyleElement::SecondColumnStripe => "secondColumnStripe",
+        }
+    }
+}
+
+/// The `CustomTableStyle` struct represents a user defined table style.
+///
+/// Excel has a fixed set of built-in table styles, see {@link TableStyle}.
+/// A custom table style defines the formatting of the parts of a table, such
+/// as the header row or the banded rows, with the font, fill and border
+/// properties of a {@link Format}. Other format properties, such as the
+/// number format or alignment, are ignored since Excel doesn't support them
+/// in table styles.
+///
+/// A custom style has to be registered with {@link Workbook#addTableStyle}
+/// and can then be used by tables with {@link Table#setStyle}.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub struct CustomTableStyle {
+    pub(crate) name: String,
+    elements: Vec<(TableStyleElement, FormatProperties)>,
+}
+
+#[wasm_bindgen]
+impl CustomTableStyle {
+    /// Create a new custom table style.
+    ///
+    /// @param {string} name - The name of the style, as shown in the Excel
+    ///   table style gallery.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The name is empty, longer than
+    ///   255 characters or the name of a built-in table style.
+    #[wasm_bindgen(constructor, skip_jsdoc)]
+    pub fn new(name: &str) -> WasmResult<CustomTableStyle> {
+        if name.is_empty() || name.chars().count() > 255 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Table style name '{name}' must be 1 to 255 characters long"
+            ))));
+        }
+        if is_builtin_name(name) {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Table style name '{name}' is the name of a built-in table style"
+            ))));
+        }
+        Ok(CustomTableStyle {
+            name: name.to_string(),
+            elements: vec![],
+        })
+    }
+
+    /// The name of the style.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Set the format of the whole table.
+    ///
+    /// The formats of the other parts of the table are applied on top of
+    /// this format.
+    ///
+    /// @param {Format} format - The {@link Format} of the table.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setWholeTable", skip_jsdoc)]
+    pub fn set_whole_table(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::WholeTable, format)
+    }
+
+    /// Set the format of the header row.
+    ///
+    /// @param {Format} format - The {@link Format} of the header row.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setHeaderRow", skip_jsdoc)]
+    pub fn set_header_row(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::HeaderRow, format)
+    }
+
+    /// Set the format of the total row.
+    ///
+    /// @param {Format} format - The {@link Format} of the total row.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setTotalRow", skip_jsdoc)]
+    pub fn set_total_row(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::TotalRow, format)
+    }
+
+    /// Set the format of the first column.
+    ///
+    /// The format is only shown for tables that enable it with
+    /// {@link Table#setFirstColumn}.
+    ///
+    /// @param {Format} format - The {@link Format} of the first column.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setFirstColumn", skip_jsdoc)]
+    pub fn set_first_column(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::FirstColumn, format)
+    }
+
+    /// Set the format of the last column.
+    ///
+    /// The format is only shown for tables that enable it with
+    /// {@link Table#setLastColumn}.
+    ///
+    /// @param {Format} format - The {@link Format} of the last column.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setLastColumn", skip_jsdoc)]
+    pub fn set_last_column(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::LastColumn, format)
+    }
+
+    /// Set the format of the odd rows of a table with banded rows.
+    ///
+    /// @param {Format} format - The {@link Format} of the first row stripe.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setFirstRowStripe", skip_jsdoc)]
+    pub fn set_first_row_stripe(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::FirstRowStripe, format)
+    }
+
+    /// Set the format of the even rows of a table with banded rows.
+    ///
+    /// @param {Format} format - The {@link Format} of the second row stripe.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setSecondRowStripe", skip_jsdoc)]
+    pub fn set_second_row_stripe(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::SecondRowStripe, format)
+    }
+
+    /// Set the format of the odd columns of a table with banded columns.
+    ///
+    /// @param {Format} format - The {@link Format} of the first column
+    ///   stripe.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setFirstColumnStripe", skip_jsdoc)]
+    pub fn set_first_column_stripe(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::FirstColumnStripe, format)
+    }
+
+    /// Set the format of the even columns of a table with banded columns.
+    ///
+    /// @param {Format} format - The {@link Format} of the second column
+    ///   stripe.
+    /// @returns {CustomTableStyle} - The CustomTableStyle object.
+    #[wasm_bindgen(js_name = "setSecondColumnStripe", skip_jsdoc)]
+    pub fn set_second_column_stripe(&self, format: &Format) -> CustomTableStyle {
+        self.set_element(TableStyleElement::SecondColumnStripe, format)
+    }
+}
+
+impl CustomTableStyle {
+    fn set_element(&self, element: TableStyleElement, format: &Format) -> CustomTableStyle {
+        let mut elements: Vec<_> = self
+            .elements
+            .iter()
+            .filter(|(existing, _)| *existing != element)
+            .cloned()
+            .collect();
+        elements.push((element, format.lock().clone()));
+        elements.sort_by_key(|(element, _)| *element as u8);
+        CustomTableStyle {
+            elements,
+            ..self.clone()
+        }
+    }
+}
+
+/// Check if a name is the name of a built-in table or pivot table style.
+fn is_builtin_name(name: &str) -> bool {
+    ["TableStyleLight", "TableStyleMedium", "TableStyleDark", "PivotStyleLight", "PivotStyleMedium", "PivotStyleDark"]
+        .iter()
+        .any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+/// Add the custom table styles, and their differential formats, to the
+/// styles part of a package saved by `rust_xlsxwriter`.
+pub(crate) fn update_package(styles: &[CustomTableStyle], package: &mut Package) -> WasmResult<()> {
+    package.edit_xml("xl/styles.xml", |xml| {
+        let mut dxf_count: usize = package::xml_attribute(xml, "dxfs", "count")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+        let mut dxfs = String::new();
+        let mut table_styles = String::new();
+        for style in styles {
+            let mut elements = String::new();
+            for (element, format) in &style.elements {
+                dxfs.push_str(&format.dxf_xml());
+                elements.push_str(&format!(
+                    "<tableStyleElement type=\"{}\" dxfId=\"{dxf_count}\"/>",
+                    element.xml_type()
+                ));
+                dxf_count += 1;
+            }
+            table_styles.push_str(&format!(
+                "<tableStyle name=\"{}\" pivot=\"0\" count=\"{}\">{elements}</tableStyle>",
+                package::escape_xml(&style.name),
+                style.elements.len()
+            ));
+        }
+
+        let table_style_count: usize = package::xml_attribute(xml, "tableStyles", "count")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0)
+            + styles.len();
+        if !package::append_to_element(xml, "dxfs", &dxfs) || !package::append_to_element(xml, "tableStyles", &table_styles) {
+            return Err(XlsxError::Internal(
+                "Missing dxfs or tableStyles element in xl/styles.xml".to_string(),
+            ));
+        }
+        package::set_xml_attribute(xml, "dxfs", "count", Some(&dxf_count.to_string()));
+        package::set_xml_attribute(xml, "tableStyles", "count", Some(&table_style_count.to_string()));
+        Ok(())
+    })
+}
@@ -0,0 +1,670 @@
+//! Workbooks created from an existing xlsx file.
+//!
+//! `rust_xlsxwriter` can only create new files, so a template workbook is
+//! given an empty worksheet, with the same name, for each worksheet of the
+//! template and the data written to them is saved by `rust_xlsxwriter` as
+//! usual. The rows of the saved worksheets are then merged into the
+//! worksheets of the template, with their formats added to the template
+//! styles, and all of the other parts of the template are kept as they are.
+
+use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+
+use rust_xlsxwriter as xlsx;
+
+use crate::error::XlsxError;
+
+use super::{
+    package::{self, Package},
+    workbook_settings::WorkbookSettings,
+    WasmResult,
+};
+
+const WORKSHEET_RELATIONSHIP: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+
+/// The parts of a package saved by `rust_xlsxwriter` that are replaced by
+/// the template. Any other part, apart from the worksheets, is for a feature
+/// that can't be added to a template.
+const SAVED_PARTS: [&str; 9] = [
+    "[Content_Types].xml",
+    "_rels/.rels",
+    "docProps/app.xml",
+    "docProps/core.xml",
+    "xl/workbook.xml",
+    "xl/_rels/workbook.xml.rels",
+    "xl/styles.xml",
+    "xl/theme/theme1.xml",
+    "xl/sharedStrings.xml",
+];
+
+/// The row attributes that are copied from the saved worksheets.
+const ROW_ATTRIBUTES: [&str; 5] = ["ht", "customHeight", "hidden", "s", "customFormat"];
+
+/// An xlsx file used as the template of a workbook.
+#[derive(Debug)]
+pub(crate) struct Template {
+    /// The xlsx file, which is read again each time the workbook is saved.
+    data: Vec<u8>,
+    /// The worksheets of the template, in workbook order.
+    pub(crate) sheets: Vec<TemplateSheet>,
+    /// Whether the template uses the 1904 date epoch.
+    pub(crate) date_1904: bool,
+}
+
+/// A worksheet of a template.
+#[derive(Debug)]
+pub(crate) struct TemplateSheet {
+    pub(crate) name: String,
+    /// The name of the worksheet part in the template package.
+    part: String,
+}
+
+impl Template {
+    /// Read the worksheets of an xlsx file.
+    pub(crate) fn from_buffer(data: &[u8]) -> WasmResult<Template> {
+        let package =
+            Package::from_buffer(data).map_err(|_| parameter_error("The template isn't an xlsx file".to_string()))?;
+        let workbook = xml_part(&package, "xl/workbook.xml")?;
+        let relationships = xml_part(&package, "xl/_rels/workbook.xml.rels")?;
+        let relationships = package::child_elements(&relationships, "Relationships", "Relationship");
+
+        let mut sheets = vec![];
+        for sheet in package::child_elements(&workbook, "sheets", "sheet") {
+            let name = package::xml_attribute(sheet, "sheet", "name").map(package::unescape_xml);
+            let id = package::xml_attribute(sheet, "sheet", "r:id");
+            let relationship = relationships
+                .iter()
+                .find(|relationship| package::xml_attribute(relationship, "Relationship", "Id") == id);
+            let (Some(name), Some(relationship)) = (name, relationship) else {
+                return Err(parameter_error("The template isn't an xlsx file".to_string()));
+            };
+            if package::xml_attribute(relationship, "Relationship", "Type") != Some(WORKSHEET_RELATIONSHIP) {
+                return Err(parameter_error(format!(
+                    "The template sheet '{name}' isn't a worksheet, only worksheets are supported"
+                )));
+            }
+            let target = package::xml_attribute(relationship, "Relationship", "Target").unwrap_or_default();
+            let part = match target.strip_prefix('/') {
+                Some(part) => part.to_string(),
+                None => format!("xl/{target}"),
+            };
+            if package.part(&part).is_none() {
+                return Err(parameter_error("The template isn't an xlsx file".to_string()));
+            }
+            sheets.push(TemplateSheet { name, part });
+        }
+        if sheets.is_empty() {
+            return Err(parameter_error("The template doesn't have any worksheets".to_string()));
+        }
+
+        let date_1904 = matches!(package::xml_attribute(&workbook, "workbookPr", "date1904"), Some("1" | "true"));
+        Ok(Template {
+            data: data.to_vec(),
+            sheets,
+            date_1904,
+        })
+    }
+
+    /// Merge the cells of a package saved by `rust_xlsxwriter` into the
+    /// template. The document properties of the saved package replace those
+    /// of the template if `properties` is set.
+    pub(crate) fn fill(&self, saved: &Package, settings: &WorkbookSettings, properties: bool) -> WasmResult<Package> {
+        check_settings(settings)?;
+        check_parts(saved)?;
+        check_workbook(&xml_part(saved, "xl/workbook.xml")?, &self.sheets)?;
+
+        let mut package = Package::from_buffer(&self.data)?;
+        let shared_strings_xml = match saved.part("xl/sharedStrings.xml") {
+            Some(_) => xml_part(saved, "xl/sharedStrings.xml")?,
+            None => String::new(),
+        };
+        let strings = shared_strings(&shared_strings_xml);
+        let saved_styles = xml_part(saved, "xl/styles.xml")?;
+        let mut styles = StyleMerger::new(&saved_styles, xml_part(&package, "xl/styles.xml")?);
+
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            let xml = xml_part(saved, &format!("xl/worksheets/sheet{}.xml", index + 1))?;
+            check_worksheet(&xml, &sheet.name)?;
+            let mut rows = parse_rows(&xml, &sheet.name)?;
+            if rows.is_empty() {
+                continue;
+            }
+            for row in &mut rows {
+                if let Some(style) = package::xml_attribute(&row.start_tag, "row", "s") {
+                    let style = styles.xf_index(style)?;
+                    package::set_xml_attribute(&mut row.start_tag, "row", "s", Some(&style));
+                }
+                for cell in row.cells.values_mut() {
+                    *cell = convert_cell(cell, &strings, &mut styles)?;
+                }
+            }
+            package.edit_xml(&sheet.part, |xml| merge_rows(xml, rows, &sheet.name))?;
+        }
+        if let Some(styles) = styles.finish() {
+            package.set_part("xl/styles.xml", styles.into_bytes());
+        }
+
+        package.edit_xml("xl/workbook.xml", |xml| {
+            // The written values can change the results of the template
+            // formulas, so they are recalculated when the file is opened.
+            if !package::set_xml_attribute(xml, "calcPr", "fullCalcOnLoad", Some("1")) {
+                let calc = r#"<calcPr fullCalcOnLoad="1"/>"#;
+                let _ = package::insert_after_element(xml, "definedNames", calc)
+                    || package::insert_after_element(xml, "externalReferences", calc)
+                    || package::insert_after_element(xml, "sheets", calc);
+            }
+            settings.update_workbook_xml(xml);
+            Ok(())
+        })?;
+        remove_calc_chain(&mut package)?;
+
+        if properties {
+            if let Some(core) = saved.part("docProps/core.xml") {
+                package.set_part("docProps/core.xml", core.to_vec());
+            }
+        }
+        Ok(package)
+    }
+}
+
+/// A row of a worksheet, with its cells by one based column number.
+struct Row {
+    number: u32,
+    /// The start tag of the row, which is never self-closing.
+    start_tag: String,
+    cells: BTreeMap<u32, String>,
+}
+
+/// Check that only the features supported by templates are used by the
+/// wrapper.
+fn check_settings(settings: &WorkbookSettings) -> WasmResult<()> {
+    let sheets = || settings.sheets.values();
+    let feature = if settings.vba_project.is_some() {
+        "a VBA project"
+    } else if settings.theme.is_some() {
+        "a theme"
+    } else if !settings.table_styles.is_empty() {
+        "table styles"
+    } else if !settings.cell_styles.is_empty() || sheets().any(|sheet| !sheet.styled_formats.is_empty()) {
+        "cell styles"
+    } else if !settings.defined_names.is_empty() {
+        "defined names"
+    } else if sheets().any(|sheet| !sheet.table_ranges.is_empty()) {
+        "tables"
+    } else if sheets().any(|sheet| !sheet.pivot_tables.is_empty()) {
+        "pivot tables"
+    } else if sheets().any(|sheet| !sheet.svg_images.is_empty()) {
+        "images"
+    } else {
+        return Ok(());
+    };
+    Err(unsupported(format!("{feature} can't be added")))
+}
+
+/// Check that the saved package only has the parts that are replaced by the
+/// template and the worksheets.
+fn check_parts(saved: &Package) -> WasmResult<()> {
+    let is_worksheet = |name: &str| {
+        name.strip_prefix("xl/worksheets/sheet")
+            .and_then(|name| name.strip_suffix(".xml"))
+            .is_some_and(|number| number.parse::<u32>().is_ok())
+    };
+    match saved
+        .part_names(|name| !SAVED_PARTS.contains(&name) && !is_worksheet(name))
+        .first()
+    {
+        Some(name) => Err(unsupported(format!("the '{name}' part can't be added"))),
+        None => Ok(()),
+    }
+}
+
+/// Check that the worksheets and workbook settings of the saved package
+/// match the template.
+fn check_workbook(xml: &str, sheets: &[TemplateSheet]) -> WasmResult<()> {
+    let names: Vec<_> = package::child_elements(xml, "sheets", "sheet")
+        .into_iter()
+        .filter_map(|sheet| package::xml_attribute(sheet, "sheet", "name").map(package::unescape_xml))
+        .collect();
+    if !names.iter().eq(sheets.iter().map(|sheet| &sheet.name)) {
+        return Err(unsupported("worksheets can't be added, removed, moved or renamed".to_string()));
+    }
+    let settings = [
+        ("<definedNames", "defined names"),
+        (" activeTab=", "the active worksheet"),
+        (" firstSheet=", "the first visible worksheet"),
+        (" state=", "hidden worksheets"),
+        ("<fileSharing", "the read-only recommendation"),
+        (" codeName=", "VBA names"),
+    ];
+    match settings.iter().find(|(marker, _)| xml.contains(marker)) {
+        Some((_, feature)) => Err(unsupported(format!("{feature} can't be set"))),
+        None => Ok(()),
+    }
+}
+
+/// Check that a saved worksheet only has cell data, and the settings that
+/// `rust_xlsxwriter` writes for every worksheet.
+fn check_worksheet(xml: &str, name: &str) -> WasmResult<()> {
+    let Some(start) = xml.find("<worksheet").and_then(|start| Some(start + xml[start..].find('>')? + 1)) else {
+        return Err(XlsxError::Internal(format!("Missing worksheet element: {name}")));
+    };
+    let end = xml.rfind("</worksheet>").unwrap_or(xml.len());
+    let mut content = xml[start..end].trim_start();
+    while !content.is_empty() {
+        let tag_end = content[1..]
+            .find([' ', '>', '/'])
+            .map_or(content.len(), |end| end + 1);
+        let tag = &content[1..tag_end];
+        let start_tag_end = content.find('>').map_or(content.len(), |end| end + 1);
+        let element_end = if content[..start_tag_end].ends_with("/>") {
+            start_tag_end
+        } else {
+            let end_tag = format!("</{tag}>");
+            content.find(&end_tag).map_or(content.len(), |end| end + end_tag.len())
+        };
+        let element = &content[..element_end];
+        let default = match tag {
+            "dimension" | "sheetData" => true,
+            "sheetViews" => {
+                element.replace(r#" tabSelected="1""#, "") == r#"<sheetViews><sheetView workbookViewId="0"/></sheetViews>"#
+            }
+            "sheetFormatPr" => element == r#"<sheetFormatPr defaultRowHeight="15"/>"#,
+            "pageMargins" => {
+                element == r#"<pageMargins left="0.7" right="0.7" top="0.75" bottom="0.75" header="0.3" footer="0.3"/>"#
+            }
+            _ => false,
+        };
+        if !default {
+            return Err(unsupported(format!(
+                "the '{tag}' settings of the worksheet '{name}' can't be changed"
+            )));
+        }
+        content = content[element_end..].trim_start();
+    }
+    Ok(())
+}
+
+/// Read the rows of a worksheet.
+fn parse_rows(xml: &str, name: &str) -> WasmResult<Vec<Row>> {
+    let invalid = || parameter_error(format!("The template worksheet '{name}' has rows or cells without a reference"));
+    let mut rows = vec![];
+    for row in package::child_elements(xml, "sheetData", "row") {
+        let start_tag = &row[..row.find('>').map_or(row.len(), |end| end + 1)];
+        let start_tag = match start_tag.strip_suffix("/>") {
+            Some(start_tag) => format!("{}>", start_tag.trim_end()),
+            None => start_tag.to_string(),
+        };
+        let number = package::xml_attribute(&start_tag, "row", "r")
+            .and_then(|number| number.parse().ok())
+            .ok_or_else(invalid)?;
+        let mut cells = BTreeMap::new();
+        for cell in package::child_elements(row, "row", "c") {
+            let col = package::xml_attribute(cell, "c", "r")
+                .and_then(column_number)
+                .ok_or_else(invalid)?;
+            cells.insert(col, cell.to_string());
+        }
+        rows.push(Row {
+            number,
+            start_tag,
+            cells,
+        });
+    }
+    Ok(rows)
+}
+
+/// Get the one based column number of an `A1` style cell reference.
+fn column_number(cell: &str) -> Option<u32> {
+    let letters = cell.trim_end_matches(|c: char| c.is_ascii_digit());
+    if letters.is_empty() || letters.len() > 3 || !letters.bytes().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    Some(letters.bytes().fold(0, |col, c| col * 26 + u32::from(c - b'A') + 1))
+}
+
+/// Convert a cell saved by `rust_xlsxwriter` to a cell of the template, with
+/// the format added to the template styles and shared strings written as
+/// inline strings.
+fn convert_cell(cell: &str, strings: &[&str], styles: &mut StyleMerger) -> WasmResult<String> {
+    let mut cell = cell.to_string();
+    if let Some(style) = package::xml_attribute(&cell, "c", "s") {
+        let style = styles.xf_index(style)?;
+        package::set_xml_attribute(&mut cell, "c", "s", Some(&style));
+    }
+    if package::xml_attribute(&cell, "c", "t") == Some("s") {
+        let index = cell
+            .split_once("<v>")
+            .and_then(|(_, value)| value.split_once("</v>"))
+            .map(|(index, _)| index.to_string())
+            .unwrap_or_default();
+        let Some(string) = index.parse::<usize>().ok().and_then(|index| strings.get(index)) else {
+            return Err(XlsxError::Internal(format!("Missing shared string: {index}")));
+        };
+        package::set_xml_attribute(&mut cell, "c", "t", Some("inlineStr"));
+        cell = cell.replacen(&format!("<v>{index}</v>"), &format!("<is>{string}</is>"), 1);
+    }
+    Ok(cell)
+}
+
+/// Merge saved rows into the `sheetData` of a template worksheet. Saved
+/// cells replace the template cells, but keep their format if they don't
+/// have one.
+fn merge_rows(xml: &mut String, saved: Vec<Row>, name: &str) -> WasmResult<()> {
+    let mut rows: BTreeMap<u32, Row> = parse_rows(xml, name)?
+        .into_iter()
+        .map(|row| (row.number, row))
+        .collect();
+    for mut row in saved {
+        // The spans are an optional hint that may no longer be correct.
+        package::set_xml_attribute(&mut row.start_tag, "row", "spans", None);
+        let existing = match rows.entry(row.number) {
+            Entry::Vacant(entry) => {
+                entry.insert(row);
+                continue;
+            }
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        package::set_xml_attribute(&mut existing.start_tag, "row", "spans", None);
+        for attribute in ROW_ATTRIBUTES {
+            if let Some(value) = package::xml_attribute(&row.start_tag, "row", attribute) {
+                let value = value.to_string();
+                package::set_xml_attribute(&mut existing.start_tag, "row", attribute, Some(&value));
+            }
+        }
+        for (col, mut cell) in row.cells {
+            if let Some(template_cell) = existing.cells.get(&col) {
+                // The cells that use a shared formula depend on the cell
+                // that holds it.
+                if template_cell.contains(r#"t="shared""#) && template_cell.contains(" ref=\"") {
+                    let reference = package::xml_attribute(template_cell, "c", "r").unwrap_or_default();
+                    return Err(parameter_error(format!(
+                        "The cell {reference} of the template worksheet '{name}' holds a shared formula and can't be overwritten"
+                    )));
+                }
+                if let (None, Some(style)) = (
+                    package::xml_attribute(&cell, "c", "s"),
+                    package::xml_attribute(template_cell, "c", "s"),
+                ) {
+                    let style = style.to_string();
+                    package::set_xml_attribute(&mut cell, "c", "s", Some(&style));
+                }
+            }
+            existing.cells.insert(col, cell);
+        }
+    }
+
+    let mut data = String::new();
+    for row in rows.values() {
+        if row.cells.is_empty() {
+            data.push_str(&format!("{}/>", &row.start_tag[..row.start_tag.len() - 1]));
+        } else {
+            data.push_str(&row.start_tag);
+            data.extend(row.cells.values().map(String::as_str));
+            data.push_str("</row>");
+        }
+    }
+    if !package::set_element_text(xml, "sheetData", &data) && !package::append_to_element(xml, "sheetData", &data) {
+        return Err(parameter_error(format!(
+            "The template worksheet '{name}' doesn't have any cell data"
+        )));
+    }
+
+    let first_row = rows.values().find(|row| !row.cells.is_empty()).map(|row| row.number);
+    let last_row = rows.values().rev().find(|row| !row.cells.is_empty()).map(|row| row.number);
+    let first_col = rows.values().filter_map(|row| row.cells.keys().next()).min();
+    let last_col = rows.values().filter_map(|row| row.cells.keys().next_back()).max();
+    if let (Some(first_row), Some(last_row), Some(&first_col), Some(&last_col)) = (first_row, last_row, first_col, last_col)
+    {
+        let range = xlsx::utility::cell_range(first_row - 1, first_col as u16 - 1, last_row - 1, last_col as u16 - 1);
+        package::set_xml_attribute(xml, "dimension", "ref", Some(&range));
+    }
+    Ok(())
+}
+
+/// Get the content of the shared strings of a package saved by
+/// `rust_xlsxwriter`, which is also the content of an inline string.
+fn shared_strings(xml: &str) -> Vec<&str> {
+    package::child_elements(xml, "sst", "si")
+        .into_iter()
+        .map(|si| {
+            let start = si.find('>').map_or(si.len(), |end| end + 1);
+            si[start..].strip_suffix("</si>").unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Remove the calculation chain of the template, which Excel rebuilds when
+/// the formulas are recalculated and which may refer to cells that no longer
+/// hold a formula.
+fn remove_calc_chain(package: &mut Package) -> WasmResult<()> {
+    if package.part("xl/calcChain.xml").is_none() {
+        return Ok(());
+    }
+    package.remove_part("xl/calcChain.xml");
+    package.edit_xml("xl/_rels/workbook.xml.rels", |xml| {
+        remove_elements(xml, "Relationships", "Relationship", |relationship| {
+            package::xml_attribute(relationship, "Relationship", "Target")
+                .is_some_and(|target| target.ends_with("calcChain.xml"))
+        });
+        Ok(())
+    })?;
+    package.edit_xml("[Content_Types].xml", |xml| {
+        remove_elements(xml, "Types", "Override", |content_type| {
+            package::xml_attribute(content_type, "Override", "PartName") == Some("/xl/calcChain.xml")
+        });
+        Ok(())
+    })
+}
+
+/// Remove the `tag` children of the first `parent` element that match a
+/// predicate.
+fn remove_elements(xml: &mut String, parent: &str, tag: &str, matches: impl Fn(&str) -> bool) {
+    let elements: Vec<String> = package::child_elements(xml, parent, tag)
+        .into_iter()
+        .filter(|element| matches(element))
+        .map(str::to_string)
+        .collect();
+    for element in elements {
+        *xml = xml.replacen(&element, "", 1);
+    }
+}
+
+/// Style elements of one kind, such as fonts, added to the template styles.
+struct StyleElements<'a> {
+    /// The elements of the saved styles.
+    saved: Vec<&'a str>,
+    /// The elements of the template styles.
+    template: Vec<String>,
+    /// The number of default elements, which are the same in both styles.
+    defaults: usize,
+    added: Vec<String>,
+    /// The template index of each saved element that has been added.
+    indexes: HashMap<usize, usize>,
+}
+
+impl<'a> StyleElements<'a> {
+    fn new(saved: &'a str, template: &str, parent: &str, tag: &str, defaults: usize) -> Self {
+        let template: Vec<_> = package::child_elements(template, parent, tag)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        StyleElements {
+            saved: package::child_elements(saved, parent, tag),
+            defaults: defaults.min(template.len()),
+            template,
+            added: vec![],
+            indexes: HashMap::new(),
+        }
+    }
+
+    /// Get the template index of a saved element, adding it to the template
+    /// if the template doesn't have the same element.
+    fn index(&mut self, index: &str, convert: impl FnOnce(&str) -> WasmResult<String>) -> WasmResult<String> {
+        let missing = || XlsxError::Internal(format!("Missing style element: {index}"));
+        let index: usize = index.parse().map_err(|_| missing())?;
+        if index < self.defaults {
+            return Ok(index.to_string());
+        }
+        if let Some(template_index) = self.indexes.get(&index) {
+            return Ok(template_index.to_string());
+        }
+        let element = convert(self.saved.get(index).ok_or_else(missing)?)?;
+        let template_index = match self.template.iter().position(|template| *template == element) {
+            Some(template_index) => template_index,
+            None => {
+                self.added.push(element);
+                self.template.len() + self.added.len() - 1
+            }
+        };
+        self.indexes.insert(index, template_index);
+        Ok(template_index.to_string())
+    }
+
+    /// Append the added elements to the template styles.
+    fn append_to(&self, xml: &mut String, parent: &str) {
+        if self.added.is_empty() {
+            return;
+        }
+        package::append_to_element(xml, parent, &self.added.concat());
+        let count = (self.template.len() + self.added.len()).to_string();
+        package::set_xml_attribute(xml, parent, "count", Some(&count));
+    }
+}
+
+/// Adds the formats of a package saved by `rust_xlsxwriter` to the styles of
+/// the template.
+struct StyleMerger<'a> {
+    template: String,
+    fonts: StyleElements<'a>,
+    fills: StyleElements<'a>,
+    borders: StyleElements<'a>,
+    xfs: StyleElements<'a>,
+    /// The saved custom number formats, by id.
+    saved_num_formats: HashMap<&'a str, &'a str>,
+    /// The template custom number format ids, by format code.
+    num_format_ids: HashMap<String, u32>,
+    next_num_format_id: u32,
+    template_num_format_count: usize,
+    added_num_formats: Vec<String>,
+}
+
+impl<'a> StyleMerger<'a> {
+    fn new(saved: &'a str, template: String) -> Self {
+        let saved_num_formats = package::child_elements(saved, "numFmts", "numFmt")
+            .into_iter()
+            .filter_map(num_format)
+            .collect();
+        let template_num_formats = package::child_elements(&template, "numFmts", "numFmt");
+        let num_format_ids: HashMap<String, u32> = template_num_formats
+            .iter()
+            .filter_map(|element| {
+                let (id, code) = num_format(element)?;
+                Some((code.to_string(), id.parse().ok()?))
+            })
+            .collect();
+        // Custom number formats start at 164.
+        let next_num_format_id = num_format_ids.values().copied().max().unwrap_or(163).max(163) + 1;
+        let template_num_format_count = template_num_formats.len();
+        StyleMerger {
+            fonts: StyleElements::new(saved, &template, "fonts", "font", 1),
+            fills: StyleElements::new(saved, &template, "fills", "fill", 2),
+            borders: StyleElements::new(saved, &template, "borders", "border", 1),
+            xfs: StyleElements::new(saved, &template, "cellXfs", "xf", 1),
+            template,
+            saved_num_formats,
+            num_format_ids,
+            next_num_format_id,
+            template_num_format_count,
+            added_num_formats: vec![],
+        }
+    }
+
+    /// Get the template index of a saved cell format, adding it and the
+    /// elements it uses to the template styles if needed.
+    fn xf_index(&mut self, index: &str) -> WasmResult<String> {
+        let StyleMerger {
+            fonts,
+            fills,
+            borders,
+            xfs,
+            saved_num_formats,
+            num_format_ids,
+            next_num_format_id,
+            added_num_formats,
+            ..
+        } = self;
+        xfs.index(index, |xf| {
+            let mut xf = xf.to_string();
+            let attribute = |xf: &str, name| package::xml_attribute(xf, "xf", name).unwrap_or("0").to_string();
+            let font = fonts.index(&attribute(&xf, "fontId"), |font| Ok(font.to_string()))?;
+            let fill = fills.index(&attribute(&xf, "fillId"), |fill| Ok(fill.to_string()))?;
+            let border = borders.index(&attribute(&xf, "borderId"), |border| Ok(border.to_string()))?;
+            let num_format = attribute(&xf, "numFmtId");
+            if let Some(code) = saved_num_formats.get(num_format.as_str()) {
+                let id = *num_format_ids.entry(code.to_string()).or_insert_with(|| {
+                    let id = *next_num_format_id;
+                    *next_num_format_id += 1;
+                    added_num_formats.push(format!(r#"<numFmt numFmtId="{id}" formatCode="{code}"/>"#));
+                    id
+                });
+                package::set_xml_attribute(&mut xf, "xf", "numFmtId", Some(&id.to_string()));
+            }
+            package::set_xml_attribute(&mut xf, "xf", "fontId", Some(&font));
+            package::set_xml_attribute(&mut xf, "xf", "fillId", Some(&fill));
+            package::set_xml_attribute(&mut xf, "xf", "borderId", Some(&border));
+            Ok(xf)
+        })
+    }
+
+    /// Get the template styles with the added formats, or `None` if no
+    /// formats were added.
+    fn finish(mut self) -> Option<String> {
+        if self.xfs.added.is_empty() {
+            return None;
+        }
+        let xml = &mut self.template;
+        if !self.added_num_formats.is_empty() {
+            let count = (self.template_num_format_count + self.added_num_formats.len()).to_string();
+            let num_formats = self.added_num_formats.concat();
+            if package::append_to_element(xml, "numFmts", &num_formats) {
+                package::set_xml_attribute(xml, "numFmts", "count", Some(&count));
+            } else {
+                package::insert_after_start_tag(
+                    xml,
+                    "styleSheet",
+                    &format!(r#"<numFmts count="{count}">{num_formats}</numFmts>"#),
+                );
+            }
+        }
+        self.fonts.append_to(xml, "fonts");
+        self.fills.append_to(xml, "fills");
+        self.borders.append_to(xml, "borders");
+        self.xfs.append_to(xml, "cellXfs");
+        Some(self.template)
+    }
+}
+
+/// Get the id and format code of a `numFmt` element.
+fn num_format(element: &str) -> Option<(&str, &str)> {
+    Some((
+        package::xml_attribute(element, "numFmt", "numFmtId")?,
+        package::xml_attribute(element, "numFmt", "formatCode")?,
+    ))
+}
+
+/// Get an XML part of a package.
+fn xml_part(package: &Package, name: &str) -> WasmResult<String> {
+    let data = package
+        .part(name)
+        .ok_or_else(|| parameter_error(format!("The template doesn't have a '{name}' part")))?;
+    String::from_utf8(data.to_vec()).map_err(|e| XlsxError::Internal(e.to_string()))
+}
+
+fn unsupported(message: String) -> XlsxError {
+    parameter_error(format!(
+        "Only cell data can be written to a workbook created from a template, {message}"
+    ))
+}
+
+fn parameter_error(message: String) -> XlsxError {
+    XlsxError::Xlsx(xlsx::XlsxError::ParameterError(message))
+}
@@ -0,0 +1,362 @@
+//! Support for custom workbook themes.
+//!
+//! `rust_xlsxwriter` writes the default Office theme, so the colors and fonts
+//! of a custom theme are patched into the theme part after the workbook has
+//! been saved.
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    color::Color,
+    package::{self, Package},
+    WasmResult,
+};
+
+const THEME_PART: &str = "xl/theme/theme1.xml";
+
+/// The colors of a theme, in the order of the theme color indexes used by
+/// {@link Color.theme}, followed by the hyperlink and followed hyperlink
+/// colors.
+pub(crate) type ThemeColors = [u32; 12];
+
+/// The colors of the default Office theme that `rust_xlsxwriter` writes.
+pub(crate) const OFFICE_THEME_COLORS: ThemeColors = [
+    0xFFFFFF, 0x000000, 0xEEECE1, 0x1F497D, 0x4F81BD, 0xC0504D,
+    0x9BBB59, 0x8064A2, 0x4BACC6, 0xF79646, 0x0000FF, 0x800080,
+];
+
+/// The `ThemeColor` enum defines the colors of a workbook theme.
+///
+/// The colors are used with {@link ThemeDefinition#setColor}. Theme colors
+/// in formats and charts, see {@link Color.theme}, refer to these colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum ThemeColor {
+    /// Dark 1, the default text color.
+    Dark1,
+    /// Light 1, the default background color.
+    Light1,
+    /// Dark 2.
+    Dark2,
+    /// Light 2.
+    Light2,
+    /// Accent 1.
+    Accent1,
+    /// Accent 2.
+    Accent2,
+    /// Accent 3.
+    Accent3,
+    /// Accent 4.
+    Accent4,
+    /// Accent 5.
+    Accent5,
+    /// Accent 6.
+    Accent6,
+    /// The color of hyperlinks.
+    Hyperlink,
+    /// The color of followed hyperlinks.
+    FollowedHyperlink,
+}
+
+impl ThemeColor {
+    const ALL: [ThemeColor; 12] = [
+        ThemeColor::Dark1,
+        ThemeColor::Light1,
+        ThemeColor::Dark2,
+        ThemeColor::Light2,
+        ThemeColor::Accent1,
+        ThemeColor::Accent2,
+        ThemeColor::Accent3,
+        ThemeColor::Accent4,
+        ThemeColor::Accent5,
+        ThemeColor::Accent6,
+        ThemeColor::Hyperlink,
+        ThemeColor::FollowedHyperlink,
+    ];
+
+    /// The index of the color in {@link ThemeColors}. The light and dark
+    /// colors are swapped in the theme color palette.
+    fn index(self) -> usize {
+        match self {
+            ThemeColor::Light1 => 0,
+            ThemeColor::Dark1 => 1,
+            ThemeColor::Light2 => 2,
+            ThemeColor::Dark2 => 3,
+            ThemeColor::Accent1 => 4,
+            ThemeColor::Accent2 => 5,
+            ThemeColor::Accent3 => 6,
+            ThemeColor::Accent4 => 7,
+            ThemeColor::Accent5 => 8,
+            ThemeColor::Accent6 => 9,
+            ThemeColor::Hyperlink => 10,
+            ThemeColor::FollowedHyperlink => 11,
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            ThemeColor::Dark1 => "a:dk1",
+            ThemeColor::Light1 => "a:lt1",
+            ThemeColor::Dark2 => "a:dk2",
+            ThemeColor::Light2 => "a:lt2",
+            ThemeColor::Accent1 => "a:accent1",
+            ThemeColor::Accent2 => "a:accent2",
+            ThemeColor::Accent3 => "a:accent3",
+            ThemeColor::Accent4 => "a:accent4",
+            ThemeColor::Accent5 => "a:accent5",
+            ThemeColor::Accent6 => "a:accent6",
+            ThemeColor::Hyperlink => "a:hlink",
+            ThemeColor::FollowedHyperlink => "a:folHlink",
+        }
+    }
+}
+
+/// The `ThemeDefinition` struct represents a custom workbook theme.
+///
+/// A theme defines the colors of the theme color palette and the heading
+/// (major) and body (minor) fonts of a workbook. A theme is either built from
+/// the default Office theme by changing its colors and fonts, or loaded from
+/// a theme file exported from Excel or PowerPoint.
+///
+/// The theme is set with {@link Workbook#setTheme}.
+///
+/// TODO: example omitted
+#[derive(Clone, Debug, Default)]
+#[wasm_bindgen]
+pub struct ThemeDefinition {
+    name: Option<String>,
+    colors: Vec<(ThemeColor, u32)>,
+    major_font: Option<String>,
+    minor_font: Option<String>,
+    /// The theme XML loaded from a file, or `None` for the default theme.
+    xml: Option<String>,
+}
+
+#[wasm_bindgen]
+impl ThemeDefinition {
+    /// Create a new theme based on the default Office theme.
+    ///
+    /// @param {string} name - The name of the theme.
+    /// @returns {ThemeDefinition} - The ThemeDefinition object.
+    #[wasm_bindgen(constructor, skip_jsdoc)]
+    pub fn new(name: &str) -> ThemeDefinition {
+        ThemeDefinition {
+            name: Some(name.to_string()),
+            ..ThemeDefinition::default()
+        }
+    }
+
+    /// Load a theme from a theme file.
+    ///
+    /// The data is either a `.thmx` theme file, as saved by Excel or
+    /// PowerPoint, or the XML of a `theme1.xml` part of an xlsx file.
+    ///
+    /// @param {Uint8Array} data - The contents of the theme file.
+    /// @returns {ThemeDefinition} - The ThemeDefinition object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The data isn't a theme file or
+    ///   the theme doesn't have a color and font scheme.
+    #[wasm_bindgen(js_name = "fromBuffer", skip_jsdoc)]
+    pub fn from_buffer(data: &[u8]) -> WasmResult<ThemeDefinition> {
+        let invalid = || {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The data isn't a theme file".to_string(),
+            ))
+        };
+        let xml = if data.starts_with(b"PK") {
+            // A `.thmx` file is a zip package with the theme in a
+            // `theme/theme` folder.
+            let package = Package::from_buffer(data).map_err(|_| invalid())?;
+            let part = package
+                .part_names(|name| name.starts_with("theme/theme/") && name.ends_with(".xml"))
+                .into_iter()
+                .next()
+                .ok_or_else(invalid)?;
+            package.part(&part).map(|xml| String::from_utf8_lossy(xml).into_owned())
+        } else {
+            std::str::from_utf8(data).ok().map(str::to_string)
+        }
+        .ok_or_else(invalid)?;
+
+        if !["<a:theme", "<a:clrScheme", "<a:majorFont", "<a:minorFont"]
+            .iter()
+            .all(|tag| xml.contains(tag))
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The theme doesn't have a color and font scheme".to_string(),
+            )));
+        }
+        Ok(ThemeDefinition {
+            xml: Some(xml),
+            ..ThemeDefinition::default()
+        })
+    }
+
+    /// Set a color of the theme.
+    ///
+    /// @param {ThemeColor} theme_color - The {@link ThemeColor} to set.
+    /// @param {Color} color - The RGB color, see {@link Color.rgb}.
+    /// @returns {ThemeDefinition} - The ThemeDefinition object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The color isn't an RGB or named
+    ///   color.
+    #[wasm_bindgen(js_name = "setColor", skip_jsdoc)]
+    pub fn set_color(&self, theme_color: ThemeColor, color: Color) -> WasmResult<ThemeDefinition> {
+        let rgb = match color.inner {
+            xlsx::Color::Default | xlsx::Color::Automatic | xlsx::Color::Theme(..) => None,
+            color => super::format_properties::rgb_of_color(color, &OFFICE_THEME_COLORS),
+        }
+        .ok_or_else(|| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "A theme color must be an RGB color".to_string(),
+            ))
+        })?;
+        let mut colors: Vec<_> = self
+            .colors
+            .iter()
+            .filter(|(existing, _)| *existing != theme_color)
+            .copied()
+            .collect();
+        colors.push((theme_color, rgb));
+        Ok(ThemeDefinition {
+            colors,
+            ..self.clone()
+        })
+    }
+
+    /// Set the font used for headings, the major font of the theme.
+    ///
+    /// @param {string} font_name - The name of the font.
+    /// @returns {ThemeDefinition} - The ThemeDefinition object.
+    #[wasm_bindgen(js_name = "setMajorFont", skip_jsdoc)]
+    pub fn set_major_font(&self, font_name: &str) -> ThemeDefinition {
+        ThemeDefinition {
+            major_font: Some(font_name.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Set the font used for body text, the minor font of the theme.
+    ///
+    /// Cells use the minor font unless their format sets another font.
+    ///
+    /// @param {string} font_name - The name of the font.
+    /// @returns {ThemeDefinition} - The ThemeDefinition object.
+    #[wasm_bindgen(js_name = "setMinorFont", skip_jsdoc)]
+    pub fn set_minor_font(&self, font_name: &str) -> ThemeDefinition {
+        ThemeDefinition {
+            minor_font: Some(font_name.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Get the RGB value of a color of the color scheme of a theme.
+fn scheme_color(xml: &str, tag: &str) -> Option<u32> {
+    let start = xml.find(&format!("<{tag}>"))?;
+    let element = &xml[start..];
+    let element = &element[..element.find(&format!("</{tag}>"))?];
+    let rgb = package::xml_attribute(element, "a:srgbClr", "val")
+        .or_else(|| package::xml_attribute(element, "a:sysClr", "lastClr"))?;
+    u32::from_str_radix(rgb, 16).ok()
+}
+
+/// Get the typeface of the latin font of a theme font.
+fn theme_font<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))?;
+    package::xml_attribute(&xml[start..], "a:latin", "typeface")
+}
+
+/// Set the typeface of the latin font of a theme font.
+fn set_theme_font(xml: &mut String, tag: &str, typeface: &str) {
+    let Some(start) = xml.find(&format!("<{tag}>")) else {
+        return;
+    };
+    let mut tail = xml.split_off(start);
+    package::set_xml_attribute(&mut tail, "a:latin", "typeface", Some(&package::escape_xml(typeface)));
+    xml.push_str(&tail);
+}
+
+/// Set the name of the fonts of a styles part that use a theme font.
+fn set_scheme_fonts(xml: &str, scheme: &str, typeface: &str) -> String {
+    let scheme = format!("<scheme val=\"{scheme}\"/>");
+    let mut fonts = xml.split("<font>");
+    let mut result = fonts.next().unwrap_or_default().to_string();
+    for font in fonts {
+        result.push_str("<font>");
+        match font.split_once("</font>") {
+            Some((properties, rest)) if properties.contains(&scheme) => {
+                let mut properties = properties.to_string();
+                package::set_xml_attribute(&mut properties, "name", "val", Some(&package::escape_xml(typeface)));
+                result.push_str(&properties);
+                result.push_str("</font>");
+                result.push_str(rest);
+            }
+            _ => result.push_str(font),
+        }
+    }
+    result
+}
+
+impl ThemeDefinition {
+    /// Get the colors of the theme.
+    pub(crate) fn colors(&self) -> ThemeColors {
+        let mut colors = OFFICE_THEME_COLORS;
+        if let Some(xml) = &self.xml {
+            for theme_color in ThemeColor::ALL {
+                if let Some(rgb) = scheme_color(xml, theme_color.tag()) {
+                    colors[theme_color.index()] = rgb;
+                }
+            }
+        }
+        for &(theme_color, rgb) in &self.colors {
+            colors[theme_color.index()] = rgb;
+        }
+        colors
+    }
+
+    /// Replace the theme of a package saved by `rust_xlsxwriter`.
+    pub(crate) fn update_package(&self, package: &mut Package) -> WasmResult<()> {
+        package.edit_xml(THEME_PART, |xml| {
+            if let Some(theme) = &self.xml {
+                xml.clone_from(theme);
+            }
+            if let Some(name) = &self.name {
+                let name = package::escape_xml(name);
+                package::set_xml_attribute(xml, "a:theme", "name", Some(&name));
+                package::set_xml_attribute(xml, "a:clrScheme", "name", Some(&name));
+                package::set_xml_attribute(xml, "a:fontScheme", "name", Some(&name));
+            }
+            for (theme_color, rgb) in &self.colors {
+                package::set_element_text(xml, theme_color.tag(), &format!("<a:srgbClr val=\"{rgb:06X}\"/>"));
+            }
+            if let Some(font) = &self.major_font {
+                set_theme_font(xml, "a:majorFont", font);
+            }
+            if let Some(font) = &self.minor_font {
+                set_theme_font(xml, "a:minorFont", font);
+            }
+            Ok(())
+        })?;
+
+        // Cells with a theme font store the name of the font too.
+        let theme = String::from_utf8_lossy(package.part(THEME_PART).unwrap_or_default()).into_owned();
+        let fonts: Vec<_> = [("major", "a:majorFont"), ("minor", "a:minorFont")]
+            .into_iter()
+            .filter_map(|(scheme, tag)| Some((scheme, theme_font(&theme, tag)?.to_string())))
+            .collect();
+        package.edit_xml("xl/styles.xml", |xml| {
+            for (scheme, typeface) in &fonts {
+                *xml = set_scheme_fonts(xml, scheme, typeface);
+            }
+            Ok(())
+        })
+    }
+}
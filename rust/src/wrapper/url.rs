@@ -1,4 +1,4 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use rust_xlsxwriter::{self as xlsx};
 use wasm_bindgen::prelude::*;
@@ -76,27 +76,49 @@ use wasm_bindgen::prelude::*;
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct Url {
-    pub(crate) inner: Arc<Mutex<xlsx::Url>>,
+    pub(crate) inner: Arc<Mutex<UrlProperties>>,
 }
 
-macro_rules! impl_method {
-    ($self:ident.$method:ident($($arg:expr),*)) => {
-        let mut lock = $self.inner.lock().unwrap();
-        let mut inner = std::mem::replace(&mut *lock, xlsx::Url::new(""));
-        inner = inner.$method($($arg),*);
-        let _ = std::mem::replace(&mut *lock, inner);
-        return Url {
-            inner: Arc::clone(&$self.inner),
+/// The properties of a url.
+///
+/// `rust_xlsxwriter` doesn't provide getters for a `Url` so the wrapper keeps
+/// its own record of the properties and builds the `rust_xlsxwriter` url from
+/// it when it's written.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UrlProperties {
+    pub(crate) link: String,
+    pub(crate) text: String,
+    pub(crate) tip: String,
+}
+
+impl UrlProperties {
+    /// Build a `rust_xlsxwriter` url with the properties.
+    pub(crate) fn to_url(&self) -> xlsx::Url {
+        xlsx::Url::new(&self.link).set_text(&self.text).set_tip(&self.tip)
+    }
+
+    /// Get the text shown in the cell of the url, which is the link without
+    /// its `internal:` or `external:` prefix if the url doesn't have a text.
+    pub(crate) fn cell_text(&self) -> String {
+        if !self.text.is_empty() {
+            return self.text.clone();
         }
-    };
+        let link = self.link.strip_prefix("internal:").unwrap_or(&self.link);
+        link.strip_prefix("external:").unwrap_or(link).to_string()
+    }
 }
 
 #[wasm_bindgen]
 impl Url {
-    pub(crate) fn lock(&self) -> std::sync::MutexGuard<'_, xlsx::Url> {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, UrlProperties> {
         self.inner.lock().unwrap()
     }
 
+    /// Build the `rust_xlsxwriter` url with the current properties.
+    pub(crate) fn to_url(&self) -> xlsx::Url {
+        self.lock().to_url()
+    }
+
     /// Create a new Url struct.
     ///
     /// @param {string} link - A string like type representing a URL.
@@ -104,7 +126,10 @@ impl Url {
     #[wasm_bindgen(constructor, skip_jsdoc)]
     pub fn new(link: &str) -> Url {
         Url {
-            inner: Arc::new(Mutex::new(xlsx::Url::new(link))),
+            inner: Arc::new(Mutex::new(UrlProperties {
+                link: link.to_string(),
+                ..UrlProperties::default()
+            })),
         }
     }
 
@@ -116,7 +141,8 @@ impl Url {
     /// @returns {Url} - The url object.
     #[wasm_bindgen(js_name = "setText", skip_jsdoc)]
     pub fn set_text(&self, text: &str) -> Url {
-        impl_method!(self.set_text(text));
+        self.lock().text = text.to_string();
+        self.clone()
     }
 
     /// Set the screen tip for the url.
@@ -127,6 +153,7 @@ impl Url {
     /// @returns {Url} - The url object.
     #[wasm_bindgen(js_name = "setTip", skip_jsdoc)]
     pub fn set_tip(&self, tip: &str) -> Url {
-        impl_method!(self.set_tip(tip));
+        self.lock().tip = tip.to_string();
+        self.clone()
     }
 }
@@ -1,5 +1,3 @@
-use chrono::DateTime;
-use chrono::Utc;
 use js_sys::Date;
 use js_sys::Object;
 use js_sys::Reflect;
@@ -7,6 +5,7 @@ use wasm_bindgen::convert::RefFromWasmAbi;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
+use super::date_conversion::DateConversion;
 use super::datetime::ExcelDateTime;
 use super::formula::Formula;
 use super::rich_string::RichString;
@@ -28,19 +27,12 @@ pub fn jsval_is_datetime(obj: &JsValue) -> bool {
     is_jsdate(obj)
 }
 
-pub fn datetime_of_jsval(obj: JsValue) -> Option<chrono::NaiveDateTime> {
-    if is_jsdate(&obj) {
-        let jsdate = try_into_jsdate(obj).unwrap();
-        let timestamp_ms = jsdate.get_time() as i64;
-
-        let seconds = timestamp_ms / 1000;
-        let nanoseconds = (timestamp_ms % 1000) * 1_000_000;
-
-        let dt = DateTime::<Utc>::from_timestamp(seconds, nanoseconds as u32);
-        dt.map(|dt| dt.naive_utc())
-    } else {
-        None
-    }
+pub fn datetime_of_jsval(
+    obj: JsValue,
+    conversion: &DateConversion,
+) -> Option<chrono::NaiveDateTime> {
+    let jsdate = try_into_jsdate(obj)?;
+    conversion.to_naive(&jsdate)
 }
 
 pub fn excel_datetime_of_jsval(obj: &JsValue) -> Option<ExcelDateTime> {
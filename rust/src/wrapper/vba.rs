@@ -0,0 +1,109 @@
+//! Support for macro enabled (xlsm) workbooks.
+//!
+//! `rust_xlsxwriter` can only read `vbaProject.bin` files from disk, which
+//! isn't available in the browser, so the VBA parts are added to the package
+//! after it has been saved.
+
+use super::{
+    package::{self, Package},
+    WasmResult,
+};
+
+const XLSX_MAIN_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
+const XLSM_MAIN_CONTENT_TYPE: &str = "application/vnd.ms-excel.sheet.macroEnabled.main+xml";
+const VBA_RELATIONSHIP: &str = "http://schemas.microsoft.com/office/2006/relationships/vbaProject";
+const VBA_SIGNATURE_RELATIONSHIP: &str =
+    "http://schemas.microsoft.com/office/2006/relationships/vbaProjectSignature";
+/// The code name GUID that Excel, and `rust_xlsxwriter` for xlsm files,
+/// write in the `fileVersion` element of a macro enabled workbook.
+const FILE_VERSION_CODE_NAME: &str = "{37E998C4-C9E5-D4B9-71C8-EB1FF731991C}";
+
+/// A `vbaProject.bin` file, and optional signature, to add to the workbook.
+#[derive(Clone, Debug)]
+pub(crate) struct VbaProject {
+    pub(crate) project: Vec<u8>,
+    pub(crate) signature: Option<Vec<u8>>,
+}
+
+impl VbaProject {
+    /// Add the VBA project to a package saved by `rust_xlsxwriter` and convert
+    /// it to a macro enabled xlsm package.
+    pub(crate) fn update_package(&self, package: &mut Package) -> WasmResult<()> {
+        package.set_part("xl/vbaProject.bin", self.project.clone());
+
+        package.edit_xml("[Content_Types].xml", |xml| {
+            *xml = xml.replace(XLSX_MAIN_CONTENT_TYPE, XLSM_MAIN_CONTENT_TYPE);
+            package::insert_after_start_tag(
+                xml,
+                "Types",
+                r#"<Default Extension="bin" ContentType="application/vnd.ms-office.vbaProject"/>"#,
+            );
+            if self.signature.is_some() {
+                package::insert_before_end_tag(
+                    xml,
+                    "Types",
+                    r#"<Override PartName="/xl/vbaProjectSignature.bin" ContentType="application/vnd.ms-office.vbaProjectSignature"/>"#,
+                );
+            }
+            Ok(())
+        })?;
+
+        package.edit_xml("xl/_rels/workbook.xml.rels", |xml| {
+            let id = xml.matches("<Relationship ").count() + 1;
+            package::insert_before_end_tag(
+                xml,
+                "Relationships",
+                &format!(r#"<Relationship Id="rId{id}" Type="{VBA_RELATIONSHIP}" Target="vbaProject.bin"/>"#),
+            );
+            Ok(())
+        })?;
+
+        if let Some(signature) = &self.signature {
+            package.set_part("xl/vbaProjectSignature.bin", signature.clone());
+            let rels = format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                 <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+                 <Relationship Id=\"rId1\" Type=\"{VBA_SIGNATURE_RELATIONSHIP}\" Target=\"vbaProjectSignature.bin\"/>\
+                 </Relationships>"
+            );
+            package.set_part("xl/_rels/vbaProject.bin.rels", rels.into_bytes());
+        }
+
+        // The VBA code refers to the workbook and worksheets by their code
+        // names so add default names, like Excel, where they aren't set.
+        package.edit_xml("xl/workbook.xml", |xml| {
+            if package::xml_attribute(xml, "fileVersion", "codeName").is_none() {
+                package::set_xml_attribute(xml, "fileVersion", "codeName", Some(FILE_VERSION_CODE_NAME));
+            }
+            if package::xml_attribute(xml, "workbookPr", "codeName").is_none() {
+                package::set_xml_attribute(xml, "workbookPr", "codeName", Some("ThisWorkbook"));
+            }
+            Ok(())
+        })?;
+
+        for name in package.part_names(|name| name.starts_with("xl/worksheets/sheet")) {
+            let Some(number) = name
+                .strip_prefix("xl/worksheets/sheet")
+                .and_then(|name| name.strip_suffix(".xml"))
+            else {
+                continue;
+            };
+            let codename = format!("Sheet{number}");
+            package.edit_xml(&name, |xml| {
+                if package::xml_attribute(xml, "sheetPr", "codeName").is_none()
+                    && !package::set_xml_attribute(xml, "sheetPr", "codeName", Some(&codename))
+                {
+                    package::insert_after_start_tag(
+                        xml,
+                        "worksheet",
+                        &format!(r#"<sheetPr codeName="{codename}"/>"#),
+                    );
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(())
+    }
+}
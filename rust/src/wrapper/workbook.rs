@@ -1,11 +1,27 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{atomic::Ordering, Arc, Mutex};
 
 use rust_xlsxwriter as xlsx;
 use wasm_bindgen::prelude::*;
 
 use crate::{
     error::XlsxError,
-    wrapper::{doc_properties::DocProperties, format::Format, worksheet::Worksheet},
+    wrapper::{
+        cell_style::NamedCellStyle,
+        date_conversion::DateConversion,
+        defined_name::{self, DefinedName},
+        doc_properties::DocProperties,
+        encryption::{self, EncryptionOptions},
+        format::Format,
+        package::Package,
+        save_options::SaveOptions,
+        table_style::CustomTableStyle,
+        template::Template,
+        theme::ThemeDefinition,
+        vba::VbaProject,
+        workbook_settings::{CalcMode, WorkbookSettings},
+        worksheet::{next_sheet_id, SheetTarget, Worksheet},
+        write_policy::WritePolicy,
+    },
 };
 
 use super::WasmResult;
@@ -17,7 +33,10 @@ use super::WasmResult;
 #[wasm_bindgen]
 pub struct Workbook {
     inner: Arc<Mutex<xlsx::Workbook>>,
-    next_sheet_index: usize,
+    settings: Arc<Mutex<WorkbookSettings>>,
+    /// The properties set with `setProperties()`, which are restored after
+    /// saving with a fixed creation time.
+    properties: Arc<Mutex<Option<xlsx::DocProperties>>>,
 }
 
 #[wasm_bindgen]
@@ -29,17 +48,75 @@ impl Workbook {
     /// everything to an xlsx file with {@link Workbook#saveToBufferSync}.
     ///
     /// **Note**: `rust_xlsxwriter` can only create new files. It cannot read or
-    /// modify existing files.
+    /// modify existing files, but an existing file can be filled in as a
+    /// template with {@link Workbook#fromTemplate}.
     ///
     /// TODO: example omitted
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         Workbook {
             inner: Arc::new(Mutex::new(xlsx::Workbook::new())),
-            next_sheet_index: 0,
+            settings: Arc::new(Mutex::new(WorkbookSettings::default())),
+            properties: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Create a workbook from an existing xlsx file used as a template.
+    ///
+    /// The workbook has a worksheet for each worksheet of the template, with
+    /// the same name, which can be got with {@link Workbook#worksheetFromName}
+    /// or {@link Workbook#worksheetFromIndex} and written to with the normal
+    /// `write*()` methods, for example to fill in values or append rows. When
+    /// the workbook is saved the written cells are merged into the template,
+    /// replacing any existing cells, and everything else in the template,
+    /// such as the styles, theme, drawings and defined names, is kept as it
+    /// is.
+    ///
+    /// Cells written without a format keep the format of the template cell
+    /// they replace, so values can be written into the formatted cells of a
+    /// template. Formats are added to the template styles, and the formulas
+    /// of the template are recalculated when the file is opened.
+    ///
+    /// Some limitations:
+    ///
+    /// - Only cell data, row heights and row formats can be written. Other
+    ///   changes, such as adding worksheets, tables, images, merged ranges or
+    ///   defined names, or changing column widths or worksheet settings, raise
+    ///   an error when the workbook is saved.
+    /// - The cells of the template can't be read back with
+    ///   {@link Worksheet#getCell}.
+    /// - Tables in the template aren't resized when rows are appended below
+    ///   them.
+    ///
+    /// @param {Uint8Array} template - The xlsx file.
+    /// @returns {Workbook} - The workbook object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The data isn't an xlsx file, or the
+    ///   template has chart sheets, which aren't supported.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "fromTemplate", skip_jsdoc)]
+    pub fn from_template(template: &[u8]) -> WasmResult<Workbook> {
+        let template = Template::from_buffer(template)?;
+        let mut workbook = xlsx::Workbook::new();
+        let mut settings = WorkbookSettings {
+            date_1904: template.date_1904,
+            ..WorkbookSettings::default()
+        };
+        for sheet in &template.sheets {
+            let _ = workbook.add_worksheet().set_name(&sheet.name)?;
+            settings.sheet_ids.push(next_sheet_id());
+        }
+        settings.template = Some(Arc::new(template));
+        Ok(Workbook {
+            inner: Arc::new(Mutex::new(workbook)),
+            settings: Arc::new(Mutex::new(settings)),
+            properties: Arc::new(Mutex::new(None)),
+        })
+    }
+
     /// Add a new worksheet to a workbook.
     ///
     /// The `addWorksheet()` method adds a new {{@link Worksheet} to a
@@ -54,8 +131,8 @@ impl Workbook {
     /// Worksheet instance owned by the Workbook so only one worksheet can be in
     /// existence at a time, see the example below. This limitation can be
     /// avoided, if necessary, by creating standalone Worksheet objects via
-    /// {@link Worksheet::constructor} and then later adding them to the workbook with
-    /// {@link Workbook#pushWorksheet}.
+    /// the {@link Worksheet} constructor and then later adding them to the
+    /// workbook with {@link Workbook#pushWorksheet}.
     ///
     /// See also the documentation on [Creating worksheets] and working with the
     /// borrow checker.
@@ -67,14 +144,13 @@ impl Workbook {
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "addWorksheet", skip_jsdoc)]
     pub fn add_worksheet(&mut self) -> Worksheet {
-        let index = self.next_sheet_index;
-        self.next_sheet_index += 1;
         let mut workbook = self.inner.lock().unwrap();
         let _ = workbook.add_worksheet();
-        Worksheet {
-            workbook: Arc::clone(&self.inner),
-            index,
-        }
+        let id = next_sheet_id();
+        let mut settings = self.settings.lock().unwrap();
+        settings.sheet_ids.push(id);
+        drop(settings);
+        Worksheet::attached(&self.inner, &self.settings, id)
     }
 
     /// Get a worksheet reference by index.
@@ -113,10 +189,8 @@ impl Workbook {
         // Reimplementation of [`rust_xlsxwriter::Workbook::worksheet_from_name()`]
         let mut workbook = self.inner.lock().unwrap();
         let _ = workbook.worksheet_from_index(index)?;
-        Ok(Worksheet {
-            workbook: Arc::clone(&self.inner),
-            index,
-        })
+        let id = self.settings.lock().unwrap().sheet_ids[index];
+        Ok(Worksheet::attached(&self.inner, &self.settings, id))
     }
 
     /// Get a worksheet reference by name.
@@ -160,10 +234,8 @@ impl Workbook {
         let mut workbook = self.inner.lock().unwrap();
         for (index, worksheet) in workbook.worksheets().iter().enumerate() {
             if worksheet.name() == name {
-                return Ok(Worksheet {
-                    workbook: Arc::clone(&self.inner),
-                    index,
-                });
+                let id = self.settings.lock().unwrap().sheet_ids[index];
+                return Ok(Worksheet::attached(&self.inner, &self.settings, id));
             }
         }
         Err(XlsxError::Xlsx(
@@ -171,6 +243,146 @@ impl Workbook {
         ))
     }
 
+    /// Add a standalone worksheet to the workbook.
+    ///
+    /// The `pushWorksheet()` method adds a worksheet created with the
+    /// {@link Worksheet} constructor to the end of the workbook. This allows
+    /// worksheets to be created and filled independently of the workbook, for
+    /// example in different code paths, and added once they are complete.
+    ///
+    /// After it has been added the worksheet object refers to the worksheet
+    /// in the workbook, so it can still be used to write data. Local defined
+    /// names and the write policy of the worksheet are carried over.
+    ///
+    /// @param {Worksheet} worksheet - The standalone worksheet to add.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The worksheet has already been added
+    ///   to a workbook, or it has dates and the workbook uses the 1904 epoch.
+    #[wasm_bindgen(js_name = "pushWorksheet", skip_jsdoc)]
+    pub fn push_worksheet(&mut self, worksheet: &Worksheet) -> WasmResult<()> {
+        let source = worksheet.target();
+        if !source.standalone {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The worksheet has already been added to a workbook".to_string(),
+            )));
+        }
+        let mut source_settings = source.settings.lock().unwrap();
+        let mut workbook = self.inner.lock().unwrap();
+        let mut settings = self.settings.lock().unwrap();
+
+        // Check everything that can fail before the worksheet is moved.
+        // Dates are converted to the epoch when they are written.
+        if source_settings.has_dates() && source_settings.date_1904 != settings.date_1904 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The worksheet has dates in a different epoch to the workbook".to_string(),
+            )));
+        }
+        let name = source.workbook.lock().unwrap().worksheet_from_index(0)?.name();
+        if !name.is_empty()
+            && workbook
+                .worksheets()
+                .iter()
+                .any(|sheet| sheet.name().to_lowercase() == name.to_lowercase())
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::SheetnameReused(name)));
+        }
+
+        let sheet = source.workbook.lock().unwrap().worksheets_mut().remove(0);
+        workbook.push_worksheet(sheet);
+        settings.sheet_ids.push(source.id);
+        if let Some(state) = source_settings.sheets.remove(&source.id) {
+            settings.sheets.insert(source.id, state);
+        }
+        if source_settings.has_dates() {
+            settings.dates_written.store(true, Ordering::Relaxed);
+        }
+        // The local names of the worksheet refer to it by id, so they can't
+        // conflict with the names of the workbook.
+        let local_names = std::mem::take(&mut source_settings.defined_names);
+        settings.defined_names.extend(local_names);
+        worksheet.set_target(SheetTarget {
+            workbook: Arc::clone(&self.inner),
+            settings: Arc::clone(&self.settings),
+            id: source.id,
+            standalone: false,
+        });
+        Ok(())
+    }
+
+    /// Move a worksheet to a new position in the workbook.
+    ///
+    /// The worksheet at index `from` is removed and inserted at index `to`,
+    /// shifting the worksheets in between. Existing worksheet objects still
+    /// refer to the same worksheets after the move.
+    ///
+    /// @param {number} from - The current index of the worksheet.
+    /// @param {number} to - The new index of the worksheet.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::UnknownWorksheetNameOrIndex`] - One of the indices is
+    ///   out of bounds.
+    #[wasm_bindgen(js_name = "moveWorksheet", skip_jsdoc)]
+    pub fn move_worksheet(&mut self, from: usize, to: usize) -> WasmResult<()> {
+        let mut workbook = self.inner.lock().unwrap();
+        let mut settings = self.settings.lock().unwrap();
+        let count = settings.sheet_ids.len();
+        if let Some(index) = [from, to].into_iter().find(|&index| index >= count) {
+            return Err(XlsxError::Xlsx(
+                xlsx::XlsxError::UnknownWorksheetNameOrIndex(index.to_string()),
+            ));
+        }
+
+        let worksheets = workbook.worksheets_mut();
+        let sheet = worksheets.remove(from);
+        worksheets.insert(to, sheet);
+        let id = settings.sheet_ids.remove(from);
+        settings.sheet_ids.insert(to, id);
+        Ok(())
+    }
+
+    /// Remove a worksheet from the workbook.
+    ///
+    /// Worksheet objects that refer to the removed worksheet can no longer be
+    /// used and will raise an error. Names that are local to the worksheet
+    /// are removed with it.
+    ///
+    /// @param {number} index - The index of the worksheet to remove.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::UnknownWorksheetNameOrIndex`] - The index is out of
+    ///   bounds.
+    /// - [`XlsxError::ParameterError`] - Defined names that aren't local to
+    ///   the worksheet refer to it, which would refer to a missing worksheet.
+    #[wasm_bindgen(js_name = "removeWorksheet", skip_jsdoc)]
+    pub fn remove_worksheet(&mut self, index: usize) -> WasmResult<()> {
+        let mut workbook = self.inner.lock().unwrap();
+        let mut settings = self.settings.lock().unwrap();
+        let sheet_name = workbook.worksheet_from_index(index)?.name();
+        let id = settings.sheet_ids[index];
+        // The local names of the worksheet are removed with it.
+        if let Some(name) = settings
+            .defined_names
+            .iter()
+            .filter(|name| name.sheet_id != Some(id))
+            .find(|name| defined_name::refers_to_sheet(&name.formula, &sheet_name))
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Worksheet '{sheet_name}' is referred to by defined name '{}' and can't be removed",
+                name.name
+            ))));
+        }
+
+        workbook.worksheets_mut().remove(index);
+        settings.sheet_ids.remove(index);
+        settings.sheets.remove(&id);
+        settings.defined_names.retain(|name| name.sheet_id != Some(id));
+        Ok(())
+    }
+
     /// Create a defined name in the workbook to use as a variable.
     ///
     /// The `defineName()` method is used to defined a variable name that can
@@ -204,9 +416,10 @@ impl Workbook {
     /// range such as `A1`, `XFD12345` or `R1C1`. If in doubt it best to test
     /// the name in Excel first.
     ///
-    /// For local defined names sheet name must exist (at the time of saving)
-    /// and if the sheet name contains spaces or special characters you must
-    /// follow the Excel convention and enclose it in single quotes:
+    /// For local defined names the worksheet must already be in the workbook,
+    /// and the name stays local to it if it is later renamed or moved. If the
+    /// sheet name contains spaces or special characters you must follow the
+    /// Excel convention and enclose it in single quotes:
     ///
     /// ```text
     ///     workbook.define_name("'New Data'!Sales", ""=Sheet2!$G$1:$G$10")?;
@@ -226,12 +439,233 @@ impl Workbook {
     ///   raise a `ParameterError` error:
     ///   * If the name doesn't start with a letter or underscore.
     ///   * If the name contains `,/*[]:\"'` or `space`.
+    ///   * If the name is already defined in the same scope.
+    /// - [`XlsxError::UnknownWorksheetNameOrIndex`] - The worksheet of a local
+    ///   name isn't in the workbook.
     ///
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "defineName", skip_jsdoc)]
     pub fn define_name(&mut self, name: &str, formula: &str) -> WasmResult<()> {
         let mut workbook = self.inner.lock().unwrap();
-        let _ = workbook.define_name(name, formula);
+        let mut settings = self.settings.lock().unwrap();
+        defined_name::define_name(&mut workbook, &mut settings, name, formula)
+    }
+
+    /// Create a global defined name that refers to a range of cells.
+    ///
+    /// The `defineNameForRange()` method is a helper for
+    /// {@link Workbook#defineName} that builds an absolute range reference,
+    /// such as `='Sheet 1'!$A$1:$B$5`, from zero indexed row and column
+    /// numbers. Sheet names with spaces or special characters are quoted
+    /// automatically.
+    ///
+    /// The reference uses the worksheet name at the time of the call, so the
+    /// worksheet name should be set first.
+    ///
+    /// @param {string} name - The variable name to define.
+    /// @param {Worksheet} worksheet - The worksheet that contains the range.
+    /// @param {number} first_row - The first row of the range.
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnOrderError`] - First row or column is larger
+    ///   than the last row or column.
+    /// - [`XlsxError::ParameterError`] - The name is invalid or already
+    ///   defined. See {@link Workbook#defineName}.
+    #[wasm_bindgen(js_name = "defineNameForRange", skip_jsdoc)]
+    pub fn define_name_for_range(
+        &mut self,
+        name: &str,
+        worksheet: &Worksheet,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+    ) -> WasmResult<()> {
+        let formula = defined_name::range_formula(
+            &worksheet.sheet_name()?,
+            first_row,
+            first_col,
+            last_row,
+            last_col,
+        )?;
+        self.define_name(name, &formula)
+    }
+
+    /// Get the names defined in the workbook.
+    ///
+    /// Returns the global and worksheet local names created with
+    /// {@link Workbook#defineName} and {@link Worksheet#defineName}, in the
+    /// order they were defined.
+    ///
+    /// @returns {DefinedName[]} - The defined names.
+    #[wasm_bindgen(js_name = "definedNames", skip_jsdoc)]
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        let mut workbook = self.inner.lock().unwrap();
+        let settings = self.settings.lock().unwrap();
+        defined_name::defined_names(&mut workbook, &settings)
+    }
+
+    /// Add a named cell style to the workbook.
+    ///
+    /// The style is shown in the custom section of the Excel "Cell Styles"
+    /// gallery. Cells with a format that is based on the style with
+    /// {@link Format#setCellStyle} are shown with the style.
+    ///
+    /// A style with the name of a built-in style, such as `"Good"` or
+    /// `"Heading 1"`, changes the appearance of that {@link CellStyle}.
+    ///
+    /// @param {string} name - The name of the style.
+    /// @param {Format} format - The {@link Format} of the style.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The name is empty, longer than 255
+    ///   characters, `Normal` or `Hyperlink`, or a style with the same name,
+    ///   ignoring case, is already added to the workbook.
+    #[wasm_bindgen(js_name = "addCellStyle", skip_jsdoc)]
+    pub fn add_cell_style(&mut self, name: &str, format: &Format) -> WasmResult<()> {
+        let style = NamedCellStyle::new(name, &format.lock())?;
+        let mut settings = self.settings.lock().unwrap();
+        if settings
+            .cell_styles
+            .iter()
+            .any(|existing| existing.name.eq_ignore_ascii_case(name))
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Cell style '{name}' is already added to the workbook"
+            ))));
+        }
+        settings.cell_styles.push(style);
+        Ok(())
+    }
+
+    /// Set the theme of the workbook.
+    ///
+    /// The theme defines the colors of the theme color palette, that are
+    /// used by theme colors in formats and charts, see {@link Color.theme},
+    /// and the heading and body fonts of the workbook. By default the
+    /// workbook uses the Office theme.
+    ///
+    /// @param {ThemeDefinition} theme - The {@link ThemeDefinition} of the
+    ///   theme.
+    #[wasm_bindgen(js_name = "setTheme", skip_jsdoc)]
+    pub fn set_theme(&self, theme: &ThemeDefinition) {
+        self.settings.lock().unwrap().theme = Some(theme.clone());
+    }
+
+    /// Add a custom table style to the workbook.
+    ///
+    /// A {@link CustomTableStyle} has to be added to the workbook before
+    /// tables that use it, via {@link Table#setStyle}, are saved. The style
+    /// is also shown in the custom section of the Excel table style gallery.
+    ///
+    /// @param {CustomTableStyle} style - The custom table style.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - A table style with the same name,
+    ///   ignoring case, is already added to the workbook.
+    #[wasm_bindgen(js_name = "addTableStyle", skip_jsdoc)]
+    pub fn add_table_style(&mut self, style: &CustomTableStyle) -> WasmResult<()> {
+        let mut settings = self.settings.lock().unwrap();
+        if settings
+            .table_styles
+            .iter()
+            .any(|existing| existing.name.eq_ignore_ascii_case(&style.name))
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Table style '{}' is already added to the workbook",
+                style.name
+            ))));
+        }
+        settings.table_styles.push(style.clone());
+        Ok(())
+    }
+
+    /// Add a vba macro project to the workbook.
+    ///
+    /// The `addVbaProject()` method can be used to add macros or functions to
+    /// a workbook using a binary VBA project file that has been extracted from
+    /// an existing Excel xlsm file. The `vba_extract` utility from
+    /// `rust_xlsxwriter`, or the `vba_extract.py` utility from XlsxWriter, can
+    /// be used to extract the `vbaProject.bin` file.
+    ///
+    /// A workbook with a VBA project is saved as a macro enabled package and
+    /// should be given an `.xlsm` extension, otherwise Excel will refuse to
+    /// open it. The workbook and worksheets are given the default VBA code
+    /// names `ThisWorkbook` and `Sheet1`, `Sheet2`, etc., unless they are set
+    /// with {@link Workbook#setVbaName} or {@link Worksheet#setVbaName}.
+    ///
+    /// Only one VBA project can be added per workbook. Adding another one
+    /// replaces the previous project.
+    ///
+    /// @param {Uint8Array} project - The contents of a `vbaProject.bin` file.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The data isn't an OLE compound file,
+    ///   which is the format of a `vbaProject.bin` file.
+    #[wasm_bindgen(js_name = "addVbaProject", skip_jsdoc)]
+    pub fn add_vba_project(&mut self, project: &[u8]) -> WasmResult<()> {
+        check_vba_data(project, "vbaProject.bin")?;
+        self.settings.lock().unwrap().vba_project = Some(VbaProject {
+            project: project.to_vec(),
+            signature: None,
+        });
+        Ok(())
+    }
+
+    /// Add a signed vba macro project to the workbook.
+    ///
+    /// The `addSignedVbaProject()` method can be used to add a signed VBA
+    /// project to a workbook. A signed project consists of the
+    /// `vbaProject.bin` file and a `vbaProjectSignature.bin` file, both of
+    /// which can be extracted from an existing signed xlsm file.
+    ///
+    /// See {@link Workbook#addVbaProject} for more details.
+    ///
+    /// @param {Uint8Array} project - The contents of a `vbaProject.bin` file.
+    /// @param {Uint8Array} signature - The contents of a
+    ///   `vbaProjectSignature.bin` file.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The project or signature data isn't
+    ///   an OLE compound file.
+    #[wasm_bindgen(js_name = "addSignedVbaProject", skip_jsdoc)]
+    pub fn add_signed_vba_project(&mut self, project: &[u8], signature: &[u8]) -> WasmResult<()> {
+        check_vba_data(project, "vbaProject.bin")?;
+        check_vba_data(signature, "vbaProjectSignature.bin")?;
+        self.settings.lock().unwrap().vba_project = Some(VbaProject {
+            project: project.to_vec(),
+            signature: Some(signature.to_vec()),
+        });
+        Ok(())
+    }
+
+    /// Set the workbook name used in VBA macros.
+    ///
+    /// VBA code refers to the workbook by its code name, which defaults to
+    /// `ThisWorkbook`. The `setVbaName()` method can be used to change it if
+    /// the VBA project that was added with {@link Workbook#addVbaProject} was
+    /// extracted from a workbook with a different code name.
+    ///
+    /// @param {string} name - The vba name. It must start with a letter and
+    ///   contain only letters, digits and underscores, and be at most 31
+    ///   characters long.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::VbaNameError`] - The name doesn't follow the Excel
+    ///   rules shown above.
+    #[wasm_bindgen(js_name = "setVbaName", skip_jsdoc)]
+    pub fn set_vba_name(&mut self, name: &str) -> WasmResult<()> {
+        let mut workbook = self.inner.lock().unwrap();
+        workbook.set_vba_name(name)?;
         Ok(())
     }
 
@@ -248,13 +682,70 @@ impl Workbook {
     ///   the xlsx file, or its sub-files.
     /// - [`XlsxError::ZipError`] - A wrapper for various zip errors when
     ///   creating the xlsx file, or its sub-files.
+    /// - [`XlsxError::ParameterError`] - The workbook was created with
+    ///   {@link Workbook#fromTemplate} and has changes other than cell data.
     ///
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "saveToBufferSync")]
     pub fn save_to_buffer_sync(&self) -> WasmResult<Vec<u8>> {
         let mut workbook = self.inner.lock().unwrap();
-        let buf = workbook.save_to_buffer()?;
-        Ok(buf)
+        let settings = self.settings.lock().unwrap();
+        let buf = match settings.save_options.creation_datetime() {
+            Some(datetime) => {
+                let properties = self.properties.lock().unwrap().clone().unwrap_or_default();
+                workbook.set_properties(&properties.clone().set_creation_datetime(&datetime));
+                let buf = workbook.save_to_buffer();
+                workbook.set_properties(&properties);
+                buf?
+            }
+            None => workbook.save_to_buffer()?,
+        };
+
+        if let Some(template) = &settings.template {
+            let properties = self.properties.lock().unwrap().is_some();
+            let package = template.fill(&Package::from_buffer(&buf)?, &settings, properties)?;
+            return package.to_buffer(settings.save_options.compression_level());
+        }
+        if !settings.needs_package_update() {
+            return Ok(buf);
+        }
+        let mut package = Package::from_buffer(&buf)?;
+        settings.update_package(&mut package)?;
+        package.to_buffer(settings.save_options.compression_level())
+    }
+
+    /// Save the Workbook as a password encrypted xlsx file and return it as a
+    /// byte vector.
+    ///
+    /// The file is encrypted with the ECMA-376 agile encryption used by Excel,
+    /// AES-256 with a SHA-512 password hash, and Excel asks for the password
+    /// when it is opened. This protects the whole file, unlike
+    /// {@link Worksheet#protect} which only stops the user editing a
+    /// worksheet.
+    ///
+    /// The random salts and keys are generated with `crypto.getRandomValues()`
+    /// so the same workbook is encrypted differently each time it is saved.
+    ///
+    /// @param {string} password - The password needed to open the file.
+    /// @param {EncryptionOptions} options - Encryption options. Use
+    ///   `new EncryptionOptions()` for the same settings as Excel.
+    /// @returns {Uint8Array} - The encrypted xlsx file.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The password is empty or longer than
+    ///   Excel's limit of 255 characters.
+    /// - Any of the errors of {@link Workbook#saveToBufferSync}.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "saveToBufferEncrypted", skip_jsdoc)]
+    pub fn save_to_buffer_encrypted(
+        &self,
+        password: &str,
+        options: &EncryptionOptions,
+    ) -> WasmResult<Vec<u8>> {
+        let buf = self.save_to_buffer_sync()?;
+        encryption::encrypt(&buf, password, options)
     }
 
     /// Add a recommendation to open the file in “read-only” mode.
@@ -281,8 +772,10 @@ impl Workbook {
     /// @param {DocProperties} properties - A reference to a {@link DocProperties} object.
     #[wasm_bindgen(js_name = "setProperties", skip_jsdoc)]
     pub fn set_properties(&self, properties: &DocProperties) {
+        let properties = properties.lock().clone();
         let mut workbook = self.inner.lock().unwrap();
-        workbook.set_properties(&properties.lock());
+        workbook.set_properties(&properties);
+        *self.properties.lock().unwrap() = Some(properties);
     }
 
     /// Set the default cell format for the workbook.
@@ -315,7 +808,197 @@ impl Workbook {
         col_width: u32,
     ) -> WasmResult<()> {
         let mut workbook = self.inner.lock().unwrap();
-        workbook.set_default_format(&format.lock().clone(), row_height, col_width)?;
+        workbook.set_default_format(&format.to_format(), row_height, col_width)?;
         Ok(())
     }
+
+    /// Use the 1904 date epoch for the workbook.
+    ///
+    /// Excel supports two date systems: the default 1900 epoch used by
+    /// Windows versions of Excel and the 1904 epoch used by older Mac versions
+    /// of Excel. Templates created on those versions, and workbooks that need
+    /// negative time differences, may rely on the 1904 epoch.
+    ///
+    /// Dates written with {@link Worksheet#write},
+    /// {@link Worksheet#writeDatetime} and the other date methods are
+    /// converted to the 1904 epoch automatically. Since the conversion happens
+    /// when the data is written this method must be called before any dates
+    /// are written to the worksheets.
+    ///
+    /// Dates before 1904-01-01 cannot be represented in the 1904 epoch and
+    /// writing them raises an error.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - Dates have already been written to
+    ///   the workbook in the 1900 epoch.
+    #[wasm_bindgen(js_name = "use1904Epoch", skip_jsdoc)]
+    pub fn use_1904_epoch(&self) -> WasmResult<()> {
+        let mut settings = self.settings.lock().unwrap();
+        if !settings.date_1904 && settings.has_dates() {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The 1904 epoch must be set before dates are written".to_string(),
+            )));
+        }
+        settings.date_1904 = true;
+        Ok(())
+    }
+
+    /// Set the size of the workbook window.
+    ///
+    /// Set the width and height of the Excel application window that is used
+    /// when the file is first opened. This is mainly of use when you want to
+    /// generate files with a consistent window size on different systems.
+    /// Sizes that are too large to store in the file are stored as the
+    /// largest size.
+    ///
+    /// @param {number} width - The window width in pixels.
+    /// @param {number} height - The window height in pixels.
+    #[wasm_bindgen(js_name = "setSize", skip_jsdoc)]
+    pub fn set_size(&self, width: u32, height: u32) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.window_size = Some((width, height));
+    }
+
+    /// Set the ratio between the worksheet tabs and the horizontal scroll bar.
+    ///
+    /// The worksheet tab area at the bottom of the Excel window shares space
+    /// with the horizontal scroll bar. This method can be used to show more
+    /// or fewer worksheet tabs by default. The Excel default is 60%.
+    ///
+    /// @param {number} ratio - The width of the tab area as a percentage, in
+    ///   the range 0 to 100.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The ratio is outside the range 0 to
+    ///   100.
+    #[wasm_bindgen(js_name = "setTabRatio", skip_jsdoc)]
+    pub fn set_tab_ratio(&self, ratio: f64) -> WasmResult<()> {
+        if !(0.0..=100.0).contains(&ratio) {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Tab ratio must be in the range 0 to 100: {ratio}"
+            ))));
+        }
+        let mut settings = self.settings.lock().unwrap();
+        // The tab ratio is stored in thousandths.
+        settings.tab_ratio = Some((ratio * 10.0).round() as u16);
+        Ok(())
+    }
+
+    /// Set the active/initially visible worksheet by index.
+    ///
+    /// This is the workbook level equivalent of {@link Worksheet#setActive}.
+    /// Any previously active worksheet is deactivated and the other
+    /// worksheets are deselected, so only the active worksheet tab is
+    /// selected.
+    ///
+    /// @param {number} index - The index of the worksheet, in creation order.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::UnknownWorksheetNameOrIndex`] - The index is out of
+    ///   bounds.
+    #[wasm_bindgen(js_name = "setActiveSheet", skip_jsdoc)]
+    pub fn set_active_sheet(&self, index: usize) -> WasmResult<()> {
+        let mut workbook = self.inner.lock().unwrap();
+        let _ = workbook.worksheet_from_index(index)?;
+        for (i, worksheet) in workbook.worksheets_mut().iter_mut().enumerate() {
+            worksheet.set_active(i == index);
+            if i != index {
+                worksheet.set_selected(false);
+            }
+        }
+        Ok(())
+    }
+
+    /// Force Excel to recalculate all formulas when the file is opened.
+    ///
+    /// Files created by `rust_xlsxwriter` don't store computed formula results
+    /// so, by default, Excel is told to do a full recalculation when the file
+    /// is loaded. For large models that use {@link CalcMode#Manual} it can be
+    /// useful to turn this off.
+    ///
+    /// @param {boolean} enable - Turn the property on/off. It is on by default.
+    #[wasm_bindgen(js_name = "setFullCalcOnLoad", skip_jsdoc)]
+    pub fn set_full_calc_on_load(&self, enable: bool) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.full_calc_on_load = enable;
+    }
+
+    /// Set the calculation mode of the workbook.
+    ///
+    /// Set the Excel calculation mode, as shown in the "Formulas ->
+    /// Calculation Options" menu. See {@link CalcMode} for the options.
+    ///
+    /// @param {CalcMode} mode - The calculation mode.
+    #[wasm_bindgen(js_name = "setCalcMode", skip_jsdoc)]
+    pub fn set_calc_mode(&self, mode: CalcMode) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.calc_mode = mode;
+    }
+
+    /// Set how JS `Date` values are converted to Excel dates.
+    ///
+    /// By default a JS `Date` is written using its UTC time. The
+    /// {@link DateConversion} can be used to write dates in the local time
+    /// zone of the host, a fixed UTC offset or an IANA time zone, and to drop
+    /// the time part of the dates.
+    ///
+    /// The conversion applies to dates written with {@link Worksheet#write},
+    /// {@link Worksheet#writeDatetime}, {@link Worksheet#writeColumn} and the
+    /// other generic write methods. It isn't applied to
+    /// {@link ExcelDateTime} values, which are already wall clock times.
+    ///
+    /// The conversion is applied when data is written so it should be set
+    /// before any dates are written to the worksheets.
+    ///
+    /// @param {DateConversion} conversion - The date conversion.
+    #[wasm_bindgen(js_name = "setDateConversion", skip_jsdoc)]
+    pub fn set_date_conversion(&self, conversion: &DateConversion) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.date_conversion = conversion.clone();
+    }
+
+    /// Set the write policy for all worksheets in the workbook.
+    ///
+    /// The {@link WritePolicy} controls how `BigInt`, `null`/`undefined` and
+    /// non-finite numbers are written by {@link Worksheet#write} and the other
+    /// generic write methods. The policy can be overridden for individual
+    /// worksheets with {@link Worksheet#setWritePolicy}.
+    ///
+    /// The policy is applied when data is written so it should be set before
+    /// writing any data.
+    ///
+    /// @param {WritePolicy} policy - The write policy.
+    #[wasm_bindgen(js_name = "setWritePolicy", skip_jsdoc)]
+    pub fn set_write_policy(&self, policy: &WritePolicy) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.write_policy = *policy;
+    }
+
+    /// Set the options used when the workbook is saved.
+    ///
+    /// The {@link SaveOptions} can set a fixed creation time and a zip
+    /// compression level so that saving identical data produces a
+    /// byte-identical file. They apply to {@link Workbook#saveToBufferSync}
+    /// and the other save methods.
+    ///
+    /// @param {SaveOptions} options - The save options.
+    #[wasm_bindgen(js_name = "setSaveOptions", skip_jsdoc)]
+    pub fn set_save_options(&self, options: &SaveOptions) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.save_options = *options;
+    }
+}
+
+/// Check that VBA data looks like an OLE compound file.
+fn check_vba_data(data: &[u8], file_name: &str) -> WasmResult<()> {
+    const OLE_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+    if !data.starts_with(&OLE_SIGNATURE) {
+        return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+            "Data isn't a valid {file_name} file"
+        ))));
+    }
+    Ok(())
 }
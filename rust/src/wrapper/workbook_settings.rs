@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use rust_xlsxwriter as xlsx;
+use wasm_bindgen::prelude::*;
+
+use crate::error::XlsxError;
+
+use super::{
+    cell_store::{CellRange, CellStore},
+    cell_style::{self, NamedCellStyle},
+    date_conversion::DateConversion,
+    datetime::ExcelSerialDateTime,
+    defined_name::{self, NameDefinition},
+    format_properties::FormatProperties,
+    package::{self, Package},
+    pivot_table::{self, PlacedPivotTable},
+    save_options::SaveOptions,
+    svg::{self, SvgImage},
+    table::{self, PlacedTable},
+    table_style::{self, CustomTableStyle},
+    template::Template,
+    theme::ThemeDefinition,
+    vba::VbaProject,
+    worksheet::SheetId,
+    write_policy::WritePolicy,
+    WasmResult,
+};
+
+/// The number of days between the Excel 1900 and 1904 date epochs.
+pub(crate) const EPOCH_1904_OFFSET: f64 = 1462.0;
+
+/// The `CalcMode` enum defines the calculation mode of a workbook.
+///
+/// This is used with the {@link Workbook#setCalcMode} method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum CalcMode {
+    /// Recalculate formulas automatically. This is the Excel default.
+    #[default]
+    Auto,
+    /// Only recalculate formulas when the user requests it, for example with
+    /// `F9`. Useful for large models with slow formulas.
+    Manual,
+    /// Recalculate formulas automatically except for data tables.
+    AutoExceptTables,
+}
+
+/// Workbook level settings that aren't supported directly by `rust_xlsxwriter`.
+///
+/// The settings are shared between a {@link Workbook} and its worksheets and
+/// are either applied when data is written or patched into the xlsx package
+/// when the workbook is saved.
+#[derive(Clone, Debug)]
+pub(crate) struct WorkbookSettings {
+    pub(crate) date_1904: bool,
+    /// Set once a date has been converted to the workbook epoch, after which
+    /// the epoch can't be changed.
+    pub(crate) dates_written: Arc<AtomicBool>,
+    pub(crate) date_conversion: DateConversion,
+    pub(crate) window_size: Option<(u32, u32)>,
+    pub(crate) tab_ratio: Option<u16>,
+    pub(crate) full_calc_on_load: bool,
+    pub(crate) calc_mode: CalcMode,
+    pub(crate) defined_names: Vec<NameDefinition>,
+    pub(crate) vba_project: Option<VbaProject>,
+    pub(crate) write_policy: WritePolicy,
+    pub(crate) save_options: SaveOptions,
+    /// The state of each worksheet, by worksheet id.
+    pub(crate) sheets: HashMap<SheetId, SheetState>,
+    /// The named cell styles added to the workbook.
+    pub(crate) cell_styles: Vec<NamedCellStyle>,
+    /// The custom table styles registered on the workbook.
+    pub(crate) table_styles: Vec<CustomTableStyle>,
+    /// The custom theme of the workbook.
+    pub(crate) theme: Option<ThemeDefinition>,
+    /// The ids of the worksheets, in workbook order.
+    pub(crate) sheet_ids: Vec<SheetId>,
+    /// The xlsx file the workbook was created from, if any.
+    pub(crate) template: Option<Arc<Template>>,
+}
+
+/// The state of a worksheet that isn't supported directly by
+/// `rust_xlsxwriter`.
+///
+/// All of the state is kept together so that it moves with the worksheet
+/// when a standalone worksheet is added to a workbook, and is dropped when
+/// the worksheet is removed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SheetState {
+    /// A write policy that overrides the workbook write policy.
+    pub(crate) write_policy: Option<WritePolicy>,
+    /// The cells written to the worksheet.
+    pub(crate) cells: Arc<Mutex<CellStore>>,
+    /// The pivot tables added to the worksheet.
+    pub(crate) pivot_tables: Vec<PlacedPivotTable>,
+    /// The tables added to the worksheet that have column filters or a
+    /// custom style.
+    pub(crate) tables: Vec<PlacedTable>,
+    /// The ranges of all of the tables added to the worksheet.
+    pub(crate) table_ranges: Vec<CellRange>,
+    /// The SVG images inserted into the worksheet.
+    pub(crate) svg_images: Vec<Arc<SvgImage>>,
+    /// The formats based on a cell style that were written to the worksheet.
+    pub(crate) styled_formats: HashSet<FormatProperties>,
+}
+
+/// The settings of a workbook that are used to convert data when it is
+/// written to one of its worksheets.
+#[derive(Clone, Debug)]
+pub(crate) struct WriteSettings {
+    pub(crate) date_1904: bool,
+    dates_written: Arc<AtomicBool>,
+    pub(crate) date_conversion: DateConversion,
+    pub(crate) write_policy: WritePolicy,
+}
+
+impl WriteSettings {
+    /// Convert a date/time to an Excel serial date in the workbook epoch.
+    ///
+    /// Dates before 1904-01-01 can't be represented in the 1904 epoch and
+    /// raise an error.
+    pub(crate) fn date_serial(&self, datetime: &impl xlsx::IntoExcelDateTime) -> WasmResult<ExcelSerialDateTime> {
+        let serial = datetime.to_excel_serial_date();
+
+        // Times without a date are the same in both epochs.
+        if serial < 1.0 {
+            return Ok(ExcelSerialDateTime(serial));
+        }
+        if self.date_1904 && serial < EPOCH_1904_OFFSET {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::DateTimeRangeError(
+                "Dates before 1904-01-01 can't be written in the 1904 epoch".to_string(),
+            )));
+        }
+        self.dates_written.store(true, Ordering::Relaxed);
+        if !self.date_1904 {
+            return Ok(ExcelSerialDateTime(serial));
+        }
+        Ok(ExcelSerialDateTime(serial - EPOCH_1904_OFFSET))
+    }
+}
+
+impl Default for WorkbookSettings {
+    fn default() -> Self {
+        WorkbookSettings {
+            date_1904: false,
+            dates_written: Arc::new(AtomicBool::new(false)),
+            date_conversion: DateConversion::default(),
+            window_size: None,
+            tab_ratio: None,
+            // `rust_xlsxwriter` always sets `fullCalcOnLoad`.
+            full_calc_on_load: true,
+            calc_mode: CalcMode::Auto,
+            defined_names: vec![],
+            vba_project: None,
+            write_policy: WritePolicy::default(),
+            save_options: SaveOptions::default(),
+            sheets: HashMap::new(),
+            cell_styles: vec![],
+            table_styles: vec![],
+            theme: None,
+            sheet_ids: vec![],
+            template: None,
+        }
+    }
+}
+
+impl WorkbookSettings {
+    /// Get the current index of a worksheet in the workbook.
+    pub(crate) fn sheet_index(&self, id: SheetId) -> WasmResult<usize> {
+        self.sheet_ids.iter().position(|&sheet_id| sheet_id == id).ok_or_else(|| {
+            XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The worksheet has been removed from the workbook".to_string(),
+            ))
+        })
+    }
+
+    /// Get the settings used to convert data written to a worksheet, with
+    /// any worksheet specific overrides applied.
+    pub(crate) fn write_settings(&self, id: SheetId) -> WriteSettings {
+        WriteSettings {
+            date_1904: self.date_1904,
+            dates_written: Arc::clone(&self.dates_written),
+            date_conversion: self.date_conversion.clone(),
+            write_policy: self
+                .sheets
+                .get(&id)
+                .and_then(|sheet| sheet.write_policy)
+                .unwrap_or(self.write_policy),
+        }
+    }
+
+    /// Check if a date has been written in the workbook epoch.
+    pub(crate) fn has_dates(&self) -> bool {
+        self.dates_written.load(Ordering::Relaxed)
+    }
+
+    fn needs_workbook_xml_update(&self) -> bool {
+        self.date_1904
+            || self.window_size.is_some()
+            || self.tab_ratio.is_some()
+            || !self.full_calc_on_load
+            || self.calc_mode != CalcMode::Auto
+            || self.defined_names.iter().any(|name| name.sheet_id.is_some())
+    }
+
+    /// Check if the saved package has to be patched.
+    pub(crate) fn needs_package_update(&self) -> bool {
+        self.needs_workbook_xml_update()
+            || self.vba_project.is_some()
+            || self.sheets.values().any(|sheet| {
+                !sheet.pivot_tables.is_empty() || !sheet.tables.is_empty() || !sheet.svg_images.is_empty()
+            })
+            || !self.table_styles.is_empty()
+            || self.theme.is_some()
+            || self.save_options.needs_package_update()
+            || cell_style::needs_package_update(self)
+    }
+
+    /// Patch the settings into a package saved by `rust_xlsxwriter`.
+    pub(crate) fn update_package(&self, package: &mut Package) -> WasmResult<()> {
+        if self.needs_workbook_xml_update() {
+            package.edit_xml("xl/workbook.xml", |xml| {
+                self.update_workbook_xml(xml);
+                Ok(())
+            })?;
+        }
+        if let Some(vba_project) = &self.vba_project {
+            vba_project.update_package(package)?;
+        }
+        cell_style::update_package(self, package)?;
+        if !self.table_styles.is_empty() {
+            table_style::update_package(&self.table_styles, package)?;
+        }
+        if self.sheets.values().any(|sheet| !sheet.tables.is_empty()) {
+            table::update_package(self, package)?;
+        }
+        // The theme fonts are set after the styles are added.
+        if let Some(theme) = &self.theme {
+            theme.update_package(package)?;
+        }
+        if self.sheets.values().any(|sheet| !sheet.pivot_tables.is_empty()) {
+            pivot_table::update_package(self, package)?;
+        }
+        let images: Vec<_> = self.sheets.values().flat_map(|sheet| sheet.svg_images.iter().cloned()).collect();
+        if !images.is_empty() {
+            svg::update_package(&images, package)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn update_workbook_xml(&self, xml: &mut String) {
+        if self.date_1904 {
+            package::set_xml_attribute(xml, "workbookPr", "date1904", Some("1"));
+        }
+
+        if let Some((width, height)) = self.window_size {
+            // The window dimensions are stored in twips, as 32 bit values.
+            let twips = |pixels: u32| (u64::from(pixels) * 1440 / 96).min(u64::from(u32::MAX)).to_string();
+            let width = twips(width);
+            let height = twips(height);
+            package::set_xml_attribute(xml, "workbookView", "windowWidth", Some(&width));
+            package::set_xml_attribute(xml, "workbookView", "windowHeight", Some(&height));
+        }
+
+        if let Some(tab_ratio) = self.tab_ratio {
+            let tab_ratio = tab_ratio.to_string();
+            package::set_xml_attribute(xml, "workbookView", "tabRatio", Some(&tab_ratio));
+        }
+
+        match self.calc_mode {
+            CalcMode::Auto => {}
+            CalcMode::Manual => {
+                package::set_xml_attribute(xml, "calcPr", "calcMode", Some("manual"));
+                package::set_xml_attribute(xml, "calcPr", "calcOnSave", Some("0"));
+            }
+            CalcMode::AutoExceptTables => {
+                package::set_xml_attribute(xml, "calcPr", "calcMode", Some("autoNoTable"));
+            }
+        }
+
+        if !self.full_calc_on_load {
+            package::set_xml_attribute(xml, "calcPr", "fullCalcOnLoad", None);
+        }
+
+        defined_name::update_workbook_xml(self, xml);
+    }
+}
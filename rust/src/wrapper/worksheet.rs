@@ -1,45 +1,343 @@
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
 
 use rust_xlsxwriter as xlsx;
 use wasm_bindgen::prelude::*;
 
 use crate::error::XlsxError;
 use crate::wrapper::{
-    chart::Chart, datetime::ExcelDateTime, excel_data::ExcelData, format::Format,
-    header_image_position::HeaderImagePosition, image::Image, table::Table, utils, WasmResult,
+    chart::Chart, datetime::ExcelDateTime, defined_name, excel_data::ExcelData, format::{Format, FormatMergeMode},
+    header_image_position::HeaderImagePosition, ignore_error::IgnoreError, image::Image, table::{self, PlacedTable, Table, TableColumn}, utils,
+    workbook_settings::{WorkbookSettings, WriteSettings}, write_policy::WritePolicy, WasmResult,
     conditional_format::ConditionalFormatBlank, conditional_format::ConditionalFormatDataBar,
     conditional_format::ConditionalFormatFormula,
 };
 
 use super::{
+    cell_store::{self, Cell, CellRange, CellStore, CellValue},
+    cell_style,
+    datetime::ExcelSerialDateTime,
+    format_properties::FormatProperties,
+    html::{self, HtmlOptions},
+    html_table::{self, HtmlTableOptions, HtmlTableValue},
     excel_data::{JsExcelData, JsExcelDataArray, JsExcelDataMatrix},
     formula::Formula,
     note::Note,
+    pivot_table::{PivotTable, PlacedPivotTable},
     rich_string::RichString,
-    url::Url,
+    theme::{ThemeDefinition, OFFICE_THEME_COLORS},
+    url::{Url, UrlProperties},
 };
 
+/// The unique id of a worksheet, used to find the worksheet in its workbook
+/// after worksheets have been added, moved or removed.
+pub(crate) type SheetId = u64;
+
+/// The maximum number of characters in a string cell.
+const MAX_STRING_LEN: usize = 32_767;
+
+/// The maximum number of cells that a format can be applied to, the size of
+/// a full column.
+const MAX_FORMATTED_CELLS: u64 = 1_048_576;
+
+static NEXT_SHEET_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_sheet_id() -> SheetId {
+    NEXT_SHEET_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The workbook that owns a worksheet.
+///
+/// Standalone worksheets are owned by a private, single sheet, workbook until
+/// they are added to a workbook with {@link Workbook#pushWorksheet}.
+#[derive(Clone)]
+pub(crate) struct SheetTarget {
+    pub(crate) workbook: Arc<Mutex<xlsx::Workbook>>,
+    pub(crate) settings: Arc<Mutex<WorkbookSettings>>,
+    pub(crate) id: SheetId,
+    pub(crate) standalone: bool,
+}
+
 /// The `Worksheet` struct represents an Excel worksheet. It handles operations
 /// such as writing data to cells or formatting the worksheet layout.
 ///
 /// TODO: example omitted
+#[derive(Clone)]
 #[wasm_bindgen]
 pub struct Worksheet {
-    pub(crate) workbook: Arc<Mutex<xlsx::Workbook>>,
-    pub(crate) index: usize,
+    target: Arc<Mutex<SheetTarget>>,
 }
 
-impl Clone for Worksheet {
-    fn clone(&self) -> Self {
+impl Worksheet {
+    pub(crate) fn attached(
+        workbook: &Arc<Mutex<xlsx::Workbook>>,
+        settings: &Arc<Mutex<WorkbookSettings>>,
+        id: SheetId,
+    ) -> Worksheet {
         Worksheet {
-            workbook: Arc::clone(&self.workbook),
-            index: self.index,
+            target: Arc::new(Mutex::new(SheetTarget {
+                workbook: Arc::clone(workbook),
+                settings: Arc::clone(settings),
+                id,
+                standalone: false,
+            })),
+        }
+    }
+
+    pub(crate) fn target(&self) -> SheetTarget {
+        self.target.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_target(&self, target: SheetTarget) {
+        *self.target.lock().unwrap() = target;
+    }
+
+    /// Get the workbook that owns the worksheet and the current index of the
+    /// worksheet in it.
+    fn sheet_ref(&self) -> WasmResult<(Arc<Mutex<xlsx::Workbook>>, usize)> {
+        let target = self.target();
+        let index = target.settings.lock().unwrap().sheet_index(target.id)?;
+        Ok((target.workbook, index))
+    }
+
+    /// Get the settings of the parent workbook used to convert written data,
+    /// with any worksheet specific overrides applied.
+    fn settings(&self) -> WriteSettings {
+        let target = self.target();
+        let settings = target.settings.lock().unwrap();
+        settings.write_settings(target.id)
+    }
+
+    /// Get the worksheet name, or an error if the worksheet has been removed.
+    pub(crate) fn sheet_name(&self) -> WasmResult<String> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index)?;
+        Ok(sheet.name().to_string())
+    }
+
+    /// Get the `rust_xlsxwriter` format of a format written to the worksheet,
+    /// and keep the formats that are based on a cell style so that the
+    /// styles can be added when the workbook is saved.
+    ///
+    /// Formats based on a cell style are written with a marker font, see
+    /// `cell_style::marker_font_name()`.
+    fn cell_format(&self, format: &FormatProperties) -> xlsx::Format {
+        if format.cell_style.is_none() {
+            return format.to_format();
+        }
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        let styled_formats = &mut settings.sheets.entry(target.id).or_default().styled_formats;
+        if !styled_formats.contains(format) {
+            styled_formats.insert(format.clone());
+        }
+        format.to_format().set_font_name(cell_style::marker_font_name(format))
+    }
+
+    /// Record cells written to the worksheet so that they can be read back.
+    fn record(&self, f: impl FnOnce(&mut CellStore)) {
+        f(&mut self.cell_store().lock().unwrap());
+    }
+
+    /// Get the record of the cells written to the worksheet.
+    fn cell_store(&self) -> Arc<Mutex<CellStore>> {
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        settings.sheets.entry(target.id).or_default().cells.clone()
+    }
+
+    /// Add a table to a worksheet, with the column filters and total row
+    /// formats that `rust_xlsxwriter` doesn't support. The `headers`
+    /// override the headers of the table columns, and the `cells` are used
+    /// to read the headers that aren't set and to apply the column filters.
+    fn insert_table(
+        &self,
+        sheet: &mut xlsx::Worksheet,
+        range: CellRange,
+        table: &Table,
+        headers: Option<&[String]>,
+        cells: &CellStore,
+    ) -> WasmResult<()> {
+        self.check_table(range, table)?;
+        let has_header_row = table.inner.has_header_row();
+        let has_total_row = table.inner.has_total_row();
+
+        let width = usize::from(range.last_col() - range.first_col()) + 1;
+        let mut columns: Vec<TableColumn> = (0..width)
+            .map(|index| table.columns.get(index).cloned().unwrap_or_default())
+            .collect();
+        if let Some(headers) = headers {
+            for (column, header) in columns.iter_mut().zip(headers) {
+                *column = column.set_header(header);
+            }
+        }
+        let filters: Vec<_> = (0..)
+            .zip(&columns)
+            .filter_map(|(offset, column)| Some((offset, column.filter.clone()?)))
+            .collect();
+
+        let inner_columns: Vec<_> = columns.iter().map(|column| column.inner.clone()).collect();
+        let inner = table.inner.clone().set_columns(&inner_columns);
+        let _ = sheet.add_table(range.first_row(), range.first_col(), range.last_row(), range.last_col(), &inner)?;
+        let target = self.target();
+        target
+            .settings
+            .lock()
+            .unwrap()
+            .sheets
+            .entry(target.id)
+            .or_default()
+            .table_ranges
+            .push(range);
+
+        if has_total_row {
+            for (col, column) in (range.first_col()..).zip(&columns) {
+                let Some(format) = &column.total_format else {
+                    continue;
+                };
+                if let Some(label) = &column.total_label {
+                    let _ = sheet.write_string_with_format(range.last_row(), col, label, format)?;
+                    continue;
+                }
+                let Some(function) = &column.total_function else {
+                    continue;
+                };
+                // Headers that aren't set are read from the header row like
+                // `rust_xlsxwriter` does.
+                let header = match &column.header {
+                    Some(header) => header.clone(),
+                    None if has_header_row => match cells.value(range.first_row(), col) {
+                        Some((CellValue::String(header), _)) => header.clone(),
+                        _ => format!("Column{}", col - range.first_col() + 1),
+                    },
+                    None => format!("Column{}", col - range.first_col() + 1),
+                };
+                if let Some(formula) = table::total_formula(function, &header) {
+                    let _ = sheet.write_formula_with_format(range.last_row(), col, formula, format)?;
+                }
+            }
+        }
+
+        if filters.is_empty() && table.custom_style.is_none() {
+            return Ok(());
+        }
+        if !filters.is_empty() {
+            let last_data_row = range.last_row() - u32::from(has_total_row);
+            for row in range.first_row() + 1..=last_data_row {
+                let shown = filters.iter().all(|(offset, filter)| {
+                    filter.matches(cells.value(row, range.first_col() + offset).map(|(value, _)| value))
+                });
+                if !shown {
+                    let _ = sheet.set_row_hidden(row)?;
+                }
+            }
+        }
+
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        settings
+            .sheets
+            .entry(target.id)
+            .or_default()
+            .tables
+            .push(PlacedTable {
+                range,
+                filters,
+                style: table.custom_style.clone(),
+            });
+        Ok(())
+    }
+
+    /// Check the properties of a table that `rust_xlsxwriter` only checks
+    /// after it has written the headers, and the ones it doesn't support,
+    /// so that nothing is written for a table that can't be added.
+    fn check_table(&self, range: CellRange, table: &Table) -> WasmResult<()> {
+        if table.columns.iter().any(|column| column.filter.is_some())
+            && !(table.inner.has_header_row() && table.autofilter)
+        {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "Table column filters need a header row and autofilter".to_string(),
+            )));
+        }
+        if let Some(name) = &table.name {
+            xlsx::utility::check_name(name)?;
+        }
+
+        let target = self.target();
+        let settings = target.settings.lock().unwrap();
+        let previous = settings
+            .sheets
+            .get(&target.id)
+            .and_then(|sheet| sheet.table_ranges.iter().find(|previous| previous.overlaps(&range)));
+        match previous {
+            Some(previous) => Err(XlsxError::Xlsx(xlsx::XlsxError::TableRangeOverlaps(
+                range.to_error_string(),
+                previous.to_error_string(),
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Register an inserted SVG image so that the SVG is added to the
+    /// drawing when the workbook is saved.
+    fn register_svg_image(&self, image: &Image) {
+        let Some(svg) = &image.svg else {
+            return;
+        };
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        let images = &mut settings.sheets.entry(target.id).or_default().svg_images;
+        if !images.iter().any(|registered| Arc::ptr_eq(registered, svg)) {
+            images.push(Arc::clone(svg));
         }
     }
 }
 
 #[wasm_bindgen]
 impl Worksheet {
+    /// Create a new standalone Worksheet object.
+    ///
+    /// Worksheets are usually created with {@link Workbook#addWorksheet}. A
+    /// standalone worksheet can be created, and have data written to it,
+    /// independently of a workbook and then be added to a workbook with
+    /// {@link Workbook#pushWorksheet}. After that the worksheet object refers
+    /// to the worksheet in the workbook.
+    ///
+    /// Data written to a standalone worksheet is converted using the default
+    /// workbook settings, such as the 1900 epoch and UTC date conversion,
+    /// rather than the settings of the workbook it is later added to. A
+    /// worksheet with dates can't be added to a workbook that uses the 1904
+    /// epoch.
+    ///
+    /// If no name is set with {@link Worksheet#setName} the worksheet is given
+    /// a default `SheetN` name when it is added to the workbook.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Worksheet {
+        let id = next_sheet_id();
+        let mut workbook = xlsx::Workbook::new();
+        // Push directly, rather than with `push_worksheet()`, so the sheet
+        // keeps an empty name until it is added to the real workbook.
+        workbook.worksheets_mut().push(xlsx::Worksheet::new());
+        let settings = WorkbookSettings {
+            sheet_ids: vec![id],
+            ..WorkbookSettings::default()
+        };
+        Worksheet {
+            target: Arc::new(Mutex::new(SheetTarget {
+                workbook: Arc::new(Mutex::new(workbook)),
+                settings: Arc::new(Mutex::new(settings)),
+                id,
+                standalone: true,
+            })),
+        }
+    }
+
     /// Get the worksheet name.
     ///
     /// Get the worksheet name that was set automatically such as Sheet1,
@@ -49,12 +347,13 @@ impl Worksheet {
     /// The worksheet name can be used to get a reference to a worksheet object
     /// using the {@link Workbook#worksheetFromName} method.
     ///
+    /// A worksheet that has been removed from its workbook with
+    /// {@link Workbook#removeWorksheet} has an empty name.
+    ///
     /// TODO: example omitted
     #[wasm_bindgen]
     pub fn name(&self) -> String {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        sheet.name().to_string()
+        self.sheet_name().unwrap_or_default()
     }
 
     /// Set the worksheet name.
@@ -99,12 +398,175 @@ impl Worksheet {
     ///
     #[wasm_bindgen(js_name = "setName", skip_jsdoc)]
     pub fn set_name(&self, name: &str) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_name(name)?;
         Ok(self.clone())
     }
 
+    /// Set the worksheet name used in VBA macros.
+    ///
+    /// VBA code refers to worksheets by their code name, which defaults to
+    /// `Sheet1`, `Sheet2`, etc., based on the worksheet position and not on the
+    /// worksheet name. The `setVbaName()` method can be used to set a
+    /// different code name to match the VBA project that was added with
+    /// {@link Workbook#addVbaProject}.
+    ///
+    /// @param {string} name - The vba name. It must start with a letter and
+    ///   contain only letters, digits and underscores, and be at most 31
+    ///   characters long.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::VbaNameError`] - The name doesn't follow the Excel
+    ///   rules shown above.
+    #[wasm_bindgen(js_name = "setVbaName", skip_jsdoc)]
+    pub fn set_vba_name(&self, name: &str) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        sheet.set_vba_name(name)?;
+        Ok(self.clone())
+    }
+
+    /// Ignore Excel errors/warnings in a worksheet cell.
+    ///
+    /// Excel flags cells that look like errors, such as numbers stored as
+    /// text, with a small green triangle in the top left corner of the cell.
+    /// These warnings are useful in interactive use but are often just noise
+    /// in generated files, for example for account codes or ZIP codes written
+    /// as strings.
+    ///
+    /// The `ignoreError()` method turns off a type of warning, see
+    /// {@link IgnoreError}, for a cell. Excel only allows one ignore rule per
+    /// cell. Repeating a cell or range raises an error, but overlapping
+    /// ranges aren't checked and Excel uses only one of their rules.
+    ///
+    /// @param {number} row - The zero indexed row number.
+    /// @param {number} col - The zero indexed column number.
+    /// @param {IgnoreError} error_type - The type of error to ignore.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds Excel's
+    ///   worksheet limits.
+    /// - [`XlsxError::ParameterError`] - An error was already ignored for the
+    ///   same cell.
+    #[wasm_bindgen(js_name = "ignoreError", skip_jsdoc)]
+    pub fn ignore_error(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        error_type: IgnoreError,
+    ) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.ignore_error(row, col, error_type.into())?;
+        Ok(self.clone())
+    }
+
+    /// Ignore Excel errors/warnings in a range of cells.
+    ///
+    /// The `ignoreErrorRange()` method turns off a type of warning for a range
+    /// of cells. See {@link Worksheet#ignoreError} for more details.
+    ///
+    /// @param {number} first_row - The first row of the range.
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    /// @param {IgnoreError} error_type - The type of error to ignore.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds Excel's
+    ///   worksheet limits.
+    /// - [`XlsxError::RowColumnOrderError`] - First row or column is larger
+    ///   than the last row or column.
+    /// - [`XlsxError::ParameterError`] - An error was already ignored for the
+    ///   same range.
+    #[wasm_bindgen(js_name = "ignoreErrorRange", skip_jsdoc)]
+    pub fn ignore_error_range(
+        &self,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+        error_type: IgnoreError,
+    ) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.ignore_error_range(first_row, first_col, last_row, last_col, error_type.into())?;
+        Ok(self.clone())
+    }
+
+    /// Create a defined name that is local to the worksheet.
+    ///
+    /// The `defineName()` method creates a worksheet local (sheet scope)
+    /// defined name. It is equivalent to calling {@link Workbook#defineName}
+    /// with a `"sheetname!name"` style name, with the sheet name quoted as
+    /// required by Excel.
+    ///
+    /// The name stays local to the worksheet if the worksheet is renamed with
+    /// {@link Worksheet#setName} or moved.
+    ///
+    /// @param {string} name - The variable name to define.
+    /// @param {string} formula - The formula, value or range that the name defines.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The name is invalid or already
+    ///   defined in the worksheet. See {@link Workbook#defineName}.
+    #[wasm_bindgen(js_name = "defineName", skip_jsdoc)]
+    pub fn define_name(&self, name: &str, formula: &str) -> WasmResult<Worksheet> {
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        settings.sheet_index(target.id)?;
+        defined_name::define_local_name(&mut settings, target.id, name, formula)?;
+        Ok(self.clone())
+    }
+
+    /// Create a worksheet local defined name that refers to a range of cells
+    /// in the worksheet.
+    ///
+    /// The `defineNameForRange()` method is a helper for
+    /// {@link Worksheet#defineName} that builds an absolute range reference,
+    /// such as `='Sheet 1'!$A$1:$B$5`, from zero indexed row and column
+    /// numbers.
+    ///
+    /// @param {string} name - The variable name to define.
+    /// @param {number} first_row - The first row of the range.
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnOrderError`] - First row or column is larger
+    ///   than the last row or column.
+    /// - [`XlsxError::ParameterError`] - The name is invalid or already
+    ///   defined in the worksheet. See {@link Workbook#defineName}.
+    #[wasm_bindgen(js_name = "defineNameForRange", skip_jsdoc)]
+    pub fn define_name_for_range(
+        &self,
+        name: &str,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+    ) -> WasmResult<Worksheet> {
+        let formula =
+            defined_name::range_formula(&self.sheet_name()?, first_row, first_col, last_row, last_col)?;
+        self.define_name(name, &formula)
+    }
+
     /// Freeze panes in a worksheet.
     ///
     /// The `set_freeze_panes()` method can be used to divide a worksheet into
@@ -131,8 +593,9 @@ impl Worksheet {
     ///
     #[wasm_bindgen(js_name = "setFreezePanes", skip_jsdoc)]
     pub fn set_freeze_panes(&self, row: xlsx::RowNum, col: xlsx::ColNum) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_freeze_panes(row, col)?;
         Ok(self.clone())
     }
@@ -161,40 +624,45 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_freeze_panes_top_cell(row, col)?;
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setHeader", skip_jsdoc)]
-    pub fn set_header(&self, header: &str) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_header(&self, header: &str) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_header(header);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setHeaderImage", skip_jsdoc)]
     pub fn set_header_image(&self, image: &Image, position: HeaderImagePosition) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_header_image(&image.lock(), position.into())?;
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setFooter", skip_jsdoc)]
-    pub fn set_footer(&self, footer: &str) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_footer(&self, footer: &str) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_footer(footer);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setFooterImage", skip_jsdoc)]
     pub fn set_footer_image(&self, image: &Image, position: HeaderImagePosition) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_footer_image(&image.lock(), position.into())?;
         Ok(self.clone())
     }
@@ -210,11 +678,12 @@ impl Worksheet {
     ///
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "setActive", skip_jsdoc)]
-    pub fn set_active(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_active(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_active(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     /// Set the width for a worksheet column.
@@ -245,9 +714,11 @@ impl Worksheet {
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "setColumnWidth", skip_jsdoc)]
     pub fn set_column_width(&self, col: xlsx::ColNum, width: f64) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_column_width(col, width)?;
+        self.record(|cells| cells.set_column_width(col, cell_store::column_width_pixels(width)));
         Ok(self.clone())
     }
 
@@ -273,9 +744,11 @@ impl Worksheet {
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "setColumnWidthPixels", skip_jsdoc)]
     pub fn set_column_width_pixels(&self, col: xlsx::ColNum, width: u32) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_column_width_pixels(col, width)?;
+        self.record(|cells| cells.set_column_width(col, width));
         Ok(self.clone())
     }
 
@@ -304,12 +777,35 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         width: f64,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_column_range_width(first_col, last_col, width)?;
+        self.record(|cells| {
+            for col in first_col..=last_col {
+                cells.set_column_width(col, cell_store::column_width_pixels(width));
+            }
+        });
         Ok(self.clone())
     }
 
+    /// Set the write policy for the worksheet.
+    ///
+    /// Override the workbook {@link WritePolicy}, set with
+    /// {@link Workbook#setWritePolicy}, for this worksheet. The policy
+    /// controls how `BigInt`, `null`/`undefined` and non-finite numbers are
+    /// written by {@link Worksheet#write} and the other generic write methods.
+    ///
+    /// @param {WritePolicy} policy - The write policy.
+    /// @returns {Worksheet} - The worksheet object.
+    #[wasm_bindgen(js_name = "setWritePolicy", skip_jsdoc)]
+    pub fn set_write_policy(&self, policy: &WritePolicy) -> Worksheet {
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        settings.sheets.entry(target.id).or_default().write_policy = Some(*policy);
+        self.clone()
+    }
+
     /// Write generic data to a cell.
     ///
     /// The `write()` method writes data of type {@link ExcelData} to a worksheet.
@@ -322,12 +818,19 @@ impl Worksheet {
     /// - {Date}
     /// - {@link Formula}
     /// - {@link Url}
+    /// - {bigint}
     ///
-    /// TODO: support bigint
+    /// See {@link Worksheet#setWritePolicy} for how `bigint`, `null` and
+    /// non-finite numbers are handled.
+    ///
+    /// Booleans are written as `TRUE`/`FALSE` by default. If `checkbox` is
+    /// true they are displayed as checkboxes instead, like
+    /// {@link Worksheet#insertCheckbox}.
     ///
     /// @param {number} row - The zero indexed row number.
     /// @param {number} col - The zero indexed column number.
     /// @param {ExcelData} data - Data to write.
+    /// @param {boolean} [checkbox] - Write booleans as checkboxes.
     /// @returns {Worksheet} - The worksheet object.
     ///
     /// # Errors
@@ -342,11 +845,18 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
         data: &JsExcelData,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let data: ExcelData = data.try_into()?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut data = ExcelData::from_js(data, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            data = data.into_checkbox();
+        }
+        let value = CellValue::from_data(&data, false);
         let _ = sheet.write(row, col, data)?;
+        self.record(|cells| cells.insert(row, col, value, None));
         Ok(self.clone())
     }
 
@@ -361,6 +871,7 @@ impl Worksheet {
     /// @param {number} col - The zero indexed column number.
     /// @param {ExcelData} data - Data to write.
     /// @param {Format} format - The {@link Format} property for the cell.
+    /// @param {boolean} [checkbox] - Write booleans as checkboxes.
     /// @returns {Worksheet} - The worksheet object.
     ///
     /// # Errors
@@ -376,11 +887,18 @@ impl Worksheet {
         col: xlsx::ColNum,
         data: &JsExcelData,
         format: &Format,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let data: ExcelData = data.try_into()?;
-        let _ = sheet.write_with_format(row, col, data, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut data = ExcelData::from_js(data, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            data = data.into_checkbox();
+        }
+        let value = CellValue::from_data(&data, true);
+        let _ = sheet.write_with_format(row, col, data, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, value, Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -415,9 +933,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_blank(row, col, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_blank(row, col, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Blank), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -450,9 +970,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         string: &str,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_string(row, col, string)?;
+        self.record(|cells| cells.insert(row, col, CellValue::string(string, false), None));
         Ok(self.clone())
     }
 
@@ -488,9 +1010,11 @@ impl Worksheet {
         string: &str,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_string_with_format(row, col, string, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_string_with_format(row, col, string, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, CellValue::string(string, true), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -537,9 +1061,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         number: f64,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_number(row, col, number)?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Number(number)), None));
         Ok(self.clone())
     }
 
@@ -589,9 +1115,11 @@ impl Worksheet {
         number: f64,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_number_with_format(row, col, number, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_number_with_format(row, col, number, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Number(number)), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -617,9 +1145,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         boolean: bool,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_boolean(row, col, boolean)?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Bool(boolean)), None));
         Ok(self.clone())
     }
 
@@ -648,9 +1178,78 @@ impl Worksheet {
         boolean: bool,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_boolean_with_format(row, col, boolean, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_boolean_with_format(row, col, boolean, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Bool(boolean)), Some(&format.lock())));
+        Ok(self.clone())
+    }
+
+    /// Insert a boolean checkbox in a worksheet cell.
+    ///
+    /// Checkboxes are a recent feature in Excel that display a boolean value
+    /// as a clickable checkbox in a cell. The underlying value is still an
+    /// Excel `TRUE`/`FALSE` boolean value that can be used in formulas and in
+    /// references.
+    ///
+    /// Older versions of Excel, and other spreadsheet applications, display
+    /// the cell as a plain `TRUE`/`FALSE` value.
+    ///
+    /// @param {number} row - The zero indexed row number.
+    /// @param {number} col - The zero indexed column number.
+    /// @param {boolean} boolean - The boolean value to display as a checkbox.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds Excel's
+    ///   worksheet limits.
+    #[wasm_bindgen(js_name = "insertCheckbox", skip_jsdoc)]
+    pub fn insert_checkbox(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        boolean: bool,
+    ) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.insert_checkbox(row, col, boolean)?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Bool(boolean)), None));
+        Ok(self.clone())
+    }
+
+    /// Insert a boolean checkbox in a worksheet cell with a cell format.
+    ///
+    /// This method allows you to insert a boolean checkbox in a worksheet cell
+    /// with a background color or other cell format property.
+    ///
+    /// See {@link Worksheet#insertCheckbox} for more details.
+    ///
+    /// @param {number} row - The zero indexed row number.
+    /// @param {number} col - The zero indexed column number.
+    /// @param {boolean} boolean - The boolean value to display as a checkbox.
+    /// @param {Format} format - The {@link Format} property for the cell.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds Excel's
+    ///   worksheet limits.
+    #[wasm_bindgen(js_name = "insertCheckboxWithFormat", skip_jsdoc)]
+    pub fn insert_checkbox_with_format(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        boolean: bool,
+        format: &Format,
+    ) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.insert_checkbox_with_format(row, col, boolean, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Bool(boolean)), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -685,17 +1284,20 @@ impl Worksheet {
         col: xlsx::ColNum,
         datetime: &JsValue,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index)?;
-        if let Some(dt) = utils::datetime_of_jsval(datetime.clone()) {
-            let _ = sheet.write_datetime(row, col, dt)?;
-            Ok(self.clone())
+        let settings = self.settings();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index)?;
+        let serial = if let Some(dt) = utils::datetime_of_jsval(datetime.clone(), &settings.date_conversion) {
+            settings.date_serial(&dt)?
         } else if let Some(dt) = utils::excel_datetime_of_jsval(datetime) {
-            let _ = sheet.write_datetime(row, col, dt)?;
-            Ok(self.clone())
+            settings.date_serial(&dt)?
         } else {
-            Err(XlsxError::InvalidDate)
-        }
+            return Err(XlsxError::InvalidDate);
+        };
+        let _ = sheet.write_datetime(row, col, serial)?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::DateTime(serial.0)), None));
+        Ok(self.clone())
     }
 
     /// Write a formatted date and/or time to a worksheet cell.
@@ -725,17 +1327,20 @@ impl Worksheet {
         datetime: &JsValue,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index)?;
-        if let Some(dt) = utils::datetime_of_jsval(datetime.clone()) {
-            let _ = sheet.write_datetime_with_format(row, col, dt, &format.lock())?;
-            Ok(self.clone())
+        let settings = self.settings();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index)?;
+        let serial = if let Some(dt) = utils::datetime_of_jsval(datetime.clone(), &settings.date_conversion) {
+            settings.date_serial(&dt)?
         } else if let Some(dt) = utils::excel_datetime_of_jsval(datetime) {
-            let _ = sheet.write_datetime_with_format(row, col, dt, &format.lock())?;
-            Ok(self.clone())
+            settings.date_serial(&dt)?
         } else {
-            Err(XlsxError::InvalidDate)
-        }
+            return Err(XlsxError::InvalidDate);
+        };
+        let _ = sheet.write_datetime_with_format(row, col, serial, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::DateTime(serial.0)), Some(&format.lock())));
+        Ok(self.clone())
     }
 
     /// Write a formatted date to a worksheet cell.
@@ -760,9 +1365,13 @@ impl Worksheet {
         date: &ExcelDateTime,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index)?;
-        let _ = sheet.write_date_with_format(row, col, &date.inner.lock().unwrap().clone(), &format.lock())?;
+        let settings = self.settings();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index)?;
+        let date = settings.date_serial(&*date.inner.lock().unwrap())?;
+        let _ = sheet.write_datetime_with_format(row, col, date, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::DateTime(date.0)), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -773,9 +1382,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         formula: &Formula,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_formula(row, col, &*formula.lock())?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::formula(formula)), None));
         Ok(self.clone())
     }
 
@@ -787,9 +1398,12 @@ impl Worksheet {
         formula: &Formula,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_formula_with_format(row, col, &*formula.lock(), &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_formula_with_format(row, col, &*formula.lock(), &self.cell_format(&format.lock()))?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(row, col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -800,9 +1414,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         link: &Url,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_url(row, col, &*link.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_url(row, col, link.to_url())?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Url(link.lock().clone())), None));
         Ok(self.clone())
     }
 
@@ -814,9 +1430,12 @@ impl Worksheet {
         link: &Url,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_url_with_format(row, col, &*link.lock(), &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_url_with_format(row, col, link.to_url(), &self.cell_format(&format.lock()))?;
+        let value = CellValue::Url(link.lock().clone());
+        self.record(|cells| cells.insert(row, col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -828,9 +1447,15 @@ impl Worksheet {
         link: &Url,
         text: &str,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_url_with_text(row, col, &*link.lock(), text)?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.write_url_with_text(row, col, link.to_url(), text)?;
+        let value = CellValue::Url(UrlProperties {
+            text: text.to_string(),
+            ..link.lock().clone()
+        });
+        self.record(|cells| cells.insert(row, col, Some(value), None));
         Ok(self.clone())
     }
 
@@ -844,16 +1469,18 @@ impl Worksheet {
         tip: &str,
         format: Option<Format>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.write_url_with_options(
-            row,
-            col,
-            &*link.lock(),
-            text,
-            tip,
-            format.map(|f| f.lock().clone()).as_ref(),
-        )?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let properties = format.map(|format| format.lock().clone());
+        let format = properties.as_ref().map(FormatProperties::to_format);
+        let _ = sheet.write_url_with_options(row, col, link.to_url(), text, tip, format.as_ref())?;
+        let value = CellValue::Url(UrlProperties {
+            text: text.to_string(),
+            tip: tip.to_string(),
+            ..link.lock().clone()
+        });
+        self.record(|cells| cells.insert(row, col, Some(value), properties.as_ref()));
         Ok(self.clone())
     }
 
@@ -864,11 +1491,14 @@ impl Worksheet {
         col: xlsx::ColNum,
         rich_string: &RichString,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let rich_string = rich_string.lock();
-        let rich_string: Vec<_> = rich_string.iter().map(|(f, s)| (f, s.as_str())).collect();
-        let _ = sheet.write_rich_string(row, col, &rich_string)?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let parts = rich_string.formats();
+        let parts: Vec<_> = parts.iter().map(|(f, s)| (f, s.as_str())).collect();
+        let _ = sheet.write_rich_string(row, col, &parts)?;
+        let value = CellValue::RichString(rich_string.lock().clone());
+        self.record(|cells| cells.insert(row, col, Some(value), None));
         Ok(self.clone())
     }
 
@@ -880,11 +1510,14 @@ impl Worksheet {
         rich_string: &RichString,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let rich_string = rich_string.lock();
-        let rich_string: Vec<_> = rich_string.iter().map(|(f, s)| (f, s.as_str())).collect();
-        let _ = sheet.write_rich_string_with_format(row, col, &rich_string, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let parts = rich_string.formats();
+        let parts: Vec<_> = parts.iter().map(|(f, s)| (f, s.as_str())).collect();
+        let _ = sheet.write_rich_string_with_format(row, col, &parts, &self.cell_format(&format.lock()))?;
+        let value = CellValue::RichString(rich_string.lock().clone());
+        self.record(|cells| cells.insert(row, col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -894,11 +1527,18 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
         values: &JsExcelDataArray,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<ExcelData> = values.try_into()?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::vec_from_js(values, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = values.into_iter().map(ExcelData::into_checkbox).collect();
+        }
+        let recorded = CellValue::from_slice(&values, false);
         let _ = sheet.write_column(row, col, values)?;
+        self.record(|cells| cells.insert_column(row, col, recorded, None));
         Ok(self.clone())
     }
 
@@ -909,11 +1549,18 @@ impl Worksheet {
         col: xlsx::ColNum,
         values: &JsExcelDataArray,
         format: &Format,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<ExcelData> = values.try_into()?;
-        let _ = sheet.write_column_with_format(row, col, values, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::vec_from_js(values, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = values.into_iter().map(ExcelData::into_checkbox).collect();
+        }
+        let recorded = CellValue::from_slice(&values, true);
+        let _ = sheet.write_column_with_format(row, col, values, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert_column(row, col, recorded, Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -923,11 +1570,22 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
         data: &JsExcelDataMatrix,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<Vec<ExcelData>> = data.try_into()?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::matrix_from_js(data, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = ExcelData::matrix_into_checkbox(values);
+        }
+        let recorded: Vec<_> = values.iter().map(|column| CellValue::from_slice(column, false)).collect();
         let _ = sheet.write_column_matrix(row, col, values)?;
+        self.record(|cells| {
+            for (column, recorded) in (col..).zip(recorded) {
+                cells.insert_column(row, column, recorded, None);
+            }
+        });
         Ok(self.clone())
     }
 
@@ -937,11 +1595,18 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
         values: &JsExcelDataArray,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<ExcelData> = values.try_into()?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::vec_from_js(values, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = values.into_iter().map(ExcelData::into_checkbox).collect();
+        }
+        let recorded = CellValue::from_slice(&values, false);
         let _ = sheet.write_row(row, col, values)?;
+        self.record(|cells| cells.insert_row(row, col, recorded, None));
         Ok(self.clone())
     }
 
@@ -952,11 +1617,18 @@ impl Worksheet {
         col: xlsx::ColNum,
         values: &JsExcelDataArray,
         format: &Format,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<ExcelData> = values.try_into()?;
-        let _ = sheet.write_row_with_format(row, col, values, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::vec_from_js(values, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = values.into_iter().map(ExcelData::into_checkbox).collect();
+        }
+        let recorded = CellValue::from_slice(&values, true);
+        let _ = sheet.write_row_with_format(row, col, values, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert_row(row, col, recorded, Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -966,11 +1638,22 @@ impl Worksheet {
         row: xlsx::RowNum,
         col: xlsx::ColNum,
         data: &JsExcelDataMatrix,
+        checkbox: Option<bool>,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let values: Vec<Vec<ExcelData>> = data.try_into()?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let mut values = ExcelData::matrix_from_js(data, &self.settings())?;
+        if checkbox.unwrap_or_default() {
+            values = ExcelData::matrix_into_checkbox(values);
+        }
+        let recorded: Vec<_> = values.iter().map(|row| CellValue::from_slice(row, false)).collect();
         let _ = sheet.write_row_matrix(row, col, values)?;
+        self.record(|cells| {
+            for (row, recorded) in (row..).zip(recorded) {
+                cells.insert_row(row, col, recorded, None);
+            }
+        });
         Ok(self.clone())
     }
 
@@ -983,8 +1666,9 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         formula: &Formula,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_array_formula(
             first_row,
             first_col,
@@ -992,6 +1676,8 @@ impl Worksheet {
             last_col,
             &*formula.lock(),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), None));
         Ok(self.clone())
     }
 
@@ -1005,16 +1691,19 @@ impl Worksheet {
         formula: &Formula,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_array_formula_with_format(
             first_row,
             first_col,
             last_row,
             last_col,
             &*formula.lock(),
-            &format.lock(),
+            &self.cell_format(&format.lock()),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -1027,8 +1716,9 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         formula: &Formula,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_dynamic_array_formula(
             first_row,
             first_col,
@@ -1036,6 +1726,8 @@ impl Worksheet {
             last_col,
             &*formula.lock(),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), None));
         Ok(self.clone())
     }
 
@@ -1049,16 +1741,19 @@ impl Worksheet {
         formula: &Formula,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_dynamic_array_formula_with_format(
             first_row,
             first_col,
             last_row,
             last_col,
             &*formula.lock(),
-            &format.lock(),
+            &self.cell_format(&format.lock()),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -1071,8 +1766,9 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         formula: &Formula,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_dynamic_array_formula(
             first_row,
             first_col,
@@ -1080,6 +1776,8 @@ impl Worksheet {
             last_col,
             &*formula.lock(),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), None));
         Ok(self.clone())
     }
 
@@ -1093,16 +1791,19 @@ impl Worksheet {
         formula: &Formula,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.write_dynamic_array_formula_with_format(
             first_row,
             first_col,
             last_row,
             last_col,
             &*formula.lock(),
-            &format.lock(),
+            &self.cell_format(&format.lock()),
         )?;
+        let value = CellValue::formula(formula);
+        self.record(|cells| cells.insert(first_row, first_col, Some(value), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -1115,9 +1816,171 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         table: &Table,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.add_table(first_row, first_col, last_row, last_col, &table.inner);
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let range = CellRange::new(first_row, first_col, last_row, last_col);
+        let store = self.cell_store();
+        self.insert_table(sheet, range, table, None, &store.lock().unwrap())?;
+        Ok(self.clone())
+    }
+
+    /// Write data to a worksheet and add a table over it.
+    ///
+    /// The `writeTable()` method writes a header row and rows of data, and
+    /// adds a {@link Table} that covers them, so the table range doesn't have
+    /// to be calculated. The range includes the header row and total row, if
+    /// they are turned on for the table.
+    ///
+    /// The `headers` set the column names and override any headers set with
+    /// {@link TableColumn#setHeader}. The other properties of the table
+    /// columns, such as formats, formulas and total row functions, are
+    /// applied to the matching columns. Cells in columns with a formula are
+    /// replaced by the formula. If there are no rows of data, a blank row is
+    /// added since Excel tables need at least one data row.
+    ///
+    /// @param {number} row - The zero indexed row of the top left cell.
+    /// @param {number} col - The zero indexed column of the top left cell.
+    /// @param {string[]} headers - The column names.
+    /// @param {ExcelData[][]} rows - The rows of data. Rows can be shorter
+    ///   than the headers.
+    /// @param {Table} table - The table properties.
+    /// @returns {CellRange} - The range of the table.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - The table exceeds Excel's
+    ///   worksheet limits.
+    /// - [`XlsxError::ParameterError`] - There are no headers, a row is
+    ///   longer than the headers, or the table has column filters without a
+    ///   header row and autofilter.
+    /// - [`XlsxError::TableError`] - The headers aren't unique.
+    /// - [`XlsxError::TableRangeOverlaps`] - The table overlaps another
+    ///   table.
+    ///
+    /// Nothing is written to the worksheet if there is an error.
+    #[wasm_bindgen(js_name = "writeTable", skip_jsdoc)]
+    pub fn write_table(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        headers: Vec<String>,
+        rows: &JsExcelDataMatrix,
+        table: &Table,
+    ) -> WasmResult<CellRange> {
+        let values = ExcelData::matrix_from_js(rows, &self.settings())?;
+        if headers.is_empty() {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(
+                "The table needs at least one header".to_string(),
+            )));
+        }
+        if let Some(index) = values.iter().position(|values| values.len() > headers.len()) {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "Table row {index} has more values than the {} headers",
+                headers.len()
+            ))));
+        }
+
+        // Check the table before any data is written.
+        let mut seen = std::collections::HashSet::new();
+        if let Some(header) = headers.iter().find(|header| !seen.insert(header.to_lowercase())) {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::TableError(format!(
+                "Table header '{header}' isn't unique"
+            ))));
+        }
+
+        let has_header_row = table.inner.has_header_row();
+        let has_total_row = table.inner.has_total_row();
+        let first_data_row = row.checked_add(u32::from(has_header_row));
+        let data_rows = values.len().max(1) as u32;
+        let last_row = first_data_row
+            .and_then(|first| first.checked_add(data_rows - 1 + u32::from(has_total_row)))
+            .filter(|&last| last < 1_048_576);
+        let last_col = u16::try_from(headers.len() - 1)
+            .ok()
+            .and_then(|offset| col.checked_add(offset))
+            .filter(|&last| last < 16_384);
+        let (Some(first_data_row), Some(last_row), Some(last_col)) = (first_data_row, last_row, last_col) else {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError));
+        };
+
+        let range = CellRange::new(row, col, last_row, last_col);
+        self.check_table(range, table)?;
+
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let recorded: Vec<_> = values.iter().map(|row| CellValue::from_slice(row, false)).collect();
+        let _ = sheet.write_row_matrix(first_data_row, col, values)?;
+        self.record(|cells| {
+            for (row, recorded) in (first_data_row..).zip(recorded) {
+                cells.insert_row(row, col, recorded, None);
+            }
+        });
+
+        let store = self.cell_store();
+        self.insert_table(sheet, range, table, Some(&headers), &store.lock().unwrap())?;
+        if has_header_row {
+            let headers = headers.iter().map(|header| Some(CellValue::String(header.clone()))).collect();
+            self.record(|cells| cells.insert_row(row, col, headers, None));
+        }
+        Ok(range)
+    }
+
+    /// Add a pivot table to a worksheet.
+    ///
+    /// The pivot table is placed with its top left cell at `row` and `col`.
+    /// Filter fields are shown above the pivot table, starting at `row`.
+    /// The layout of the pivot table is calculated by Excel when the file is
+    /// opened so the cells below and to the right of it should be left empty.
+    ///
+    /// @param {number} row - The zero indexed row number.
+    /// @param {number} col - The zero indexed column number.
+    /// @param {PivotTable} pivot_table - The pivot table to add.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds
+    ///   Excel's worksheet limits.
+    /// - [`XlsxError::ParameterError`] - The pivot table doesn't have a
+    ///   source or a value field, has value fields with the same caption, or
+    ///   has the name of another pivot table of the worksheet.
+    ///
+    /// Errors that depend on the source data, such as fields that aren't in
+    /// the source data or a pivot table that extends past Excel's worksheet
+    /// limits, are reported when the workbook is saved.
+    #[wasm_bindgen(js_name = "addPivotTable", skip_jsdoc)]
+    pub fn add_pivot_table(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        pivot_table: &PivotTable,
+    ) -> WasmResult<Worksheet> {
+        // Excel's worksheet limits.
+        if row >= 1_048_576 || col >= 16_384 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError));
+        }
+        pivot_table.validate()?;
+        let target = self.target();
+        let mut settings = target.settings.lock().unwrap();
+        let pivot_tables = &mut settings.sheets.entry(target.id).or_default().pivot_tables;
+        if let Some(name) = pivot_table.name() {
+            let lower = name.to_lowercase();
+            if pivot_tables
+                .iter()
+                .any(|placed| placed.pivot_table.name().is_some_and(|previous| previous.to_lowercase() == lower))
+            {
+                return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                    "Pivot table name '{name}' is already used in the worksheet"
+                ))));
+            }
+        }
+        pivot_tables.push(PlacedPivotTable {
+            row,
+            col,
+            pivot_table: pivot_table.clone(),
+        });
         Ok(self.clone())
     }
 
@@ -1156,9 +2019,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         image: &Image,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.embed_image(row, col, &image.lock())?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Image), None));
         Ok(self.clone())
     }
 
@@ -1170,9 +2035,11 @@ impl Worksheet {
         image: &Image,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.embed_image_with_format(row, col, &image.lock(), &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.embed_image_with_format(row, col, &image.lock(), &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.insert(row, col, Some(CellValue::Image), Some(&format.lock())));
         Ok(self.clone())
     }
 
@@ -1222,9 +2089,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         image: &Image,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_image(row, col, &image.lock())?;
+        self.register_svg_image(image);
         Ok(self.clone())
     }
 
@@ -1237,9 +2106,11 @@ impl Worksheet {
         x_offset: u32,
         y_offset: u32,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_image_with_offset(row, col, &image.lock(), x_offset, y_offset)?;
+        self.register_svg_image(image);
         Ok(self.clone())
     }
 
@@ -1251,9 +2122,11 @@ impl Worksheet {
         image: &Image,
         keep_aspect_ratio: bool,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_image_fit_to_cell(row, col, &image.lock(), keep_aspect_ratio)?;
+        self.register_svg_image(image);
         Ok(self.clone())
     }
 
@@ -1264,9 +2137,11 @@ impl Worksheet {
         col: xlsx::ColNum,
         image: &Image,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_image_fit_to_cell_centered(row, col, &image.lock())?;
+        self.register_svg_image(image);
         Ok(self.clone())
     }
 
@@ -1295,8 +2170,9 @@ impl Worksheet {
         col: xlsx::ColNum,
         chart: &Chart,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_chart(row, col, &chart.lock())?;
         Ok(self.clone())
     }
@@ -1310,8 +2186,9 @@ impl Worksheet {
         x_offset: u32,
         y_offset: u32,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_chart_with_offset(
             row,
             col,
@@ -1324,20 +2201,127 @@ impl Worksheet {
 
     #[wasm_bindgen(js_name = "clearCell")]
     pub fn clear_cell(&self, row: xlsx::RowNum, col: xlsx::ColNum) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.clear_cell(row, col);
+        self.record(|cells| cells.remove(row, col));
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "clearCellFormat")]
     pub fn clear_cell_format(&self, row: xlsx::RowNum, col: xlsx::ColNum) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.clear_cell_format(row, col);
+        self.record(|cells| cells.clear_format(row, col));
         Ok(self.clone())
     }
 
+    /// Get a cell that has been written to the worksheet.
+    ///
+    /// The value is returned as the {@link ExcelData} that would write it,
+    /// along with a copy of the cell format. See {@link Cell#value} for how
+    /// the different cell types are returned.
+    ///
+    /// Only data written with the worksheet methods is returned. Values that
+    /// are calculated by Excel, such as formula results, aren't available.
+    ///
+    /// @param {number} row - The zero indexed row number.
+    /// @param {number} col - The zero indexed column number.
+    /// @returns {Cell | undefined} - The cell, or `undefined` if nothing has
+    ///   been written to it.
+    #[wasm_bindgen(js_name = "getCell", skip_jsdoc)]
+    pub fn get_cell(&self, row: xlsx::RowNum, col: xlsx::ColNum) -> Option<Cell> {
+        let settings = self.settings();
+        let store = self.cell_store();
+        let cells = store.lock().unwrap();
+        cells.get(row, col, &settings)
+    }
+
+    /// Get all of the cells that have been written to the worksheet.
+    ///
+    /// The cells are returned in row major order: left to right and then top
+    /// to bottom.
+    ///
+    /// @returns {Cell[]} - The written cells.
+    #[wasm_bindgen(js_name = "cells", skip_jsdoc)]
+    pub fn cells(&self) -> Vec<Cell> {
+        let settings = self.settings();
+        let store = self.cell_store();
+        let cells = store.lock().unwrap();
+        cells.cells(&settings)
+    }
+
+    /// Get the range of cells that have been written to the worksheet.
+    ///
+    /// Blank cells that only hold a format are included in the range, like
+    /// in Excel.
+    ///
+    /// @returns {CellRange | undefined} - The used range, or `undefined` if
+    ///   the worksheet is empty.
+    #[wasm_bindgen(js_name = "usedRange", skip_jsdoc)]
+    pub fn used_range(&self) -> Option<CellRange> {
+        self.cell_store().lock().unwrap().used_range()
+    }
+
+    /// Get the zero indexed last row that has been written to.
+    ///
+    /// @returns {number | undefined} - The last row, or `undefined` if the
+    ///   worksheet is empty.
+    #[wasm_bindgen(js_name = "lastRow", skip_jsdoc)]
+    pub fn last_row(&self) -> Option<xlsx::RowNum> {
+        self.used_range().map(|range| range.last_row())
+    }
+
+    /// Get the zero indexed last column that has been written to.
+    ///
+    /// @returns {number | undefined} - The last column, or `undefined` if the
+    ///   worksheet is empty.
+    #[wasm_bindgen(js_name = "lastCol", skip_jsdoc)]
+    pub fn last_col(&self) -> Option<xlsx::ColNum> {
+        self.used_range().map(|range| range.last_col())
+    }
+
+    /// Render the worksheet as an HTML table.
+    ///
+    /// The cells that have been written to the worksheet are rendered with
+    /// their merged ranges, column widths, row heights, fonts, fills,
+    /// borders and alignment, derived from the same {@link Format} objects
+    /// that are used in the xlsx file. Numbers and dates are displayed with
+    /// their number format, as they would be in Excel. Theme colors use the
+    /// colors of the workbook theme, see {@link Workbook#setTheme}. This can
+    /// be used to preview a worksheet in a browser before it is saved.
+    ///
+    /// Some limitations:
+    ///
+    /// - Formulas aren't calculated so formula cells show the result set
+    ///   with {@link Formula#setResult}, or are rendered empty.
+    /// - Text rotation, diagonal borders and embedded images aren't
+    ///   rendered.
+    /// - Only `http`, `https`, `ftp` and `mailto` URLs are rendered as links.
+    ///
+    /// @param {HtmlOptions} options - The rendering options.
+    /// @returns {string} - The HTML table.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "toHtml", skip_jsdoc)]
+    pub fn to_html(&self, options: &HtmlOptions) -> String {
+        let settings = self.settings();
+        let target = self.target();
+        let theme_colors = target
+            .settings
+            .lock()
+            .unwrap()
+            .theme
+            .as_ref()
+            .map_or(OFFICE_THEME_COLORS, ThemeDefinition::colors);
+        let store = self.cell_store();
+        let cells = store.lock().unwrap();
+        html::render(&cells, settings.date_1904, &theme_colors, options)
+    }
+
     /// Autofit the worksheet column widths, approximately.
     ///
     /// There is no option in the xlsx file format that can be used to say
@@ -1378,11 +2362,12 @@ impl Worksheet {
     /// page of data without incurring the performance penalty of autofitting
     /// thousands of non-visible rows.
     #[wasm_bindgen(js_name = "autofit")]
-    pub fn autofit(&self) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn autofit(&self) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.autofit();
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "autofilter")]
@@ -1393,8 +2378,9 @@ impl Worksheet {
         last_row: xlsx::RowNum,
         last_col: xlsx::ColNum,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet
             .autofilter(first_row, first_col, last_row, last_col)
             .unwrap();
@@ -1422,8 +2408,9 @@ impl Worksheet {
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "protect")]
     pub fn protect(&self) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.protect();
         Ok(self.clone())
     }
@@ -1445,11 +2432,12 @@ impl Worksheet {
     ///
     /// TODO: example omitted
     #[wasm_bindgen(js_name = "setHidden", skip_jsdoc)]
-    pub fn set_hidden(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_hidden(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_hidden(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     /// Merge a range of cells.
@@ -1494,24 +2482,180 @@ impl Worksheet {
         value: &str,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.merge_range(
             first_row,
             first_col,
             last_row,
             last_col,
             value,
-            &format.lock(),
+            &self.cell_format(&format.lock()),
         )?;
+        self.record(|cells| {
+            let format = format.lock();
+            for row in first_row..=last_row {
+                for col in first_col..=last_col {
+                    cells.insert(row, col, Some(CellValue::Blank), Some(&format));
+                }
+            }
+            cells.insert(first_row, first_col, CellValue::string(value, true), Some(&format));
+            cells.add_merged_range(CellRange::new(first_row, first_col, last_row, last_col));
+        });
+        Ok(self.clone())
+    }
+
+    /// Write an HTML table to the worksheet.
+    ///
+    /// The first `<table>` element in the HTML is written with its top left
+    /// cell at `row` and `col`, so that tables copied from a web page or a
+    /// data grid keep their layout and styling:
+    ///
+    /// - Cells with a `rowspan` or `colspan` are written as merged ranges.
+    ///   Like in browsers a cell spans at most 1000 columns and 65534 rows,
+    ///   and at most 10000 cells in total, and spans are cut at the edge of
+    ///   the worksheet.
+    /// - The inline CSS of the `<table>`, `<tr>`, `<td>` and `<th>` elements,
+    ///   and inline elements like `<b>` and `<span style>`, is converted to a
+    ///   {@link Format}. The supported properties are `font-weight`,
+    ///   `font-style`, `text-decoration`, `font-family`, `font-size`, `color`,
+    ///   `background-color`, `text-align`, `vertical-align`, `white-space`
+    ///   and the `border` properties. `<th>` cells are bold and centered.
+    /// - Numbers and ISO 8601 dates are detected and written as Excel numbers
+    ///   and dates, see {@link HtmlTableOptions}.
+    ///
+    /// Line breaks from `<br>` and block elements are kept and the cell text
+    /// is wrapped. Formatting of part of the text of a cell is applied to the
+    /// whole cell.
+    ///
+    /// @param {number} row - The zero indexed row number of the top left
+    ///   cell.
+    /// @param {number} col - The zero indexed column number of the top left
+    ///   cell.
+    /// @param {string} html - The HTML containing the table.
+    /// @param {HtmlTableOptions} options - The options for the conversion.
+    /// @returns {Worksheet} - The worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::ParameterError`] - The HTML doesn't contain a
+    ///   `<table>` element.
+    /// - [`XlsxError::RowColumnLimitError`] - A cell of the table starts
+    ///   outside Excel's worksheet limits.
+    /// - [`XlsxError::MergeRangeOverlaps`] - A cell with a `rowspan` or
+    ///   `colspan` overlaps another merged cell of the table or a previous
+    ///   merge range.
+    /// - [`XlsxError::MaxStringLengthExceeded`] - The text of a cell exceeds
+    ///   Excel's limit of 32,767 characters.
+    ///
+    /// Nothing is written to the worksheet when an error is returned.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "writeHtmlTable", skip_jsdoc)]
+    pub fn write_html_table(
+        &self,
+        row: xlsx::RowNum,
+        col: xlsx::ColNum,
+        html: &str,
+        options: &HtmlTableOptions,
+    ) -> WasmResult<Worksheet> {
+        let settings = self.settings();
+        let table = html_table::parse(html, options)?;
+
+        // Everything that can fail is checked before any cell is written,
+        // so that a table that can't be written leaves the worksheet
+        // unchanged.
+        let mut merged_ranges = self.cell_store().lock().unwrap().merged_ranges().to_vec();
+        let mut cells = Vec::with_capacity(table.len());
+        for cell in table {
+            // Spans are cut at the edge of the worksheet, like Excel does
+            // when a table is pasted.
+            let position = |start: u32, offset: u32, span: u32, limit: u32| -> WasmResult<(u32, u32)> {
+                let first = start
+                    .checked_add(offset)
+                    .filter(|&first| first < limit)
+                    .ok_or(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError))?;
+                Ok((first, first.saturating_add(span - 1).min(limit - 1)))
+            };
+            let (first_row, last_row) = position(row, cell.row, cell.row_span, 1_048_576)?;
+            let (first_col, last_col) = position(col.into(), cell.col, cell.col_span, 16_384)?;
+            let (Ok(first_col), Ok(last_col)) = (xlsx::ColNum::try_from(first_col), xlsx::ColNum::try_from(last_col))
+            else {
+                return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError));
+            };
+            let range = CellRange::new(first_row, first_col, last_row, last_col);
+            let merged = first_row != last_row || first_col != last_col;
+            if merged {
+                if let Some(previous) = merged_ranges.iter().find(|previous| previous.overlaps(&range)) {
+                    return Err(XlsxError::Xlsx(xlsx::XlsxError::MergeRangeOverlaps(
+                        range.to_error_string(),
+                        previous.to_error_string(),
+                    )));
+                }
+                merged_ranges.push(range);
+            }
+            let formatted = cell.format.is_some() || merged;
+            let value = match cell.value {
+                HtmlTableValue::Blank => formatted.then_some(CellValue::Blank),
+                HtmlTableValue::String(string) if string.chars().count() > MAX_STRING_LEN => {
+                    return Err(XlsxError::Xlsx(xlsx::XlsxError::MaxStringLengthExceeded));
+                }
+                HtmlTableValue::String(string) => Some(CellValue::String(string)),
+                HtmlTableValue::Number(number) => Some(CellValue::Number(number)),
+                HtmlTableValue::DateTime(datetime) => Some(CellValue::DateTime(settings.date_serial(&datetime)?.0)),
+            };
+            cells.push((range, merged, value, formatted.then(|| cell.format.unwrap_or_default())));
+        }
+
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        for (range, merged, value, properties) in cells {
+            let (first_row, first_col) = (range.first_row(), range.first_col());
+            let format = self.cell_format(properties.as_ref().unwrap_or(&FormatProperties::default()));
+            if merged {
+                let _ = sheet.merge_range(first_row, first_col, range.last_row(), range.last_col(), "", &format)?;
+            }
+            match &value {
+                Some(CellValue::String(string)) => {
+                    let _ = sheet.write_string_with_format(first_row, first_col, string, &format)?;
+                }
+                Some(CellValue::Number(number)) => {
+                    let _ = sheet.write_number_with_format(first_row, first_col, *number, &format)?;
+                }
+                Some(CellValue::DateTime(serial)) => {
+                    let serial = ExcelSerialDateTime(*serial);
+                    let _ = sheet.write_datetime_with_format(first_row, first_col, serial, &format)?;
+                }
+                Some(_) => {
+                    let _ = sheet.write_blank(first_row, first_col, &format)?;
+                }
+                None => {}
+            }
+            self.record(|cells| {
+                let format = properties.as_ref();
+                if merged {
+                    for row in first_row..=range.last_row() {
+                        for col in first_col..=range.last_col() {
+                            cells.insert(row, col, Some(CellValue::Blank), format);
+                        }
+                    }
+                    cells.add_merged_range(range);
+                }
+                cells.insert(first_row, first_col, value, format);
+            });
+        }
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setRowHeight")]
     pub fn set_row_height(&mut self, row: xlsx::RowNum, height: f64) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_row_height(row, height)?;
+        self.record(|cells| cells.set_row_height(row, (height * 4.0 / 3.0).round() as u32));
         Ok(self.clone())
     }
 
@@ -1521,9 +2665,11 @@ impl Worksheet {
         row: xlsx::RowNum,
         height: u32,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_row_height_pixels(row, height)?;
+        self.record(|cells| cells.set_row_height(row, height));
         Ok(self.clone())
     }
 
@@ -1536,9 +2682,11 @@ impl Worksheet {
         last_col: xlsx::ColNum,
         format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
-        let _ = sheet.set_range_format(first_row, first_col, last_row, last_col, &format.lock())?;
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        let _ = sheet.set_range_format(first_row, first_col, last_row, last_col, &self.cell_format(&format.lock()))?;
+        self.record(|cells| cells.set_range_format(first_row, first_col, last_row, last_col, &format.lock()));
         Ok(self.clone())
     }
 
@@ -1552,121 +2700,251 @@ impl Worksheet {
         format: &Format,
         border_format: &Format,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_range_format_with_border(
             first_row,
             first_col,
             last_row,
             last_col,
-            &format.lock(),
-            &border_format.lock(),
+            &self.cell_format(&format.lock()),
+            &border_format.to_format(),
         )?;
+        // `rust_xlsxwriter` combines the cell format with the sides of the
+        // border format at the edges of the range.
+        self.record(|cells| {
+            let (format, border_format) = (format.lock(), border_format.lock());
+            for row in first_row..=last_row {
+                for col in first_col..=last_col {
+                    let mut combined = format.clone();
+                    if row == first_row {
+                        combined.border_top = border_format.border_top;
+                    }
+                    if row == last_row {
+                        combined.border_bottom = border_format.border_bottom;
+                    }
+                    if col == first_col {
+                        combined.border_left = border_format.border_left;
+                    }
+                    if col == last_col {
+                        combined.border_right = border_format.border_right;
+                    }
+                    cells.set_format(row, col, &combined);
+                }
+            }
+        });
+        Ok(self.clone())
+    }
+
+    /// Merge a format into the formats of a range of cells.
+    ///
+    /// Unlike {@link Worksheet#setRangeWithFormat}, which replaces the
+    /// formats of the cells, this method merges the properties of the format
+    /// into the existing format of each cell, see {@link Format#merge}. The
+    /// values of the cells, and properties such as their number formats, are
+    /// kept. This can be used to add a fill, for example to stripe the rows
+    /// of existing data, or to add a border around a range.
+    ///
+    /// Cells in the range that haven't been written get the format as blank
+    /// cells, so the range can have at most 1,048,576 cells, the size of a
+    /// full column.
+    ///
+    /// @param {number} first_row - The first row of the range.
+    /// @param {number} first_col - The first column of the range.
+    /// @param {number} last_row - The last row of the range.
+    /// @param {number} last_col - The last column of the range.
+    /// @param {Format} format - The {@link Format} to merge into the cells.
+    /// @param {FormatMergeMode} [mode] - How the format is merged, see
+    ///   {@link FormatMergeMode}. The default is `Overlay`.
+    /// @returns {Worksheet} - The Worksheet object.
+    ///
+    /// # Errors
+    ///
+    /// - [`XlsxError::RowColumnLimitError`] - Row or column exceeds Excel's
+    ///   worksheet limits.
+    /// - [`XlsxError::RowColumnOrderError`] - The first row or column is
+    ///   larger than the last row or column.
+    /// - [`XlsxError::ParameterError`] - The range has more than 1,048,576
+    ///   cells.
+    ///
+    /// TODO: example omitted
+    #[wasm_bindgen(js_name = "applyFormatToRange", skip_jsdoc)]
+    pub fn apply_format_to_range(
+        &self,
+        first_row: xlsx::RowNum,
+        first_col: xlsx::ColNum,
+        last_row: xlsx::RowNum,
+        last_col: xlsx::ColNum,
+        format: &Format,
+        mode: Option<FormatMergeMode>,
+    ) -> WasmResult<Worksheet> {
+        // Excel's worksheet limits.
+        if last_row >= 1_048_576 || last_col >= 16_384 {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnLimitError));
+        }
+        if first_row > last_row || first_col > last_col {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::RowColumnOrderError));
+        }
+        let cell_count = u64::from(last_row - first_row + 1) * u64::from(last_col - first_col + 1);
+        if cell_count > MAX_FORMATTED_CELLS {
+            return Err(XlsxError::Xlsx(xlsx::XlsxError::ParameterError(format!(
+                "A format can be applied to at most {MAX_FORMATTED_CELLS} cells, the range has {cell_count}"
+            ))));
+        }
+        let properties = format.lock().clone();
+        let mode = mode.unwrap_or_default();
+
+        // Cells usually share a few formats, so each one is merged and built
+        // once.
+        let mut merged_ids: HashMap<Option<FormatProperties>, usize> = HashMap::new();
+        let mut merged: Vec<(FormatProperties, xlsx::Format)> = Vec::new();
+        let mut formats = Vec::new();
+        {
+            let store = self.cell_store();
+            let cells = store.lock().unwrap();
+            for row in first_row..=last_row {
+                for col in first_col..=last_col {
+                    let existing = cells.value(row, col).and_then(|(_, format)| format.cloned());
+                    let id = *merged_ids.entry(existing).or_insert_with_key(|existing| {
+                        let format = mode.merge(existing.as_ref().unwrap_or(&FormatProperties::default()), &properties);
+                        merged.push((format.clone(), self.cell_format(&format)));
+                        merged.len() - 1
+                    });
+                    formats.push((row, col, id));
+                }
+            }
+        }
+
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
+        for &(row, col, id) in &formats {
+            let _ = sheet.set_cell_format(row, col, &merged[id].1)?;
+        }
+        self.record(|cells| {
+            for &(row, col, id) in &formats {
+                cells.set_format(row, col, &merged[id].0);
+            }
+        });
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setLandscape")]
-    pub fn set_landscape(&self) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_landscape(&self) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_landscape();
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPortrait")]
-    pub fn set_portrait(&self) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_portrait(&self) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_portrait();
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPaperSize")]
-    pub fn set_paper_size(&self, paper_size: u8) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_paper_size(&self, paper_size: u8) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_paper_size(paper_size);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintFirstPageNumber")]
-    pub fn set_print_first_page_number(&self, number: u16) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_first_page_number(&self, number: u16) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_first_page_number(number);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintScale")]
-    pub fn set_print_scale(&self, scale: u16) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_scale(&self, scale: u16) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_scale(scale);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintFitToPages")]
-    pub fn set_print_fit_to_pages(&self, width: u16, height: u16) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_fit_to_pages(&self, width: u16, height: u16) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_fit_to_pages(width, height);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintCenterHorizontally")]
-    pub fn set_print_center_horizontally(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_center_horizontally(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_center_horizontally(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintCenterVertically")]
-    pub fn set_print_center_vertically(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_center_vertically(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_center_vertically(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setScreenGridlines")]
-    pub fn set_screen_gridlines(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_screen_gridlines(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_screen_gridlines(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintGridlines")]
-    pub fn set_print_gridlines(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_gridlines(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_gridlines(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintBlackAndWhite")]
-    pub fn set_print_black_and_white(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_black_and_white(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_black_and_white(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintDraft")]
-    pub fn set_print_draft(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_draft(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_draft(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintHeadings")]
-    pub fn set_print_headings(&self, enable: bool) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    pub fn set_print_headings(&self, enable: bool) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_print_headings(enable);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setPrintArea", skip_jsdoc)]
@@ -1677,24 +2955,27 @@ impl Worksheet {
         last_row: xlsx::RowNum,
         last_col: xlsx::ColNum,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_print_area(first_row, first_col, last_row, last_col)?;
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setRepeatRows", skip_jsdoc)]
     pub fn set_repeat_rows(&self, first_row: xlsx::RowNum, last_row: xlsx::RowNum) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_repeat_rows(first_row, last_row)?;
         Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "setRepeatColumns", skip_jsdoc)]
     pub fn set_repeat_columns(&self, first_col: xlsx::ColNum, last_col: xlsx::ColNum) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.set_repeat_columns(first_col, last_col)?;
         Ok(self.clone())
     }
@@ -1706,8 +2987,9 @@ impl Worksheet {
         col: xlsx::ColNum,
         note: &Note,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.insert_note(row, col, &*note.lock())?;
         Ok(self.clone())
     }
@@ -1723,8 +3005,9 @@ impl Worksheet {
         first_row: xlsx::RowNum,
         last_row: xlsx::RowNum,
     ) -> WasmResult<Worksheet> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let _ = sheet.group_rows(first_row, last_row)?;
         Ok(self.clone())
     }
@@ -1750,11 +3033,12 @@ impl Worksheet {
         bottom: f64,
         header: f64,
         footer: f64,
-    ) -> Worksheet {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+    ) -> WasmResult<Worksheet> {
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         sheet.set_margins(left, right, top, bottom, header, footer);
-        self.clone()
+        Ok(self.clone())
     }
 
     #[wasm_bindgen(js_name = "addConditionalFormatBlank", skip_jsdoc)]
@@ -1766,8 +3050,9 @@ impl Worksheet {
         last_col: u16,
         format: ConditionalFormatBlank,
     ) -> Result<Worksheet, JsValue> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let inner = format.inner.clone();
         let _ = sheet.add_conditional_format(first_row, first_col, last_row, last_col, &inner);
         Ok(self.clone())
@@ -1782,8 +3067,9 @@ impl Worksheet {
         last_col: u16,
         format: ConditionalFormatDataBar,
     ) -> Result<Worksheet, JsValue> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let inner = format.inner.clone();
         let _ = sheet.add_conditional_format(first_row, first_col, last_row, last_col, &inner);
         Ok(self.clone())
@@ -1798,8 +3084,9 @@ impl Worksheet {
         last_col: u16,
         format: ConditionalFormatFormula,
     ) -> Result<Worksheet, JsValue> {
-        let mut book = self.workbook.lock().unwrap();
-        let sheet = book.worksheet_from_index(self.index).unwrap();
+        let (workbook, index) = self.sheet_ref()?;
+        let mut book = workbook.lock().unwrap();
+        let sheet = book.worksheet_from_index(index).unwrap();
         let inner = format.inner.clone();
         let _ = sheet.add_conditional_format(first_row, first_col, last_row, last_col, &inner);
         Ok(self.clone())
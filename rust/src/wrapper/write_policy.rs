@@ -0,0 +1,114 @@
+use wasm_bindgen::prelude::*;
+
+/// The `BigIntPolicy` enum defines how `BigInt` values are written to cells.
+///
+/// Excel stores numbers as 64 bit floats so integers outside of
+/// `Number.MIN_SAFE_INTEGER..=Number.MAX_SAFE_INTEGER` lose precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum BigIntPolicy {
+    /// Write the value as a number if it can be represented exactly and as a
+    /// string otherwise. This is the default.
+    #[default]
+    NumberIfSafe,
+    /// Always write the value as a string. Useful for 64 bit database ids.
+    String,
+    /// Raise an error.
+    Error,
+}
+
+/// The `NullPolicy` enum defines how `null` and `undefined` values are
+/// written to cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum NullPolicy {
+    /// Write an empty string, or a formatted blank cell when a format is
+    /// given. This is the default.
+    #[default]
+    EmptyString,
+    /// Write a formatted blank cell when a format is given, and otherwise
+    /// clear the cell, removing any existing cell data.
+    Blank,
+    /// Don't write anything, leaving any existing cell data in place.
+    Skip,
+}
+
+/// The `NonFinitePolicy` enum defines how `NaN`, `Infinity` and `-Infinity`
+/// are written to cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum NonFinitePolicy {
+    /// Write the value like {@link Worksheet#writeNumber}, which stores it as
+    /// a `#NUM!` or `#DIV/0!` string. This is the default.
+    #[default]
+    Number,
+    /// Write the Excel `#NUM!` error.
+    NumError,
+    /// Write a formatted blank cell when a format is given, and otherwise
+    /// clear the cell.
+    Blank,
+    /// Raise an error.
+    Error,
+}
+
+/// The `WritePolicy` struct defines how JS values that don't map directly to
+/// Excel cell types are written with {@link Worksheet#write} and the other
+/// generic write methods.
+///
+/// A policy can be set for all worksheets with
+/// {@link Workbook#setWritePolicy} and overridden for a single worksheet with
+/// {@link Worksheet#setWritePolicy}.
+///
+/// TODO: example omitted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[wasm_bindgen]
+pub struct WritePolicy {
+    pub(crate) bigint: BigIntPolicy,
+    pub(crate) null: NullPolicy,
+    pub(crate) non_finite: NonFinitePolicy,
+}
+
+#[wasm_bindgen]
+impl WritePolicy {
+    /// Create a new `WritePolicy` with the default policies.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WritePolicy {
+        WritePolicy::default()
+    }
+
+    /// Set how `BigInt` values are written.
+    ///
+    /// @param {BigIntPolicy} policy - The policy for `BigInt` values.
+    /// @returns {WritePolicy} - The WritePolicy object.
+    #[wasm_bindgen(js_name = "setBigInt", skip_jsdoc)]
+    pub fn set_bigint(&self, policy: BigIntPolicy) -> WritePolicy {
+        WritePolicy {
+            bigint: policy,
+            ..*self
+        }
+    }
+
+    /// Set how `null` and `undefined` values are written.
+    ///
+    /// @param {NullPolicy} policy - The policy for `null` values.
+    /// @returns {WritePolicy} - The WritePolicy object.
+    #[wasm_bindgen(js_name = "setNull", skip_jsdoc)]
+    pub fn set_null(&self, policy: NullPolicy) -> WritePolicy {
+        WritePolicy {
+            null: policy,
+            ..*self
+        }
+    }
+
+    /// Set how `NaN`, `Infinity` and `-Infinity` are written.
+    ///
+    /// @param {NonFinitePolicy} policy - The policy for non-finite numbers.
+    /// @returns {WritePolicy} - The WritePolicy object.
+    #[wasm_bindgen(js_name = "setNonFinite", skip_jsdoc)]
+    pub fn set_non_finite(&self, policy: NonFinitePolicy) -> WritePolicy {
+        WritePolicy {
+            non_finite: policy,
+            ..*self
+        }
+    }
+}
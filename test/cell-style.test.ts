@@ -0,0 +1,155 @@
+import { Workbook, Format, CellStyle, Color } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm cell style test", () => {
+  test("format based on built-in cell style", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Act
+    worksheet.writeWithFormat(0, 0, "Good", Format.fromCellStyle(CellStyle.Good));
+    worksheet.writeWithFormat(
+      1,
+      0,
+      "Good and bold",
+      Format.fromCellStyle(CellStyle.Good).setBold(),
+    );
+    worksheet.writeWithFormat(2, 0, "Italic", new Format().setItalic());
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain(
+      '<xf numFmtId="0" fontId="4" fillId="3" borderId="0" applyNumberFormat="0" applyBorder="0" applyAlignment="0" applyProtection="0"/></cellStyleXfs>',
+    );
+    expect(styles).toContain(
+      '<xf numFmtId="0" fontId="1" fillId="2" borderId="0" xfId="1" applyFont="1" applyFill="1"/><xf numFmtId="0" fontId="2" fillId="2" borderId="0" xfId="1" applyFont="1" applyFill="1"/><xf numFmtId="0" fontId="3" fillId="0" borderId="0" xfId="0" applyFont="1"/>',
+    );
+    expect(styles).toContain(
+      '<cellStyles count="2"><cellStyle name="Normal" xfId="0" builtinId="0"/><cellStyle name="Good" xfId="1" builtinId="26"/></cellStyles>',
+    );
+  });
+
+  test("built-in cell style with theme colors", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Act
+    worksheet.writeWithFormat(
+      0,
+      0,
+      "Heading",
+      Format.fromCellStyle(CellStyle.Heading1),
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain('<sz val="15"/><color theme="3"/>');
+    expect(styles).toContain('<bottom style="thick"><color theme="4"/></bottom>');
+    expect(styles).not.toContain("1F497D");
+  });
+
+  test("formats with the same properties and different styles", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Act
+    worksheet.writeWithFormat(0, 0, "Plain", new Format().setBold());
+    worksheet.writeWithFormat(
+      1,
+      0,
+      "Good",
+      new Format().setBold().setCellStyle("Good"),
+    );
+    worksheet.writeWithFormat(
+      2,
+      0,
+      "Bad",
+      new Format().setBold().setCellStyle("Bad"),
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain(
+      '<cellXfs count="4"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/><xf numFmtId="0" fontId="2" fillId="0" borderId="0" xfId="1" applyFont="1"/><xf numFmtId="0" fontId="3" fillId="0" borderId="0" xfId="2" applyFont="1"/></cellXfs>',
+    );
+    expect(styles).not.toContain("\uE000");
+    const sheet = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet).toContain('<c r="A2" s="2"');
+    expect(sheet).toContain('<c r="A3" s="3"');
+  });
+
+  test("add cell style", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const brand = new Format()
+      .setFontColor(Color.rgb(0x112233))
+      .setNumFormat("0.000");
+
+    // Act
+    workbook.addCellStyle("Brand", brand);
+    workbook.addCellStyle("Percent", new Format().setNumFormat("0.0%"));
+    worksheet.writeWithFormat(
+      0,
+      0,
+      1.5,
+      brand.clone().setItalic().setCellStyle("brand"),
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain(
+      '<cellStyle name="Brand" xfId="1"/><cellStyle name="Percent" xfId="2" builtinId="5" customBuiltin="1"/>',
+    );
+    expect(styles).toContain(
+      '<xf numFmtId="164" fontId="1" fillId="0" borderId="0" xfId="1" applyNumberFormat="1" applyFont="1"/>',
+    );
+    expect(styles).toContain('<numFmt numFmtId="165" formatCode="0.0%"/>');
+  });
+
+  test("formats without a cell style only use the Normal style", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Act
+    worksheet.writeWithFormat(0, 0, 0.5, new Format().setNumFormatIndex(9));
+    worksheet.writeWithFormat(1, 0, "Bold", new Format().setBold());
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain(
+      '<cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>',
+    );
+  });
+
+  test("add cell style errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addCellStyle("Brand", new Format().setBold());
+
+    // Assert
+    expect(() => workbook.addCellStyle("", new Format())).toThrow(
+      /ParameterError/,
+    );
+    expect(() => workbook.addCellStyle("Normal", new Format())).toThrow(
+      /ParameterError/,
+    );
+    expect(() => workbook.addCellStyle("BRAND", new Format())).toThrow(
+      "Cell style 'BRAND' is already added to the workbook",
+    );
+  });
+});
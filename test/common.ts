@@ -61,6 +61,19 @@ export const readXlsx = async (buf: Uint8Array) => {
   return { files };
 };
 
+// Read a part of an xlsx file without the normalization done by `readXlsx`.
+export const readXlsxPart = async (buf: Uint8Array, name: string) => {
+  const zipEntries = await unzip(Buffer.from(buf));
+  return zipEntries
+    .find((zipEntry) => zipEntry.entryName === name)
+    ?.data.toString();
+};
+
+export const readXlsxBinaryPart = async (buf: Uint8Array, name: string) => {
+  const zipEntries = await unzip(Buffer.from(buf));
+  return zipEntries.find((zipEntry) => zipEntry.entryName === name)?.data;
+};
+
 export interface XlsxFile {
   files: Map<string, string>;
 }
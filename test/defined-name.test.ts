@@ -0,0 +1,88 @@
+import { Workbook } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm defined name test", () => {
+  test("define global and local names", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet().setName("Sales Data");
+
+    // Act
+    workbook.defineName("Exchange_rate", "=0.96");
+    workbook.defineNameForRange("Totals", worksheet, 0, 0, 9, 1);
+    worksheet.defineName("Rate", "=0.5");
+    worksheet.defineNameForRange("First", 0, 0, 0, 0);
+
+    // Assert
+    const names = workbook.definedNames();
+    expect(names.map((n) => [n.name, n.sheetName, n.formula])).toEqual([
+      ["Exchange_rate", undefined, "=0.96"],
+      ["Totals", undefined, "='Sales Data'!$A$1:$B$10"],
+      ["Rate", "Sales Data", "=0.5"],
+      ["First", "Sales Data", "='Sales Data'!$A$1"],
+    ]);
+
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const xml = actual.files.get("xl/workbook.xml");
+    expect(xml).toContain('<definedName name="Exchange_rate">0.96</definedName>');
+    expect(xml).toContain(
+      "<definedName name=\"Totals\">'Sales Data'!$A$1:$B$10</definedName>",
+    );
+    expect(xml).toContain('<definedName name="Rate" localSheetId="0">0.5</definedName>');
+  });
+
+  test("local names follow their worksheet", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().setName("First");
+    const worksheet = workbook.addWorksheet().setName("Data");
+    const removed = workbook.addWorksheet().setName("Removed");
+    worksheet.defineName("Rate", "=0.5");
+    workbook.defineName("Data!Total", "=Data!$A$1");
+    removed.defineName("Rate", "=1");
+
+    // Act
+    worksheet.setName("Renamed");
+    workbook.moveWorksheet(1, 0);
+    workbook.removeWorksheet(2);
+
+    // Assert
+    const names = workbook.definedNames();
+    expect(names.map((n) => [n.name, n.sheetName])).toEqual([
+      ["Rate", "Renamed"],
+      ["Total", "Renamed"],
+    ]);
+
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const xml = actual.files.get("xl/workbook.xml");
+    expect(xml).toContain('<definedName name="Rate" localSheetId="0">0.5</definedName>');
+    expect(xml).toContain(
+      '<definedName name="Total" localSheetId="0">Data!$A$1</definedName>',
+    );
+    expect(xml).not.toContain('localSheetId="2"');
+  });
+
+  test("define name errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    workbook.defineName("Rate", "=1");
+
+    // Assert
+    expect(() => workbook.defineName("1Rate", "=1")).toThrow(/ParameterError/);
+    expect(() => workbook.defineName("rate", "=2")).toThrow(/ParameterError/);
+    expect(() => workbook.defineName("Missing!Rate", "=2")).toThrow(
+      /UnknownWorksheetNameOrIndex/,
+    );
+    worksheet.defineName("Rate", "=2");
+    expect(() => worksheet.defineName("RATE", "=3")).toThrow(/ParameterError/);
+    expect(() => worksheet.defineNameForRange("Data", 5, 0, 1, 0)).toThrow(
+      /RowColumnOrderError/,
+    );
+  });
+});
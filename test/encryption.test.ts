@@ -0,0 +1,210 @@
+import { createDecipheriv, createHash, createHmac } from "node:crypto";
+import { Workbook, EncryptionOptions } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+const oleSignature = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+const ENDOFCHAIN = 0xfffffffe;
+
+/** Read the streams of a small OLE compound file, by name. */
+function readCompoundFile(buf: Uint8Array): Map<string, Buffer> {
+  const data = Buffer.from(buf);
+  const sectorSize = 1 << data.readUInt16LE(30);
+  const miniSectorSize = 1 << data.readUInt16LE(32);
+  const miniStreamCutoff = data.readUInt32LE(56);
+  const sector = (index: number) =>
+    data.subarray((index + 1) * sectorSize, (index + 2) * sectorSize);
+
+  // Small files only have the FAT sector locations stored in the header.
+  const fatSectors = [];
+  for (let i = 0; i < data.readUInt32LE(44); i++) {
+    fatSectors.push(sector(data.readUInt32LE(76 + 4 * i)));
+  }
+  const fat = Buffer.concat(fatSectors);
+  const readChain = (start: number) => {
+    const sectors = [];
+    for (let s = start; s !== ENDOFCHAIN; s = fat.readUInt32LE(4 * s)) {
+      sectors.push(sector(s));
+    }
+    return Buffer.concat(sectors);
+  };
+
+  const dir = readChain(data.readUInt32LE(48));
+  const miniFat = readChain(data.readUInt32LE(60));
+  // The mini stream is stored in the sectors of the root entry.
+  const miniStream = readChain(dir.readUInt32LE(116));
+  const readMiniChain = (start: number) => {
+    const sectors = [];
+    for (let s = start; s !== ENDOFCHAIN; s = miniFat.readUInt32LE(4 * s)) {
+      sectors.push(
+        miniStream.subarray(s * miniSectorSize, (s + 1) * miniSectorSize),
+      );
+    }
+    return Buffer.concat(sectors);
+  };
+
+  const streams = new Map<string, Buffer>();
+  for (let offset = 0; offset < dir.length; offset += 128) {
+    // Only streams, object type 2, have data.
+    if (dir[offset + 66] !== 2) {
+      continue;
+    }
+    const nameLength = dir.readUInt16LE(offset + 64);
+    const name = dir.toString("utf16le", offset, offset + nameLength - 2);
+    const start = dir.readUInt32LE(offset + 116);
+    const size = dir.readUInt32LE(offset + 120);
+    const stream =
+      size < miniStreamCutoff ? readMiniChain(start) : readChain(start);
+    streams.set(name, stream.subarray(0, size));
+  }
+  return streams;
+}
+
+const sha512 = (...parts: Buffer[]) =>
+  createHash("sha512").update(Buffer.concat(parts)).digest();
+
+const uint32 = (value: number) => {
+  const buf = Buffer.alloc(4);
+  buf.writeUInt32LE(value);
+  return buf;
+};
+
+const aesDecrypt = (key: Buffer, iv: Buffer, data: Buffer) => {
+  const decipher = createDecipheriv("aes-256-cbc", key, iv);
+  decipher.setAutoPadding(false);
+  return Buffer.concat([decipher.update(data), decipher.final()]);
+};
+
+/**
+ * Decrypt an agile encrypted workbook as described in MS-OFFCRYPTO, checking
+ * the password verifier and the data integrity HMAC on the way.
+ */
+function decryptWorkbook(buf: Uint8Array, password: string): Buffer {
+  const streams = readCompoundFile(buf);
+  const info = streams.get("EncryptionInfo")!.subarray(8).toString("utf8");
+  const encryptedPackage = streams.get("EncryptedPackage")!;
+  const attribute = (element: string, name: string) => {
+    const match = new RegExp(`<${element} [^>]*\\b${name}="([^"]*)"`).exec(
+      info,
+    );
+    return match![1]!;
+  };
+  const base64 = (element: string, name: string) =>
+    Buffer.from(attribute(element, name), "base64");
+
+  const passwordSalt = base64("p:encryptedKey", "saltValue");
+  const spinCount = Number(attribute("p:encryptedKey", "spinCount"));
+  let hash = sha512(passwordSalt, Buffer.from(password, "utf16le"));
+  for (let i = 0; i < spinCount; i++) {
+    hash = sha512(uint32(i), hash);
+  }
+  const passwordDecrypt = (blockKey: number[], name: string) =>
+    aesDecrypt(
+      sha512(hash, Buffer.from(blockKey)).subarray(0, 32),
+      passwordSalt,
+      base64("p:encryptedKey", name),
+    );
+
+  const verifier = passwordDecrypt(
+    [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79],
+    "encryptedVerifierHashInput",
+  );
+  const verifierHash = passwordDecrypt(
+    [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e],
+    "encryptedVerifierHashValue",
+  );
+  expect(verifierHash.subarray(0, 64)).toEqual(sha512(verifier));
+  const secretKey = passwordDecrypt(
+    [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6],
+    "encryptedKeyValue",
+  ).subarray(0, 32);
+
+  const keyDataSalt = base64("keyData", "saltValue");
+  const blockIv = (blockKey: Buffer) =>
+    sha512(keyDataSalt, blockKey).subarray(0, 16);
+  const hmacKey = aesDecrypt(
+    secretKey,
+    blockIv(Buffer.from([0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6])),
+    base64("dataIntegrity", "encryptedHmacKey"),
+  );
+  const hmacValue = aesDecrypt(
+    secretKey,
+    blockIv(Buffer.from([0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33])),
+    base64("dataIntegrity", "encryptedHmacValue"),
+  );
+  expect(
+    createHmac("sha512", hmacKey).update(encryptedPackage).digest(),
+  ).toEqual(hmacValue);
+
+  // The package is encrypted in 4096 byte segments after its 8 byte size.
+  const size = Number(encryptedPackage.readBigUInt64LE(0));
+  const segments = [];
+  for (let i = 0, offset = 8; offset < encryptedPackage.length; i++) {
+    const segment = encryptedPackage.subarray(offset, offset + 4096);
+    segments.push(aesDecrypt(secretKey, blockIv(uint32(i)), segment));
+    offset += segment.length;
+  }
+  return Buffer.concat(segments).subarray(0, size);
+}
+
+describe("xlsx-wasm encryption test", () => {
+  test("save encrypted workbook", () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().write(0, 0, "Salary");
+
+    // Act
+    const options = new EncryptionOptions().setSpinCount(1000);
+    const actual = workbook.saveToBufferEncrypted("secret", options);
+    const again = workbook.saveToBufferEncrypted("secret", options);
+
+    // Assert
+    expect(Array.from(actual.slice(0, 8))).toEqual(oleSignature);
+    const text = new TextDecoder("latin1").decode(actual);
+    expect(text).toContain('<keyData saltSize="16" blockSize="16" keyBits="256"');
+    expect(text).toContain('spinCount="1000"');
+    expect(text).not.toContain("Salary");
+    expect(new TextDecoder("utf-16le").decode(actual)).toContain(
+      "EncryptedPackage",
+    );
+    // The salts and keys are random.
+    expect(again).not.toEqual(actual);
+  });
+
+  test("decrypt encrypted workbook", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().write(0, 0, "Salary");
+
+    // Act
+    const options = new EncryptionOptions().setSpinCount(1000);
+    const encrypted = workbook.saveToBufferEncrypted("secret", options);
+
+    // Assert
+    const actual = await readXlsx(decryptWorkbook(encrypted, "secret"));
+    expect(actual.files.get("xl/sharedStrings.xml")).toContain("Salary");
+  });
+
+  test("encryption errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet();
+
+    // Assert
+    const options = new EncryptionOptions();
+    expect(() => workbook.saveToBufferEncrypted("", options)).toThrow(
+      /Password/,
+    );
+    expect(() =>
+      workbook.saveToBufferEncrypted("x".repeat(256), options),
+    ).toThrow(/Password/);
+    expect(() => new EncryptionOptions().setSpinCount(10_000_001)).toThrow(
+      /ParameterError/,
+    );
+  });
+});
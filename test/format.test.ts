@@ -1,17 +1,20 @@
 import {
   Color,
   ExcelDateTime,
+  FontScheme,
   Format,
   FormatAlign,
   FormatBorder,
   FormatDiagonalBorder,
+  FormatMergeMode,
   FormatScript,
   FormatUnderline,
   Formula,
+  Image,
   Workbook,
 } from "wasm-xlsxwriter/web";
 import { describe, test, beforeAll, expect } from "vitest";
-import { initWasModule, readXlsx, readXlsxFile } from "./common";
+import { initWasModule, loadFile, readXlsx, readXlsxFile } from "./common";
 
 beforeAll(async () => {
   await initWasModule();
@@ -293,4 +296,224 @@ describe("xlsx-wasm test", () => {
     );
     expect(actual).matchXlsx(expected);
   });
+
+  test("format from object", () => {
+    // Arrange
+    const chained = new Format()
+      .setBold()
+      .setFontSize(12)
+      .setFontColor(Color.rgb(0x112233))
+      .setNumFormat("0.00")
+      .setBorder(FormatBorder.Thin)
+      .setAlign(FormatAlign.Center)
+      .setAlign(FormatAlign.VerticalCenter);
+
+    // Act
+    const format = Format.fromObject({
+      bold: true,
+      fontSize: 12,
+      fontColor: "#112233",
+      numFormat: "0.00",
+      border: FormatBorder.Thin,
+      align: FormatAlign.Center,
+      verticalAlign: FormatAlign.VerticalCenter,
+    });
+
+    // Assert
+    expect(format.equals(chained)).toBe(true);
+    expect(format.hash()).toBe(chained.hash());
+    expect(format.equals(chained.clone().setItalic())).toBe(false);
+    expect(format.hash()).not.toBe(chained.clone().setItalic().hash());
+    const styled = chained.clone().setCellStyle("Good");
+    expect(format.equals(styled)).toBe(false);
+    expect(format.hash()).not.toBe(styled.hash());
+  });
+
+  test("format to object", () => {
+    // Arrange
+    const format = new Format()
+      .setItalic()
+      .setFontName("Arial")
+      .setBorderBottom(FormatBorder.Double)
+      .setBorderBottomColor(Color.red())
+      .setBackgroundColor(Color.theme(4, 1))
+      .setUnlocked();
+
+    // Act
+    const object = format.toObject();
+
+    // Assert
+    expect(object).toEqual({
+      fontName: "Arial",
+      fontScheme: FontScheme.None,
+      italic: true,
+      borderBottom: FormatBorder.Double,
+      borderBottomColor: "red",
+      backgroundColor: { theme: 4, shade: 1 },
+      locked: false,
+    });
+    expect(new Format().toObject()).toEqual({});
+    const restored = Format.fromObject(JSON.parse(JSON.stringify(object)));
+    expect(restored.equals(format)).toBe(true);
+  });
+
+  test("format to object with implied properties", () => {
+    // Arrange
+    const format = new Format().setHyperlink().setBold().setRotation(-45);
+
+    // Act
+    const object = format.toObject();
+
+    // Assert
+    expect(object).toEqual({ hyperlink: true, bold: true, rotation: -45 });
+    expect(Format.fromObject(object).equals(format)).toBe(true);
+  });
+
+  test("format from object errors", () => {
+    // Assert
+    expect(() => Format.fromObject({ bolt: true } as any)).toThrow(
+      "Unknown format property 'bolt'",
+    );
+    expect(() => Format.fromObject({ bold: "yes" } as any)).toThrow(
+      "Expected a boolean for format property 'bold'",
+    );
+    expect(() => Format.fromObject({ fontColor: "teal" })).toThrow(
+      /ParameterError/,
+    );
+    expect(() => Format.fromObject({ border: 99 })).toThrow(/ParameterError/);
+    expect(() =>
+      Format.fromObject({ fontColor: { theme: 10, shade: 0 } }),
+    ).toThrow("Format property 'fontColor' isn't a valid color");
+    expect(() =>
+      Format.fromObject({ backgroundColor: { theme: 9, shade: 6 } }),
+    ).toThrow("Format property 'backgroundColor' isn't a valid color");
+  });
+
+  test("format merge", () => {
+    // Arrange
+    const base = new Format()
+      .setNumFormat("0.00")
+      .setFontColor(Color.red())
+      .setBorderBottom(FormatBorder.Thin);
+    const highlight = new Format()
+      .setFontColor(Color.green())
+      .setBackgroundColor(Color.yellow());
+
+    // Act
+    const overlay = base.clone().merge(highlight);
+    const underlay = base.clone().merge(highlight, FormatMergeMode.Underlay);
+
+    // Assert
+    expect(overlay.toObject()).toEqual({
+      numFormat: "0.00",
+      fontColor: "green",
+      borderBottom: FormatBorder.Thin,
+      backgroundColor: "yellow",
+    });
+    expect(underlay.toObject()).toEqual({
+      numFormat: "0.00",
+      fontColor: "red",
+      borderBottom: FormatBorder.Thin,
+      backgroundColor: "yellow",
+    });
+    expect(base.toObject().backgroundColor).toBeUndefined();
+  });
+
+  test("apply format to range", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const price = new Format().setNumFormat("$0.00");
+    worksheet.writeWithFormat(0, 0, 1.5, price);
+    worksheet.writeWithFormat(1, 0, 2.5, price);
+    worksheet.write(0, 1, "Apple");
+
+    // Act
+    worksheet.applyFormatToRange(
+      0,
+      0,
+      0,
+      1,
+      new Format().setBackgroundColor(Color.rgb(0xeeeeee)),
+    );
+    worksheet.applyFormatToRange(
+      1,
+      0,
+      1,
+      1,
+      new Format().setBorderBottom(FormatBorder.Thin),
+    );
+
+    // Assert
+    expect(worksheet.getCell(0, 0)?.value).toBe(1.5);
+    expect(worksheet.getCell(0, 0)?.format?.toObject()).toEqual({
+      numFormat: "$0.00",
+      backgroundColor: "#EEEEEE",
+    });
+    expect(worksheet.getCell(0, 1)?.format?.toObject()).toEqual({
+      backgroundColor: "#EEEEEE",
+    });
+    expect(worksheet.getCell(1, 0)?.format?.toObject()).toEqual({
+      numFormat: "$0.00",
+      borderBottom: FormatBorder.Thin,
+    });
+    expect(worksheet.getCell(1, 1)?.value).toBeNull();
+    const styles = (await readXlsx(workbook.saveToBufferSync())).files.get(
+      "xl/styles.xml",
+    );
+    expect(styles).toContain('<numFmt numFmtId="164" formatCode="$0.00"/>');
+    expect(() =>
+      worksheet.applyFormatToRange(1, 0, 0, 0, new Format().setBold()),
+    ).toThrow(/RowColumnOrderError/);
+    expect(() =>
+      worksheet.applyFormatToRange(0, 0, 1048575, 1, new Format().setBold()),
+    ).toThrow("A format can be applied to at most 1048576 cells");
+
+    worksheet.setRangeFormatWithBorder(
+      5,
+      0,
+      6,
+      1,
+      new Format().setItalic(),
+      new Format().setBorder(FormatBorder.Thin),
+    );
+    expect(worksheet.getCell(5, 0)?.format?.toObject()).toEqual({
+      italic: true,
+      borderTop: FormatBorder.Thin,
+      borderLeft: FormatBorder.Thin,
+    });
+    expect(worksheet.getCell(6, 1)?.format?.toObject()).toEqual({
+      italic: true,
+      borderBottom: FormatBorder.Thin,
+      borderRight: FormatBorder.Thin,
+    });
+  });
+
+  test("apply format to range with an embedded image", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const image = new Image(loadFile("./fixtures/rust.png"));
+    worksheet.embedImageWithFormat(0, 0, image, new Format().setBold());
+    worksheet.embedImage(1, 0, image);
+
+    // Act
+    worksheet.applyFormatToRange(0, 0, 1, 0, new Format().setItalic());
+
+    // Assert
+    expect(worksheet.getCell(0, 0)?.value).toBeNull();
+    expect(worksheet.getCell(0, 0)?.format?.toObject()).toEqual({
+      bold: true,
+      italic: true,
+    });
+    expect(worksheet.getCell(1, 0)?.format?.toObject()).toEqual({
+      italic: true,
+    });
+    expect(worksheet.usedRange()?.lastRow).toBe(1);
+    const sheet = (await readXlsx(workbook.saveToBufferSync())).files.get(
+      "xl/worksheets/sheet1.xml",
+    );
+    expect(sheet).toContain('<c r="A1" s="2" t="e" vm="1">');
+    expect(sheet).toContain('<c r="A2" s="3" t="e" vm="1">');
+  });
 });
@@ -0,0 +1,222 @@
+import {
+  Workbook,
+  Format,
+  FormatAlign,
+  Color,
+  HtmlOptions,
+  HtmlTableOptions,
+  Formula,
+  Url,
+} from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm test", () => {
+  test("render worksheet as html", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const header = new Format()
+      .setBold()
+      .setBackgroundColor(Color.yellow())
+      .setAlign(FormatAlign.Center);
+    const number = new Format().setNumFormat("#,##0.00");
+    const date = new Format().setNumFormat("yyyy-mm-dd");
+
+    // Act
+    worksheet.mergeRange(0, 0, 0, 1, "Title <1>", new Format().setBold());
+    worksheet.writeWithFormat(1, 0, 1234.567, number);
+    worksheet.writeWithFormat(1, 1, new Date(Date.UTC(2024, 0, 31)), date);
+    worksheet.writeWithFormat(2, 0, "Header", header);
+    worksheet.write(2, 1, true);
+    worksheet.writeWithFormat(3, 0, 1e300, date);
+    worksheet.writeWithFormat(3, 1, -1, date);
+    worksheet.setColumnWidthPixels(0, 120);
+    worksheet.setRowHeight(1, 30);
+    const html = worksheet.toHtml(new HtmlOptions());
+
+    // Assert
+    expect(html).toMatch(/^<table/);
+    expect(html).toContain('<td colspan="2"');
+    expect(html).toContain("Title &lt;1&gt;");
+    expect(html).toContain('<col style="width:120px">');
+    expect(html).toContain('<tr style="height:40px">');
+    expect(html).toContain("font-weight:bold");
+    expect(html).toContain("background-color:#FFFF00");
+    expect(html).toContain("text-align:center");
+    expect(html).toContain(">1,234.57</td>");
+    expect(html).toContain(">2024-01-31</td>");
+    expect(html).toContain(">TRUE</td>");
+    expect(html.match(/>####<\/td>/g)).toHaveLength(2);
+  });
+
+  test("render html options", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.write(0, 0, "a");
+    worksheet.write(5, 3, "b");
+
+    // Act
+    const html = worksheet.toHtml(
+      new HtmlOptions().setRange(0, 0, 1, 1).setHeaders(true).setGridlines(false),
+    );
+
+    // Assert
+    expect(html).toContain(">A</th>");
+    expect(html).toContain(">2</th>");
+    expect(html).not.toContain(">b</td>");
+    expect(html).not.toContain("border-top:1px solid #D4D4D4");
+    expect(() => new HtmlOptions().setRange(1, 0, 0, 0)).toThrow();
+  });
+
+  test("render hyperlink and formula cells as html", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const link = new Format().setHyperlink().setBold();
+
+    // Act
+    worksheet.writeUrlWithFormat(0, 0, new Url("https://example.com"), link);
+    worksheet.writeFormulaWithFormat(
+      1,
+      0,
+      new Formula("=1+1").setResult("2"),
+      new Format().setNumFormat("0.00"),
+    );
+    worksheet.writeFormula(2, 0, new Formula('="a"&"b"').setResult("ab"));
+    worksheet.writeFormula(3, 0, new Formula("=1/0").setResult("#DIV/0!"));
+    worksheet.writeFormula(4, 0, new Formula("=A1"));
+    const html = worksheet.toHtml(new HtmlOptions());
+
+    // Assert
+    expect(html).toContain("color:#0000FF");
+    expect(html).not.toContain("color:#F79646");
+    expect(html).toContain(">2.00</td>");
+    expect(html).toContain(">ab</td>");
+    expect(html).toContain(">#DIV/0!</td>");
+  });
+
+  test("write html table", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const html = `
+      <table style="font-family: Arial">
+        <tr><th colspan="2">Name &amp; Total</th><th>Date</th></tr>
+        <tr>
+          <td rowspan="2" style="color: #ff0000; border: 1px solid black">A</td>
+          <td style="background-color: rgb(255, 255, 0)">1,234.50</td>
+          <td>2024-01-31</td>
+        </tr>
+        <tr><td><b>12.5%</b></td><td>007</td></tr>
+      </table>`;
+
+    // Act
+    worksheet.writeHtmlTable(1, 1, html, new HtmlTableOptions());
+
+    // Assert
+    expect(worksheet.getCell(1, 1)?.value).toBe("Name & Total");
+    expect(worksheet.getCell(2, 1)?.value).toBe("A");
+    expect(worksheet.getCell(2, 2)?.value).toBe(1234.5);
+    expect(worksheet.getCell(2, 3)?.value).toEqual(
+      new Date(Date.UTC(2024, 0, 31)),
+    );
+    expect(worksheet.getCell(3, 2)?.value).toBe(0.125);
+    expect(worksheet.getCell(3, 3)?.value).toBe("007");
+
+    const rendered = worksheet.toHtml(new HtmlOptions());
+    expect(rendered).toContain('<td colspan="2"');
+    expect(rendered).toContain('<td rowspan="2"');
+    expect(rendered).toContain("font-family:'Arial'");
+    expect(rendered).toContain("color:#FF0000");
+    expect(rendered).toContain("background-color:#FFFF00");
+    expect(rendered).toContain(">1,234.50</td>");
+    expect(rendered).toContain(">12.5%</td>");
+    expect(rendered).toContain(">2024-01-31</td>");
+  });
+
+  test("write html table with large spans", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const html = `
+      <table>
+        <tr><td rowspan="100000" colspan="5000">A</td></tr>
+        <tr><td>B</td></tr>
+      </table>`;
+
+    // Act
+    worksheet.writeHtmlTable(0, 0, html, new HtmlTableOptions());
+    worksheet.writeHtmlTable(
+      1_048_574,
+      0,
+      '<table><tr><td rowspan="5">C</td></tr></table>',
+      new HtmlTableOptions(),
+    );
+
+    // Assert
+    expect(worksheet.getCell(0, 0)?.value).toBe("A");
+    expect(worksheet.getCell(9, 999)?.value).toBeNull();
+    expect(worksheet.getCell(10, 0)).toBeUndefined();
+    expect(worksheet.getCell(1, 1000)?.value).toBe("B");
+    expect(worksheet.getCell(1_048_575, 0)?.value).toBeNull();
+    expect(worksheet.usedRange()?.lastRow).toBe(1_048_575);
+  });
+
+  test("write html table options and errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const options = new HtmlTableOptions()
+      .setDetectNumbers(false)
+      .setDetectDates(false);
+
+    // Act
+    worksheet.writeHtmlTable(
+      0,
+      0,
+      "<table><tr><td>1</td><td>2024-01-31</td></tr></table>",
+      options,
+    );
+
+    // Assert
+    expect(worksheet.getCell(0, 0)?.value).toBe("1");
+    expect(worksheet.getCell(0, 1)?.value).toBe("2024-01-31");
+    expect(() =>
+      worksheet.writeHtmlTable(0, 0, "<p>text</p>", new HtmlTableOptions()),
+    ).toThrow("HTML doesn't contain a <table> element");
+    expect(() =>
+      worksheet.writeHtmlTable(
+        1048575,
+        0,
+        "<table><tr><td>a</td></tr><tr><td>b</td></tr></table>",
+        options,
+      ),
+    ).toThrow(/RowColumnLimitError/);
+    expect(worksheet.getCell(1048575, 0)).toBeUndefined();
+    worksheet.mergeRange(5, 1, 6, 2, "merged", new Format());
+    expect(() =>
+      worksheet.writeHtmlTable(
+        4,
+        0,
+        "<table><tr><td>a</td></tr><tr><td colspan=2>b</td></tr></table>",
+        options,
+      ),
+    ).toThrow(/MergeRangeOverlaps/);
+    expect(worksheet.getCell(4, 0)).toBeUndefined();
+    expect(() =>
+      worksheet.writeHtmlTable(
+        10,
+        0,
+        "<table><tr><td>a</td><td rowspan=2>b</td></tr><tr><td colspan=2>c</td></tr></table>",
+        options,
+      ),
+    ).toThrow(/MergeRangeOverlaps/);
+    expect(worksheet.getCell(10, 0)).toBeUndefined();
+  });
+});
@@ -1,6 +1,18 @@
-import { Workbook, Image, ObjectMovement } from "wasm-xlsxwriter/web";
+import {
+  Workbook,
+  Image,
+  HeaderImagePosition,
+  ObjectMovement,
+  SvgOptions,
+} from "wasm-xlsxwriter/web";
 import { describe, test, beforeAll, expect } from "vitest";
-import { initWasModule, loadFile, readXlsx, readXlsxFile } from "./common";
+import {
+  initWasModule,
+  loadFile,
+  readXlsx,
+  readXlsxBinaryPart,
+  readXlsxFile,
+} from "./common";
 
 beforeAll(async () => {
   await initWasModule();
@@ -96,3 +108,188 @@ describe("xlsx-wasm test", () => {
     expect(actual).matchXlsx(expected);
   });
 });
+
+describe("xlsx-wasm test", () => {
+  test("image from rgba pixels", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const pixels = new Uint8Array(4 * 3 * 2).fill(0x80);
+
+    // Act
+    const image = Image.fromRgba(4, 3, new Uint8Array(4 * 4 * 3).fill(0xff));
+    worksheet.insertImage(0, 0, image);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.has("xl/media/image1.png")).toBe(true);
+    expect(actual.files.get("xl/drawings/drawing1.xml")).toContain(
+      '<a:ext cx="38100" cy="28575"/>',
+    );
+    expect(() => Image.fromRgba(4, 3, pixels)).toThrow(/ParameterError/);
+    expect(() => Image.fromRgba(0, 0, new Uint8Array())).toThrow(
+      /ParameterError/,
+    );
+  });
+
+  test("image from rgba pixels with transparency", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const pixels = new Uint8Array(2 * 2 * 4).fill(0xff);
+    pixels[3] = 0x80;
+
+    // Act
+    worksheet.insertImage(0, 0, Image.fromRgba(2, 2, pixels));
+    worksheet.insertImage(
+      4,
+      0,
+      Image.fromRgba(2, 2, new Uint8Array(2 * 2 * 4).fill(0xff)),
+    );
+
+    // Assert
+    const buf = workbook.saveToBufferSync();
+    // The color type is byte 25 of a PNG, in the IHDR chunk: 6 is RGBA and
+    // 2 is RGB.
+    const transparent = await readXlsxBinaryPart(buf, "xl/media/image1.png");
+    const opaque = await readXlsxBinaryPart(buf, "xl/media/image2.png");
+    expect(transparent?.[25]).toBe(6);
+    expect(opaque?.[25]).toBe(2);
+  });
+
+  test("image from data url", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const url = `data:image/png;base64,${loadFile("./fixtures/rust.png").toString("base64")}`;
+
+    // Act
+    worksheet.insertImage(0, 0, Image.fromDataUrl(url));
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.has("xl/media/image1.png")).toBe(true);
+    expect(() => Image.fromDataUrl("data:image/png,abc")).toThrow(
+      /ParameterError/,
+    );
+    expect(() => Image.fromDataUrl("not a url")).toThrow(/ParameterError/);
+  });
+
+  test("image from svg", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const svg = new TextEncoder().encode(
+      '<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 6" width="4"><rect width="8" height="6"/></svg>',
+    );
+
+    // Act
+    worksheet.insertImage(0, 0, Image.fromSvg(svg));
+    worksheet.setHeader("&L&G");
+    worksheet.setHeaderImage(
+      Image.fromSvg(
+        svg,
+        new SvgOptions().setFallback(loadFile("./fixtures/rust.png")),
+      ),
+      HeaderImagePosition.Left,
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.has("xl/media/image1.png")).toBe(true);
+    expect(actual.files.has("xl/media/image1.svg")).toBe(true);
+    expect(actual.files.get("[Content_Types].xml")).toContain(
+      '<Default Extension="svg" ContentType="image/svg+xml"/>',
+    );
+    const drawing = actual.files.get("xl/drawings/drawing1.xml");
+    expect(drawing).toContain('<a:ext cx="38100" cy="28575"/>');
+    expect(drawing).toContain("<asvg:svgBlip");
+    expect(
+      actual.files.get("xl/drawings/_rels/vmlDrawing1.vml.rels"),
+    ).not.toContain(".svg");
+  });
+
+  test("image from svg with options", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const svg = new TextEncoder().encode('<svg><circle r="5"/></svg>');
+    const fallback = loadFile("./fixtures/rust.png");
+
+    // Act
+    const image = Image.fromSvg(
+      svg,
+      new SvgOptions().setFallback(fallback).setSize(20, 20),
+    );
+    worksheet.insertImage(0, 0, image);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.has("xl/media/image1.svg")).toBe(true);
+    expect(actual.files.get("xl/drawings/drawing1.xml")).toContain(
+      '<a:ext cx="190500" cy="190500"/>',
+    );
+    expect(() => Image.fromSvg(svg)).toThrow(/ParameterError/);
+    expect(() => Image.fromSvg(fallback)).toThrow(/ParameterError/);
+  });
+
+  test("image from svg without fallback", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const image = Image.fromSvg(
+      new TextEncoder().encode(
+        '<svg width="10" height="10"><rect width="5" height="5" fill="red"/></svg>',
+      ),
+    );
+
+    // Act
+    worksheet.embedImage(0, 0, image);
+    worksheet.setHeader("&L&G");
+    worksheet.setHeaderImage(image, HeaderImagePosition.Left);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/media/image1.png")).toContain("IDAT");
+    expect(actual.files.get("xl/media/image1.png")).toContain("Source");
+    expect(() =>
+      Image.fromSvg(
+        new TextEncoder().encode('<svg width="5000" height="10"></svg>'),
+      ),
+    ).toThrow(/ParameterError/);
+  });
+
+  test("svg fallback images are distinct from raster images", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const png = loadFile("./fixtures/rust.png");
+    const svg = new TextEncoder().encode('<svg width="10" height="10"></svg>');
+
+    // Act
+    worksheet.insertImage(0, 0, new Image(png));
+    worksheet.insertImage(
+      10,
+      0,
+      Image.fromSvg(svg, new SvgOptions().setFallback(png)),
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.has("xl/media/image2.png")).toBe(true);
+    const drawing = actual.files.get("xl/drawings/drawing1.xml")!;
+    expect(drawing.match(/<asvg:svgBlip/g)).toHaveLength(1);
+    expect(() =>
+      Image.fromSvg(svg, new SvgOptions().setFallback(svg)),
+    ).toThrow(/ParameterError/);
+    expect(() =>
+      Image.fromSvg(svg, new SvgOptions().setFallback(png.subarray(0, 8))),
+    ).toThrow("The fallback image of an SVG image must be a PNG image");
+    expect(() =>
+      Image.fromSvg(
+        svg,
+        new SvgOptions().setFallback(png.subarray(0, png.length - 12)),
+      ),
+    ).toThrow("The fallback image of an SVG image must be a PNG image");
+  });
+});
@@ -0,0 +1,106 @@
+import {
+  CurrencyPlacement,
+  DateStyle,
+  Format,
+  NegativeStyle,
+  NumFormat,
+  Workbook,
+} from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm test", () => {
+  test("num format builder", () => {
+    // Assert
+    expect(new NumFormat().toString()).toBe("0");
+    expect(
+      new NumFormat().setDecimalPlaces(2).setThousandsSeparator(true).toString(),
+    ).toBe("#,##0.00");
+    expect(new NumFormat().setCurrency().toString()).toBe("$#,##0.00");
+    expect(
+      new NumFormat().setDecimalPlaces(0).setCurrency().toString(),
+    ).toBe("$#,##0");
+    expect(
+      new NumFormat()
+        .setLocale("de-DE")
+        .setCurrency()
+        .setNegativeStyle(NegativeStyle.Red)
+        .toString(),
+    ).toBe("#,##0.00 [$€-407];[Red]-#,##0.00 [$€-407]");
+    expect(
+      new NumFormat()
+        .setLocale("ja-JP")
+        .setCurrency()
+        .setNegativeStyle(NegativeStyle.Parentheses)
+        .toString(),
+    ).toBe("[$¥-411]#,##0_);([$¥-411]#,##0)");
+    expect(
+      new NumFormat()
+        .setCurrency("CHF")
+        .setCurrencyPlacement(CurrencyPlacement.AfterWithSpace)
+        .toString(),
+    ).toBe("#,##0.00 [$CHF-409]");
+    expect(new NumFormat().setDecimalPlaces(1).setPercent().toString()).toBe(
+      "0.0%",
+    );
+    expect(new NumFormat().setDecimalPlaces(2).setScientific().toString()).toBe(
+      "0.00E+00",
+    );
+    expect(new NumFormat().setLocale("fr-FR").setDate(DateStyle.Short).toString()).toBe(
+      "[$-40C]dd/mm/yyyy",
+    );
+    expect(new NumFormat().setDatePattern("yyyy-mm-dd hh:mm").toString()).toBe(
+      "yyyy-mm-dd hh:mm",
+    );
+    expect(
+      new NumFormat().setSuffix(" kg").setTextSection("Note: ", "").toString(),
+    ).toBe('0" kg";-0" kg";0" kg";"Note: "@');
+    expect(
+      new NumFormat()
+        .addCondition("<", 1, new NumFormat().setDecimalPlaces(3))
+        .toString(),
+    ).toBe("[<1]0.000;0");
+    expect(
+      new NumFormat()
+        .addCondition("<", 1, new NumFormat().setDecimalPlaces(1))
+        .setTextSection("", " units")
+        .toString(),
+    ).toBe('[<1]0.0;0;0;@" units"');
+  });
+
+  test("num format validation", () => {
+    // Assert
+    expect(() => new NumFormat().setLocale("xx-XX")).toThrow(/ParameterError/);
+    expect(() => new NumFormat().setDecimalPlaces(31)).toThrow(/ParameterError/);
+    expect(() => new NumFormat().setCurrency("")).toThrow(/ParameterError/);
+    expect(() => new NumFormat().setDatePattern("yyyy-qq")).toThrow(
+      /ParameterError/,
+    );
+    expect(() => new NumFormat().addCondition("!=", 1, new NumFormat())).toThrow(
+      /ParameterError/,
+    );
+  });
+
+  test("num format with format", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const numFormat = new NumFormat().setLocale("de-DE").setCurrency();
+
+    // Act
+    worksheet.writeWithFormat(
+      0,
+      0,
+      1234.5,
+      new Format().setNumFormat(numFormat.toString()),
+    );
+
+    // Assert
+    expect(worksheet.getCell(0, 0)?.value).toBe(1234.5);
+    expect(workbook.saveToBufferSync().length).toBeGreaterThan(0);
+  });
+});
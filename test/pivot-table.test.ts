@@ -0,0 +1,195 @@
+import { Workbook, PivotTable, PivotFunction, Table } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm pivot table test", () => {
+  test("add pivot table", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const data = workbook.addWorksheet().setName("Data");
+    data.writeRow(0, 0, ["Region", "Product", "Sales"]);
+    data.writeRow(1, 0, ["East", "A", 10]);
+    data.writeRow(2, 0, ["West", "B", 20.5]);
+    data.writeRow(3, 0, ["East", "B", 5]);
+    const pivotTable = new PivotTable()
+      .setName("Summary")
+      .setSourceRange(data, 0, 0, 3, 2)
+      .addRowField("Region")
+      .addColumnField("Product")
+      .addValueField("Sales", PivotFunction.Sum)
+      .addValueField("Sales", PivotFunction.Count, "Orders");
+
+    // Act
+    workbook.addWorksheet().addPivotTable(2, 1, pivotTable);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const cache = actual.files.get("xl/pivotCache/pivotCacheDefinition1.xml");
+    expect(cache).toContain('<worksheetSource ref="A1:C4" sheet="Data"/>');
+    expect(cache).toContain('refreshOnLoad="1"');
+    expect(cache).toContain(
+      '<cacheField name="Region" numFmtId="0"><sharedItems count="2"><s v="East"/><s v="West"/></sharedItems>',
+    );
+    expect(
+      actual.files.get("xl/pivotCache/pivotCacheRecords1.xml"),
+    ).toContain('<r><x v="1"/><x v="1"/><n v="20.5"/></r>');
+    const definition = actual.files.get("xl/pivotTables/pivotTable1.xml");
+    expect(definition).toContain('name="Summary" cacheId="1"');
+    expect(definition).toContain('<rowFields count="1"><field x="0"/></rowFields>');
+    expect(definition).toContain(
+      '<colFields count="2"><field x="1"/><field x="-2"/></colFields>',
+    );
+    expect(definition).toContain('<dataField name="Sum of Sales" fld="2"');
+    expect(definition).toContain('<dataField name="Orders" fld="2" subtotal="count"');
+    expect(actual.files.get("xl/worksheets/_rels/sheet2.xml.rels")).toContain(
+      'Target="../pivotTables/pivotTable1.xml"',
+    );
+    expect(actual.files.get("xl/workbook.xml")).toMatch(
+      /<calcPr [^>]*\/><pivotCaches><pivotCache cacheId="1" r:id="rId6"\/><\/pivotCaches><\/workbook>/,
+    );
+    expect(actual.files.get("[Content_Types].xml")).toContain(
+      'PartName="/xl/pivotTables/pivotTable1.xml"',
+    );
+  });
+
+  test("pivot table from table with filter", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeRow(0, 0, ["Region", "Sales"]);
+    worksheet.writeRow(1, 0, ["East", 1]);
+    worksheet.writeRow(2, 0, ["West", 2]);
+    worksheet.addTable(0, 0, 2, 1, new Table().setName("Sales"));
+    const pivotTable = new PivotTable()
+      .setSourceTable("sales")
+      .addFilterField("Region", "West")
+      .addValueField("Sales", PivotFunction.Average, "Mean");
+
+    // Act
+    worksheet.addPivotTable(0, 4, pivotTable);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(
+      actual.files.get("xl/pivotCache/pivotCacheDefinition1.xml"),
+    ).toContain('<worksheetSource name="Sales"/>');
+    expect(actual.files.get("xl/pivotTables/pivotTable1.xml")).toContain(
+      '<pageField fld="0" item="1" hier="-1"/>',
+    );
+  });
+
+  test("pivot table errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeRow(0, 0, ["Region", "Sales"]);
+    worksheet.writeRow(1, 0, ["East", 1]);
+
+    // Assert
+    expect(() => new PivotTable().setSourceRange(worksheet, 0, 0, 0, 1)).toThrow(
+      /ParameterError/,
+    );
+    expect(() => new PivotTable().setStyle("Fancy")).toThrow(/ParameterError/);
+    expect(() =>
+      new PivotTable().addRowField("Region").addColumnField("region"),
+    ).toThrow(/ParameterError/);
+    expect(() => worksheet.addPivotTable(0, 4, new PivotTable())).toThrow(
+      /ParameterError/,
+    );
+
+    worksheet.addPivotTable(
+      0,
+      4,
+      new PivotTable()
+        .setSourceRange(worksheet, 0, 0, 1, 1)
+        .addRowField("Product")
+        .addValueField("Sales", PivotFunction.Sum),
+    );
+    expect(() => workbook.saveToBufferSync()).toThrow(
+      "Pivot table field 'Product' isn't in the source data",
+    );
+
+    const summary = new PivotTable()
+      .setSourceRange(worksheet, 0, 0, 1, 1)
+      .addRowField("Region")
+      .addValueField("Sales", PivotFunction.Sum);
+    expect(() =>
+      worksheet.addPivotTable(
+        40,
+        4,
+        summary
+          .addValueField("Sales", PivotFunction.Count, "Orders")
+          .addValueField("Sales", PivotFunction.Max, "orders"),
+      ),
+    ).toThrow("Pivot table value caption 'orders' isn't unique");
+    worksheet.addPivotTable(60, 4, summary.setName("Summary"));
+    expect(() =>
+      worksheet.addPivotTable(80, 4, summary.setName("SUMMARY")),
+    ).toThrow("Pivot table name 'SUMMARY' is already used in the worksheet");
+  });
+
+  test("pivot table save errors", () => {
+    // Arrange
+    const edge = new Workbook();
+    const edgeSheet = edge.addWorksheet();
+    const captions = new Workbook();
+    const captionsSheet = captions.addWorksheet();
+    for (const worksheet of [edgeSheet, captionsSheet]) {
+      worksheet.writeRow(0, 0, ["Region", "Sales"]);
+      worksheet.writeRow(1, 0, ["East", 1]);
+    }
+
+    // Act
+    edgeSheet.addPivotTable(
+      1048574,
+      0,
+      new PivotTable()
+        .setSourceRange(edgeSheet, 0, 0, 1, 1)
+        .addRowField("Region")
+        .addValueField("Sales", PivotFunction.Sum),
+    );
+    captionsSheet.addPivotTable(
+      0,
+      4,
+      new PivotTable()
+        .setSourceRange(captionsSheet, 0, 0, 1, 1)
+        .addValueField("Sales", PivotFunction.Sum)
+        .addValueField("Sales", PivotFunction.Count, "sum of sales"),
+    );
+
+    // Assert
+    expect(() => edge.saveToBufferSync()).toThrow(/RowColumnLimitError/);
+    expect(() => captions.saveToBufferSync()).toThrow(
+      "Pivot table value caption 'sum of sales' isn't unique",
+    );
+  });
+
+  test("default pivot table names skip used names", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeRow(0, 0, ["Region", "Sales"]);
+    worksheet.writeRow(1, 0, ["East", 1]);
+    const pivotTable = new PivotTable()
+      .setSourceRange(worksheet, 0, 0, 1, 1)
+      .addRowField("Region")
+      .addValueField("Sales", PivotFunction.Sum);
+
+    // Act
+    worksheet.addPivotTable(0, 4, pivotTable);
+    worksheet.addPivotTable(10, 4, pivotTable.setName("pivottable1"));
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/pivotTables/pivotTable1.xml")).toContain(
+      'name="PivotTable2" cacheId="1"',
+    );
+    expect(actual.files.get("xl/pivotTables/pivotTable2.xml")).toContain(
+      'name="pivottable1" cacheId="2"',
+    );
+  });
+});
@@ -1,4 +1,17 @@
-import { Workbook, TableFunction, TableColumn, Formula, Table } from "wasm-xlsxwriter/web";
+import {
+  Workbook,
+  TableFunction,
+  TableColumn,
+  Formula,
+  Table,
+  TableFilter,
+  TableFilterOperator,
+  Format,
+  FormatBorder,
+  Color,
+  CustomTableStyle,
+  TableStyle,
+} from "wasm-xlsxwriter/web";
 import { describe, test, beforeAll, expect } from "vitest";
 import { initWasModule, readXlsx, readXlsxFile } from "./common";
 
@@ -51,4 +64,289 @@ describe("xlsx-wasm test", () => {
     const expected = await readXlsxFile("./expected/table.xlsx");
     expect(actual).matchXlsx(expected);
   });
+
+  test("write table data and table in one call", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const headers = [
+      "Product",
+      "Quarter 1",
+      "Quarter 2",
+      "Quarter 3",
+      "Quarter 4",
+      "Year",
+    ];
+    const rows = [
+      ["Apples", 10000, 5000, 8000, 6000],
+      ["Pears", 2000, 3000, 4000, 5000],
+      ["Bananas", 6000, 6000, 6500, 6000],
+      ["Oranges", 500, 300, 200, 700],
+    ];
+    const columns = [
+      new TableColumn().setTotalLabel("Totals"),
+      new TableColumn().setTotalFunction(TableFunction.sum()),
+      new TableColumn().setTotalFunction(TableFunction.sum()),
+      new TableColumn().setTotalFunction(TableFunction.sum()),
+      new TableColumn().setTotalFunction(TableFunction.sum()),
+      new TableColumn()
+        .setTotalFunction(TableFunction.sum())
+        .setFormula(new Formula("SUM(Table1[@[Quarter 1]:[Quarter 4]])")),
+    ];
+    const table = new Table().setColumns(columns).setTotalRow(true);
+
+    // Act
+    const range = worksheet.writeTable(2, 1, headers, rows, table);
+
+    // Assert
+    expect([range.firstRow, range.firstCol, range.lastRow, range.lastCol]).toEqual(
+      [2, 1, 7, 6],
+    );
+    expect(worksheet.getCell(2, 1)?.value).toBe("Product");
+    expect(worksheet.getCell(6, 5)?.value).toBe(700);
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const expected = await readXlsxFile("./expected/table.xlsx");
+    expect(actual).matchXlsx(expected);
+  });
+
+  test("write table errors", () => {
+    // Arrange
+    const worksheet = new Workbook().addWorksheet();
+
+    // Assert
+    expect(() => worksheet.writeTable(0, 0, [], [], new Table())).toThrow(
+      /ParameterError/,
+    );
+    expect(() =>
+      worksheet.writeTable(0, 0, ["a"], [[1, 2]], new Table()),
+    ).toThrow(/ParameterError/);
+    expect(() =>
+      worksheet.writeTable(0, 0, ["a", "A"], [], new Table()),
+    ).toThrow(/TableError/);
+    expect(() =>
+      worksheet.writeTable(1048575, 0, ["a"], [[1]], new Table()),
+    ).toThrow(/RowColumnLimitError/);
+
+    const range = worksheet.writeTable(0, 0, ["a", "b"], [], new Table());
+    expect([range.firstRow, range.lastRow, range.lastCol]).toEqual([0, 1, 1]);
+  });
+
+  test("failed write table leaves the worksheet unchanged", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeTable(0, 0, ["a", "b"], [[1, 2]], new Table());
+    const filtered = new Table()
+      .setAutofilter(false)
+      .setColumns([new TableColumn().setFilter(TableFilter.list(["new"]))]);
+
+    // Act
+    const overlap = () =>
+      worksheet.writeTable(1, 1, ["c"], [["new"]], new Table());
+    const filter = () =>
+      worksheet.writeTable(5, 0, ["d"], [["new"]], filtered);
+
+    // Assert
+    expect(overlap).toThrow(/TableRangeOverlaps/);
+    expect(filter).toThrow(/ParameterError/);
+    expect(worksheet.getCell(2, 1)).toBeUndefined();
+    expect(worksheet.getCell(6, 0)).toBeUndefined();
+    expect(worksheet.usedRange()?.lastRow).toBe(1);
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/worksheets/sheet1.xml")).not.toContain(
+      "new",
+    );
+  });
+
+  test("table column filters and total format", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const columns = [
+      new TableColumn()
+        .setHeader("Region")
+        .setTotalLabel("Total")
+        .setTotalFormat(new Format().setBold())
+        .setFilter(TableFilter.list(["East", "West"])),
+      new TableColumn()
+        .setHeader("Sales")
+        .setTotalFunction(TableFunction.sum())
+        .setFilter(
+          TableFilter.custom(TableFilterOperator.GreaterThan, "20").and(
+            TableFilterOperator.LessThanOrEqualTo,
+            "250",
+          ),
+        ),
+    ];
+    const table = new Table()
+      .setColumns(columns)
+      .setTotalRow(true)
+      .setLastColumn(true);
+
+    // Act
+    worksheet.writeTable(
+      0,
+      0,
+      ["Region", "Sales"],
+      [
+        ["East", 10],
+        ["West", 200],
+        ["North", 50],
+      ],
+      table,
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const tableXml = actual.files.get("xl/tables/table1.xml");
+    expect(tableXml).toContain(
+      '<autoFilter ref="A1:B4"><filterColumn colId="0"><filters><filter val="East"/><filter val="West"/></filters></filterColumn>',
+    );
+    expect(tableXml).toContain(
+      '<customFilters and="1"><customFilter operator="greaterThan" val="20"/><customFilter operator="lessThanOrEqual" val="250"/></customFilters>',
+    );
+    expect(tableXml).toContain('showLastColumn="1"');
+    const sheetXml = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheetXml).toContain('<row r="2" spans="1:2" hidden="1">');
+    expect(sheetXml).toContain('<row r="3" spans="1:2">');
+    expect(sheetXml).toContain('<row r="4" spans="1:2" hidden="1">');
+    expect(sheetXml).toMatch(/<c r="A5" s="1" t="s">/);
+    expect(() =>
+      worksheet.addTable(
+        10,
+        0,
+        12,
+        0,
+        new Table()
+          .setAutofilter(false)
+          .setColumns([new TableColumn().setFilter(TableFilter.list(["a"]))]),
+      ),
+    ).toThrow(/ParameterError/);
+  });
+
+  test("table structured references", () => {
+    // Arrange
+    const columns = [
+      new TableColumn().setHeader("Price"),
+      new TableColumn().setHeader("Qty"),
+      new TableColumn().setHeader("Unit Cost"),
+    ];
+    const table = new Table().setName("Orders").setColumns(columns);
+
+    // Assert
+    expect(
+      `${table.columnReference("price", true)}*${table.columnReference("Qty", true)}`,
+    ).toBe("Orders[@Price]*Orders[@Qty]");
+    expect(table.columnReference("Unit Cost", true)).toBe(
+      "Orders[@[Unit Cost]]",
+    );
+    expect(table.columnReference("Unit Cost", false)).toBe(
+      "Orders[Unit Cost]",
+    );
+    expect(table.columnRangeReference("Price", "Qty", true)).toBe(
+      "Orders[@[Price]:[Qty]]",
+    );
+    expect(
+      new Table().setColumns(columns).columnReference("Price", false),
+    ).toBe("[Price]");
+    expect(table.subtotalFormula(TableFunction.sum(), "Qty")).toBeInstanceOf(
+      Formula,
+    );
+    expect(() => table.columnReference("Amount", true)).toThrow(
+      "Column 'Amount' isn't a header of the table",
+    );
+    expect(() =>
+      table.subtotalFormula(TableFunction.custom(new Formula("1")), "Qty"),
+    ).toThrow(/ParameterError/);
+  });
+
+  test("custom table style", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeRow(0, 0, ["Region", "Sales"]);
+    worksheet.writeRow(1, 0, ["East", 1]);
+    const style = new CustomTableStyle("Brand")
+      .setWholeTable(
+        new Format().setBorder(FormatBorder.Thin).setBorderColor(Color.red()),
+      )
+      .setHeaderRow(
+        new Format()
+          .setBold()
+          .setFontColor(Color.white())
+          .setBackgroundColor(Color.rgb(0x123456)),
+      );
+
+    // Act
+    workbook.addTableStyle(style);
+    worksheet.addTable(0, 0, 1, 1, new Table().setStyle(style));
+    worksheet.addTable(3, 0, 4, 1, new Table().setStyle(TableStyle.Light1));
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain(
+      '<dxf><font><b/><color rgb="FFFFFFFF"/></font><fill><patternFill><bgColor rgb="FF123456"/></patternFill></fill></dxf>',
+    );
+    expect(styles).toContain(
+      '<tableStyles count="1" defaultTableStyle="TableStyleMedium9" defaultPivotStyle="PivotStyleLight16"><tableStyle name="Brand" pivot="0" count="2"><tableStyleElement type="wholeTable" dxfId="0"/><tableStyleElement type="headerRow" dxfId="1"/></tableStyle></tableStyles>',
+    );
+    expect(actual.files.get("xl/tables/table1.xml")).toContain(
+      '<tableStyleInfo name="Brand"',
+    );
+    expect(actual.files.get("xl/tables/table2.xml")).toContain(
+      '<tableStyleInfo name="TableStyleLight1"',
+    );
+  });
+
+  test("custom table style name is case insensitive", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeRow(0, 0, ["Region", "Sales"]);
+    worksheet.writeRow(1, 0, ["East", 1]);
+
+    // Act
+    workbook.addTableStyle(
+      new CustomTableStyle("Brand").setHeaderRow(new Format().setBold()),
+    );
+    worksheet.addTable(
+      0,
+      0,
+      1,
+      1,
+      new Table().setStyle(new CustomTableStyle("brand")),
+    );
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/tables/table1.xml")).toContain(
+      '<tableStyleInfo name="Brand"',
+    );
+  });
+
+  test("custom table style errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Assert
+    expect(() => new CustomTableStyle("TableStyleMedium2")).toThrow(
+      /ParameterError/,
+    );
+    workbook.addTableStyle(new CustomTableStyle("Brand"));
+    expect(() => workbook.addTableStyle(new CustomTableStyle("brand"))).toThrow(
+      /ParameterError/,
+    );
+    worksheet.addTable(
+      0,
+      0,
+      1,
+      1,
+      new Table().setStyle(new CustomTableStyle("Other")),
+    );
+    expect(() => workbook.saveToBufferSync()).toThrow(
+      "Table style 'Other' isn't added to the workbook",
+    );
+  });
 });
@@ -0,0 +1,81 @@
+import { Workbook, Format, Formula } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm template test", () => {
+  const createTemplate = () => {
+    const workbook = new Workbook();
+    const header = new Format().setBold().setBackgroundColor("#FF0000");
+    const input = new Format().setNumFormat("0.0%");
+    const report = workbook.addWorksheet().setName("R&D Report");
+    report.writeWithFormat(0, 0, "Name", header);
+    report.writeWithFormat(0, 1, "Rate", header);
+    report.writeBlank(1, 1, input);
+    report.writeFormula(5, 1, new Formula("=SUM(B2:B4)"));
+    report.setColumnWidth(0, 30);
+    workbook.addWorksheet().setName("Notes");
+    workbook.defineName("Total", "='R&D Report'!$B$6");
+    return workbook.saveToBufferSync();
+  };
+
+  test("fill a template", async () => {
+    // Arrange
+    const workbook = Workbook.fromTemplate(createTemplate());
+    const worksheet = workbook.worksheetFromName("R&D Report");
+    const bold = new Format().setBold().setNumFormat("0.000");
+
+    // Act
+    worksheet.write(1, 0, "first");
+    worksheet.write(1, 1, 0.5);
+    worksheet.writeWithFormat(2, 1, 2, bold);
+    worksheet.writeRow(10, 0, ["appended", 3]);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const sheet = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet).toContain('<col min="1" max="1" width="30.7109375"');
+    expect(sheet).toContain('<dimension ref="A1:B11"/>');
+    expect(sheet).toContain(
+      '<c r="A2" t="inlineStr"><is><t>first</t></is></c><c r="B2" s="2"><v>0.5</v></c>',
+    );
+    expect(sheet).toContain('<c r="B3" s="3"><v>2</v></c>');
+    expect(sheet).toContain("<f>SUM(B2:B4)</f>");
+    expect(sheet).toContain(
+      '<row r="11"><c r="A11" t="inlineStr"><is><t>appended</t></is></c><c r="B11"><v>3</v></c></row>',
+    );
+    const styles = actual.files.get("xl/styles.xml");
+    expect(styles).toContain('<numFmt numFmtId="165" formatCode="0.000"/>');
+    expect(styles).toContain('<cellXfs count="4">');
+    const xml = actual.files.get("xl/workbook.xml");
+    expect(xml).toContain(
+      `<definedName name="Total">'R&amp;D Report'!$B$6</definedName>`,
+    );
+    expect(xml).toContain('fullCalcOnLoad="1"');
+    expect(workbook.worksheetFromIndex(1).name()).toBe("Notes");
+  });
+
+  test("template errors", () => {
+    // Arrange
+    const template = createTemplate();
+    const added = Workbook.fromTemplate(template);
+    const resized = Workbook.fromTemplate(template);
+    const named = Workbook.fromTemplate(template);
+
+    // Act
+    added.addWorksheet();
+    resized.worksheetFromIndex(0).setColumnWidth(3, 20);
+    named.defineName("Rate", "='R&D Report'!$B$2");
+
+    // Assert
+    expect(() => Workbook.fromTemplate(new Uint8Array([1, 2, 3]))).toThrow(
+      /ParameterError/,
+    );
+    expect(() => added.saveToBufferSync()).toThrow(/worksheets can't be added/);
+    expect(() => resized.saveToBufferSync()).toThrow(/'cols' settings/);
+    expect(() => named.saveToBufferSync()).toThrow(/defined names/);
+  });
+});
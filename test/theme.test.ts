@@ -0,0 +1,115 @@
+import {
+  Workbook,
+  ThemeDefinition,
+  ThemeColor,
+  Color,
+  Format,
+  HtmlOptions,
+  Url,
+} from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm theme test", () => {
+  test("set custom theme", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().write(0, 0, "Hello");
+    const theme = new ThemeDefinition("Corporate")
+      .setColor(ThemeColor.Dark1, Color.rgb(0x202020))
+      .setColor(ThemeColor.Accent1, Color.rgb(0x005a9c))
+      .setMajorFont("Georgia")
+      .setMinorFont("Arial");
+
+    // Act
+    workbook.setTheme(theme);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const xml = actual.files.get("xl/theme/theme1.xml");
+    expect(xml).toContain('<a:clrScheme name="Corporate">');
+    expect(xml).toContain('<a:dk1><a:srgbClr val="202020"/></a:dk1>');
+    expect(xml).toContain('<a:accent1><a:srgbClr val="005A9C"/></a:accent1>');
+    expect(xml).toContain('<a:majorFont><a:latin typeface="Georgia"/>');
+    expect(xml).toContain('<a:minorFont><a:latin typeface="Arial"/>');
+    expect(actual.files.get("xl/styles.xml")).toContain(
+      '<name val="Arial"/><family val="2"/><scheme val="minor"/>',
+    );
+  });
+
+  test("load theme from buffer", async () => {
+    // Arrange
+    const source = new Workbook();
+    source.addWorksheet();
+    source.setTheme(
+      new ThemeDefinition("Loaded").setColor(
+        ThemeColor.Accent2,
+        Color.rgb(0x112233),
+      ),
+    );
+    const themeXml = (await readXlsx(source.saveToBufferSync())).files.get(
+      "xl/theme/theme1.xml",
+    )!;
+    const workbook = new Workbook();
+    workbook.addWorksheet();
+
+    // Act
+    workbook.setTheme(
+      ThemeDefinition.fromBuffer(new TextEncoder().encode(themeXml)).setColor(
+        ThemeColor.Hyperlink,
+        Color.rgb(0xabcdef),
+      ),
+    );
+
+    // Assert
+    const xml = (await readXlsx(workbook.saveToBufferSync())).files.get(
+      "xl/theme/theme1.xml",
+    );
+    expect(xml).toContain('name="Loaded"');
+    expect(xml).toContain('<a:accent2><a:srgbClr val="112233"/></a:accent2>');
+    expect(xml).toContain('<a:hlink><a:srgbClr val="ABCDEF"/></a:hlink>');
+  });
+
+  test("render theme colors as html with the workbook theme", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    workbook.setTheme(
+      new ThemeDefinition("Corporate")
+        .setColor(ThemeColor.Accent1, Color.rgb(0x005a9c))
+        .setColor(ThemeColor.Hyperlink, Color.rgb(0x00aa00)),
+    );
+
+    // Act
+    worksheet.writeWithFormat(
+      0,
+      0,
+      "Accent",
+      new Format().setFontColor(Color.theme(4, 0)),
+    );
+    worksheet.writeUrl(1, 0, new Url("https://example.com"));
+    const html = worksheet.toHtml(new HtmlOptions());
+
+    // Assert
+    expect(html).toContain("color:#005A9C");
+    expect(html).toContain("color:#00AA00");
+    expect(html).not.toContain("color:#4F81BD");
+  });
+
+  test("theme errors", () => {
+    // Assert
+    expect(() =>
+      new ThemeDefinition("Theme").setColor(
+        ThemeColor.Accent1,
+        Color.theme(4, 0),
+      ),
+    ).toThrow(/ParameterError/);
+    expect(() =>
+      ThemeDefinition.fromBuffer(new TextEncoder().encode("<theme/>")),
+    ).toThrow(/ParameterError/);
+  });
+});
@@ -0,0 +1,60 @@
+import { Workbook } from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+// The OLE compound file signature followed by some dummy data.
+const oleData = new Uint8Array([
+  0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1, 0x00, 0x01, 0x02, 0x03,
+]);
+
+describe("xlsx-wasm vba test", () => {
+  test("add vba project", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet();
+    workbook.addWorksheet().setVbaName("Summary");
+
+    // Act
+    workbook.addSignedVbaProject(oleData, oleData);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("[Content_Types].xml")).toContain(
+      "application/vnd.ms-excel.sheet.macroEnabled.main+xml",
+    );
+    expect(actual.files.get("xl/_rels/workbook.xml.rels")).toContain(
+      'Target="vbaProject.bin"',
+    );
+    expect(actual.files.get("xl/_rels/vbaProject.bin.rels")).toContain(
+      'Target="vbaProjectSignature.bin"',
+    );
+    const workbookXml = actual.files.get("xl/workbook.xml");
+    expect(workbookXml).toContain(
+      '<fileVersion appName="xl" lastEdited="4" lowestEdited="4" rupBuild="4505" codeName="{37E998C4-C9E5-D4B9-71C8-EB1FF731991C}"/>',
+    );
+    expect(workbookXml).toContain('codeName="ThisWorkbook"');
+    expect(actual.files.get("xl/worksheets/sheet1.xml")).toContain(
+      '<sheetPr codeName="Sheet1"/>',
+    );
+    expect(actual.files.get("xl/worksheets/sheet2.xml")).toContain(
+      '<sheetPr codeName="Summary"/>',
+    );
+  });
+
+  test("vba errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Assert
+    expect(() => workbook.addVbaProject(new Uint8Array([1, 2, 3]))).toThrow(
+      /ParameterError/,
+    );
+    expect(() => workbook.setVbaName("1Book")).toThrow(/VbaNameError/);
+    expect(() => worksheet.setVbaName("Sheet 1")).toThrow(/VbaNameError/);
+  });
+});
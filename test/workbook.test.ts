@@ -0,0 +1,234 @@
+import {
+  Workbook,
+  CalcMode,
+  DateConversion,
+  DocProperties,
+  Format,
+  SaveOptions,
+  Worksheet,
+} from "wasm-xlsxwriter/web";
+import { describe, test, beforeAll, expect } from "vitest";
+import { initWasModule, readXlsx, readXlsxPart } from "./common";
+
+beforeAll(async () => {
+  await initWasModule();
+});
+
+describe("xlsx-wasm workbook options test", () => {
+  test("set window size, tab ratio and calc options", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet();
+    workbook.addWorksheet();
+
+    // Act
+    workbook.setSize(1200, 800);
+    workbook.setTabRatio(75);
+    workbook.setActiveSheet(0);
+    workbook.setActiveSheet(1);
+    workbook.setCalcMode(CalcMode.Manual);
+    workbook.setFullCalcOnLoad(false);
+
+    // Assert
+    const buffer = workbook.saveToBufferSync();
+    const actual = await readXlsx(buffer);
+    const xml = actual.files.get("xl/workbook.xml");
+    // `readXlsx` removes the window size, so it is read from the raw part.
+    const rawXml = await readXlsxPart(buffer, "xl/workbook.xml");
+    expect(rawXml).toContain('windowWidth="18000"');
+    expect(rawXml).toContain('windowHeight="12000"');
+    expect(xml).toContain('tabRatio="750"');
+    expect(xml).toContain('activeTab="1"');
+    expect(xml).toContain('calcMode="manual"');
+    expect(xml).not.toContain("fullCalcOnLoad");
+    expect(actual.files.get("xl/worksheets/sheet1.xml")).not.toContain("tabSelected");
+    expect(actual.files.get("xl/worksheets/sheet2.xml")).toContain('tabSelected="1"');
+  });
+
+  test("use 1904 epoch", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.use1904Epoch();
+    const worksheet = workbook.addWorksheet();
+    const format = new Format().setNumFormat("yyyy-mm-dd");
+
+    // Act
+    worksheet.writeWithFormat(0, 0, new Date(Date.UTC(1904, 0, 2)), format);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/workbook.xml")).toContain('date1904="1"');
+    expect(actual.files.get("xl/worksheets/sheet1.xml")).toContain("<v>1</v>");
+  });
+
+  test("use 1904 epoch errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const standalone = new Worksheet();
+    standalone.write(0, 0, new Date(Date.UTC(2024, 0, 1)));
+
+    // Act
+    worksheet.write(0, 0, new Date(Date.UTC(2024, 0, 1)));
+
+    // Assert
+    expect(() => workbook.use1904Epoch()).toThrow(/ParameterError/);
+
+    const epoch1904 = new Workbook();
+    epoch1904.use1904Epoch();
+    const sheet1904 = epoch1904.addWorksheet();
+    expect(() => sheet1904.write(0, 0, new Date(Date.UTC(1903, 11, 31)))).toThrow(
+      /DateTimeRangeError/,
+    );
+    expect(() => epoch1904.pushWorksheet(standalone)).toThrow(/ParameterError/);
+  });
+
+  test("set tab ratio error", () => {
+    // Arrange
+    const workbook = new Workbook();
+
+    // Assert
+    expect(() => workbook.setTabRatio(120)).toThrow(/ParameterError/);
+  });
+
+  test("save reproducible output", () => {
+    // Arrange
+    const createdAt = new Date(Date.UTC(2024, 0, 2, 3, 4, 5));
+    const save = (options: SaveOptions) => {
+      const workbook = new Workbook();
+      workbook.addWorksheet().write(0, 0, "Hello");
+      workbook.setProperties(new DocProperties().setAuthor("Alex"));
+      workbook.setSaveOptions(options);
+      return workbook.saveToBufferSync();
+    };
+
+    // Act
+    const options = new SaveOptions().setCreationDatetime(createdAt);
+    const first = save(options);
+    const second = save(options);
+    const stored = save(options.setCompressionLevel(0));
+
+    // Assert
+    expect(second).toEqual(first);
+    // Stored parts are uncompressed so the timestamp is visible.
+    const text = new TextDecoder("latin1").decode(stored);
+    expect(text).toContain(
+      '<dcterms:created xsi:type="dcterms:W3CDTF">2024-01-02T03:04:05Z',
+    );
+    expect(text).toContain(
+      '<dcterms:modified xsi:type="dcterms:W3CDTF">2024-01-02T03:04:05Z',
+    );
+    expect(text).toContain("<dc:creator>Alex</dc:creator>");
+    expect(stored.length).toBeGreaterThan(first.length);
+    expect(() => new SaveOptions().setCompressionLevel(10)).toThrow(
+      /ParameterError/,
+    );
+    expect(() =>
+      new SaveOptions().setCreationDatetime(new Date(NaN)),
+    ).toThrow();
+  });
+});
+
+describe("xlsx-wasm date conversion test", () => {
+  test("convert dates in a time zone", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Act
+    workbook.setDateConversion(DateConversion.timeZone("Asia/Tokyo"));
+    worksheet.write(0, 0, new Date(Date.UTC(2024, 0, 1, 15, 0)));
+    workbook.setDateConversion(
+      DateConversion.fixedOffset(-300).setDateOnly(true),
+    );
+    worksheet.writeColumn(1, 0, [new Date(Date.UTC(2024, 0, 1, 3, 30))]);
+    workbook.setDateConversion(DateConversion.utc());
+    worksheet.writeDatetime(2, 0, new Date(Date.UTC(2024, 0, 1, 15, 0)));
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const sheet = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet).toContain('<c r="A1"><v>45293</v></c>');
+    expect(sheet).toContain('<c r="A2"><v>45291</v></c>');
+    expect(sheet).toContain('<c r="A3"><v>45292.625</v></c>');
+  });
+
+  test("unknown time zone", () => {
+    expect(() => DateConversion.timeZone("Mars/Olympus_Mons")).toThrow(
+      /ParameterError/,
+    );
+  });
+});
+
+describe("xlsx-wasm worksheet order test", () => {
+  test("push, move and remove worksheets", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const first = workbook.addWorksheet().setName("First");
+    const standalone = new Worksheet().setName("Standalone");
+    standalone.write(0, 0, "standalone");
+    const removed = workbook.addWorksheet().setName("Removed");
+
+    // Act
+    workbook.pushWorksheet(standalone);
+    workbook.moveWorksheet(2, 0);
+    workbook.removeWorksheet(2);
+    standalone.write(1, 0, "after push");
+    first.write(0, 0, "first");
+
+    // Assert
+    expect(workbook.worksheetFromIndex(0).name()).toBe("Standalone");
+    expect(workbook.worksheetFromIndex(1).name()).toBe("First");
+    expect(() => removed.write(0, 0, 1)).toThrow(/removed/);
+    expect(() => workbook.pushWorksheet(standalone)).toThrow(/ParameterError/);
+    expect(() => workbook.moveWorksheet(0, 5)).toThrow(
+      /UnknownWorksheetNameOrIndex/,
+    );
+
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const xml = actual.files.get("xl/workbook.xml");
+    expect(xml).toMatch(/<sheet name="Standalone"[^>]*\/><sheet name="First"/);
+    expect(xml).not.toContain("Removed");
+    const sharedStrings = actual.files.get("xl/sharedStrings.xml");
+    expect(sharedStrings).toContain("after push");
+    expect(sharedStrings).toContain("first");
+  });
+
+  test("remove worksheet with a local name that refers to it", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().setName("Data");
+    workbook
+      .addWorksheet()
+      .setName("Report")
+      .defineName("Area", "=Report!$A$1:$D$20");
+
+    // Act
+    workbook.removeWorksheet(1);
+
+    // Assert
+    expect(workbook.definedNames()).toEqual([]);
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/workbook.xml")).not.toContain("definedName");
+  });
+
+  test("push and remove worksheet errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.addWorksheet().setName("Data").defineName("Total", "=Data!$A$1");
+    workbook.addWorksheet().setName("Rates");
+    workbook.defineName("Rate", "=Rates!$A$1");
+    const standalone = new Worksheet().setName("Data");
+    standalone.defineName("Total", "=Data!$B$1");
+    standalone.write(0, 0, "standalone");
+
+    // Assert
+    expect(() => workbook.pushWorksheet(standalone)).toThrow(/SheetnameReused/);
+    expect(standalone.name()).toBe("Data");
+    expect(() => standalone.write(1, 0, "still standalone")).not.toThrow();
+    expect(() => workbook.worksheetFromIndex(2)).toThrow(
+      /UnknownWorksheetNameOrIndex/,
+    );
+    expect(() => workbook.removeWorksheet(1)).toThrow(/Rate/);
+  });
+});
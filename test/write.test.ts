@@ -1,4 +1,15 @@
-import { Workbook, Format, Formula, RichString } from "wasm-xlsxwriter/web";
+import {
+  Workbook,
+  Format,
+  Formula,
+  RichString,
+  IgnoreError,
+  WritePolicy,
+  BigIntPolicy,
+  NullPolicy,
+  NonFinitePolicy,
+  Worksheet,
+} from "wasm-xlsxwriter/web";
 import { describe, test, beforeAll, expect } from "vitest";
 import { initWasModule, readXlsx, readXlsxFile } from "./common";
 
@@ -154,4 +165,202 @@ describe("xlsx-wasm test", () => {
     const expected = await readXlsxFile("./expected/write_freeze_panes.xlsx");
     expect(actual).matchXlsx(expected);
   });
+
+  test("write checkboxes", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const format = new Format().setBold();
+
+    // Act
+    const worksheet = workbook.addWorksheet();
+    worksheet.insertCheckbox(0, 0, true);
+    worksheet.insertCheckboxWithFormat(1, 0, false, format);
+    worksheet.write(2, 0, true, true);
+    worksheet.writeColumn(0, 1, [true, "done", false], true);
+    worksheet.write(3, 0, true);
+    worksheet.writeRow(4, 0, [true, false], true);
+    worksheet.writeRowWithFormat(5, 0, [true], format, true);
+    worksheet.writeRowMatrix(6, 0, [[false], [true]], true);
+    worksheet.writeColumnMatrix(8, 0, [[true, 1]], true);
+    worksheet.writeRow(10, 0, [true]);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(
+      actual.files.has("xl/featurePropertyBag/featurePropertyBag.xml"),
+    ).toBe(true);
+    const sheet = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet).toMatch(/<c r="A1" s="(\d+)" t="b"><v>1<\/v><\/c>/);
+    expect(sheet).toMatch(/<c r="B1" s="\d+" t="b"><v>1<\/v><\/c>/);
+    expect(sheet).toContain('<c r="A4" t="b"><v>1</v></c>');
+    for (const cell of ["A5", "B5", "A6", "A7", "A8", "A9"]) {
+      expect(sheet).toMatch(new RegExp(`<c r="${cell}" s="\\d+" t="b">`));
+    }
+    expect(sheet).toContain('<c r="A10"><v>1</v></c>');
+    expect(sheet).toContain('<c r="A11" t="b"><v>1</v></c>');
+  });
+
+  test("ignore errors", async () => {
+    // Arrange
+    const workbook = new Workbook();
+
+    // Act
+    const worksheet = workbook.addWorksheet();
+    worksheet.writeColumn(0, 0, ["00123", "00456"]);
+    worksheet.ignoreErrorRange(0, 0, 1, 0, IgnoreError.NumberStoredAsText);
+    worksheet.ignoreError(2, 0, IgnoreError.FormulaError);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const sheet = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet).toContain('numberStoredAsText="1"');
+    expect(sheet).toContain('sqref="A1:A2"');
+    expect(sheet).toContain('evalError="1"');
+    expect(() =>
+      worksheet.ignoreErrorRange(0, 0, 1, 0, IgnoreError.TwoDigitTextYear),
+    ).toThrow(/ParameterError/);
+    expect(() =>
+      worksheet.ignoreError(2, 0, IgnoreError.TwoDigitTextYear),
+    ).toThrow(/ParameterError/);
+  });
+
+  test("write policy", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.setWritePolicy(
+      new WritePolicy()
+        .setNull(NullPolicy.Skip)
+        .setNonFinite(NonFinitePolicy.NumError),
+    );
+    const worksheet = workbook.addWorksheet();
+    const other = workbook
+      .addWorksheet()
+      .setWritePolicy(new WritePolicy().setBigInt(BigIntPolicy.String));
+
+    // Act
+    worksheet.write(0, 0, 42n);
+    worksheet.write(1, 0, 12345678901234567890n);
+    worksheet.write(2, 0, null);
+    worksheet.write(3, 0, NaN);
+    other.write(0, 0, 42n);
+
+    // Assert
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    const sheet1 = actual.files.get("xl/worksheets/sheet1.xml");
+    expect(sheet1).toContain('<c r="A1"><v>42</v></c>');
+    expect(sheet1).toContain('<c r="A2" t="s">');
+    expect(sheet1).not.toContain('r="A3"');
+    expect(sheet1).toContain("<f>#NUM!</f>");
+    expect(actual.files.get("xl/worksheets/sheet2.xml")).toContain(
+      '<c r="A1" t="s">',
+    );
+  });
+
+  test("write policy blank and default non-finite", async () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook
+      .addWorksheet()
+      .setWritePolicy(new WritePolicy().setNull(NullPolicy.Blank));
+    const other = workbook.addWorksheet();
+
+    // Act
+    worksheet.write(0, 0, "old");
+    worksheet.write(0, 0, null);
+    other.write(0, 0, NaN);
+
+    // Assert
+    expect(worksheet.getCell(0, 0)).toBeUndefined();
+    const actual = await readXlsx(workbook.saveToBufferSync());
+    expect(actual.files.get("xl/worksheets/sheet1.xml")).not.toContain(
+      'r="A1"',
+    );
+    expect(actual.files.get("xl/worksheets/sheet2.xml")).not.toContain(
+      "<f>",
+    );
+  });
+
+  test("write policy errors", () => {
+    // Arrange
+    const workbook = new Workbook();
+    workbook.setWritePolicy(
+      new WritePolicy()
+        .setBigInt(BigIntPolicy.Error)
+        .setNonFinite(NonFinitePolicy.Error),
+    );
+    const worksheet = workbook.addWorksheet();
+
+    // Assert
+    expect(() => worksheet.write(0, 0, 1n)).toThrow(/bigint/);
+    expect(() => worksheet.write(0, 0, Infinity)).toThrow(/Infinity/);
+  });
+
+  test("read back written cells", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+    const bold = new Format().setBold();
+
+    // Act
+    worksheet.write(1, 1, "text");
+    worksheet.writeWithFormat(1, 2, 1.5, bold);
+    worksheet.writeRow(3, 0, [true, new Date(Date.UTC(2024, 0, 1))]);
+    worksheet.write(4, 4, new Formula("=PI()"));
+    worksheet.write(5, 0, "");
+    worksheet.writeBlank(6, 3, bold);
+    worksheet.clearCell(6, 3);
+
+    // Assert
+    expect(worksheet.getCell(1, 1)?.value).toBe("text");
+    expect(worksheet.getCell(1, 1)?.format).toBeUndefined();
+    expect(worksheet.getCell(1, 2)?.value).toBe(1.5);
+    expect(worksheet.getCell(1, 2)?.format).toBeInstanceOf(Format);
+    expect(worksheet.getCell(3, 0)?.value).toBe(true);
+    expect(worksheet.getCell(3, 1)?.value).toEqual(
+      new Date(Date.UTC(2024, 0, 1)),
+    );
+    expect(worksheet.getCell(4, 4)?.value).toBeInstanceOf(Formula);
+    expect(worksheet.getCell(5, 0)).toBeUndefined();
+    expect(worksheet.getCell(6, 3)).toBeUndefined();
+
+    const range = worksheet.usedRange();
+    expect([range?.firstRow, range?.firstCol]).toEqual([1, 0]);
+    expect([range?.lastRow, range?.lastCol]).toEqual([4, 4]);
+    expect(worksheet.lastRow()).toBe(4);
+    expect(worksheet.lastCol()).toBe(4);
+    expect(worksheet.cells().map((cell) => [cell.row, cell.col])).toEqual([
+      [1, 1],
+      [1, 2],
+      [3, 0],
+      [3, 1],
+      [4, 4],
+    ]);
+  });
+
+  test("read back an empty worksheet", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const worksheet = workbook.addWorksheet();
+
+    // Assert
+    expect(worksheet.usedRange()).toBeUndefined();
+    expect(worksheet.lastRow()).toBeUndefined();
+    expect(worksheet.cells()).toEqual([]);
+  });
+
+  test("read back a standalone worksheet after it is pushed", () => {
+    // Arrange
+    const workbook = new Workbook();
+    const standalone = new Worksheet();
+
+    // Act
+    standalone.write(0, 0, "text");
+    workbook.pushWorksheet(standalone);
+    standalone.write(1, 0, 2);
+
+    // Assert
+    expect(standalone.getCell(0, 0)?.value).toBe("text");
+    expect(standalone.getCell(1, 0)?.value).toBe(2);
+    expect(standalone.lastRow()).toBe(1);
+  });
 });